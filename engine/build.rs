@@ -0,0 +1,13 @@
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rustc-check-cfg=cfg(run_return)");
+
+    // The platforms where winit can return from its event loop, see `EventLoopExtRunReturn`
+    let os = std::env::var("CARGO_CFG_TARGET_OS").unwrap_or_default();
+    if matches!(
+        os.as_str(),
+        "windows" | "macos" | "android" | "linux" | "dragonfly" | "freebsd" | "netbsd" | "openbsd"
+    ) {
+        println!("cargo:rustc-cfg=run_return");
+    }
+}
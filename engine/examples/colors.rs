@@ -1,10 +1,8 @@
 use std::sync::Arc;
 
-use bytemuck::Pod;
-use bytemuck::Zeroable;
 use log::trace;
 use simple_logger::SimpleLogger;
-use vulkano::buffer::{BufferUsage, CpuAccessibleBuffer, TypedBufferAccess};
+use vulkano::buffer::TypedBufferAccess;
 use vulkano::command_buffer::{AutoCommandBufferBuilder, CommandBufferUsage, SubpassContents};
 use vulkano::pipeline::graphics::input_assembly::InputAssemblyState;
 use vulkano::pipeline::graphics::vertex_input::BuffersDefinition;
@@ -13,19 +11,12 @@ use vulkano::pipeline::GraphicsPipeline;
 use vulkano::render_pass::Subpass;
 
 use quasar_engine::drawing::engine::Engine;
-
-#[repr(C)]
-#[derive(Default, Copy, Clone, Pod, Zeroable)]
-struct Vertex {
-    position: [f32; 2],
-}
-
-vulkano::impl_vertex!(Vertex, position);
+use quasar_engine::drawing::vertex::{upload_vertices, Vertex2d};
 
 fn main() {
     SimpleLogger::new().init().unwrap();
 
-    let engine = Engine::new();
+    let engine = Engine::new_or_panic();
 
     // This example uses a single fragment shader responsible for rendering the whole screen
     // Because fragment shaders are only called on visible vertices, we need to force the rasterizer to consider
@@ -33,22 +24,17 @@ fn main() {
 
     // For this, we use a trick from https://stackoverflow.com/a/59739538: we draw a single triangle that covers the
     // whole screen, so a single fragment shader is called for everything displayed.
-    let vertex1 = Vertex {
+    let vertex1 = Vertex2d {
         position: [-1.0, -1.0],
     };
-    let vertex2 = Vertex {
+    let vertex2 = Vertex2d {
         position: [-1.0, 4.0],
     };
-    let vertex3 = Vertex {
+    let vertex3 = Vertex2d {
         position: [4.0, -1.0],
     };
 
-    let vertex_buffer = CpuAccessibleBuffer::from_iter(
-        Arc::clone(engine.hardware.graphics_device()),
-        BufferUsage::vertex_buffer(),
-        false,
-        vec![vertex1, vertex2, vertex3].into_iter(),
-    ).unwrap();
+    let vertex_buffer = upload_vertices(engine.hardware.graphics_device(), &[vertex1, vertex2, vertex3]);
 
     trace!("Creating the render pass");
     let render_pass = vulkano::single_pass_renderpass!(
@@ -74,7 +60,7 @@ fn main() {
 
     trace!("Creating the graphics pipeline");
     let pipeline = GraphicsPipeline::start()
-        .vertex_input_state(BuffersDefinition::new().vertex::<Vertex>())
+        .vertex_input_state(BuffersDefinition::new().vertex::<Vertex2d>())
         .vertex_shader(vs.entry_point("main").unwrap(), ())
         .input_assembly_state(InputAssemblyState::new())
         .viewport_state(ViewportState::viewport_dynamic_scissor_irrelevant())
@@ -109,6 +95,7 @@ fn main() {
 }
 
 
+#[allow(clippy::needless_question_mark)]
 mod vs {
     vulkano_shaders::shader! {
         ty: "vertex",
@@ -132,6 +119,7 @@ void main() {
     }
 }
 
+#[allow(clippy::needless_question_mark)]
 mod fs {
     vulkano_shaders::shader! {
         ty: "fragment",
@@ -0,0 +1,172 @@
+use std::sync::Arc;
+
+use image::RgbaImage;
+use log::info;
+use simple_logger::SimpleLogger;
+use vulkano::buffer::{BufferUsage, CpuAccessibleBuffer};
+use vulkano::command_buffer::{AutoCommandBufferBuilder, CommandBufferUsage, PrimaryCommandBuffer};
+use vulkano::descriptor_set::{PersistentDescriptorSet, WriteDescriptorSet};
+use vulkano::format::Format;
+use vulkano::image::view::ImageView;
+use vulkano::image::{ImageDimensions, StorageImage};
+use vulkano::pipeline::{ComputePipeline, Pipeline, PipelineBindPoint};
+use vulkano::sync::GpuFuture;
+
+use quasar_engine::drawing::engine::Engine;
+
+/// Size of the generated image, in pixels.
+const WIDTH: u32 = 1024;
+const HEIGHT: u32 = 1024;
+
+/// The number of iterations after which a point is considered to be inside the set.
+const MAX_ITERATIONS: u32 = 500;
+
+/// The point of the complex plane displayed at the center of the image.
+const CENTER: [f32; 2] = [-0.5, 0.0];
+
+/// The height of the complex plane displayed, smaller values zoom in.
+const ZOOM: f32 = 2.5;
+
+/// Work groups are 8×8 invocations, see the shader.
+const WORK_GROUP_SIZE: u32 = 8;
+
+fn main() {
+    SimpleLogger::new().init().unwrap();
+
+    let engine = Engine::new_headless().expect("Couldn't instantiate the engine");
+    let device = engine.compute_device();
+    let queue = engine.compute_queue();
+
+    let image = StorageImage::new(
+        Arc::clone(device),
+        ImageDimensions::Dim2d {
+            width: WIDTH,
+            height: HEIGHT,
+            array_layers: 1,
+        },
+        Format::R8G8B8A8_UNORM,
+        Some(queue.family()),
+    )
+        .unwrap();
+
+    let buffer = CpuAccessibleBuffer::from_iter(
+        Arc::clone(device),
+        BufferUsage::transfer_destination(),
+        false,
+        (0..WIDTH * HEIGHT * 4).map(|_| 0u8),
+    )
+        .unwrap();
+
+    let shader = cs::load(Arc::clone(device)).unwrap();
+    let pipeline = ComputePipeline::new(
+        Arc::clone(device),
+        shader.entry_point("main").unwrap(),
+        &cs::SpecializationConstants {
+            MAX_ITERATIONS,
+        },
+        None,
+        |_| {},
+    )
+        .unwrap();
+    let set = PersistentDescriptorSet::new(
+        Arc::clone(&pipeline.layout().set_layouts()[0]),
+        [WriteDescriptorSet::image_view(
+            0,
+            ImageView::new_default(Arc::clone(&image)).unwrap(),
+        )],
+    )
+        .unwrap();
+
+    // The image is computed, then copied to the CPU in the same submission
+    let mut builder = AutoCommandBufferBuilder::primary(
+        Arc::clone(device),
+        queue.family(),
+        CommandBufferUsage::OneTimeSubmit,
+    )
+        .unwrap();
+    builder
+        .bind_pipeline_compute(Arc::clone(&pipeline))
+        .bind_descriptor_sets(PipelineBindPoint::Compute, Arc::clone(pipeline.layout()), 0, set)
+        .push_constants(
+            Arc::clone(pipeline.layout()),
+            0,
+            cs::ty::View {
+                center: CENTER,
+                zoom: ZOOM,
+            },
+        )
+        .dispatch([
+            WIDTH.div_ceil(WORK_GROUP_SIZE),
+            HEIGHT.div_ceil(WORK_GROUP_SIZE),
+            1,
+        ])
+        .unwrap()
+        .copy_image_to_buffer(Arc::clone(&image) as Arc<_>, Arc::clone(&buffer) as Arc<_>)
+        .unwrap();
+
+    info!("Computing the Mandelbrot set ({} iterations)…", MAX_ITERATIONS);
+    builder
+        .build()
+        .unwrap()
+        .execute(Arc::clone(queue))
+        .unwrap()
+        .then_signal_fence_and_flush()
+        .unwrap()
+        .wait(None)
+        .unwrap();
+
+    let pixels = buffer.read().unwrap();
+    let image = RgbaImage::from_raw(WIDTH, HEIGHT, pixels.to_vec()).unwrap();
+    image.save("mandelbrot.png").unwrap();
+    info!("Saved mandelbrot.png");
+}
+
+#[allow(clippy::needless_question_mark)]
+mod cs {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        src: "
+#version 450
+layout(local_size_x = 8, local_size_y = 8, local_size_z = 1) in;
+
+layout(set = 0, binding = 0, rgba8) uniform writeonly image2D img;
+
+layout(constant_id = 0) const uint MAX_ITERATIONS = 500;
+
+layout(push_constant) uniform View {
+    vec2 center;
+    float zoom;
+} view;
+
+void main() {
+    ivec2 size = imageSize(img);
+    if (gl_GlobalInvocationID.x >= size.x || gl_GlobalInvocationID.y >= size.y) {
+        return;
+    }
+
+    // The y axis of images goes down, the imaginary axis goes up
+    vec2 norm = (vec2(gl_GlobalInvocationID.xy) + 0.5) / vec2(size) - 0.5;
+    vec2 c = view.center + vec2(norm.x * float(size.x) / float(size.y), -norm.y) * view.zoom;
+
+    vec2 z = vec2(0.0);
+    uint i;
+    for (i = 0; i < MAX_ITERATIONS; i++) {
+        z = vec2(z.x * z.x - z.y * z.y, 2.0 * z.x * z.y) + c;
+        if (dot(z, z) > 4.0) {
+            break;
+        }
+    }
+
+    vec4 color = vec4(0.0, 0.0, 0.0, 1.0);
+    if (i < MAX_ITERATIONS) {
+        // Smooth coloring, without bands between the iteration counts
+        float smoothed = float(i) + 1.0 - log2(log2(dot(z, z)) / 2.0);
+        float t = smoothed / float(MAX_ITERATIONS);
+        t = pow(t, 0.3);
+        color.rgb = 0.5 + 0.5 * cos(6.2831 * (t + vec3(0.0, 0.1, 0.2)));
+    }
+
+    imageStore(img, ivec2(gl_GlobalInvocationID.xy), color);
+}"
+    }
+}
@@ -1,10 +1,8 @@
 use std::sync::Arc;
 
-use bytemuck::Pod;
-use bytemuck::Zeroable;
 use log::trace;
 use simple_logger::SimpleLogger;
-use vulkano::buffer::{BufferUsage, CpuAccessibleBuffer, TypedBufferAccess};
+use vulkano::buffer::TypedBufferAccess;
 use vulkano::command_buffer::{AutoCommandBufferBuilder, CommandBufferUsage, SubpassContents};
 use vulkano::pipeline::graphics::input_assembly::InputAssemblyState;
 use vulkano::pipeline::graphics::vertex_input::BuffersDefinition;
@@ -13,39 +11,26 @@ use vulkano::pipeline::GraphicsPipeline;
 use vulkano::render_pass::Subpass;
 
 use quasar_engine::drawing::engine::Engine;
-
-#[repr(C)]
-#[derive(Default, Copy, Clone, Pod, Zeroable)]
-struct Vertex {
-    position: [f32; 2],
-}
-
-vulkano::impl_vertex!(Vertex, position);
+use quasar_engine::drawing::vertex::{upload_vertices, Vertex2d};
 
 fn main() {
     SimpleLogger::new().init().unwrap();
 
-    let engine = Engine::new();
+    let engine = Engine::new_or_panic();
 
     // Simple triangle
     trace!("Creating the triangle's vertices");
-    let vertex1 = Vertex {
+    let vertex1 = Vertex2d {
         position: [-0.5, -0.5],
     };
-    let vertex2 = Vertex {
+    let vertex2 = Vertex2d {
         position: [0.0, 0.5],
     };
-    let vertex3 = Vertex {
+    let vertex3 = Vertex2d {
         position: [0.5, -0.25],
     };
 
-    let vertex_buffer = CpuAccessibleBuffer::from_iter(
-        Arc::clone(engine.hardware.graphics_device()),
-        BufferUsage::vertex_buffer(),
-        false,
-        vec![vertex1, vertex2, vertex3].into_iter(),
-    )
-        .unwrap();
+    let vertex_buffer = upload_vertices(engine.hardware.graphics_device(), &[vertex1, vertex2, vertex3]);
 
     trace!("Creating the render pass");
     let render_pass = vulkano::single_pass_renderpass!(
@@ -72,7 +57,7 @@ fn main() {
     trace!("Creating the graphics pipeline");
     let pipeline = GraphicsPipeline::start()
         // We need to indicate the layout of the vertices.
-        .vertex_input_state(BuffersDefinition::new().vertex::<Vertex>())
+        .vertex_input_state(BuffersDefinition::new().vertex::<Vertex2d>())
         // A Vulkan shader can in theory contain multiple entry points, so we have to specify
         // which one.
         .vertex_shader(vs.entry_point("main").unwrap(), ())
@@ -114,6 +99,7 @@ fn main() {
     });
 }
 
+#[allow(clippy::needless_question_mark)]
 mod vs {
     vulkano_shaders::shader! {
         ty: "vertex",
@@ -127,6 +113,7 @@ mod vs {
     }
 }
 
+#[allow(clippy::needless_question_mark)]
 mod fs {
     vulkano_shaders::shader! {
         ty: "fragment",
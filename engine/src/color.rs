@@ -0,0 +1,127 @@
+/// Decodes a single sRGB-encoded channel, between 0 and 1, to linear.
+pub fn srgb_channel_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Encodes a single linear channel, between 0 and 1, to sRGB.
+pub fn linear_channel_to_srgb(value: f32) -> f32 {
+    if value <= 0.0031308 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Decodes an sRGB-encoded color, as picked in most image editors, to linear.
+pub fn srgb_to_linear(color: [f32; 3]) -> [f32; 3] {
+    color.map(srgb_channel_to_linear)
+}
+
+/// Encodes a linear color to sRGB.
+pub fn linear_to_srgb(color: [f32; 3]) -> [f32; 3] {
+    color.map(linear_channel_to_srgb)
+}
+
+/// A color in linear RGB, with a linear alpha.
+///
+/// Shaders, clear values and vertex colors expect linear values:
+/// with an sRGB attachment, the encoding happens when the color is written.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Color {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+    pub a: f32,
+}
+
+impl Color {
+    pub const BLACK: Color = Color::linear(0.0, 0.0, 0.0);
+    pub const WHITE: Color = Color::linear(1.0, 1.0, 1.0);
+
+    /// An opaque color from linear values.
+    pub const fn linear(r: f32, g: f32, b: f32) -> Self {
+        Color { r, g, b, a: 1.0 }
+    }
+
+    /// An opaque color from sRGB-encoded values between 0 and 1.
+    pub fn from_srgb(r: f32, g: f32, b: f32) -> Self {
+        let [r, g, b] = srgb_to_linear([r, g, b]);
+        Color::linear(r, g, b)
+    }
+
+    /// An opaque color from sRGB-encoded bytes, for example `#ff8000` is `from_srgb_u8(0xff, 0x80, 0x00)`.
+    pub fn from_srgb_u8(r: u8, g: u8, b: u8) -> Self {
+        Color::from_srgb(r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0)
+    }
+
+    pub fn with_alpha(self, a: f32) -> Self {
+        Color { a, ..self }
+    }
+
+    /// The sRGB-encoded values between 0 and 1, without alpha.
+    pub fn to_srgb(self) -> [f32; 3] {
+        linear_to_srgb([self.r, self.g, self.b])
+    }
+
+    /// The sRGB-encoded bytes, without alpha. Channels outside of `[0, 1]` are clamped.
+    pub fn to_srgb_u8(self) -> [u8; 3] {
+        self.to_srgb()
+            .map(|channel| (channel.clamp(0.0, 1.0) * 255.0).round() as u8)
+    }
+
+    /// The linear values with alpha, as expected by clear values and shaders.
+    pub fn to_linear(self) -> [f32; 4] {
+        [self.r, self.g, self.b, self.a]
+    }
+}
+
+impl From<Color> for [f32; 4] {
+    fn from(color: Color) -> Self {
+        color.to_linear()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn srgb_u8_round_trip() {
+        for value in 0..=255 {
+            let color = Color::from_srgb_u8(value, value, value);
+            assert_eq!(color.to_srgb_u8(), [value; 3], "{} doesn't round trip", value);
+        }
+    }
+
+    #[test]
+    fn decoding_breakpoint() {
+        assert_eq!(srgb_channel_to_linear(0.04045), 0.04045 / 12.92);
+
+        // Both sides of the breakpoint meet
+        let above = srgb_channel_to_linear(0.04045 + 1e-6);
+        assert!((above - 0.04045 / 12.92).abs() < 1e-6);
+
+        assert_eq!(srgb_channel_to_linear(0.0), 0.0);
+        assert!((srgb_channel_to_linear(1.0) - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn encoding_breakpoint() {
+        assert_eq!(linear_channel_to_srgb(0.0031308), 0.0031308 * 12.92);
+
+        let above = linear_channel_to_srgb(0.0031308 + 1e-7);
+        assert!((above - 0.0031308 * 12.92).abs() < 1e-5);
+
+        assert_eq!(linear_channel_to_srgb(0.0), 0.0);
+        assert!((linear_channel_to_srgb(1.0) - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn clamps_out_of_range() {
+        assert_eq!(Color::linear(-0.5, 1.5, 100.0).to_srgb_u8(), [0, 255, 255]);
+    }
+}
@@ -0,0 +1,128 @@
+use std::error::Error;
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use vulkano::buffer::cpu_access::{WriteLock, WriteLockError};
+use vulkano::buffer::{BufferContents, BufferUsage, CpuAccessibleBuffer};
+use vulkano::device::Device;
+use vulkano::memory::DeviceMemoryAllocationError;
+
+/// Host-visible data the GPU reads every frame, such as uniforms, duplicated so it can be updated without stalling.
+///
+/// Writing into a buffer the GPU is still reading from the previous frame is a hazard.
+/// Instead, each update goes into a copy no submitted frame is using anymore:
+/// the locks vulkano takes while a frame is in flight are released when its fence is signaled.
+///
+/// Every frame, call [`DoubleBuffered::current_mut`] once to update the data,
+/// then bind [`DoubleBuffered::current`].
+pub struct DoubleBuffered<T>
+    where
+        T: BufferContents,
+{
+    buffers: Vec<Arc<CpuAccessibleBuffer<T>>>,
+    current: AtomicUsize,
+}
+
+impl<T> DoubleBuffered<T>
+    where
+        T: BufferContents + Copy,
+{
+    /// Creates two copies of `data`, enough for a single frame in flight.
+    pub fn new(device: &Arc<Device>, usage: BufferUsage, data: T) -> Result<Self, BufferedError> {
+        Self::with_copies(device, usage, data, 2)
+    }
+
+    /// Creates `copies` copies of `data`: use one more than the number of frames in flight, at least one.
+    pub fn with_copies(
+        device: &Arc<Device>,
+        usage: BufferUsage,
+        data: T,
+        copies: usize,
+    ) -> Result<Self, BufferedError> {
+        if copies == 0 {
+            return Err(BufferedError::NoCopies);
+        }
+
+        let buffers = (0..copies)
+            .map(|_| CpuAccessibleBuffer::from_data(Arc::clone(device), usage, false, data))
+            .collect::<Result<_, _>>()?;
+
+        Ok(DoubleBuffered {
+            buffers,
+            current: AtomicUsize::new(0),
+        })
+    }
+}
+
+impl<T> DoubleBuffered<T>
+    where
+        T: BufferContents,
+{
+    /// The copy to bind for the current frame: the last one returned by [`DoubleBuffered::current_mut`].
+    pub fn current(&self) -> &Arc<CpuAccessibleBuffer<T>> {
+        &self.buffers[self.current.load(Ordering::Acquire)]
+    }
+
+    /// Switches to the next copy the GPU isn't reading from, and locks it for writing.
+    ///
+    /// The copy starts with the data it was last written with, which is usually from a few frames ago.
+    ///
+    /// Fails with [`WriteLockError::GpuLocked`] if every copy is still in use: more frames are in flight
+    /// than there are copies.
+    pub fn current_mut(&self) -> Result<WriteLock<'_, T>, WriteLockError> {
+        let previous = self.current.load(Ordering::Acquire);
+        let count = self.buffers.len();
+
+        let mut error = WriteLockError::GpuLocked;
+        for offset in 1..=count {
+            let index = (previous + offset) % count;
+            match self.buffers[index].write() {
+                Ok(lock) => {
+                    self.current.store(index, Ordering::Release);
+                    return Ok(lock);
+                }
+                Err(e) => error = e,
+            }
+        }
+
+        Err(error)
+    }
+
+    /// The number of copies.
+    pub fn copies(&self) -> usize {
+        self.buffers.len()
+    }
+}
+
+#[derive(Debug)]
+pub enum BufferedError {
+    /// The value would have zero copies.
+    NoCopies,
+    /// A copy couldn't be created.
+    Creation(DeviceMemoryAllocationError),
+}
+
+impl fmt::Display for BufferedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BufferedError::NoCopies => write!(f, "a buffered value needs at least one copy"),
+            BufferedError::Creation(e) => write!(f, "couldn't create a buffered copy: {}", e),
+        }
+    }
+}
+
+impl Error for BufferedError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            BufferedError::NoCopies => None,
+            BufferedError::Creation(e) => Some(e),
+        }
+    }
+}
+
+impl From<DeviceMemoryAllocationError> for BufferedError {
+    fn from(e: DeviceMemoryAllocationError) -> Self {
+        BufferedError::Creation(e)
+    }
+}
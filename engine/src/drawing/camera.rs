@@ -0,0 +1,488 @@
+use std::error::Error;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use bytemuck::{Pod, Zeroable};
+use vulkano::swapchain::SurfaceTransform;
+
+use crate::drawing::screen::Screen;
+
+/// A point of view the scene can be rendered from.
+///
+/// Matrices are column-major, as GLSL expects them.
+pub trait Camera: Send {
+    /// Transforms world coordinates to view coordinates.
+    fn view(&self) -> [[f32; 4]; 4];
+
+    /// Transforms view coordinates to clip space, for a viewport of the given width divided by its height.
+    fn projection(&self, aspect_ratio: f32) -> [[f32; 4]; 4];
+}
+
+/// A camera with a perspective projection, looking from `position` at `target`.
+///
+/// The world is right-handed, with `up` pointing up on screen. Depth goes from 0 at `near` to 1 at `far`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PerspectiveCamera {
+    pub position: [f32; 3],
+    pub target: [f32; 3],
+    pub up: [f32; 3],
+    /// The vertical field of view, in radians.
+    pub fov: f32,
+    pub near: f32,
+    pub far: f32,
+}
+
+impl Default for PerspectiveCamera {
+    fn default() -> Self {
+        PerspectiveCamera {
+            position: [0.0, 0.0, 0.0],
+            target: [0.0, 0.0, -1.0],
+            up: [0.0, 1.0, 0.0],
+            fov: std::f32::consts::FRAC_PI_3,
+            near: 0.1,
+            far: 1000.0,
+        }
+    }
+}
+
+impl PerspectiveCamera {
+    /// A camera at the origin looking towards -Z, with the vertical field of view `fov`, in radians.
+    ///
+    /// The field of view must be between 0 and π, and the planes must satisfy `0 < near < far`.
+    pub fn perspective(fov: f32, near: f32, far: f32) -> Result<Self, CameraError> {
+        let valid_fov = 0.0 < fov && fov < std::f32::consts::PI;
+        if !valid_fov {
+            return Err(CameraError::FieldOfView(fov));
+        }
+        let valid_planes = 0.0 < near && near < far;
+        if !valid_planes {
+            return Err(CameraError::Planes { near, far });
+        }
+
+        Ok(PerspectiveCamera {
+            fov,
+            near,
+            far,
+            ..Default::default()
+        })
+    }
+
+    /// A camera at `eye` looking at `target`, with the default projection.
+    pub fn look_at(eye: [f32; 3], target: [f32; 3]) -> Self {
+        PerspectiveCamera {
+            position: eye,
+            target,
+            ..Default::default()
+        }
+    }
+
+    pub fn view_matrix(&self) -> [[f32; 4]; 4] {
+        let forward = normalize(sub(self.target, self.position));
+        let side = normalize(cross(forward, self.up));
+        let up = cross(side, forward);
+        let eye = self.position;
+
+        [
+            [side[0], up[0], -forward[0], 0.0],
+            [side[1], up[1], -forward[1], 0.0],
+            [side[2], up[2], -forward[2], 0.0],
+            [-dot(side, eye), -dot(up, eye), dot(forward, eye), 1.0],
+        ]
+    }
+
+    pub fn projection_matrix(&self, aspect_ratio: f32) -> [[f32; 4]; 4] {
+        let focal = 1.0 / (self.fov / 2.0).tan();
+        let depth = self.far / (self.near - self.far);
+
+        // The Y axis of Vulkan's clip space points down
+        [
+            [focal / aspect_ratio, 0.0, 0.0, 0.0],
+            [0.0, -focal, 0.0, 0.0],
+            [0.0, 0.0, depth, -1.0],
+            [0.0, 0.0, self.near * depth, 0.0],
+        ]
+    }
+}
+
+impl Camera for PerspectiveCamera {
+    fn view(&self) -> [[f32; 4]; 4] {
+        self.view_matrix()
+    }
+
+    fn projection(&self, aspect_ratio: f32) -> [[f32; 4]; 4] {
+        self.projection_matrix(aspect_ratio)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CameraError {
+    /// The vertical field of view isn't between 0 and π radians.
+    FieldOfView(f32),
+    /// The near plane isn't between the camera and the far plane.
+    Planes { near: f32, far: f32 },
+}
+
+impl fmt::Display for CameraError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CameraError::FieldOfView(fov) => write!(f, "the field of view must be between 0 and π radians, not {}", fov),
+            CameraError::Planes { near, far } => {
+                write!(f, "the planes must satisfy 0 < near < far, not near = {} and far = {}", near, far)
+            }
+        }
+    }
+}
+
+impl Error for CameraError {}
+
+/// A camera shared between the application, which moves it, and the engine, which renders from it.
+pub type SharedCamera = Arc<Mutex<dyn Camera>>;
+
+/// The matrices of a camera, laid out to be copied into a uniform buffer.
+#[repr(C)]
+#[derive(Default, Copy, Clone, Debug, Pod, Zeroable)]
+pub struct CameraMatrices {
+    pub view: [[f32; 4]; 4],
+    pub projection: [[f32; 4]; 4],
+    pub view_projection: [[f32; 4]; 4],
+}
+
+/// The camera the scene is currently rendered from, see [`Engine::set_active_camera`](crate::drawing::engine::Engine::set_active_camera).
+///
+/// Switching cameras doesn't touch their state: switching back to a camera renders it from where it was left.
+///
+/// Its projection is rotated by the transform of the swapchain, see `Screen::pre_transform`.
+#[derive(Default)]
+pub struct ActiveCamera {
+    camera: Mutex<Option<SharedCamera>>,
+    pre_transform: Mutex<SurfaceTransform>,
+}
+
+impl ActiveCamera {
+    /// Renders from `camera`, starting with the next frame.
+    pub fn set(&self, camera: SharedCamera) {
+        *self.lock() = Some(camera);
+    }
+
+    pub fn clear(&self) {
+        *self.lock() = None;
+    }
+
+    pub fn get(&self) -> Option<SharedCamera> {
+        self.lock().clone()
+    }
+
+    /// The transform of the swapchain, folded into the projection.
+    pub fn pre_transform(&self) -> SurfaceTransform {
+        *self.pre_transform.lock().expect("The pre-transform was poisoned")
+    }
+
+    pub(crate) fn set_pre_transform(&self, transform: SurfaceTransform) {
+        *self.pre_transform.lock().expect("The pre-transform was poisoned") = transform;
+    }
+
+    /// The matrices of the active camera for a viewport with the given aspect ratio, if there is one.
+    ///
+    /// The aspect ratio is the one of the swapchain images: when they are rotated by a quarter turn,
+    /// the camera is given the aspect ratio of the display instead.
+    pub fn matrices(&self, aspect_ratio: f32) -> Option<CameraMatrices> {
+        let camera = self.get()?;
+        let camera = camera.lock().expect("The camera was poisoned");
+
+        let transform = self.pre_transform();
+        let aspect_ratio = if swaps_axes(transform) { 1.0 / aspect_ratio } else { aspect_ratio };
+
+        let view = camera.view();
+        let projection = multiply(&pretransform_matrix(transform), &camera.projection(aspect_ratio));
+        Some(CameraMatrices {
+            view,
+            projection,
+            view_projection: multiply(&projection, &view),
+        })
+    }
+
+    /// Same as [`ActiveCamera::matrices`], with the aspect ratio of the swapchain images of `screen`.
+    pub fn screen_matrices(&self, screen: &Screen) -> Option<CameraMatrices> {
+        self.matrices(screen.aspect_ratio())
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Option<SharedCamera>> {
+        self.camera.lock().expect("The active camera was poisoned")
+    }
+}
+
+/// Rotates and mirrors clip space by `transform`, so images presented with this transform appear upright.
+///
+/// Multiply projections by it, on the left.
+pub fn pretransform_matrix(transform: SurfaceTransform) -> [[f32; 4]; 4] {
+    // The pre-transform is applied by the application instead of the presentation engine: the image is mirrored,
+    // then rotated clockwise on screen, where Y points down. Each case is the image of the X and Y axes
+    let (x, y) = match transform {
+        SurfaceTransform::Rotate90 => ([0.0, 1.0], [-1.0, 0.0]),
+        SurfaceTransform::Rotate180 => ([-1.0, 0.0], [0.0, -1.0]),
+        SurfaceTransform::Rotate270 => ([0.0, -1.0], [1.0, 0.0]),
+        SurfaceTransform::HorizontalMirror => ([-1.0, 0.0], [0.0, 1.0]),
+        SurfaceTransform::HorizontalMirrorRotate90 => ([0.0, -1.0], [-1.0, 0.0]),
+        SurfaceTransform::HorizontalMirrorRotate180 => ([1.0, 0.0], [0.0, -1.0]),
+        SurfaceTransform::HorizontalMirrorRotate270 => ([0.0, 1.0], [1.0, 0.0]),
+        _ => ([1.0, 0.0], [0.0, 1.0]),
+    };
+
+    [
+        [x[0], x[1], 0.0, 0.0],
+        [y[0], y[1], 0.0, 0.0],
+        [0.0, 0.0, 1.0, 0.0],
+        [0.0, 0.0, 0.0, 1.0],
+    ]
+}
+
+/// Whether `transform` rotates by a quarter turn, swapping the width and the height of the images.
+pub fn swaps_axes(transform: SurfaceTransform) -> bool {
+    matches!(
+        transform,
+        SurfaceTransform::Rotate90
+            | SurfaceTransform::Rotate270
+            | SurfaceTransform::HorizontalMirrorRotate90
+            | SurfaceTransform::HorizontalMirrorRotate270
+    )
+}
+
+/// The product of two column-major matrices.
+fn multiply(a: &[[f32; 4]; 4], b: &[[f32; 4]; 4]) -> [[f32; 4]; 4] {
+    let mut result = [[0.0; 4]; 4];
+    for (column, result_column) in result.iter_mut().enumerate() {
+        for (row, value) in result_column.iter_mut().enumerate() {
+            *value = (0..4).map(|k| a[k][row] * b[column][k]).sum();
+        }
+    }
+    result
+}
+
+/// The inverse of a column-major matrix, by Gauss-Jordan elimination, or `None` if it isn't invertible.
+pub(crate) fn invert(m: &[[f32; 4]; 4]) -> Option<[[f32; 4]; 4]> {
+    // Rows of the augmented matrix [m | identity]
+    let mut rows = [[0.0f32; 8]; 4];
+    for (row, augmented) in rows.iter_mut().enumerate() {
+        for column in 0..4 {
+            augmented[column] = m[column][row];
+        }
+        augmented[4 + row] = 1.0;
+    }
+
+    for column in 0..4 {
+        let pivot = (column..4)
+            .max_by(|&a, &b| rows[a][column].abs().total_cmp(&rows[b][column].abs()))
+            .unwrap();
+        if rows[pivot][column].abs() < f32::EPSILON {
+            return None;
+        }
+        rows.swap(column, pivot);
+
+        let scale = rows[column][column];
+        rows[column].iter_mut().for_each(|value| *value /= scale);
+        for row in 0..4 {
+            if row != column {
+                let factor = rows[row][column];
+                let pivot_row = rows[column];
+                for (value, pivot_value) in rows[row].iter_mut().zip(pivot_row) {
+                    *value -= factor * pivot_value;
+                }
+            }
+        }
+    }
+
+    let mut result = [[0.0; 4]; 4];
+    for (column, result_column) in result.iter_mut().enumerate() {
+        for (row, value) in result_column.iter_mut().enumerate() {
+            *value = rows[row][4 + column];
+        }
+    }
+    Some(result)
+}
+
+fn sub(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+fn normalize(v: [f32; 3]) -> [f32; 3] {
+    let length = dot(v, v).sqrt();
+    [v[0] / length, v[1] / length, v[2] / length]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const IDENTITY: [[f32; 4]; 4] = [
+        [1.0, 0.0, 0.0, 0.0],
+        [0.0, 1.0, 0.0, 0.0],
+        [0.0, 0.0, 1.0, 0.0],
+        [0.0, 0.0, 0.0, 1.0],
+    ];
+
+    fn transform(m: &[[f32; 4]; 4], point: [f32; 4]) -> [f32; 4] {
+        let mut result = [0.0; 4];
+        for (row, value) in result.iter_mut().enumerate() {
+            *value = (0..4).map(|k| m[k][row] * point[k]).sum();
+        }
+        result
+    }
+
+    fn assert_close<const N: usize>(actual: [f32; N], expected: [f32; N]) {
+        let close = actual.iter().zip(expected).all(|(a, e)| (a - e).abs() < 1e-4);
+        assert!(close, "{:?} isn't {:?}", actual, expected);
+    }
+
+    #[test]
+    fn multiply_by_identity() {
+        let camera = PerspectiveCamera::look_at([1.0, 2.0, 3.0], [0.0, 0.0, 0.0]);
+        let view = camera.view_matrix();
+        assert_eq!(multiply(&IDENTITY, &view), view);
+        assert_eq!(multiply(&view, &IDENTITY), view);
+    }
+
+    #[test]
+    fn invert_round_trip() {
+        let camera = PerspectiveCamera {
+            position: [4.0, -2.0, 7.0],
+            target: [0.5, 1.0, -3.0],
+            near: 0.5,
+            far: 50.0,
+            ..Default::default()
+        };
+        let m = multiply(&camera.projection_matrix(16.0 / 9.0), &camera.view_matrix());
+        let inverse = invert(&m).unwrap();
+
+        for (column, expected) in multiply(&m, &inverse).iter().zip(IDENTITY) {
+            assert_close(*column, expected);
+        }
+        for (column, expected) in multiply(&inverse, &m).iter().zip(IDENTITY) {
+            assert_close(*column, expected);
+        }
+    }
+
+    #[test]
+    fn invert_singular() {
+        let mut m = IDENTITY;
+        m[2] = [0.0; 4];
+        assert_eq!(invert(&m), None);
+    }
+
+    #[test]
+    fn look_at() {
+        let camera = PerspectiveCamera::look_at([3.0, 0.0, 0.0], [0.0, 0.0, 0.0]);
+        let view = camera.view_matrix();
+
+        // The eye is at the origin of the view, looking towards -Z with +Y up
+        assert_close(transform(&view, [3.0, 0.0, 0.0, 1.0]), [0.0, 0.0, 0.0, 1.0]);
+        assert_close(transform(&view, [0.0, 0.0, 0.0, 1.0]), [0.0, 0.0, -3.0, 1.0]);
+        assert_close(transform(&view, [0.0, 1.0, 0.0, 1.0]), [0.0, 1.0, -3.0, 1.0]);
+        assert_close(transform(&view, [0.0, 0.0, -1.0, 1.0]), [1.0, 0.0, -3.0, 1.0]);
+    }
+
+    #[test]
+    fn projection_depth() {
+        let camera = PerspectiveCamera::perspective(std::f32::consts::FRAC_PI_2, 0.5, 20.0).unwrap();
+        let projection = camera.projection_matrix(1.0);
+        let depth = |z: f32| {
+            let clip = transform(&projection, [0.0, 0.0, z, 1.0]);
+            clip[2] / clip[3]
+        };
+
+        assert_close([depth(-0.5)], [0.0]);
+        assert_close([depth(-20.0)], [1.0]);
+        let middle = depth(-5.0);
+        assert!(0.0 < middle && middle < 1.0);
+
+        // With a right angle field of view, the top of the frustum maps to the top of clip space, where Y is -1
+        let top = transform(&projection, [0.0, 1.0, -1.0, 1.0]);
+        assert_close([top[1] / top[3]], [-1.0]);
+    }
+
+    /// Where the top-right and the bottom-right corners of clip space land with `transform`.
+    fn corners(transform: SurfaceTransform) -> ([f32; 2], [f32; 2]) {
+        let m = pretransform_matrix(transform);
+        let top_right = transform_point(&m, [1.0, -1.0]);
+        let bottom_right = transform_point(&m, [1.0, 1.0]);
+        (top_right, bottom_right)
+    }
+
+    fn transform_point(m: &[[f32; 4]; 4], [x, y]: [f32; 2]) -> [f32; 2] {
+        let [x, y, _, _] = transform(m, [x, y, 0.5, 1.0]);
+        [x, y]
+    }
+
+    #[test]
+    fn pretransform_identity() {
+        assert_eq!(corners(SurfaceTransform::Identity), ([1.0, -1.0], [1.0, 1.0]));
+    }
+
+    #[test]
+    fn pretransform_rotate_90() {
+        // A quarter turn clockwise: the right edge becomes the bottom edge
+        assert_eq!(corners(SurfaceTransform::Rotate90), ([1.0, 1.0], [-1.0, 1.0]));
+    }
+
+    #[test]
+    fn pretransform_rotate_180() {
+        assert_eq!(corners(SurfaceTransform::Rotate180), ([-1.0, 1.0], [-1.0, -1.0]));
+    }
+
+    #[test]
+    fn pretransform_rotate_270() {
+        // A quarter turn counter-clockwise: the right edge becomes the top edge
+        assert_eq!(corners(SurfaceTransform::Rotate270), ([-1.0, -1.0], [1.0, -1.0]));
+    }
+
+    #[test]
+    fn pretransform_mirror() {
+        assert_eq!(corners(SurfaceTransform::HorizontalMirror), ([-1.0, -1.0], [-1.0, 1.0]));
+    }
+
+    #[test]
+    fn pretransform_mirror_rotate_90() {
+        // Mirrored to the left edge, which a quarter turn clockwise moves to the top edge
+        assert_eq!(corners(SurfaceTransform::HorizontalMirrorRotate90), ([1.0, -1.0], [-1.0, -1.0]));
+    }
+
+    #[test]
+    fn pretransform_mirror_rotate_180() {
+        assert_eq!(corners(SurfaceTransform::HorizontalMirrorRotate180), ([1.0, 1.0], [1.0, -1.0]));
+    }
+
+    #[test]
+    fn pretransform_mirror_rotate_270() {
+        // Mirrored to the left edge, which a quarter turn counter-clockwise moves to the bottom edge
+        assert_eq!(corners(SurfaceTransform::HorizontalMirrorRotate270), ([-1.0, 1.0], [1.0, 1.0]));
+    }
+
+    #[test]
+    fn invalid_perspectives() {
+        use std::f32::consts::PI;
+
+        assert_eq!(PerspectiveCamera::perspective(0.0, 0.1, 10.0), Err(CameraError::FieldOfView(0.0)));
+        assert_eq!(PerspectiveCamera::perspective(PI, 0.1, 10.0), Err(CameraError::FieldOfView(PI)));
+        assert!(matches!(PerspectiveCamera::perspective(f32::NAN, 0.1, 10.0), Err(CameraError::FieldOfView(_))));
+        assert_eq!(
+            PerspectiveCamera::perspective(1.0, 0.0, 10.0),
+            Err(CameraError::Planes { near: 0.0, far: 10.0 })
+        );
+        assert_eq!(
+            PerspectiveCamera::perspective(1.0, 10.0, 10.0),
+            Err(CameraError::Planes { near: 10.0, far: 10.0 })
+        );
+        assert!(PerspectiveCamera::perspective(1.0, 0.1, 10.0).is_ok());
+    }
+}
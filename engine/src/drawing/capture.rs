@@ -0,0 +1,86 @@
+#[cfg(feature = "renderdoc")]
+use std::ffi::c_void;
+#[cfg(feature = "renderdoc")]
+use std::ptr;
+#[cfg(feature = "renderdoc")]
+use std::sync::Mutex;
+
+#[cfg(feature = "renderdoc")]
+use log::{debug, info};
+#[cfg(feature = "renderdoc")]
+use renderdoc::{RenderDoc, V110};
+
+/// Programmatic frame captures with the RenderDoc in-application API.
+///
+/// Captures are only recorded when the application is started from RenderDoc, and the engine is compiled
+/// with the `renderdoc` feature. Otherwise, all methods do nothing.
+pub struct Capture {
+    #[cfg(feature = "renderdoc")]
+    renderdoc: Option<Mutex<RenderDoc<V110>>>,
+}
+
+impl Capture {
+    #[cfg(feature = "renderdoc")]
+    pub(crate) fn new() -> Self {
+        let renderdoc = match RenderDoc::new() {
+            Ok(renderdoc) => {
+                info!("RenderDoc is attached, frame captures are enabled");
+                Some(Mutex::new(renderdoc))
+            }
+            Err(e) => {
+                debug!("RenderDoc isn't attached, frame captures are disabled: {}", e);
+                None
+            }
+        };
+
+        Capture { renderdoc }
+    }
+
+    #[cfg(not(feature = "renderdoc"))]
+    pub(crate) fn new() -> Self {
+        Capture {}
+    }
+
+    /// Whether captures are recorded.
+    pub fn is_available(&self) -> bool {
+        #[cfg(feature = "renderdoc")]
+        return self.renderdoc.is_some();
+
+        #[cfg(not(feature = "renderdoc"))]
+        false
+    }
+
+    /// Captures the next frame presented to the window.
+    pub fn trigger(&self) {
+        #[cfg(feature = "renderdoc")]
+        if let Some(renderdoc) = &self.renderdoc {
+            renderdoc
+                .lock()
+                .expect("The RenderDoc API was poisoned")
+                .trigger_capture();
+        }
+    }
+
+    /// Captures all the work submitted until [`Capture::end`] is called, instead of a single frame.
+    pub fn start(&self) {
+        #[cfg(feature = "renderdoc")]
+        if let Some(renderdoc) = &self.renderdoc {
+            // Null handles capture the only device and window of the application
+            renderdoc
+                .lock()
+                .expect("The RenderDoc API was poisoned")
+                .start_frame_capture(ptr::null::<c_void>(), ptr::null());
+        }
+    }
+
+    /// Ends the capture started by [`Capture::start`], and saves it.
+    pub fn end(&self) {
+        #[cfg(feature = "renderdoc")]
+        if let Some(renderdoc) = &self.renderdoc {
+            renderdoc
+                .lock()
+                .expect("The RenderDoc API was poisoned")
+                .end_frame_capture(ptr::null::<c_void>(), ptr::null());
+        }
+    }
+}
@@ -0,0 +1,141 @@
+use std::error::Error;
+use std::fmt;
+
+use vulkano::command_buffer::{AutoCommandBufferBuilder, AutoCommandBufferBuilderContextError, PrimaryAutoCommandBuffer};
+use vulkano::format::{ClearValue, Format, NumericType};
+use vulkano::image::attachment::{ClearAttachment, ClearRect};
+use vulkano::pipeline::graphics::viewport::Scissor;
+use vulkano::render_pass::Subpass;
+
+/// An attachment of the current subpass, cleared by [`ClearRegion::clear_region`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClearTarget {
+    /// The color attachment at this index of the subpass, as in `layout(location = …)`.
+    Color(u32),
+    /// The depth and stencil attachment of the subpass: the aspects cleared depend on the clear value.
+    DepthStencil,
+}
+
+/// Clears part of an attachment in the middle of a render pass, for example the depth between two objects.
+pub trait ClearRegion {
+    /// Clears `rect` of the `target` attachment of `subpass` to `value`.
+    ///
+    /// `subpass` must be the subpass being recorded. The clear value must match the attachment:
+    /// `Float`, `Int` or `Uint` for color attachments, following their format, and `Depth`, `Stencil`
+    /// or `DepthStencil` for the aspects of the depth and stencil attachment.
+    fn clear_region(
+        &mut self,
+        subpass: &Subpass,
+        target: ClearTarget,
+        rect: Scissor,
+        value: ClearValue,
+    ) -> Result<&mut Self, ClearRegionError>;
+}
+
+impl ClearRegion for AutoCommandBufferBuilder<PrimaryAutoCommandBuffer> {
+    fn clear_region(
+        &mut self,
+        subpass: &Subpass,
+        target: ClearTarget,
+        rect: Scissor,
+        value: ClearValue,
+    ) -> Result<&mut Self, ClearRegionError> {
+        let attachment = clear_attachment(subpass, target, value)?;
+        if rect.dimensions[0] == 0 || rect.dimensions[1] == 0 {
+            return Err(ClearRegionError::EmptyRect);
+        }
+
+        let rect = ClearRect {
+            rect_offset: rect.origin,
+            rect_extent: rect.dimensions,
+            base_array_layer: 0,
+            layer_count: 1,
+        };
+        // vulkano doesn't export the error of clear_attachments, only its message can be inspected
+        self.clear_attachments([attachment], [rect]).map_err(|e| {
+            let message = e.to_string();
+            if message == AutoCommandBufferBuilderContextError::ForbiddenOutsideRenderPass.to_string() {
+                ClearRegionError::OutsideRenderPass
+            } else {
+                ClearRegionError::Clear(message)
+            }
+        })
+    }
+}
+
+/// Checks that `value` can clear the `target` attachment of `subpass`.
+fn clear_attachment(subpass: &Subpass, target: ClearTarget, value: ClearValue) -> Result<ClearAttachment, ClearRegionError> {
+    let attachments = subpass.render_pass().attachments();
+    let description = subpass.subpass_desc();
+
+    match target {
+        ClearTarget::Color(index) => {
+            let format = description
+                .color_attachments
+                .get(index as usize)
+                .and_then(Option::as_ref)
+                .and_then(|reference| attachments[reference.attachment as usize].format)
+                .ok_or(ClearRegionError::NoSuchAttachment(target))?;
+
+            let matches = match (format.type_color(), value) {
+                (Some(NumericType::UINT), ClearValue::Uint(_)) => true,
+                (Some(NumericType::SINT), ClearValue::Int(_)) => true,
+                (Some(NumericType::UINT | NumericType::SINT), _) => false,
+                (Some(_), ClearValue::Float(_)) => true,
+                _ => false,
+            };
+            if !matches {
+                return Err(ClearRegionError::MismatchedValue { format, value });
+            }
+
+            Ok(ClearAttachment::Color(value, index))
+        }
+        ClearTarget::DepthStencil => {
+            let format = description
+                .depth_stencil_attachment
+                .as_ref()
+                .and_then(|reference| attachments[reference.attachment as usize].format)
+                .ok_or(ClearRegionError::NoSuchAttachment(target))?;
+
+            let aspects = format.aspects();
+            match value {
+                ClearValue::Depth(depth) if aspects.depth => Ok(ClearAttachment::Depth(depth)),
+                ClearValue::Stencil(stencil) if aspects.stencil => Ok(ClearAttachment::Stencil(stencil)),
+                ClearValue::DepthStencil(values) if aspects.depth && aspects.stencil => {
+                    Ok(ClearAttachment::DepthStencil(values))
+                }
+                _ => Err(ClearRegionError::MismatchedValue { format, value }),
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ClearRegionError {
+    /// The command isn't recorded inside a render pass.
+    OutsideRenderPass,
+    /// The subpass has no such attachment.
+    NoSuchAttachment(ClearTarget),
+    /// The clear value doesn't match the format or the aspects of the attachment.
+    MismatchedValue { format: Format, value: ClearValue },
+    /// The rectangle to clear has a zero width or height.
+    EmptyRect,
+    /// The clear was refused by vulkano, for example because the rectangle is outside of the render area.
+    Clear(String),
+}
+
+impl fmt::Display for ClearRegionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClearRegionError::OutsideRenderPass => write!(f, "attachments can only be cleared inside a render pass"),
+            ClearRegionError::NoSuchAttachment(target) => write!(f, "the subpass has no attachment {:?}", target),
+            ClearRegionError::MismatchedValue { format, value } => {
+                write!(f, "an attachment of format {:?} can't be cleared with {:?}", format, value)
+            }
+            ClearRegionError::EmptyRect => write!(f, "the region to clear is empty"),
+            ClearRegionError::Clear(e) => write!(f, "couldn't clear the region: {}", e),
+        }
+    }
+}
+
+impl Error for ClearRegionError {}
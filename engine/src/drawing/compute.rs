@@ -0,0 +1,128 @@
+use std::error::Error;
+use std::fmt;
+use std::sync::Arc;
+
+use log::trace;
+use vulkano::command_buffer::{
+    AutoCommandBufferBuilder, CommandBufferExecError, CommandBufferUsage, DispatchError, PrimaryCommandBuffer,
+};
+use vulkano::descriptor_set::{DescriptorSetCreationError, PersistentDescriptorSet, WriteDescriptorSet};
+use vulkano::pipeline::compute::ComputePipelineCreationError;
+use vulkano::pipeline::{ComputePipeline, Pipeline, PipelineBindPoint};
+use vulkano::shader::EntryPoint;
+use vulkano::sync::{FlushError, GpuFuture};
+
+use crate::drawing::hardware::Hardware;
+
+/// Builds a compute pipeline from `entry_point`, dispatches it on the compute queue with `writes` bound
+/// to the descriptor set 0, and waits until it is done.
+pub(crate) fn run_compute(
+    hardware: &Hardware,
+    entry_point: EntryPoint,
+    writes: impl IntoIterator<Item = WriteDescriptorSet>,
+    dispatch: [u32; 3],
+) -> Result<(), ComputeError> {
+    let queue = hardware.compute_queue().ok_or(ComputeError::NoComputeQueue)?;
+    let device = queue.device();
+
+    // The pipeline cache belongs to the graphics device
+    let cache = if Arc::ptr_eq(device, hardware.graphics_device()) {
+        Some(Arc::clone(hardware.pipeline_cache()))
+    } else {
+        None
+    };
+    let pipeline = ComputePipeline::new(Arc::clone(device), entry_point, &(), cache, |_| {})?;
+
+    let mut builder = AutoCommandBufferBuilder::primary(
+        Arc::clone(device),
+        queue.family(),
+        CommandBufferUsage::OneTimeSubmit,
+    )
+        .expect("Couldn't create the compute command buffer");
+    builder.bind_pipeline_compute(Arc::clone(&pipeline));
+
+    // Shaders without any resource have no descriptor set
+    if let Some(layout) = pipeline.layout().set_layouts().first() {
+        let set = PersistentDescriptorSet::new(Arc::clone(layout), writes)?;
+        builder.bind_descriptor_sets(PipelineBindPoint::Compute, Arc::clone(pipeline.layout()), 0, set);
+    }
+
+    builder.dispatch(dispatch)?;
+    let command_buffer = builder.build().expect("Couldn't build the compute command buffer");
+
+    trace!("Dispatching {:?} work groups", dispatch);
+    command_buffer
+        .execute(Arc::clone(queue))?
+        .then_signal_fence_and_flush()?
+        .wait(None)?;
+
+    Ok(())
+}
+
+#[derive(Debug)]
+pub enum ComputeError {
+    /// No queue family of the devices supports compute, see [`Hardware::compute_queue`].
+    NoComputeQueue,
+    Pipeline(ComputePipelineCreationError),
+    /// The descriptor writes don't match the descriptor set 0 of the shader.
+    DescriptorSet(DescriptorSetCreationError),
+    Dispatch(DispatchError),
+    Execution(CommandBufferExecError),
+    Flush(FlushError),
+}
+
+impl fmt::Display for ComputeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ComputeError::NoComputeQueue => write!(f, "no queue supports compute"),
+            ComputeError::Pipeline(e) => write!(f, "couldn't create the compute pipeline: {}", e),
+            ComputeError::DescriptorSet(e) => write!(f, "couldn't create the descriptor set: {}", e),
+            ComputeError::Dispatch(e) => write!(f, "couldn't record the dispatch: {}", e),
+            ComputeError::Execution(e) => write!(f, "couldn't execute the dispatch: {}", e),
+            ComputeError::Flush(e) => write!(f, "couldn't wait for the dispatch: {}", e),
+        }
+    }
+}
+
+impl Error for ComputeError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ComputeError::Pipeline(e) => Some(e),
+            ComputeError::DescriptorSet(e) => Some(e),
+            ComputeError::Dispatch(e) => Some(e),
+            ComputeError::Execution(e) => Some(e),
+            ComputeError::Flush(e) => Some(e),
+            ComputeError::NoComputeQueue => None,
+        }
+    }
+}
+
+impl From<ComputePipelineCreationError> for ComputeError {
+    fn from(e: ComputePipelineCreationError) -> Self {
+        ComputeError::Pipeline(e)
+    }
+}
+
+impl From<DescriptorSetCreationError> for ComputeError {
+    fn from(e: DescriptorSetCreationError) -> Self {
+        ComputeError::DescriptorSet(e)
+    }
+}
+
+impl From<DispatchError> for ComputeError {
+    fn from(e: DispatchError) -> Self {
+        ComputeError::Dispatch(e)
+    }
+}
+
+impl From<CommandBufferExecError> for ComputeError {
+    fn from(e: CommandBufferExecError) -> Self {
+        ComputeError::Execution(e)
+    }
+}
+
+impl From<FlushError> for ComputeError {
+    fn from(e: FlushError) -> Self {
+        ComputeError::Flush(e)
+    }
+}
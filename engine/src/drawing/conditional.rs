@@ -0,0 +1,201 @@
+use std::error::Error;
+use std::fmt;
+use std::ptr;
+use std::sync::Arc;
+
+use vulkano::command_buffer::sys::UnsafeCommandBufferBuilder;
+use vulkano::device::Device;
+use vulkano::VulkanObject;
+
+/// The 32-bit predicate of `VK_EXT_conditional_rendering`: the draws and dispatches recorded between
+/// [`ConditionBuffer::begin`] and [`ConditionBuffer::end`] are skipped while its value is zero.
+///
+/// vulkano doesn't know the conditional rendering buffer usage, so the buffer and its host-visible memory are
+/// created through the raw Vulkan functions of the device, and the commands are recorded into an
+/// `UnsafeCommandBufferBuilder`.
+pub struct ConditionBuffer {
+    device: Arc<Device>,
+    buffer: ash::vk::Buffer,
+    memory: ash::vk::DeviceMemory,
+    value: *mut u32,
+}
+
+// The mapped value is only written through `&mut self`
+unsafe impl Send for ConditionBuffer {}
+unsafe impl Sync for ConditionBuffer {}
+
+impl ConditionBuffer {
+    /// Creates the predicate, initially letting the draws through.
+    ///
+    /// The extension and the `conditional_rendering` feature are enabled by
+    /// [`Hardware`](crate::drawing::hardware::Hardware) when the graphics device supports them,
+    /// see [`Hardware::supports_conditional_rendering`](crate::drawing::hardware::Hardware::supports_conditional_rendering).
+    pub fn new(device: &Arc<Device>) -> Result<Self, ConditionalError> {
+        if !device.enabled_extensions().ext_conditional_rendering || !device.enabled_features().conditional_rendering {
+            return Err(ConditionalError::NotEnabled);
+        }
+
+        let handle = device.internal_object();
+        let fns = &device.fns().v1_0;
+        let size = std::mem::size_of::<u32>() as ash::vk::DeviceSize;
+
+        unsafe {
+            let info = ash::vk::BufferCreateInfo {
+                size,
+                usage: ash::vk::BufferUsageFlags::CONDITIONAL_RENDERING_EXT,
+                sharing_mode: ash::vk::SharingMode::EXCLUSIVE,
+                ..Default::default()
+            };
+            let mut buffer = ash::vk::Buffer::null();
+            check((fns.create_buffer)(handle, &info, ptr::null(), &mut buffer))?;
+
+            let mut requirements = ash::vk::MemoryRequirements::default();
+            (fns.get_buffer_memory_requirements)(handle, buffer, &mut requirements);
+            let memory_type = device
+                .physical_device()
+                .memory_types()
+                .find(|ty| {
+                    requirements.memory_type_bits & (1 << ty.id()) != 0
+                        && ty.is_host_visible()
+                        && ty.is_host_coherent()
+                });
+            let memory_type = match memory_type {
+                Some(ty) => ty.id(),
+                None => {
+                    (fns.destroy_buffer)(handle, buffer, ptr::null());
+                    return Err(ConditionalError::NoHostVisibleMemory);
+                }
+            };
+
+            let info = ash::vk::MemoryAllocateInfo {
+                allocation_size: requirements.size,
+                memory_type_index: memory_type,
+                ..Default::default()
+            };
+            let mut memory = ash::vk::DeviceMemory::null();
+            if let Err(e) = check((fns.allocate_memory)(handle, &info, ptr::null(), &mut memory)) {
+                (fns.destroy_buffer)(handle, buffer, ptr::null());
+                return Err(e);
+            }
+
+            let mut value = ptr::null_mut();
+            let flags = ash::vk::MemoryMapFlags::empty();
+            let mapped = check((fns.bind_buffer_memory)(handle, buffer, memory, 0))
+                .and_then(|_| check((fns.map_memory)(handle, memory, 0, size, flags, &mut value)));
+            if let Err(e) = mapped {
+                (fns.destroy_buffer)(handle, buffer, ptr::null());
+                (fns.free_memory)(handle, memory, ptr::null());
+                return Err(e);
+            }
+
+            let value = value as *mut u32;
+            value.write(1);
+
+            Ok(ConditionBuffer {
+                device: Arc::clone(device),
+                buffer,
+                memory,
+                value,
+            })
+        }
+    }
+
+    /// Lets the conditional draws through, or skips them.
+    ///
+    /// The memory is coherent, so the value is seen by the command buffers submitted after the call.
+    /// Command buffers already executing may read either value.
+    pub fn set(&mut self, enabled: bool) {
+        unsafe { self.value.write_volatile(enabled as u32) }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        unsafe { self.value.read_volatile() != 0 }
+    }
+
+    /// Starts a conditional block: until [`ConditionBuffer::end`], the draws and dispatches recorded into `builder`
+    /// are skipped if the predicate is zero, or if it isn't zero when `inverted`.
+    ///
+    /// # Safety
+    ///
+    /// - `builder` must belong to the device of the predicate;
+    /// - `builder` must not already be inside a conditional block;
+    /// - the block must be ended in the same subpass it was started in, or outside a render pass if it was started
+    ///   outside one;
+    /// - the predicate must not be dropped before the command buffer finishes executing.
+    pub unsafe fn begin(&self, builder: &mut UnsafeCommandBufferBuilder, inverted: bool) {
+        let flags = if inverted {
+            ash::vk::ConditionalRenderingFlagsEXT::INVERTED
+        } else {
+            ash::vk::ConditionalRenderingFlagsEXT::empty()
+        };
+        let info = ash::vk::ConditionalRenderingBeginInfoEXT {
+            buffer: self.buffer,
+            offset: 0,
+            flags,
+            ..Default::default()
+        };
+        (self.device.fns().ext_conditional_rendering.cmd_begin_conditional_rendering_ext)(
+            builder.internal_object(),
+            &info,
+        );
+    }
+
+    /// Ends the conditional block started by [`ConditionBuffer::begin`].
+    ///
+    /// # Safety
+    ///
+    /// - `builder` must be inside a conditional block, started in the current subpass or outside a render pass.
+    pub unsafe fn end(&self, builder: &mut UnsafeCommandBufferBuilder) {
+        (self.device.fns().ext_conditional_rendering.cmd_end_conditional_rendering_ext)(builder.internal_object());
+    }
+}
+
+impl Drop for ConditionBuffer {
+    fn drop(&mut self) {
+        let handle = self.device.internal_object();
+        let fns = &self.device.fns().v1_0;
+        unsafe {
+            (fns.unmap_memory)(handle, self.memory);
+            (fns.destroy_buffer)(handle, self.buffer, ptr::null());
+            (fns.free_memory)(handle, self.memory, ptr::null());
+        }
+    }
+}
+
+fn check(result: ash::vk::Result) -> Result<(), ConditionalError> {
+    match result {
+        ash::vk::Result::SUCCESS => Ok(()),
+        error => Err(ConditionalError::Vulkan(error)),
+    }
+}
+
+#[derive(Debug)]
+pub enum ConditionalError {
+    /// The device wasn't created with `VK_EXT_conditional_rendering` and its `conditional_rendering` feature.
+    NotEnabled,
+    /// None of the host-visible and coherent memory types can hold the predicate.
+    NoHostVisibleMemory,
+    /// Creating the buffer or its memory failed.
+    Vulkan(ash::vk::Result),
+}
+
+impl fmt::Display for ConditionalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConditionalError::NotEnabled => write!(f, "conditional rendering isn't enabled on the device"),
+            ConditionalError::NoHostVisibleMemory => {
+                write!(f, "no host-visible memory type can hold the conditional rendering predicate")
+            }
+            ConditionalError::Vulkan(e) => write!(f, "couldn't create the conditional rendering predicate: {}", e),
+        }
+    }
+}
+
+impl Error for ConditionalError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ConditionalError::Vulkan(e) => Some(e),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,396 @@
+use std::sync::{Arc, Mutex};
+
+use vulkano::command_buffer::{AutoCommandBufferBuilder, PrimaryAutoCommandBuffer, SubpassContents};
+use vulkano::descriptor_set::{PersistentDescriptorSet, WriteDescriptorSet};
+use vulkano::format::{ClearValue, Format};
+use vulkano::image::view::ImageView;
+use vulkano::image::{AttachmentImage, ImageViewAbstract};
+use vulkano::pipeline::graphics::color_blend::AttachmentBlend;
+use vulkano::pipeline::graphics::viewport::Viewport;
+use vulkano::pipeline::{GraphicsPipeline, Pipeline, PipelineBindPoint};
+use vulkano::render_pass::{Framebuffer, FramebufferCreateInfo, RenderPass, Subpass};
+
+use crate::drawing::camera;
+use crate::drawing::fullscreen;
+use crate::drawing::hardware::Hardware;
+
+/// The format of the albedo in the G-buffer.
+pub const ALBEDO_FORMAT: Format = Format::R8G8B8A8_UNORM;
+
+/// The format of the world-space normals in the G-buffer.
+pub const NORMAL_FORMAT: Format = Format::R16G16B16A16_SFLOAT;
+
+/// A light accumulated by the [`Deferred`] renderer.
+///
+/// Colors are linear, and already multiplied by the intensity of the light.
+/// The forward renderer uses [`Light`](crate::drawing::lights::Light) instead, which has spot lights but no ambient light.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DeferredLight {
+    /// Lights every surface evenly, regardless of its orientation.
+    Ambient { color: [f32; 3] },
+    /// A light infinitely far away, such as the sun, shining towards `direction`.
+    Directional { direction: [f32; 3], color: [f32; 3] },
+    /// A light shining in every direction from `position`, fading out until `range`.
+    Point { position: [f32; 3], color: [f32; 3], range: f32 },
+}
+
+impl DeferredLight {
+    fn constants(&self, inverse_view_projection: [[f32; 4]; 4]) -> fs::ty::Light {
+        let (position, color) = match *self {
+            DeferredLight::Ambient { color } => ([0.0, 0.0, 0.0, 0.0], [color[0], color[1], color[2], 0.0]),
+            DeferredLight::Directional { direction, color } => (
+                [direction[0], direction[1], direction[2], 1.0],
+                [color[0], color[1], color[2], 0.0],
+            ),
+            DeferredLight::Point { position, color, range } => (
+                [position[0], position[1], position[2], 2.0],
+                [color[0], color[1], color[2], range],
+            ),
+        };
+
+        fs::ty::Light {
+            inverse_view_projection,
+            position,
+            color,
+        }
+    }
+}
+
+/// The G-buffer, sized for the frames it is used with.
+struct GBuffer {
+    extent: [u32; 2],
+    albedo: Arc<ImageView<AttachmentImage>>,
+    normal: Arc<ImageView<AttachmentImage>>,
+    depth: Arc<ImageView<AttachmentImage>>,
+    descriptor_set: Arc<PersistentDescriptorSet>,
+    // The framebuffers of the G-buffer pass, with the output they were created for
+    framebuffers: Vec<(Arc<dyn ImageViewAbstract>, Arc<Framebuffer>)>,
+}
+
+/// A deferred renderer: the geometry is drawn once into a G-buffer, which is then lit by each light in turn.
+///
+/// The geometry pass writes the albedo (location 0) and the world-space normal (location 1) of the surfaces,
+/// and the depth. Pipelines drawing the geometry must be created for [`Deferred::geometry_subpass`],
+/// with depth testing enabled:
+/// ```glsl
+/// layout(location = 0) out vec4 g_albedo;
+/// layout(location = 1) out vec4 g_normal;
+/// ```
+/// The lighting pass reads the G-buffer as input attachments, and adds the contribution of each light
+/// to the frame. Pixels no geometry was drawn to keep the background color.
+pub struct Deferred {
+    hardware: Arc<Hardware>,
+    deferred_pass: Arc<RenderPass>,
+    render_pass: Arc<RenderPass>,
+    pipeline: Arc<GraphicsPipeline>,
+    gbuffer: Mutex<Option<GBuffer>>,
+    lights: Mutex<Vec<DeferredLight>>,
+    background: Mutex<[f32; 4]>,
+}
+
+impl Deferred {
+    /// Prepares a deferred renderer writing to images of `output_format`.
+    pub fn new(hardware: Arc<Hardware>, output_format: Format) -> Self {
+        let device = hardware.graphics_device();
+
+        let depth_supported = device
+            .physical_device()
+            .format_properties(Format::D32_SFLOAT)
+            .optimal_tiling_features
+            .depth_stencil_attachment;
+        let depth_format = if depth_supported { Format::D32_SFLOAT } else { Format::D16_UNORM };
+
+        let deferred_pass = vulkano::ordered_passes_renderpass!(
+            Arc::clone(device),
+            attachments: {
+                output: {
+                    load: Clear,
+                    store: Store,
+                    format: output_format,
+                    samples: 1,
+                },
+                albedo: {
+                    load: Clear,
+                    store: DontCare,
+                    format: ALBEDO_FORMAT,
+                    samples: 1,
+                },
+                normal: {
+                    load: Clear,
+                    store: DontCare,
+                    format: NORMAL_FORMAT,
+                    samples: 1,
+                },
+                depth: {
+                    load: Clear,
+                    store: DontCare,
+                    format: depth_format,
+                    samples: 1,
+                }
+            },
+            passes: [
+                {
+                    color: [albedo, normal],
+                    depth_stencil: {depth},
+                    input: []
+                },
+                {
+                    color: [output],
+                    depth_stencil: {},
+                    input: [albedo, normal, depth]
+                }
+            ]
+        )
+            .expect("Couldn't create the deferred render pass");
+
+        let render_pass = vulkano::single_pass_renderpass!(
+            Arc::clone(device),
+            attachments: {
+                color: {
+                    load: DontCare,
+                    store: Store,
+                    format: output_format,
+                    samples: 1,
+                }
+            },
+            pass: {
+                color: [color],
+                depth_stencil: {}
+            }
+        )
+            .expect("Couldn't create the deferred output render pass");
+
+        let fs = fs::load(Arc::clone(device)).expect("Couldn't load the deferred lighting shader");
+        let pipeline = fullscreen::blended_pipeline(
+            device,
+            fs.entry_point("main").unwrap(),
+            Subpass::from(Arc::clone(&deferred_pass), 1).unwrap(),
+            AttachmentBlend::additive(),
+        );
+
+        Deferred {
+            hardware,
+            deferred_pass,
+            render_pass,
+            pipeline,
+            gbuffer: Mutex::new(None),
+            lights: Mutex::new(Vec::new()),
+            background: Mutex::new([0.0, 0.0, 0.0, 1.0]),
+        }
+    }
+
+    /// The render pass the frames given to [`Deferred::render`] must be compatible with.
+    ///
+    /// Pass it to [`Engine::run`](crate::drawing::engine::Engine::run) to render straight to the screen.
+    pub fn render_pass(&self) -> &Arc<RenderPass> {
+        &self.render_pass
+    }
+
+    /// The subpass the pipelines drawing the geometry must be created for.
+    pub fn geometry_subpass(&self) -> Subpass {
+        Subpass::from(Arc::clone(&self.deferred_pass), 0).unwrap()
+    }
+
+    /// Lights the next frames with `light`, in addition to the current lights.
+    pub fn add_light(&self, light: DeferredLight) {
+        self.lock_lights().push(light);
+    }
+
+    pub fn clear_lights(&self) {
+        self.lock_lights().clear();
+    }
+
+    pub fn lights(&self) -> Vec<DeferredLight> {
+        self.lock_lights().clone()
+    }
+
+    /// The color of the pixels no geometry is drawn to.
+    pub fn set_background(&self, color: [f32; 4]) {
+        *self.background.lock().expect("The deferred background was poisoned") = color;
+    }
+
+    /// Records the geometry pass, by calling `geometry`, then the lighting pass, writing the whole of `frame`.
+    ///
+    /// `view_projection` is the matrix the geometry was drawn with,
+    /// used to find the position of each pixel lit by point lights. They are skipped if it isn't invertible.
+    pub fn render<G>(
+        &self,
+        builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        frame: &Arc<Framebuffer>,
+        viewport: &Viewport,
+        view_projection: [[f32; 4]; 4],
+        geometry: G,
+    ) where
+        G: FnOnce(&mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>),
+    {
+        let (framebuffer, descriptor_set) = self.framebuffer(frame);
+        let background = *self.background.lock().expect("The deferred background was poisoned");
+        let inverse_view_projection = camera::invert(&view_projection);
+
+        builder
+            .begin_render_pass(
+                framebuffer,
+                SubpassContents::Inline,
+                [
+                    ClearValue::Float(background),
+                    ClearValue::Float([0.0; 4]),
+                    ClearValue::Float([0.0; 4]),
+                    ClearValue::Depth(1.0),
+                ],
+            )
+            .expect("Couldn't begin the deferred geometry pass")
+            .set_viewport(0, [viewport.clone()]);
+        geometry(builder);
+
+        builder
+            .next_subpass(SubpassContents::Inline)
+            .expect("Couldn't begin the deferred lighting pass")
+            .set_viewport(0, [viewport.clone()])
+            .bind_pipeline_graphics(Arc::clone(&self.pipeline))
+            .bind_descriptor_sets(
+                PipelineBindPoint::Graphics,
+                Arc::clone(self.pipeline.layout()),
+                0,
+                descriptor_set,
+            );
+        for light in self.lock_lights().iter() {
+            // Point lights are placed by unprojecting the depth, which a singular view-projection doesn't allow
+            let inverse_view_projection = match (inverse_view_projection, light) {
+                (Some(inverse), _) => inverse,
+                (None, DeferredLight::Point { .. }) => continue,
+                (None, _) => [[0.0; 4]; 4],
+            };
+            builder.push_constants(
+                Arc::clone(self.pipeline.layout()),
+                0,
+                light.constants(inverse_view_projection),
+            );
+            fullscreen::draw(builder);
+        }
+        builder
+            .end_render_pass()
+            .expect("Couldn't end the deferred lighting pass");
+    }
+
+    /// The framebuffer rendering into `frame` through the G-buffer, which is recreated when the size of the frames changes.
+    fn framebuffer(&self, frame: &Arc<Framebuffer>) -> (Arc<Framebuffer>, Arc<PersistentDescriptorSet>) {
+        let output = Arc::clone(&frame.attachments()[0]);
+        let extent = frame.extent();
+
+        let mut gbuffer = self.gbuffer.lock().expect("The G-buffer was poisoned");
+        if gbuffer.as_ref().map(|gbuffer| gbuffer.extent) != Some(extent) {
+            *gbuffer = Some(self.create_gbuffer(extent));
+        }
+        let gbuffer = gbuffer.as_mut().unwrap();
+
+        let existing = gbuffer
+            .framebuffers
+            .iter()
+            .find(|(view, _)| Arc::as_ptr(view) as *const () == Arc::as_ptr(&output) as *const ());
+        if let Some((_, framebuffer)) = existing {
+            return (Arc::clone(framebuffer), Arc::clone(&gbuffer.descriptor_set));
+        }
+
+        let framebuffer = Framebuffer::new(
+            Arc::clone(&self.deferred_pass),
+            FramebufferCreateInfo {
+                attachments: vec![
+                    Arc::clone(&output),
+                    Arc::clone(&gbuffer.albedo) as _,
+                    Arc::clone(&gbuffer.normal) as _,
+                    Arc::clone(&gbuffer.depth) as _,
+                ],
+                ..Default::default()
+            },
+        )
+            .expect("Couldn't create the deferred framebuffer");
+        gbuffer.framebuffers.push((output, Arc::clone(&framebuffer)));
+
+        (framebuffer, Arc::clone(&gbuffer.descriptor_set))
+    }
+
+    fn create_gbuffer(&self, extent: [u32; 2]) -> GBuffer {
+        let device = self.hardware.graphics_device();
+        let attachment = |format| {
+            let image = AttachmentImage::transient_input_attachment(Arc::clone(device), extent, format)
+                .expect("Couldn't create the G-buffer");
+            ImageView::new_default(image).expect("Couldn't create the G-buffer view")
+        };
+
+        let depth_format = self.deferred_pass.attachments()[3].format.unwrap();
+        let albedo = attachment(ALBEDO_FORMAT);
+        let normal = attachment(NORMAL_FORMAT);
+        let depth = attachment(depth_format);
+
+        let descriptor_set = PersistentDescriptorSet::new(
+            Arc::clone(&self.pipeline.layout().set_layouts()[0]),
+            [
+                WriteDescriptorSet::image_view(0, Arc::clone(&albedo) as _),
+                WriteDescriptorSet::image_view(1, Arc::clone(&normal) as _),
+                WriteDescriptorSet::image_view(2, Arc::clone(&depth) as _),
+            ],
+        )
+            .expect("Couldn't create the deferred lighting descriptor set");
+
+        GBuffer {
+            extent,
+            albedo,
+            normal,
+            depth,
+            descriptor_set,
+            framebuffers: Vec::new(),
+        }
+    }
+
+    fn lock_lights(&self) -> std::sync::MutexGuard<'_, Vec<DeferredLight>> {
+        self.lights.lock().expect("The deferred lights were poisoned")
+    }
+}
+
+#[allow(clippy::needless_question_mark)]
+mod fs {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        src: "
+#version 450
+layout(location = 0) in vec2 uv;
+layout(location = 0) out vec4 f_color;
+
+layout(input_attachment_index = 0, set = 0, binding = 0) uniform subpassInput g_albedo;
+layout(input_attachment_index = 1, set = 0, binding = 1) uniform subpassInput g_normal;
+layout(input_attachment_index = 2, set = 0, binding = 2) uniform subpassInput g_depth;
+
+// position.w: 0 for ambient lights, 1 for directional lights, 2 for point lights
+// color.a: the range of point lights
+layout(push_constant) uniform Light {
+    mat4 inverse_view_projection;
+    vec4 position;
+    vec4 color;
+} light;
+
+void main() {
+    float depth = subpassLoad(g_depth).r;
+    if (depth >= 1.0) {
+        discard;
+    }
+
+    vec3 albedo = subpassLoad(g_albedo).rgb;
+    vec3 normal = normalize(subpassLoad(g_normal).xyz);
+
+    vec3 radiance;
+    if (light.position.w < 0.5) {
+        radiance = light.color.rgb;
+    } else if (light.position.w < 1.5) {
+        radiance = light.color.rgb * max(dot(normal, -normalize(light.position.xyz)), 0.0);
+    } else {
+        vec4 world = light.inverse_view_projection * vec4(uv * 2.0 - 1.0, depth, 1.0);
+        vec3 to_light = light.position.xyz - world.xyz / world.w;
+        float distance = length(to_light);
+        float attenuation = clamp(1.0 - distance / light.color.a, 0.0, 1.0);
+        radiance = light.color.rgb * max(dot(normal, to_light / distance), 0.0) * attenuation * attenuation;
+    }
+
+    f_color = vec4(albedo * radiance, 0.0);
+}"
+    }
+}
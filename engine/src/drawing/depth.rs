@@ -0,0 +1,60 @@
+use bytemuck::{Pod, Zeroable};
+
+/// How depth is distributed between the near and the far planes.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum DepthMode {
+    /// The depth written by the projection matrix: precise close to the camera, very imprecise far from it.
+    #[default]
+    Standard,
+    /// The depth is computed by the shaders from the logarithm of the distance to the camera,
+    /// which keeps a usable precision over planetary distances.
+    ///
+    /// `c` trades precision close to the camera for precision far from it; 1 is a good start.
+    /// The shaders must follow the contract of [`LOGARITHMIC_DEPTH_GLSL`].
+    Logarithmic { c: f32 },
+}
+
+impl DepthMode {
+    /// The constants the shaders need to compute the depth, for a scene extending up to `far`.
+    ///
+    /// With [`DepthMode::Standard`], the shaders shouldn't touch the depth: the constants are zero.
+    pub fn constants(&self, far: f32) -> DepthConstants {
+        match *self {
+            DepthMode::Standard => DepthConstants::default(),
+            DepthMode::Logarithmic { c } => DepthConstants {
+                c,
+                coefficient: 1.0 / (c * far + 1.0).log2(),
+            },
+        }
+    }
+}
+
+/// The constants of the logarithmic depth, laid out to be copied into a uniform buffer or push constants
+/// declared as `{ float c; float coefficient; }`.
+#[repr(C)]
+#[derive(Default, Copy, Clone, Debug, PartialEq, Pod, Zeroable)]
+pub struct DepthConstants {
+    pub c: f32,
+    /// `1 / log2(c * far + 1)`
+    pub coefficient: f32,
+}
+
+/// GLSL functions computing the logarithmic depth, to paste in the shaders before `main`.
+///
+/// The contract:
+/// - the vertex shader calls `log_depth_vertex(gl_Position.w, c)` and passes the result to the fragment shader,
+///   with the same location in both;
+/// - the fragment shader writes `gl_FragDepth = log_depth_fragment(value, coefficient)` with the value it received;
+/// - `c` and `coefficient` come from [`DepthMode::constants`];
+/// - the depth test uses `Less` or `LessOrEqual`, and the depth attachment is cleared to 1.
+///
+/// Writing `gl_FragDepth` disables early depth tests, so overdraw costs more than with standard depth.
+pub const LOGARITHMIC_DEPTH_GLSL: &str = "
+float log_depth_vertex(float w, float c) {
+    return 1.0 + c * w;
+}
+
+float log_depth_fragment(float value, float coefficient) {
+    return log2(max(value, 1e-6)) * coefficient;
+}
+";
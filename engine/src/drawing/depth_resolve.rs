@@ -0,0 +1,210 @@
+use std::error::Error;
+use std::fmt;
+use std::sync::Arc;
+
+use vulkano::command_buffer::{AutoCommandBufferBuilder, PrimaryAutoCommandBuffer, SubpassContents};
+use vulkano::descriptor_set::{PersistentDescriptorSet, WriteDescriptorSet};
+use vulkano::format::ClearValue;
+use vulkano::image::{ImageLayout, ImageViewAbstract, SampleCount};
+use vulkano::pipeline::graphics::viewport::Viewport;
+use vulkano::pipeline::{GraphicsPipeline, Pipeline, PipelineBindPoint};
+use vulkano::render_pass::{Framebuffer, LoadOp, RenderPass, StoreOp, Subpass};
+use vulkano::sampler::{Sampler, SamplerCreateInfo};
+
+use crate::drawing::fullscreen;
+use crate::drawing::hardware::Hardware;
+use crate::drawing::render_pass::{Attachment, RenderPassBuilder};
+
+/// How the samples of a pixel are combined into a single depth.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DepthResolveMode {
+    /// The depth of the first sample.
+    #[default]
+    SampleZero,
+    Average,
+    /// The closest sample, with a standard depth range.
+    Min,
+    /// The farthest sample, with a standard depth range.
+    Max,
+}
+
+impl DepthResolveMode {
+    fn id(self) -> u32 {
+        match self {
+            DepthResolveMode::SampleZero => 0,
+            DepthResolveMode::Average => 1,
+            DepthResolveMode::Min => 2,
+            DepthResolveMode::Max => 3,
+        }
+    }
+}
+
+/// Resolves a multisampled depth image into a single-sampled one, for example to run SSAO on an MSAA scene.
+///
+/// `VK_KHR_depth_stencil_resolve` can't be used through vulkano 0.29, which has no way to add a depth resolve
+/// attachment to a subpass: instead, a fullscreen pass reads each sample and writes the combined depth.
+/// It works on every device, at the cost of an extra pass.
+pub struct DepthResolve {
+    render_pass: Arc<RenderPass>,
+    pipeline: Arc<GraphicsPipeline>,
+    descriptor_set: Arc<PersistentDescriptorSet>,
+    samples: SampleCount,
+    mode: DepthResolveMode,
+}
+
+impl DepthResolve {
+    /// Prepares a pass resolving the multisampled depth image `source`.
+    ///
+    /// `source` must be in the `ShaderReadOnlyOptimal` or `DepthStencilReadOnlyOptimal` layout when the pass runs:
+    /// the depth attachment of the pass that rendered it must be stored, and end in one of these layouts.
+    /// The resolved image ends in the `ShaderReadOnlyOptimal` layout.
+    pub fn new(
+        hardware: &Hardware,
+        source: Arc<dyn ImageViewAbstract>,
+        mode: DepthResolveMode,
+    ) -> Result<Self, DepthResolveError> {
+        let device = hardware.graphics_device();
+
+        let format = source.format().ok_or(DepthResolveError::NotDepth)?;
+        if !format.aspects().depth {
+            return Err(DepthResolveError::NotDepth);
+        }
+        let samples = source.image().samples();
+        if samples == SampleCount::Sample1 {
+            return Err(DepthResolveError::NotMultisampled);
+        }
+
+        let render_pass = RenderPassBuilder::new()
+            .attachment(
+                Attachment::depth(format)
+                    .load(LoadOp::DontCare)
+                    .store(StoreOp::Store)
+                    .layouts(ImageLayout::Undefined, ImageLayout::ShaderReadOnlyOptimal),
+            )
+            .build(Arc::clone(device))
+            .expect("Couldn't create the depth resolve render pass");
+
+        let fs = fs::load(Arc::clone(device)).expect("Couldn't load the depth resolve shader");
+        let pipeline = fullscreen::depth_pipeline(
+            device,
+            fs.entry_point("main").unwrap(),
+            Subpass::from(Arc::clone(&render_pass), 0).unwrap(),
+        );
+
+        // Multisampled images are only read with texelFetch, the sampler is never used for filtering
+        let sampler = Sampler::new(Arc::clone(device), SamplerCreateInfo::default())
+            .expect("Couldn't create the depth resolve sampler");
+
+        let descriptor_set = PersistentDescriptorSet::new(
+            Arc::clone(&pipeline.layout().set_layouts()[0]),
+            [WriteDescriptorSet::image_view_sampler(0, source, sampler)],
+        )
+            .expect("Couldn't create the depth resolve descriptor set");
+
+        Ok(DepthResolve {
+            render_pass,
+            pipeline,
+            descriptor_set,
+            samples,
+            mode,
+        })
+    }
+
+    /// The render pass the frames given to [`DepthResolve::resolve`] must be compatible with:
+    /// their single attachment is a single-sampled image of the format of the source.
+    pub fn render_pass(&self) -> &Arc<RenderPass> {
+        &self.render_pass
+    }
+
+    pub fn mode(&self) -> DepthResolveMode {
+        self.mode
+    }
+
+    /// Records the resolve pass, writing the whole of `frame`.
+    pub fn resolve(
+        &self,
+        builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        frame: &Arc<Framebuffer>,
+        viewport: &Viewport,
+    ) {
+        let parameters = fs::ty::Parameters {
+            samples: self.samples as u32,
+            mode: self.mode.id(),
+        };
+
+        builder
+            .begin_render_pass(Arc::clone(frame), SubpassContents::Inline, [ClearValue::None])
+            .expect("Couldn't begin the depth resolve pass")
+            .set_viewport(0, [viewport.clone()])
+            .bind_pipeline_graphics(Arc::clone(&self.pipeline))
+            .bind_descriptor_sets(
+                PipelineBindPoint::Graphics,
+                Arc::clone(self.pipeline.layout()),
+                0,
+                Arc::clone(&self.descriptor_set),
+            )
+            .push_constants(Arc::clone(self.pipeline.layout()), 0, parameters);
+        fullscreen::draw(builder);
+        builder
+            .end_render_pass()
+            .expect("Couldn't end the depth resolve pass");
+    }
+}
+
+#[derive(Debug)]
+pub enum DepthResolveError {
+    /// The source image doesn't have a depth format.
+    NotDepth,
+    /// The source image has a single sample, there is nothing to resolve.
+    NotMultisampled,
+}
+
+impl fmt::Display for DepthResolveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DepthResolveError::NotDepth => write!(f, "the source image doesn't have a depth format"),
+            DepthResolveError::NotMultisampled => write!(f, "the source image isn't multisampled"),
+        }
+    }
+}
+
+impl Error for DepthResolveError {}
+
+#[allow(clippy::needless_question_mark)]
+mod fs {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        src: "
+#version 450
+layout(set = 0, binding = 0) uniform sampler2DMS source;
+
+layout(push_constant) uniform Parameters {
+    uint samples;
+    uint mode;
+} parameters;
+
+void main() {
+    ivec2 texel = ivec2(gl_FragCoord.xy);
+    float depth = texelFetch(source, texel, 0).r;
+
+    if (parameters.mode != 0) {
+        for (int i = 1; i < int(parameters.samples); i++) {
+            float current = texelFetch(source, texel, i).r;
+            if (parameters.mode == 1) {
+                depth += current;
+            } else if (parameters.mode == 2) {
+                depth = min(depth, current);
+            } else {
+                depth = max(depth, current);
+            }
+        }
+
+        if (parameters.mode == 1) {
+            depth /= float(parameters.samples);
+        }
+    }
+
+    gl_FragDepth = depth;
+}"
+    }
+}
@@ -0,0 +1,276 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use log::{debug, error, warn};
+use vulkano::descriptor_set::layout::{DescriptorSetLayout, DescriptorType};
+use vulkano::descriptor_set::pool::{
+    DescriptorPool, DescriptorPoolAlloc, DescriptorSetAllocateInfo,
+    UnsafeDescriptorPool, UnsafeDescriptorPoolCreateInfo,
+};
+use vulkano::descriptor_set::sys::UnsafeDescriptorSet;
+use vulkano::device::{Device, DeviceOwned};
+use vulkano::OomError;
+
+/// The number of sets of each block, unless configured otherwise.
+const DEFAULT_SETS_PER_BLOCK: u32 = 64;
+
+/// How full the allocator can get before warnings are logged, when it is limited.
+const WARNING_RATIO: f32 = 0.9;
+
+/// A snapshot of the usage of a [`DescriptorAllocator`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DescriptorPoolStats {
+    /// The descriptor sets currently alive.
+    pub allocated_sets: u32,
+    /// The number of Vulkan descriptor pools created so far.
+    pub blocks: usize,
+    /// The number of sets the existing blocks can hold, whatever the descriptors they need.
+    pub capacity: u32,
+}
+
+/// A descriptor pool that grows by blocks instead of failing when it is full.
+///
+/// Like vulkano's standard pool, each block is a Vulkan descriptor pool, created when no existing block
+/// has room for a set; unlike it, the size of the blocks is configurable, their number can be limited,
+/// and the allocator reports how many sets are alive.
+/// Pass it to `PersistentDescriptorSet::new_with_pool`, or to [`DescriptorSetBuilder::build_in`](crate::drawing::descriptors::DescriptorSetBuilder::build_in).
+/// Clones share the same blocks.
+#[derive(Clone)]
+pub struct DescriptorAllocator {
+    shared: Arc<Shared>,
+}
+
+struct Shared {
+    device: Arc<Device>,
+    blocks: Mutex<Vec<Arc<Mutex<Block>>>>,
+    sets_per_block: AtomicU32,
+    // usize::MAX if there is no limit
+    max_blocks: AtomicUsize,
+    allocated_sets: AtomicU32,
+}
+
+struct Block {
+    pool: UnsafeDescriptorPool,
+    remaining_sets: u32,
+    remaining_descriptors: HashMap<DescriptorType, u32>,
+}
+
+impl DescriptorAllocator {
+    pub fn new(device: Arc<Device>) -> Self {
+        DescriptorAllocator {
+            shared: Arc::new(Shared {
+                device,
+                blocks: Mutex::new(Vec::new()),
+                sets_per_block: AtomicU32::new(DEFAULT_SETS_PER_BLOCK),
+                max_blocks: AtomicUsize::new(usize::MAX),
+                allocated_sets: AtomicU32::new(0),
+            }),
+        }
+    }
+
+    /// The number of sets each new block can hold. Existing blocks aren't affected.
+    ///
+    /// Larger blocks mean fewer Vulkan pools, but more memory reserved up front. Blocks hold at least one set.
+    pub fn set_sets_per_block(&self, sets: u32) -> Result<(), DescriptorAllocatorError> {
+        if sets == 0 {
+            return Err(DescriptorAllocatorError::EmptyBlocks);
+        }
+        self.shared.sets_per_block.store(sets, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Limits the number of blocks; once they are all full, allocations fail. `None` removes the limit.
+    ///
+    /// A warning is logged when the limit is about to be reached.
+    pub fn set_max_blocks(&self, max: Option<usize>) {
+        self.shared.max_blocks.store(max.unwrap_or(usize::MAX), Ordering::Relaxed);
+    }
+
+    pub fn stats(&self) -> DescriptorPoolStats {
+        let blocks = self.lock();
+        DescriptorPoolStats {
+            allocated_sets: self.shared.allocated_sets.load(Ordering::Relaxed),
+            blocks: blocks.len(),
+            capacity: blocks
+                .iter()
+                .map(|block| block.lock().expect("A descriptor pool block was poisoned").pool.max_sets())
+                .sum(),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<Arc<Mutex<Block>>>> {
+        self.shared.blocks.lock().expect("The descriptor pool was poisoned")
+    }
+
+    fn allocate_in(
+        &self,
+        block: &Arc<Mutex<Block>>,
+        layout: &DescriptorSetLayout,
+        variable_descriptor_count: u32,
+    ) -> Option<DescriptorAllocation> {
+        let mut locked = block.lock().expect("A descriptor pool block was poisoned");
+        let fits = locked.remaining_sets > 0
+            && layout.descriptor_counts().iter().all(|(ty, &count)| {
+                locked.remaining_descriptors.get(ty).copied().unwrap_or_default() >= count
+            });
+        if !fits {
+            return None;
+        }
+
+        let set = unsafe {
+            locked.pool.allocate_descriptor_sets([DescriptorSetAllocateInfo {
+                layout,
+                variable_descriptor_count,
+            }])
+        };
+        // A fragmented block is skipped, another one is tried or created
+        let set = set.ok()?.next()?;
+
+        locked.remaining_sets -= 1;
+        for (&ty, &count) in layout.descriptor_counts() {
+            *locked.remaining_descriptors.entry(ty).or_default() -= count;
+        }
+        self.shared.allocated_sets.fetch_add(1, Ordering::Relaxed);
+
+        Some(DescriptorAllocation {
+            block: Arc::clone(block),
+            set: Some(set),
+            descriptor_counts: layout.descriptor_counts().clone(),
+            allocator: Arc::clone(&self.shared),
+        })
+    }
+}
+
+unsafe impl DescriptorPool for DescriptorAllocator {
+    type Alloc = DescriptorAllocation;
+
+    fn allocate(
+        &mut self,
+        layout: &DescriptorSetLayout,
+        variable_descriptor_count: u32,
+    ) -> Result<DescriptorAllocation, OomError> {
+        let mut blocks = self.lock();
+
+        for block in blocks.iter() {
+            if let Some(allocation) = self.allocate_in(block, layout, variable_descriptor_count) {
+                return Ok(allocation);
+            }
+        }
+
+        let max_blocks = self.shared.max_blocks.load(Ordering::Relaxed);
+        if blocks.len() >= max_blocks {
+            error!(
+                "The descriptor pool is exhausted: its {} blocks are full, and it is limited to {}",
+                blocks.len(),
+                max_blocks
+            );
+            return Err(OomError::OutOfDeviceMemory);
+        }
+        if max_blocks != usize::MAX && (blocks.len() + 1) as f32 >= max_blocks as f32 * WARNING_RATIO {
+            warn!(
+                "The descriptor pool is almost exhausted: creating block {} out of {}",
+                blocks.len() + 1,
+                max_blocks
+            );
+        }
+
+        let sets = self.shared.sets_per_block.load(Ordering::Relaxed);
+        let pool_sizes = layout
+            .descriptor_counts()
+            .iter()
+            .map(|(&ty, &count)| Some((ty, count.checked_mul(sets)?)))
+            .collect::<Option<HashMap<_, _>>>();
+        let pool_sizes = match pool_sizes {
+            Some(pool_sizes) => pool_sizes,
+            None => {
+                error!(
+                    "A descriptor pool block of {} sets can't hold the descriptors of the layout, use smaller blocks",
+                    sets
+                );
+                return Err(OomError::OutOfDeviceMemory);
+            }
+        };
+
+        debug!("Creating descriptor pool block {} for {} sets", blocks.len() + 1, sets);
+        let pool = UnsafeDescriptorPool::new(
+            Arc::clone(&self.shared.device),
+            UnsafeDescriptorPoolCreateInfo {
+                max_sets: sets,
+                pool_sizes,
+                can_free_descriptor_sets: true,
+                ..Default::default()
+            },
+        )?;
+        let block = Arc::new(Mutex::new(Block {
+            remaining_sets: pool.max_sets(),
+            remaining_descriptors: pool.pool_sizes().clone(),
+            pool,
+        }));
+        blocks.push(Arc::clone(&block));
+
+        self.allocate_in(&block, layout, variable_descriptor_count)
+            .ok_or(OomError::OutOfDeviceMemory)
+    }
+}
+
+unsafe impl DeviceOwned for DescriptorAllocator {
+    fn device(&self) -> &Arc<Device> {
+        &self.shared.device
+    }
+}
+
+/// A descriptor set allocated by a [`DescriptorAllocator`], freed when dropped.
+pub struct DescriptorAllocation {
+    block: Arc<Mutex<Block>>,
+    set: Option<UnsafeDescriptorSet>,
+    descriptor_counts: HashMap<DescriptorType, u32>,
+    // Keeps the statistics up to date
+    allocator: Arc<Shared>,
+}
+
+impl DescriptorPoolAlloc for DescriptorAllocation {
+    fn inner(&self) -> &UnsafeDescriptorSet {
+        self.set.as_ref().unwrap()
+    }
+
+    fn inner_mut(&mut self) -> &mut UnsafeDescriptorSet {
+        self.set.as_mut().unwrap()
+    }
+}
+
+impl Drop for DescriptorAllocation {
+    fn drop(&mut self) {
+        // The set is gone even if it can't be given back to its block
+        self.allocator.allocated_sets.fetch_sub(1, Ordering::Relaxed);
+
+        let mut block = self.block.lock().expect("A descriptor pool block was poisoned");
+        if let Err(e) = unsafe { block.pool.free_descriptor_sets(self.set.take()) } {
+            warn!("Couldn't free a descriptor set: {}", e);
+            return;
+        }
+
+        block.remaining_sets += 1;
+        for (&ty, &count) in &self.descriptor_counts {
+            *block.remaining_descriptors.entry(ty).or_default() += count;
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DescriptorAllocatorError {
+    /// The blocks would hold zero sets.
+    EmptyBlocks,
+}
+
+impl fmt::Display for DescriptorAllocatorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DescriptorAllocatorError::EmptyBlocks => write!(f, "a descriptor pool block must hold at least one set"),
+        }
+    }
+}
+
+impl Error for DescriptorAllocatorError {}
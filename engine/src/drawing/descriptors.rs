@@ -0,0 +1,405 @@
+use std::error::Error;
+use std::fmt;
+use std::sync::Arc;
+
+use vulkano::buffer::view::BufferViewAbstract;
+use vulkano::command_buffer::AutoCommandBufferBuilder;
+use vulkano::descriptor_set::layout::{DescriptorSetLayout, DescriptorType};
+use vulkano::descriptor_set::pool::DescriptorPool;
+use vulkano::descriptor_set::{DescriptorSetCreationError, PersistentDescriptorSet, WriteDescriptorSet};
+use vulkano::device::{Device, DeviceOwned};
+use vulkano::format::Format;
+use vulkano::image::ImageViewAbstract;
+use vulkano::pipeline::Pipeline;
+use vulkano::sampler::Sampler;
+
+/// Builds a descriptor set, checking each binding against the type declared by the layout.
+///
+/// Samplers and images can be bound separately (`sampler` and `texture2D` in GLSL, combined with
+/// `sampler2D(texture, sampler)`), so a single sampler can serve many textures.
+pub struct DescriptorSetBuilder {
+    layout: Arc<DescriptorSetLayout>,
+    writes: Vec<(DescriptorType, WriteDescriptorSet)>,
+    // The bindings combining a comparison sampler with an image, and the format of the image
+    comparisons: Vec<(u32, Option<Format>)>,
+    // The texel buffer bindings whose format doesn't support their descriptor type
+    unsupported_texels: Vec<(u32, DescriptorType, Option<Format>)>,
+}
+
+impl DescriptorSetBuilder {
+    pub fn new(layout: Arc<DescriptorSetLayout>) -> Self {
+        DescriptorSetBuilder {
+            layout,
+            writes: Vec::new(),
+            comparisons: Vec::new(),
+            unsupported_texels: Vec::new(),
+        }
+    }
+
+    /// Builds the descriptor set number `set` of `pipeline`.
+    pub fn for_pipeline<P>(pipeline: &P, set: usize) -> Result<Self, DescriptorError>
+        where
+            P: Pipeline,
+    {
+        let layout = pipeline
+            .layout()
+            .set_layouts()
+            .get(set)
+            .ok_or(DescriptorError::MissingSet(set))?;
+
+        Ok(Self::new(Arc::clone(layout)))
+    }
+
+    /// Binds a standalone `sampler`.
+    pub fn sampler(mut self, binding: u32, sampler: Arc<Sampler>) -> Self {
+        self.writes.push((
+            DescriptorType::Sampler,
+            WriteDescriptorSet::sampler(binding, sampler),
+        ));
+        self
+    }
+
+    /// Binds an image to be sampled with a sampler bound separately.
+    pub fn sampled_image(mut self, binding: u32, image: Arc<dyn ImageViewAbstract>) -> Self {
+        self.writes.push((
+            DescriptorType::SampledImage,
+            WriteDescriptorSet::image_view(binding, image),
+        ));
+        self
+    }
+
+    /// Binds an array of images to be sampled with a sampler bound separately, starting from the first element.
+    pub fn sampled_images<I>(mut self, binding: u32, images: I) -> Self
+        where
+            I: IntoIterator<Item = Arc<dyn ImageViewAbstract>>,
+    {
+        self.writes.push((
+            DescriptorType::SampledImage,
+            WriteDescriptorSet::image_view_array(binding, 0, images),
+        ));
+        self
+    }
+
+    /// Binds an image with the sampler it is read with.
+    ///
+    /// Comparison samplers can only be combined with depth images.
+    pub fn combined_image_sampler(
+        mut self,
+        binding: u32,
+        image: Arc<dyn ImageViewAbstract>,
+        sampler: Arc<Sampler>,
+    ) -> Self {
+        if sampler.compare().is_some() {
+            self.comparisons.push((binding, image.format()));
+        }
+        self.writes.push((
+            DescriptorType::CombinedImageSampler,
+            WriteDescriptorSet::image_view_sampler(binding, image, sampler),
+        ));
+        self
+    }
+
+    /// Binds a buffer view read as formatted texels (`samplerBuffer` in GLSL),
+    /// see [`texel_buffer_view`](crate::drawing::texel::texel_buffer_view).
+    pub fn uniform_texel_buffer(mut self, binding: u32, view: Arc<dyn BufferViewAbstract>) -> Self {
+        if !view.format_features().uniform_texel_buffer {
+            self.unsupported_texels
+                .push((binding, DescriptorType::UniformTexelBuffer, view.format()));
+        }
+        self.writes.push((
+            DescriptorType::UniformTexelBuffer,
+            WriteDescriptorSet::buffer_view(binding, view),
+        ));
+        self
+    }
+
+    /// Binds a buffer view read and written as formatted texels (`imageBuffer` in GLSL).
+    pub fn storage_texel_buffer(mut self, binding: u32, view: Arc<dyn BufferViewAbstract>) -> Self {
+        if !view.format_features().storage_texel_buffer {
+            self.unsupported_texels
+                .push((binding, DescriptorType::StorageTexelBuffer, view.format()));
+        }
+        self.writes.push((
+            DescriptorType::StorageTexelBuffer,
+            WriteDescriptorSet::buffer_view(binding, view),
+        ));
+        self
+    }
+
+    /// Binds an array of images with the samplers they are read with, starting from the first element:
+    /// `sampler2D textures[64]` in GLSL, or `sampler2D textures[]` for a binding with a variable descriptor count.
+    ///
+    /// Indexing the array with a value that isn't constant, such as a material index, needs the
+    /// `shader_sampled_image_array_dynamic_indexing` feature, which the engine enables when the device supports it.
+    pub fn combined_image_samplers<I>(mut self, binding: u32, elements: I) -> Self
+        where
+            I: IntoIterator<Item = (Arc<dyn ImageViewAbstract>, Arc<Sampler>)>,
+    {
+        let elements: Vec<_> = elements.into_iter().collect();
+        if let Some((image, _)) = elements.iter().find(|(_, sampler)| sampler.compare().is_some()) {
+            self.comparisons.push((binding, image.format()));
+        }
+        self.writes.push((
+            DescriptorType::CombinedImageSampler,
+            WriteDescriptorSet::image_view_sampler_array(binding, 0, elements),
+        ));
+        self
+    }
+
+    pub fn build(self) -> Result<Arc<PersistentDescriptorSet>, DescriptorError> {
+        let mut pool = Device::standard_descriptor_pool(self.layout.device());
+        self.build_in(&mut pool)
+    }
+
+    /// Same as [`DescriptorSetBuilder::build`], allocating the set from `pool` instead of vulkano's standard pool,
+    /// for example a clone of [`Hardware::descriptor_pool`](crate::drawing::hardware::Hardware::descriptor_pool).
+    pub fn build_in<P>(self, pool: &mut P) -> Result<Arc<PersistentDescriptorSet<P::Alloc>>, DescriptorError>
+        where
+            P: DescriptorPool,
+    {
+        if self.layout.push_descriptor() {
+            return Err(DescriptorError::PushDescriptorLayout);
+        }
+        self.check()?;
+
+        // Runtime-sized arrays are allocated with as many elements as are written
+        let variable_count = self
+            .writes
+            .iter()
+            .filter(|(_, write)| {
+                self.layout
+                    .bindings()
+                    .get(&write.binding())
+                    .is_some_and(|binding| binding.variable_descriptor_count)
+            })
+            .map(|(_, write)| write.first_array_element() + write.elements().len())
+            .max()
+            .unwrap_or(0);
+
+        Ok(PersistentDescriptorSet::new_with_pool(
+            self.layout,
+            variable_count,
+            pool,
+            self.writes.into_iter().map(|(_, write)| write),
+        )?)
+    }
+
+    /// Records the descriptors directly into `builder`, as the set number `set` of `pipeline`,
+    /// instead of allocating a descriptor set.
+    ///
+    /// The set must be declared with [`PipelineBuilder::push_descriptor_set`](crate::drawing::pipeline::PipelineBuilder::push_descriptor_set),
+    /// and the builder created with [`DescriptorSetBuilder::for_pipeline`] for the same set.
+    pub fn push<L, P>(
+        self,
+        builder: &mut AutoCommandBufferBuilder<L>,
+        pipeline: &P,
+        set: u32,
+    ) -> Result<(), DescriptorError>
+        where
+            P: Pipeline,
+    {
+        let layout = pipeline
+            .layout()
+            .set_layouts()
+            .get(set as usize)
+            .filter(|layout| Arc::ptr_eq(layout, &self.layout))
+            .ok_or(DescriptorError::MissingSet(set as usize))?;
+        if !layout.push_descriptor() {
+            return Err(DescriptorError::NotPushDescriptor(set));
+        }
+        if !layout.device().enabled_extensions().khr_push_descriptor {
+            return Err(DescriptorError::PushDescriptorsNotSupported);
+        }
+        self.check()?;
+
+        builder.push_descriptor_set(
+            pipeline.bind_point(),
+            Arc::clone(pipeline.layout()),
+            set,
+            self.writes.into_iter().map(|(_, write)| write),
+        );
+        Ok(())
+    }
+
+    /// Checks each binding against the layout.
+    fn check(&self) -> Result<(), DescriptorError> {
+        let max_sampled_images = self
+            .layout
+            .device()
+            .physical_device()
+            .properties()
+            .max_per_stage_descriptor_sampled_images;
+
+        for (expected, write) in &self.writes {
+            let binding = write.binding();
+            let layout_binding = self
+                .layout
+                .bindings()
+                .get(&binding)
+                .ok_or(DescriptorError::MissingBinding(binding))?;
+            let declared = layout_binding.descriptor_type;
+
+            if declared != *expected {
+                return Err(DescriptorError::WrongType {
+                    binding,
+                    declared,
+                    bound: *expected,
+                });
+            }
+
+            let count = write.first_array_element() + write.elements().len();
+            if count > layout_binding.descriptor_count {
+                return Err(DescriptorError::ArrayTooLarge {
+                    binding,
+                    count,
+                    declared: layout_binding.descriptor_count,
+                });
+            }
+            let samples_images = matches!(
+                declared,
+                DescriptorType::SampledImage | DescriptorType::CombinedImageSampler
+            );
+            if samples_images && count > max_sampled_images {
+                return Err(DescriptorError::TooManySampledImages {
+                    binding,
+                    count,
+                    max: max_sampled_images,
+                });
+            }
+        }
+
+        for (binding, format) in &self.comparisons {
+            if !format.is_some_and(|format| format.aspects().depth) {
+                return Err(DescriptorError::ComparisonWithoutDepth {
+                    binding: *binding,
+                    format: *format,
+                });
+            }
+        }
+
+        if let Some((binding, descriptor_type, format)) = self.unsupported_texels.first() {
+            return Err(DescriptorError::UnsupportedTexelFormat {
+                binding: *binding,
+                descriptor_type: *descriptor_type,
+                format: *format,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub enum DescriptorError {
+    /// The pipeline layout doesn't have this descriptor set.
+    MissingSet(usize),
+    /// The descriptor set layout doesn't have this binding.
+    MissingBinding(u32),
+    /// The descriptor bound doesn't have the type declared by the layout.
+    WrongType {
+        binding: u32,
+        declared: DescriptorType,
+        bound: DescriptorType,
+    },
+    /// More elements are bound than the array of the layout has.
+    ArrayTooLarge {
+        binding: u32,
+        count: u32,
+        declared: u32,
+    },
+    /// More images are bound than a shader stage can sample, see `max_per_stage_descriptor_sampled_images`.
+    TooManySampledImages { binding: u32, count: u32, max: u32 },
+    /// A comparison sampler is combined with an image that doesn't have a depth format.
+    ComparisonWithoutDepth {
+        binding: u32,
+        format: Option<Format>,
+    },
+    /// The format of a texel buffer view doesn't support the descriptor type it is bound as.
+    UnsupportedTexelFormat {
+        binding: u32,
+        descriptor_type: DescriptorType,
+        format: Option<Format>,
+    },
+    /// The layout is for push descriptors, which can't be allocated: use [`DescriptorSetBuilder::push`].
+    PushDescriptorLayout,
+    /// The set isn't declared as a push descriptor set by the pipeline.
+    NotPushDescriptor(u32),
+    /// The device doesn't have the `khr_push_descriptor` extension.
+    PushDescriptorsNotSupported,
+    Creation(DescriptorSetCreationError),
+}
+
+impl fmt::Display for DescriptorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DescriptorError::MissingSet(set) => {
+                write!(f, "the pipeline doesn't have a descriptor set {}", set)
+            }
+            DescriptorError::MissingBinding(binding) => {
+                write!(f, "the descriptor set doesn't have a binding {}", binding)
+            }
+            DescriptorError::WrongType {
+                binding,
+                declared,
+                bound,
+            } => write!(
+                f,
+                "the binding {} is declared as {:?}, but a {:?} was bound",
+                binding, declared, bound
+            ),
+            DescriptorError::ArrayTooLarge {
+                binding,
+                count,
+                declared,
+            } => write!(
+                f,
+                "{} elements are bound to the binding {}, whose array has {} elements",
+                count, binding, declared
+            ),
+            DescriptorError::TooManySampledImages { binding, count, max } => write!(
+                f,
+                "{} images are bound to the binding {}, but a shader stage can only sample {}",
+                count, binding, max
+            ),
+            DescriptorError::ComparisonWithoutDepth { binding, format } => write!(
+                f,
+                "the binding {} combines a comparison sampler with a {:?} image, which isn't a depth image",
+                binding, format
+            ),
+            DescriptorError::UnsupportedTexelFormat {
+                binding,
+                descriptor_type,
+                format,
+            } => write!(
+                f,
+                "the binding {} is a {:?}, which the format {:?} of its buffer view doesn't support",
+                binding, descriptor_type, format
+            ),
+            DescriptorError::PushDescriptorLayout => {
+                write!(f, "the descriptor set layout is for push descriptors, it can't be allocated")
+            }
+            DescriptorError::NotPushDescriptor(set) => {
+                write!(f, "the descriptor set {} isn't declared as a push descriptor set", set)
+            }
+            DescriptorError::PushDescriptorsNotSupported => {
+                write!(f, "the device doesn't support push descriptors")
+            }
+            DescriptorError::Creation(e) => write!(f, "couldn't create the descriptor set: {}", e),
+        }
+    }
+}
+
+impl Error for DescriptorError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            DescriptorError::Creation(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<DescriptorSetCreationError> for DescriptorError {
+    fn from(e: DescriptorSetCreationError) -> Self {
+        DescriptorError::Creation(e)
+    }
+}
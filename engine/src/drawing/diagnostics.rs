@@ -0,0 +1,250 @@
+use std::ffi::CString;
+use std::fmt;
+use std::sync::Arc;
+
+use serde::Serialize;
+use vulkano::device::{Device, Queue};
+use vulkano::swapchain::Swapchain;
+use winit::window::Window;
+
+use crate::drawing::hardware::Hardware;
+use crate::drawing::screen::Screen;
+
+/// Everything needed to reproduce the environment the engine runs in.
+///
+/// Its [`Display`](fmt::Display) implementation is meant to be attached to bug reports,
+/// [`Diagnostics::to_json`] to be read by tools.
+#[derive(Debug, Clone, Serialize)]
+pub struct Diagnostics {
+    pub graphics_device: DeviceReport,
+    /// `None` if compute runs on the graphics device, or if there is no compute queue.
+    pub compute_device: Option<DeviceReport>,
+    pub queues: Vec<QueueReport>,
+    pub swapchain: SwapchainReport,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DeviceReport {
+    pub name: String,
+    pub device_type: String,
+    pub vendor_id: u32,
+    pub device_id: u32,
+    pub driver_version: u32,
+    /// The version of Vulkan supported by the physical device.
+    pub api_version: String,
+    /// The version of Vulkan actually used by the engine.
+    pub used_api_version: String,
+    pub enabled_extensions: Vec<String>,
+    pub enabled_features: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct QueueReport {
+    /// What the engine uses the queue for.
+    pub role: String,
+    pub device: String,
+    pub family: u32,
+    pub index: u32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SwapchainReport {
+    pub format: String,
+    pub color_space: String,
+    pub present_mode: String,
+    pub image_count: u32,
+    pub extent: [u32; 2],
+}
+
+impl Diagnostics {
+    pub(crate) fn new(hardware: &Hardware, screen: &Screen) -> Self {
+        let graphics_device = hardware.graphics_device();
+        let compute_device = hardware.compute_device();
+
+        let mut queues = vec![QueueReport::new("graphics", hardware.graphics_queue())];
+        if let Some(queue) = hardware.compute_queue() {
+            queues.push(QueueReport::new("compute", queue));
+        }
+        if hardware.has_dedicated_transfer_queue() {
+            queues.push(QueueReport::new("transfer", hardware.transfer_queue()));
+        }
+
+        Diagnostics {
+            graphics_device: DeviceReport::new(graphics_device),
+            compute_device: compute_device
+                .filter(|device| !Arc::ptr_eq(graphics_device, device))
+                .map(|device| DeviceReport::new(device)),
+            queues,
+            swapchain: SwapchainReport::new(screen.swapchain()),
+        }
+    }
+
+    /// The report as pretty-printed JSON, with the field names of the structs.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("Couldn't serialize the diagnostics")
+    }
+}
+
+impl DeviceReport {
+    fn new(device: &Device) -> Self {
+        let physical = device.physical_device();
+        let properties = physical.properties();
+
+        let enabled_extensions: Vec<CString> = device.enabled_extensions().into();
+
+        DeviceReport {
+            name: properties.device_name.clone(),
+            device_type: format!("{:?}", properties.device_type),
+            vendor_id: properties.vendor_id,
+            device_id: properties.device_id,
+            driver_version: properties.driver_version,
+            api_version: physical.api_version().to_string(),
+            used_api_version: device.api_version().to_string(),
+            enabled_extensions: enabled_extensions
+                .into_iter()
+                .map(|extension| extension.to_string_lossy().into_owned())
+                .collect(),
+            enabled_features: enabled_features(device),
+        }
+    }
+}
+
+/// Vulkano doesn't provide a way to iterate over features,
+/// so the enabled ones are extracted from the `Debug` representation (`Features { name: bool, … }`).
+fn enabled_features(device: &Device) -> Vec<String> {
+    let features = format!("{:?}", device.enabled_features());
+
+    features
+        .trim_start_matches("Features {")
+        .trim_end_matches('}')
+        .split(',')
+        .filter_map(|feature| feature.trim().strip_suffix(": true"))
+        .map(String::from)
+        .collect()
+}
+
+impl QueueReport {
+    fn new(role: &str, queue: &Queue) -> Self {
+        QueueReport {
+            role: role.to_string(),
+            device: queue.device().physical_device().properties().device_name.clone(),
+            family: queue.family().id(),
+            index: queue.id_within_family(),
+        }
+    }
+}
+
+impl SwapchainReport {
+    fn new(swapchain: &Swapchain<Window>) -> Self {
+        SwapchainReport {
+            format: format!("{:?}", swapchain.image_format()),
+            color_space: format!("{:?}", swapchain.image_color_space()),
+            present_mode: format!("{:?}", swapchain.present_mode()),
+            image_count: swapchain.image_count(),
+            extent: swapchain.image_extent(),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Graphics device:")?;
+        write!(f, "{}", self.graphics_device)?;
+
+        match &self.compute_device {
+            Some(device) => {
+                writeln!(f, "Compute device:")?;
+                write!(f, "{}", device)?;
+            }
+            None if self.queues.iter().any(|queue| queue.role == "compute") => {
+                writeln!(f, "Compute device: same as graphics")?
+            }
+            None => writeln!(f, "Compute device: none")?,
+        }
+
+        writeln!(f, "Queues:")?;
+        for queue in &self.queues {
+            writeln!(
+                f,
+                " - {}: {} / family {} / queue {}",
+                queue.role, queue.device, queue.family, queue.index
+            )?;
+        }
+
+        writeln!(f, "Swapchain:")?;
+        writeln!(f, "   Format: {}", self.swapchain.format)?;
+        writeln!(f, "   Color space: {}", self.swapchain.color_space)?;
+        writeln!(f, "   Present mode: {}", self.swapchain.present_mode)?;
+        writeln!(f, "   Images: {}", self.swapchain.image_count)?;
+        writeln!(
+            f,
+            "   Extent: {}×{}",
+            self.swapchain.extent[0], self.swapchain.extent[1]
+        )
+    }
+}
+
+impl fmt::Display for DeviceReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "   Name: {} ({})", self.name, self.device_type)?;
+        writeln!(
+            f,
+            "   Vendor: {:#06x} / device {:#06x}",
+            self.vendor_id, self.device_id
+        )?;
+        writeln!(f, "   Driver: {}", self.driver_version)?;
+        writeln!(
+            f,
+            "   API: {} (using {})",
+            self.api_version, self.used_api_version
+        )?;
+        writeln!(f, "   Extensions: {}", self.enabled_extensions.join(", "))?;
+        writeln!(f, "   Features: {}", self.enabled_features.join(", "))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn to_json() {
+        let diagnostics = Diagnostics {
+            graphics_device: DeviceReport {
+                name: "Test GPU".to_string(),
+                device_type: "DiscreteGpu".to_string(),
+                vendor_id: 0x10de,
+                device_id: 0x2204,
+                driver_version: 42,
+                api_version: "1.3.0".to_string(),
+                used_api_version: "1.2.0".to_string(),
+                enabled_extensions: vec!["VK_KHR_swapchain".to_string()],
+                enabled_features: vec![],
+            },
+            compute_device: None,
+            queues: vec![QueueReport {
+                role: "graphics".to_string(),
+                device: "Test GPU".to_string(),
+                family: 0,
+                index: 0,
+            }],
+            swapchain: SwapchainReport {
+                format: "B8G8R8A8_SRGB".to_string(),
+                color_space: "SrgbNonLinear".to_string(),
+                present_mode: "Fifo".to_string(),
+                image_count: 3,
+                extent: [800, 600],
+            },
+        };
+
+        let json: serde_json::Value = serde_json::from_str(&diagnostics.to_json()).unwrap();
+        assert_eq!(json["graphics_device"]["name"], "Test GPU");
+        assert_eq!(json["graphics_device"]["vendor_id"], 0x10de);
+        assert_eq!(json["graphics_device"]["enabled_extensions"][0], "VK_KHR_swapchain");
+        assert_eq!(json["compute_device"], serde_json::Value::Null);
+        assert_eq!(json["queues"][0]["role"], "graphics");
+        assert_eq!(json["swapchain"]["image_count"], 3);
+        assert_eq!(json["swapchain"]["extent"], serde_json::json!([800, 600]));
+    }
+}
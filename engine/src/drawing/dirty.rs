@@ -0,0 +1,89 @@
+use std::sync::Mutex;
+
+use vulkano::command_buffer::{AutoCommandBufferBuilder, PrimaryAutoCommandBuffer};
+use vulkano::format::ClearValue;
+use vulkano::image::attachment::{ClearAttachment, ClearRect};
+use vulkano::pipeline::graphics::viewport::{Scissor, Viewport};
+
+/// The part of the window redrawn each frame, to only update what changed in a mostly-static UI.
+///
+/// The window must keep its contents between frames, with [`Clear::Preserve`](crate::drawing::window::Clear::Preserve):
+/// each swapchain image keeps what was drawn to it, so with multiple images the region must also cover
+/// what changed during the previous frames.
+#[derive(Default)]
+pub struct DirtyRegion {
+    rect: Mutex<Option<Scissor>>,
+}
+
+impl DirtyRegion {
+    /// Restricts the next frames to `rect`, in pixels.
+    pub fn set(&self, rect: Scissor) {
+        *self.lock() = Some(rect);
+    }
+
+    /// Redraws the whole window, which is the default.
+    pub fn clear(&self) {
+        *self.lock() = None;
+    }
+
+    pub fn get(&self) -> Option<Scissor> {
+        *self.lock()
+    }
+
+    /// The region to redraw within `viewport`: the whole viewport if no region is set.
+    pub fn scissor(&self, viewport: &Viewport) -> Scissor {
+        let [x, y] = [viewport.origin[0] as u32, viewport.origin[1] as u32];
+        let [width, height] = [viewport.dimensions[0] as u32, viewport.dimensions[1] as u32];
+
+        match self.get() {
+            Some(rect) => {
+                let origin = [rect.origin[0].clamp(x, x + width), rect.origin[1].clamp(y, y + height)];
+                let end = [
+                    (rect.origin[0] + rect.dimensions[0]).clamp(origin[0], x + width),
+                    (rect.origin[1] + rect.dimensions[1]).clamp(origin[1], y + height),
+                ];
+                Scissor {
+                    origin,
+                    dimensions: [end[0] - origin[0], end[1] - origin[1]],
+                }
+            }
+            None => Scissor {
+                origin: [x, y],
+                dimensions: [width, height],
+            },
+        }
+    }
+
+    /// Restricts the following draws to the region, and clears the first color attachment in it to `clear`, if any.
+    ///
+    /// Must be recorded inside the render pass, after binding a pipeline with a dynamic scissor
+    /// (see [`PipelineBuilder::dynamic_states`](crate::drawing::pipeline::PipelineBuilder::dynamic_states)).
+    pub fn apply(
+        &self,
+        builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        viewport: &Viewport,
+        clear: Option<ClearValue>,
+    ) {
+        let scissor = self.scissor(viewport);
+        if let Some(clear) = clear {
+            if scissor.dimensions[0] > 0 && scissor.dimensions[1] > 0 {
+                builder
+                    .clear_attachments(
+                        [ClearAttachment::Color(clear, 0)],
+                        [ClearRect {
+                            rect_offset: scissor.origin,
+                            rect_extent: scissor.dimensions,
+                            base_array_layer: 0,
+                            layer_count: 1,
+                        }],
+                    )
+                    .expect("Couldn't clear the dirty region");
+            }
+        }
+        builder.set_scissor(0, [scissor]);
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Option<Scissor>> {
+        self.rect.lock().expect("The dirty region was poisoned")
+    }
+}
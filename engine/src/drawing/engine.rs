@@ -1,181 +1,1100 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-use log::{debug, warn};
-use vulkano::command_buffer::PrimaryAutoCommandBuffer;
-use vulkano::image::{ImageAccess, SwapchainImage};
-use vulkano::image::view::ImageView;
+use bytemuck::Pod;
+use log::{debug, error, info, warn};
+use vulkano::buffer::{BufferContents, CpuAccessibleBuffer, TypedBufferAccess};
+use vulkano::command_buffer::{AutoCommandBufferBuilder, CommandBufferUsage, PrimaryAutoCommandBuffer, SubpassContents};
+use vulkano::format::{ClearValue, Format};
+use vulkano::device::physical::SurfacePropertiesError;
+use vulkano::device::Queue;
+use vulkano::image::ImageAccess;
+use vulkano::image::view::{ImageView, ImageViewAbstract};
 use vulkano::pipeline::graphics::viewport::Viewport;
-use vulkano::render_pass::{Framebuffer, FramebufferCreateInfo, RenderPass};
-use vulkano::swapchain::{acquire_next_image, AcquireError, SwapchainCreationError};
+use vulkano::pipeline::GraphicsPipeline;
+use vulkano::render_pass::{Framebuffer, FramebufferCreateInfo, LoadOp, RenderPass, Subpass};
+use vulkano::swapchain::{acquire_next_image, AcquireError, ColorSpace, CompositeAlpha, SwapchainCreationError};
 use vulkano::sync;
-use vulkano::sync::{FlushError, GpuFuture};
+use vulkano::sync::{FlushError, GpuFuture, PipelineStages, Semaphore};
+use winit::dpi::PhysicalSize;
 use winit::event::{Event, WindowEvent};
 use winit::event_loop::{ControlFlow, EventLoop};
-use winit::window::Window;
+#[cfg(run_return)]
+use winit::platform::run_return::EventLoopExtRunReturn;
+use winit::window::CursorIcon;
 
-use crate::drawing::hardware::Hardware;
+use crate::drawing::camera::{ActiveCamera, SharedCamera};
+use crate::drawing::diagnostics::Diagnostics;
+use crate::drawing::dirty::DirtyRegion;
+use crate::drawing::features::HardwareError;
+use crate::drawing::grid::DebugGrid;
+use crate::drawing::hardware::{Hardware, HardwareConfig};
+use crate::drawing::headless::HeadlessEngine;
+use crate::drawing::input;
+use crate::drawing::input::InputState;
+use crate::drawing::lights::{Light, LightId, Lights};
+use crate::drawing::manifest;
+use crate::drawing::manifest::{LoadProgress, ManifestError, PipelineManifest, PipelineRegistry};
+use crate::drawing::offscreen;
+use crate::drawing::offscreen::OffscreenError;
+use crate::drawing::pacing::{AcquireTimeout, FpsCap, FrameStats, FrameTimer};
+use crate::drawing::present;
+use crate::drawing::profiler::{Profiler, ProfilerError};
+use crate::drawing::present::{Displayed, PresentLog, PresentQueueError, PresentReport};
+use crate::drawing::readback::ReadbackHandle;
+use crate::drawing::replay::{Invalidation, RecordedFrames};
+use crate::drawing::resources::Resources;
 use crate::drawing::screen::Screen;
+use crate::drawing::screenshot::PendingScreenshot;
+use crate::drawing::submit;
+use crate::drawing::submit::{SubmitError, Submission};
+use crate::drawing::target::{RenderTarget, RenderTargets, TargetError};
+use crate::drawing::tone_map::{ToneMap, ToneMapOperator};
+use crate::drawing::uniform::{UniformBuffer, UniformError};
+use crate::drawing::warm;
+use crate::drawing::window::{premultiply, WindowConfig};
+
+type InputHandler = Box<dyn FnMut(&WindowEvent)>;
+type ComputePass = Box<dyn FnMut(&Hardware) -> PrimaryAutoCommandBuffer>;
 
 pub struct Engine {
-    event_loop: EventLoop<()>,
+    // Taken when the engine starts running
+    event_loop: Option<EventLoop<()>>,
     pub hardware: Arc<Hardware>,
     pub screen: Arc<Screen>,
+    /// The meshes, textures and pipelines referenced by handles.
+    /// Clone it into the draw closure of [`Engine::run`] to resolve them.
+    pub resources: Arc<Resources>,
+    /// The offscreen render targets, see [`Engine::resize_target`].
+    pub targets: Arc<RenderTargets>,
+    /// The camera the scene is rendered from, see [`Engine::set_active_camera`].
+    /// Clone it into the draw closure of [`Engine::run`] to get its matrices each frame.
+    pub camera: Arc<ActiveCamera>,
+    /// The part of the window redrawn each frame, the whole window by default.
+    /// Clone it into the draw closure of [`Engine::run`] to apply it with [`DirtyRegion::apply`].
+    pub dirty_region: Arc<DirtyRegion>,
+    /// The keys and buttons pressed, and the position of the cursor, updated while the engine runs.
+    /// Clone it into the draw closure of [`Engine::run`] to read it each frame.
+    pub input: Arc<InputState>,
+    input_handler: Option<InputHandler>,
+    compute_pass: Option<ComputePass>,
+    pipelines: Vec<Arc<GraphicsPipeline>>,
+    lights: Arc<Lights>,
+    profiler: Arc<Profiler>,
+    fps_cap: FpsCap,
+    acquire_timeout: Option<AcquireTimeout>,
+    frame_timer: Option<Arc<FrameTimer>>,
+    present_queue: Arc<Queue>,
+    present_log: Arc<PresentLog>,
+    resize_debounce: Duration,
+    invalidation: Invalidation,
 }
 
 impl Engine {
     /// Instantiates the Quasar Engine.
-    pub fn new() -> Engine {
+    ///
+    /// Fails if Vulkan isn't available, or no device can render to the window.
+    pub fn new() -> Result<Engine, EngineError> {
+        Self::with_window(WindowConfig::default())
+    }
+
+    /// Instantiates the Quasar Engine without a window, to run compute shaders on machines without a display.
+    pub fn new_headless() -> Result<HeadlessEngine, EngineError> {
+        HeadlessEngine::new()
+    }
+
+    /// Same as [`Engine::new`], panicking if the engine can't be created.
+    pub fn new_or_panic() -> Engine {
+        Self::new().expect("Couldn't instantiate the engine")
+    }
+
+    /// Instantiates the Quasar Engine, creating its window as described by `window`.
+    pub fn with_window(window: WindowConfig) -> Result<Engine, EngineError> {
+        Self::with_config(window, &HardwareConfig::default())
+    }
+
+    /// Instantiates the Quasar Engine, selecting the devices as described by `hardware`.
+    pub fn with_config(window: WindowConfig, hardware: &HardwareConfig) -> Result<Engine, EngineError> {
         let event_loop = EventLoop::new();
-        let hardware = Arc::new(Hardware::new(&event_loop));
-        let screen = Arc::new(Screen::new(Arc::clone(&hardware), &event_loop));
+        let hardware = Arc::new(Hardware::new(&event_loop, &window, hardware)?);
+        let screen = Arc::new(Screen::new(Arc::clone(&hardware), &event_loop, &window)?);
+        // A frame can't be in flight without holding one of the swapchain images
+        let resources = Arc::new(Resources::new(screen.images().len() as u64));
+        let targets = Arc::new(RenderTargets::new(Arc::clone(&hardware)));
+        let present_queue = Arc::clone(hardware.graphics_queue());
+        let profiler = Arc::new(Profiler::new(&hardware, screen.images().len())?);
+        let lights = Arc::new(Lights::new(hardware.graphics_device(), screen.images().len()));
+        let camera = Arc::new(ActiveCamera::default());
+        camera.set_pre_transform(screen.pre_transform());
 
         debug!("Vulkan initialization finished.");
-        Engine {
-            event_loop,
+        Ok(Engine {
+            event_loop: Some(event_loop),
             hardware,
             screen,
+            resources,
+            targets,
+            camera,
+            dirty_region: Arc::new(DirtyRegion::default()),
+            input: Arc::new(InputState::default()),
+            input_handler: None,
+            compute_pass: None,
+            pipelines: Vec::new(),
+            lights,
+            profiler,
+            fps_cap: FpsCap::default(),
+            acquire_timeout: None,
+            frame_timer: None,
+            present_queue,
+            present_log: Arc::new(PresentLog::default()),
+            resize_debounce: Duration::ZERO,
+            invalidation: Invalidation::default(),
+        })
+    }
+
+    /// Prevents the user from resizing the window below `size`, in pixels. `None` removes the constraint.
+    pub fn set_min_size(&self, size: Option<[u32; 2]>) {
+        self.hardware
+            .window()
+            .set_min_inner_size(size.map(PhysicalSize::<u32>::from));
+    }
+
+    /// Prevents the user from resizing the window above `size`, in pixels. `None` removes the constraint.
+    pub fn set_max_size(&self, size: Option<[u32; 2]>) {
+        self.hardware
+            .window()
+            .set_max_inner_size(size.map(PhysicalSize::<u32>::from));
+    }
+
+    /// Captures the next frame with RenderDoc, if the application was started from it.
+    ///
+    /// From the draw closure of [`Engine::run`], use [`Hardware::capture`] instead.
+    pub fn trigger_capture(&self) {
+        self.hardware.capture().trigger();
+    }
+
+    /// Starts capturing all the submitted work with RenderDoc, if the application was started from it.
+    pub fn start_capture(&self) {
+        self.hardware.capture().start();
+    }
+
+    /// Ends the capture started by [`Engine::start_capture`].
+    pub fn end_capture(&self) {
+        self.hardware.capture().end();
+    }
+
+    /// Summarizes the devices, queues and swapchain the engine runs on, to be attached to bug reports.
+    pub fn diagnostics(&self) -> Diagnostics {
+        Diagnostics::new(&self.hardware, &self.screen)
+    }
+
+    /// Measures the GPU time of named zones, with as many sets of queries as there are frames in flight.
+    ///
+    /// Clone it into the draw closure of [`Engine::run`].
+    pub fn profiler(&self) -> Arc<Profiler> {
+        Arc::clone(&self.profiler)
+    }
+
+    /// Lights the scene with `light`, starting with the next frame.
+    ///
+    /// At most [`MAX_LIGHTS`](crate::drawing::lights::MAX_LIGHTS) lights are rendered.
+    pub fn add_light(&self, light: Light) -> LightId {
+        self.lights.add(light)
+    }
+
+    /// Removes the light `id`, returning it if it wasn't already removed.
+    pub fn remove_light(&self, id: LightId) -> Option<Light> {
+        self.lights.remove(id)
+    }
+
+    /// The lights of the scene, in a storage buffer updated when they change.
+    ///
+    /// Clone it into the draw closure of [`Engine::run`] to bind [`Lights::buffer`] each frame.
+    pub fn lights(&self) -> Arc<Lights> {
+        Arc::clone(&self.lights)
+    }
+
+    /// Calls `handler` with the keyboard and mouse events of the window while the engine runs,
+    /// after [`Engine::input`] is updated, for example to build camera controls.
+    pub fn set_input_handler<H>(&mut self, handler: H)
+        where
+            H: FnMut(&WindowEvent) + 'static,
+    {
+        self.input_handler = Some(Box::new(handler));
+    }
+
+    /// Calls `pass` before each frame drawn by [`Engine::run`] and its variants, and executes the command buffer
+    /// it returns on the compute queue, for example to simulate particles before drawing them.
+    ///
+    /// The command buffer must be recorded for [`Hardware::compute_queue`]. The draw of the frame waits
+    /// on a semaphore until the compute pass is done, so it can read its results. When the compute queue is on
+    /// another device, see [`Hardware::shares_device`], the engine waits for the compute pass on the CPU instead.
+    /// Without a compute queue, the pass is ignored.
+    pub fn set_compute_pass<C>(&mut self, pass: C)
+        where
+            C: FnMut(&Hardware) -> PrimaryAutoCommandBuffer + 'static,
+    {
+        if self.hardware.compute_queue().is_none() {
+            warn!("No queue supports compute, the compute pass is ignored");
+            return;
+        }
+        self.compute_pass = Some(Box::new(pass));
+    }
+
+    /// Changes the cursor displayed over the window.
+    pub fn set_cursor_icon(&self, cursor: CursorIcon) {
+        self.hardware.window().set_cursor_icon(cursor);
+    }
+
+    /// Hides or shows the cursor while it is over the window.
+    pub fn set_cursor_visible(&self, visible: bool) {
+        self.hardware.window().set_cursor_visible(visible);
+    }
+
+    /// Renders from `camera`, starting with the next frame. Pipelines don't need to be rebuilt.
+    pub fn set_active_camera(&self, camera: SharedCamera) {
+        self.camera.set(camera);
+    }
+
+    /// Creates a [`UniformBuffer`] with one buffer per swapchain image, see [`Engine::run_indexed`].
+    pub fn uniform_buffer<T>(&self, binding: u32, initial: T) -> Result<UniformBuffer<T>, UniformError>
+        where
+            T: Pod + Send + Sync,
+    {
+        UniformBuffer::new(self.hardware.graphics_device(), self.screen.images().len(), binding, initial)
+    }
+
+    /// Limits how many frames [`Engine::run`] renders per second. Frames are uncapped by default.
+    pub fn set_fps_cap(&mut self, cap: FpsCap) {
+        self.fps_cap = cap;
+    }
+
+    /// Starts measuring the time between the frames presented by [`Engine::run`], averaging the frame rate
+    /// over the last `window` frames.
+    ///
+    /// Frames aren't measured until it is called. Calling it again returns the same timer.
+    pub fn frame_timer(&mut self, window: usize) -> Arc<FrameTimer> {
+        Arc::clone(self.frame_timer.get_or_insert_with(|| Arc::new(FrameTimer::new(window))))
+    }
+
+    /// Skips the frames for which no swapchain image is available in time, instead of waiting for one.
+    /// `None`, the default, waits indefinitely.
+    pub fn set_acquire_timeout(&mut self, timeout: Option<AcquireTimeout>) {
+        self.acquire_timeout = timeout;
+    }
+
+    /// Presents the frames through `queue` instead of the graphics queue, to compare presentation paths.
+    ///
+    /// The queue must belong to the graphics device and be able to present to the window.
+    /// If its family isn't the graphics family, the swapchain is recreated with images shared by both families.
+    pub fn set_present_queue(&mut self, queue: Arc<Queue>) -> Result<(), PresentQueueError> {
+        present::check_queue(&self.hardware, &queue)?;
+
+        let families = [self.hardware.graphics_queue().family().id(), queue.family().id()];
+        self.screen = Arc::new(self.screen.with_sharing(&families)?);
+        self.present_queue = queue;
+        Ok(())
+    }
+
+    /// Waits for the window size to be stable for `debounce` before recreating the swapchain,
+    /// instead of recreating it for each step of an interactive resize.
+    ///
+    /// No frame is rendered while the size settles. The swapchain is recreated immediately by default.
+    pub fn set_resize_debounce(&mut self, debounce: Duration) {
+        self.resize_debounce = debounce;
+    }
+
+    /// Whether the most recent frames were presented, and why they weren't.
+    ///
+    /// Clone it into the draw closure of [`Engine::run`] to check the previous frames.
+    pub fn present_log(&self) -> Arc<PresentLog> {
+        Arc::clone(&self.present_log)
+    }
+
+    /// Builds all the pipelines of `manifest` on a thread pool, through the pipeline cache of the hardware,
+    /// calling `progress` after each one, for example to update a loading screen.
+    ///
+    /// The pipelines are kept alive as long as the engine, and returned by name.
+    pub fn load_pipelines<P>(
+        &mut self,
+        manifest: &PipelineManifest,
+        registry: &PipelineRegistry,
+        progress: P,
+    ) -> Result<HashMap<String, Arc<GraphicsPipeline>>, ManifestError>
+        where
+            P: FnMut(LoadProgress),
+    {
+        let start = Instant::now();
+        let pipelines = manifest::load(&self.hardware, manifest, registry, progress)?;
+        info!("Built {} pipelines in {:?}", pipelines.len(), start.elapsed());
+
+        self.pipelines.extend(pipelines.values().cloned());
+        Ok(pipelines)
+    }
+
+    /// Prepares `pipelines` before the first frame, to avoid a hitch the first time they are used.
+    ///
+    /// Pipelines are compiled when they are built; with `dummy_draw`, a throwaway draw is also executed
+    /// with each pipeline that doesn't read any resource, to trigger the work drivers defer to the first use.
+    ///
+    /// Returns how long warming took.
+    pub fn warm_pipelines(&self, pipelines: &[Arc<GraphicsPipeline>], dummy_draw: bool) -> Duration {
+        let start = Instant::now();
+
+        let mut drawn = 0;
+        for pipeline in pipelines {
+            if dummy_draw && warm::dummy_draw(&self.hardware, pipeline) {
+                drawn += 1;
+            }
         }
+
+        let elapsed = start.elapsed();
+        info!(
+            "Warmed {} pipelines ({} with a dummy draw) in {:?}",
+            pipelines.len(),
+            drawn,
+            elapsed
+        );
+        elapsed
+    }
+
+    /// Submits `command_buffer` to `queue` with explicit semaphores, outside of the frames of [`Engine::run`].
+    ///
+    /// # Safety
+    ///
+    /// See [`submit::submit_to`]: the semaphores and the resources used by the command buffer aren't tracked.
+    pub unsafe fn submit_to(
+        &self,
+        queue: &Arc<Queue>,
+        command_buffer: PrimaryAutoCommandBuffer,
+        waits: &[(Arc<Semaphore>, PipelineStages)],
+        signals: &[Arc<Semaphore>],
+    ) -> Result<Submission, SubmitError> {
+        submit::submit_to(queue, command_buffer, waits, signals)
+    }
+
+    /// Reallocates the render target `name` and its framebuffers with new dimensions, see [`RenderTargets::resize`].
+    pub fn resize_target(&self, name: &str, dimensions: [u32; 2]) -> Result<Arc<RenderTarget>, TargetError> {
+        self.targets.resize(name, dimensions)
+    }
+
+    /// Prepares a fullscreen pass mapping the linear HDR `source` to the screen.
+    ///
+    /// Render the scene into `source`, then call [`ToneMap::draw`] from the draw closure of [`Engine::run`],
+    /// which must be started with [`ToneMap::render_pass`].
+    pub fn tone_map(
+        &self,
+        source: &RenderTarget,
+        operator: ToneMapOperator,
+        exposure: f32,
+    ) -> ToneMap {
+        ToneMap::new(
+            &self.hardware,
+            self.screen.swapchain().image_format(),
+            source,
+            operator,
+            exposure,
+        )
     }
 
-    pub fn run<D>(mut self, render_pass: Arc<RenderPass>, draw: D)
+    /// Renders a single frame into `image` instead of the window, for example an image shared with a video encoder.
+    ///
+    /// `render_pass` must have a single attachment with the format and sample count of `image`.
+    /// The draw closure is the same as for [`Engine::run`]; returns once the GPU is done.
+    pub fn render_into<D>(
+        &self,
+        render_pass: Arc<RenderPass>,
+        image: Arc<dyn ImageAccess>,
+        draw: D,
+    ) -> Result<(), OffscreenError>
+        where
+            D: FnOnce(&Hardware, &Screen, &Arc<Framebuffer>, &Viewport) -> PrimaryAutoCommandBuffer,
+    {
+        offscreen::render_into(&self.hardware, &self.screen, render_pass, image, draw)
+    }
+
+    /// Renders a single image of `dimensions` without presenting it, and returns its pixels once the GPU is done,
+    /// for example to post-process the scene or save it.
+    ///
+    /// The image is the single-sampled color attachment of `render_pass`, its other attachments are created
+    /// for the call, see [`offscreen::render`]. The pixels are tightly packed rows, in the format of the image.
+    pub fn render_offscreen<D>(
+        &self,
+        render_pass: Arc<RenderPass>,
+        dimensions: [u32; 2],
+        draw: D,
+    ) -> Result<Arc<CpuAccessibleBuffer<[u8]>>, OffscreenError>
+        where
+            D: FnOnce(&Hardware, &Arc<Framebuffer>, &Viewport) -> PrimaryAutoCommandBuffer,
+    {
+        let format = offscreen::color_format(&render_pass)?;
+        offscreen::render_on(&self.hardware, dimensions, format, render_pass, draw)
+    }
+
+    /// Starts copying `buffer` to the CPU, see [`Hardware::read_buffer_async`].
+    pub fn read_buffer_async<T, B>(&self, buffer: Arc<B>) -> ReadbackHandle<T>
+        where
+            B: TypedBufferAccess<Content = [T]> + 'static,
+            T: Copy + Send + Sync + 'static,
+            [T]: BufferContents,
+    {
+        self.hardware.read_buffer_async(buffer)
+    }
+
+    /// Prepares an infinite ground grid, drawn with [`DebugGrid::draw`] from the draw closure of [`Engine::run`].
+    pub fn debug_grid(&self, subpass: Subpass) -> DebugGrid {
+        DebugGrid::new(&self.hardware, subpass)
+    }
+
+    /// Controls when [`Engine::run_static`] records its command buffers again.
+    pub fn invalidation(&self) -> Invalidation {
+        self.invalidation.clone()
+    }
+
+    /// Draws the frames with `draw` until the window is closed, then exits the process.
+    ///
+    /// If the swapchain can't be recreated, or no image can be acquired from it, the error is logged and the
+    /// process exits: use [`Engine::run_return`] to handle it.
+    pub fn run<D>(self, render_pass: Arc<RenderPass>, draw: D)
         where
             D: Fn(&Hardware, &Screen, &Arc<Framebuffer>, &Viewport) -> PrimaryAutoCommandBuffer
             + 'static,
     {
+        self.run_with(render_pass, move |hardware, screen, framebuffers, image_num, viewport| {
+            Arc::new(draw(hardware, screen, &framebuffers[image_num], viewport))
+        })
+    }
+
+    /// Like [`Engine::run`], but the draw closure is also given the index of the swapchain image drawn into,
+    /// for example to update the [`UniformBuffer`](crate::drawing::uniform::UniformBuffer) of this image.
+    pub fn run_indexed<D>(self, render_pass: Arc<RenderPass>, draw: D)
+        where
+            D: Fn(&Hardware, &Screen, &Arc<Framebuffer>, usize, &Viewport) -> PrimaryAutoCommandBuffer
+            + 'static,
+    {
+        self.run_with(render_pass, move |hardware, screen, framebuffers, image_num, viewport| {
+            Arc::new(draw(hardware, screen, &framebuffers[image_num], image_num, viewport))
+        })
+    }
+
+    /// Like [`Engine::run`], for windows that are only cleared to `clear_color`, see [`Engine::run_clear_with`].
+    pub fn run_clear(self, render_pass: Arc<RenderPass>, clear_color: [f32; 4]) {
+        self.run_clear_with(render_pass, clear_color, |_, _, _, _| {})
+    }
+
+    /// Like [`Engine::run`], but the engine records the command buffer of each frame: it begins `render_pass`
+    /// clearing the window to `clear_color`, lets `draw` record its commands in the first subpass, then ends it.
+    ///
+    /// Other attachments cleared by the render pass, such as the depth, are cleared to 1 or 0.
+    /// The color is premultiplied if the window uses `PreMultiplied` composite alpha.
+    pub fn run_clear_with<D>(self, render_pass: Arc<RenderPass>, clear_color: [f32; 4], draw: D)
+        where
+            D: Fn(&Hardware, &Screen, &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>, &Viewport) + 'static,
+    {
+        let clear_color = if self.screen.composite_alpha() == CompositeAlpha::PreMultiplied {
+            premultiply(clear_color)
+        } else {
+            clear_color
+        };
+
+        // Computed once, then copied into each frame
+        let clear_values: Vec<ClearValue> = render_pass
+            .attachments()
+            .iter()
+            .enumerate()
+            .map(|(i, attachment)| match (i, attachment.load_op) {
+                (0, LoadOp::Clear) => ClearValue::Float(clear_color),
+                (_, LoadOp::Clear) => warm::clear_value(attachment.format.expect("Attachments always have a format")),
+                _ => ClearValue::None,
+            })
+            .collect();
+        let subpasses = render_pass.subpasses().len();
+
+        self.run(render_pass, move |hardware, screen, frame, viewport| {
+            let mut builder = AutoCommandBufferBuilder::primary(
+                Arc::clone(hardware.graphics_device()),
+                hardware.graphics_queue().family(),
+                CommandBufferUsage::OneTimeSubmit,
+            )
+                .expect("Couldn't create the command buffer of the frame");
+
+            builder
+                .begin_render_pass(Arc::clone(frame), SubpassContents::Inline, clear_values.iter().copied())
+                .expect("Couldn't begin the render pass");
+            draw(hardware, screen, &mut builder, viewport);
+            for _ in 1..subpasses {
+                builder
+                    .next_subpass(SubpassContents::Inline)
+                    .expect("Couldn't go to the next subpass");
+            }
+            builder.end_render_pass().expect("Couldn't end the render pass");
+
+            builder.build().expect("Couldn't build the command buffer of the frame")
+        })
+    }
+
+    /// Like [`Engine::run`], for scenes that don't change between frames: instead of recording a command buffer
+    /// each frame, `record` is called once per framebuffer and the command buffers are submitted again.
+    ///
+    /// The command buffers must be built with the `MultipleSubmit` usage.
+    /// They are recorded again when the swapchain is recreated, and after [`Invalidation::invalidate`]
+    /// is called on [`Engine::invalidation`].
+    pub fn run_static<R>(self, render_pass: Arc<RenderPass>, record: R)
+        where
+            R: Fn(&Hardware, &Screen, &Arc<Framebuffer>, &Viewport) -> PrimaryAutoCommandBuffer
+            + 'static,
+    {
+        let mut frames = RecordedFrames::new(self.invalidation.clone());
+
+        self.run_with(render_pass, move |hardware, screen, framebuffers, image_num, viewport| {
+            let framebuffer = &framebuffers[image_num];
+            frames.get_or_record(framebuffers, framebuffer, || {
+                record(hardware, screen, framebuffer, viewport)
+            })
+        })
+    }
+
+    /// Like [`Engine::run`], but exits after `frames` frames are presented, instead of when the window is closed.
+    ///
+    /// Returns once the GPU is done with the last frame, with the time the frames took:
+    /// for benchmarks, or to render images deterministically.
+    /// Fails if the swapchain can't be recreated, or no image can be acquired from it.
+    #[cfg(run_return)]
+    pub fn run_frames<D>(
+        mut self,
+        frames: u64,
+        render_pass: Arc<RenderPass>,
+        draw: D,
+    ) -> Result<FrameStats, EngineError>
+        where
+            D: Fn(&Hardware, &Screen, &Arc<Framebuffer>, &Viewport) -> PrimaryAutoCommandBuffer,
+    {
+        self.run_until(Some(frames), render_pass, draw)
+    }
+
+    /// Like [`Engine::run`], but returns once the window is closed instead of exiting the process,
+    /// so the application can continue, for example to save its state. The window is closed when the engine is dropped.
+    ///
+    /// Returns once the GPU is done with the last frame, with the time the frames took.
+    /// Fails if the swapchain can't be recreated, or no image can be acquired from it.
+    ///
+    /// Only available on the platforms where winit can return from its event loop: not on the web nor on iOS.
+    /// On macOS, the application must not expect the event loop to handle system events after it returns,
+    /// such as the menu bar. Since the event loop is consumed, an engine can only run once.
+    #[cfg(run_return)]
+    pub fn run_return<D>(mut self, render_pass: Arc<RenderPass>, draw: D) -> Result<FrameStats, EngineError>
+        where
+            D: Fn(&Hardware, &Screen, &Arc<Framebuffer>, &Viewport) -> PrimaryAutoCommandBuffer,
+    {
+        self.run_until(None, render_pass, draw)
+    }
+
+    #[cfg(run_return)]
+    fn run_until<D>(
+        &mut self,
+        limit: Option<u64>,
+        render_pass: Arc<RenderPass>,
+        draw: D,
+    ) -> Result<FrameStats, EngineError>
+        where
+            D: Fn(&Hardware, &Screen, &Arc<Framebuffer>, &Viewport) -> PrimaryAutoCommandBuffer,
+    {
+        let mut event_loop = self.event_loop.take().expect("The engine is already running");
+        let mut frame_loop = FrameLoop::new(self, render_pass, limit);
+
+        event_loop.run_return(|event, _, control_flow| {
+            frame_loop.handle(self, event, control_flow, |hardware, screen, framebuffers, image_num, viewport| {
+                Arc::new(draw(hardware, screen, &framebuffers[image_num], viewport))
+            });
+        });
+
+        // The last frame may still be rendering or presenting
+        self.hardware
+            .graphics_queue()
+            .wait()
+            .expect("Couldn't wait for the last frame");
+        self.present_queue
+            .wait()
+            .expect("Couldn't wait for the last frame");
+
+        if let Some(error) = frame_loop.error {
+            return Err(error);
+        }
+        let mut stats = frame_loop.stats;
+        stats.total = frame_loop.started.elapsed();
+        Ok(stats)
+    }
+
+    fn run_with<D>(mut self, render_pass: Arc<RenderPass>, mut draw: D)
+        where
+            D: FnMut(&Hardware, &Screen, &[Arc<Framebuffer>], usize, &Viewport) -> Arc<PrimaryAutoCommandBuffer>
+            + 'static,
+    {
+        let event_loop = self.event_loop.take().expect("The engine is already running");
+        let mut frame_loop = FrameLoop::new(&self, render_pass, None);
+
+        event_loop.run(move |event, _, control_flow| {
+            frame_loop.handle(&mut self, event, control_flow, &mut draw);
+        });
+    }
+}
+
+/// The state of the frames rendered by [`Engine::run`] and its variants.
+struct FrameLoop {
+    render_pass: Arc<RenderPass>,
+    viewport: Viewport,
+    framebuffers: Vec<Arc<Framebuffer>>,
+    recreate_swapchain: bool,
+    // When the last resize happened, until the swapchain is recreated
+    pending_resize: Option<Instant>,
+    frame_interval: Option<Duration>,
+    next_frame: Instant,
+    previous_frame_end: Option<Box<dyn GpuFuture>>,
+    // The number of frames to present before exiting, if any
+    limit: Option<u64>,
+    started: Instant,
+    last_present: Instant,
+    stats: FrameStats,
+    // The number of frames submitted
+    frame: u64,
+    // The frames skipped in a row, and since the start of the current second
+    consecutive_skips: u32,
+    recent_skips: u64,
+    skips_since: Instant,
+    // The screenshots being copied by the GPU
+    screenshots: Vec<PendingScreenshot>,
+    // The error which stopped the loop, if any
+    error: Option<EngineError>,
+}
+
+impl FrameLoop {
+    fn new(engine: &Engine, render_pass: Arc<RenderPass>, limit: Option<u64>) -> Self {
         let mut viewport = Viewport {
             origin: [0.0, 0.0],
             dimensions: [0.0, 0.0],
             depth_range: 0.0..1.0,
         };
 
-        let mut framebuffers = window_size_dependent_setup(
-            self.screen.images(),
-            Arc::clone(&render_pass),
-            &mut viewport,
-        );
+        let framebuffers = window_size_dependent_setup(&engine.screen, Arc::clone(&render_pass), &mut viewport);
 
-        let mut recreate_swapchain = false;
+        let now = Instant::now();
+        FrameLoop {
+            render_pass,
+            viewport,
+            framebuffers,
+            recreate_swapchain: false,
+            pending_resize: None,
+            frame_interval: engine.fps_cap.interval(engine.hardware.window()),
+            next_frame: now,
+            previous_frame_end: Some(sync::now(Arc::clone(engine.hardware.graphics_device())).boxed()),
+            limit,
+            started: now,
+            last_present: now,
+            stats: FrameStats::default(),
+            frame: 0,
+            consecutive_skips: 0,
+            recent_skips: 0,
+            skips_since: now,
+            screenshots: Vec::new(),
+            error: None,
+        }
+    }
 
-        let mut previous_frame_end =
-            Some(sync::now(Arc::clone(self.hardware.graphics_device())).boxed());
+    /// Stops the loop because of `error`, which is returned by the variants of [`Engine::run`] that return.
+    fn fail(&mut self, error: EngineError, control_flow: &mut ControlFlow) {
+        error!("Stopping the engine: {}", error);
+        self.error = Some(error);
+        *control_flow = ControlFlow::Exit;
+    }
+
+    /// Counts a frame for which no swapchain image was acquired before the timeout.
+    fn skip_frame(&mut self, timeout: Option<AcquireTimeout>) {
+        self.stats.skipped += 1;
+        self.recent_skips += 1;
+        self.consecutive_skips += 1;
+        debug!("No swapchain image was acquired in time, skipping the frame ({} in a row)", self.consecutive_skips);
+
+        if timeout.is_some_and(|timeout| timeout.should_recreate(self.consecutive_skips)) {
+            warn!("{} frames were skipped in a row, recreating the swapchain", self.consecutive_skips);
+            self.recreate_swapchain = true;
+            self.consecutive_skips = 0;
+        }
+    }
+
+    /// Logs how many frames were skipped during the last second, if any.
+    fn report_skips(&mut self) {
+        let now = Instant::now();
+        if now - self.skips_since < Duration::from_secs(1) {
+            return;
+        }
 
-        self.event_loop.run(move |event, _, control_flow| {
-            match event {
-                Event::WindowEvent {
-                    event: WindowEvent::CloseRequested,
-                    ..
-                } => {
+        if self.recent_skips > 0 {
+            info!("{} frames skipped in the last second", self.recent_skips);
+        }
+        self.recent_skips = 0;
+        self.skips_since = now;
+    }
+
+    fn handle<D>(&mut self, engine: &mut Engine, event: Event<()>, control_flow: &mut ControlFlow, mut draw: D)
+        where
+            D: FnMut(&Hardware, &Screen, &[Arc<Framebuffer>], usize, &Viewport) -> Arc<PrimaryAutoCommandBuffer>,
+    {
+        if let Event::WindowEvent { event: window_event, .. } = &event {
+            if input::is_input(window_event) {
+                engine.input.handle(window_event);
+                if let Some(handler) = &mut engine.input_handler {
+                    handler(window_event);
+                }
+            }
+        }
+
+        match event {
+            Event::WindowEvent {
+                event: WindowEvent::CloseRequested,
+                ..
+            } => {
+                *control_flow = ControlFlow::Exit;
+            }
+            Event::WindowEvent {
+                event: WindowEvent::Resized(_),
+                ..
+            } => {
+                if engine.resize_debounce.is_zero() {
+                    self.recreate_swapchain = true;
+                } else {
+                    self.pending_resize = Some(Instant::now());
+                }
+            }
+            Event::WindowEvent {
+                event: WindowEvent::Moved(_) | WindowEvent::ScaleFactorChanged { .. },
+                ..
+            } => {
+                // The window may be on another monitor, with another refresh rate
+                self.frame_interval = engine.fps_cap.interval(engine.hardware.window());
+            }
+            Event::RedrawEventsCleared => {
+                if self.limit.is_some_and(|limit| self.stats.frames >= limit) {
                     *control_flow = ControlFlow::Exit;
+                    return;
                 }
-                Event::WindowEvent {
-                    event: WindowEvent::Resized(_),
-                    ..
-                } => {
-                    recreate_swapchain = true;
+
+                // Minimized windows have a zero size, which swapchains can't have: nothing is drawn until restored
+                let size = engine.hardware.window().inner_size();
+                if size.width == 0 || size.height == 0 {
+                    self.recreate_swapchain = true;
+                    *control_flow = ControlFlow::Wait;
+                    return;
                 }
-                Event::RedrawEventsCleared => {
-                    // Clean stuff reserved by the GPU
-                    previous_frame_end.as_mut().unwrap().cleanup_finished();
-
-                    //region Recreate the swapchain if necessary
-                    if recreate_swapchain {
-                        let new_screen = self.screen.recreate();
-                        let new_screen = match new_screen {
-                            Ok(r) => r,
-                            Err(SwapchainCreationError::ImageExtentNotSupported { .. }) => return,
-                            Err(e) => panic!("Couldn't recreate the swapchain: {:?}", e),
-                        };
-                        self.screen = Arc::new(new_screen);
-
-                        framebuffers = window_size_dependent_setup(
-                            self.screen.images(),
-                            render_pass.clone(),
-                            &mut viewport,
-                        );
-                        recreate_swapchain = false;
+
+                if let Some(resized) = self.pending_resize {
+                    let settled = resized + engine.resize_debounce;
+                    if Instant::now() < settled {
+                        *control_flow = ControlFlow::WaitUntil(settled);
+                        return;
                     }
-                    //endregion
-
-                    let (image_num, suboptimal, acquire_future) =
-                        match acquire_next_image(Arc::clone(self.screen.swapchain()), None) {
-                            Ok(r) => r,
-                            Err(AcquireError::OutOfDate) => {
-                                recreate_swapchain = true;
-                                return;
-                            }
-                            Err(e) => panic!("Failed to acquire next image: {:?}", e),
-                        };
-
-                    if suboptimal {
-                        recreate_swapchain = true;
+                    self.pending_resize = None;
+                    self.recreate_swapchain = true;
+                }
+
+                if let Some(interval) = self.frame_interval {
+                    let now = Instant::now();
+                    if now < self.next_frame {
+                        *control_flow = ControlFlow::WaitUntil(self.next_frame);
+                        return;
                     }
+                    // Late frames are not caught up on
+                    self.next_frame = (self.next_frame + interval).max(now);
+                }
+                *control_flow = ControlFlow::Poll;
+
+                // Clean stuff reserved by the GPU
+                self.previous_frame_end.as_mut().unwrap().cleanup_finished();
+                self.screenshots.retain(|screenshot| !screenshot.try_save());
 
-                    let command_buffer = draw(
-                        &self.hardware,
-                        &self.screen,
-                        &framebuffers[image_num],
-                        &viewport,
+                //region Recreate the swapchain if necessary
+                if self.recreate_swapchain {
+                    let new_screen = engine.screen.recreate();
+                    let new_screen = match new_screen {
+                        Ok(r) => r,
+                        Err(EngineError::Swapchain(SwapchainCreationError::ImageExtentNotSupported { .. })) => return,
+                        Err(e) => return self.fail(e, control_flow),
+                    };
+                    engine.screen = Arc::new(new_screen);
+                    engine.camera.set_pre_transform(engine.screen.pre_transform());
+
+                    // The depth image is recreated with the size of the new swapchain images
+                    self.framebuffers = window_size_dependent_setup(
+                        &engine.screen,
+                        self.render_pass.clone(),
+                        &mut self.viewport,
                     );
+                    self.recreate_swapchain = false;
+                }
+                //endregion
 
-                    let future = previous_frame_end
-                        .take()
-                        .unwrap()
-                        .join(acquire_future)
-                        .then_execute(Arc::clone(self.hardware.graphics_queue()), command_buffer)
-                        .unwrap()
-                        .then_swapchain_present(
-                            Arc::clone(self.hardware.graphics_queue()),
-                            Arc::clone(self.screen.swapchain()),
-                            image_num,
-                        )
-                        .then_signal_fence_and_flush();
-
-                    match future {
-                        Ok(future) => {
-                            previous_frame_end = Some(future.boxed());
+                self.report_skips();
+                let timeout = engine.acquire_timeout.map(|timeout| timeout.timeout());
+                let (image_num, suboptimal, acquire_future) =
+                    match acquire_next_image(Arc::clone(engine.screen.swapchain()), timeout) {
+                        Ok(r) => r,
+                        Err(AcquireError::OutOfDate) => {
+                            self.recreate_swapchain = true;
+                            return;
                         }
-                        Err(FlushError::OutOfDate) => {
-                            recreate_swapchain = true;
-                            previous_frame_end = Some(
-                                sync::now(Arc::clone(self.hardware.graphics_device())).boxed(),
-                            );
+                        Err(AcquireError::Timeout) => {
+                            self.skip_frame(engine.acquire_timeout);
+                            return;
                         }
-                        Err(e) => {
-                            warn!("Failed to flush future: {:?}", e);
-                            previous_frame_end = Some(
-                                sync::now(Arc::clone(self.hardware.graphics_device())).boxed(),
-                            );
+                        Err(e) => return self.fail(e.into(), control_flow),
+                    };
+                self.consecutive_skips = 0;
+
+                if suboptimal {
+                    self.recreate_swapchain = true;
+                }
+
+                let compute = engine
+                    .compute_pass
+                    .as_mut()
+                    .and_then(|pass| submit_compute(&engine.hardware, pass(&engine.hardware)));
+
+                let command_buffer = draw(
+                    &engine.hardware,
+                    &engine.screen,
+                    &self.framebuffers,
+                    image_num,
+                    &self.viewport,
+                );
+
+                // The swapchain image can only be copied while it is acquired, after the frame is drawn
+                let screenshot = engine
+                    .screen
+                    .take_screenshot(engine.hardware.graphics_queue(), image_num);
+
+                let mut future = self
+                    .previous_frame_end
+                    .take()
+                    .unwrap()
+                    .join(acquire_future)
+                    .boxed();
+                if let Some(compute) = compute {
+                    future = future.join(compute).boxed();
+                }
+                let mut future = future
+                    .then_execute(Arc::clone(engine.hardware.graphics_queue()), command_buffer)
+                    .unwrap()
+                    .boxed();
+                let screenshot = match screenshot {
+                    Some((copy, screenshot)) => {
+                        future = future
+                            .then_execute(Arc::clone(engine.hardware.graphics_queue()), copy)
+                            .expect("Couldn't execute the screenshot command buffer")
+                            .boxed();
+                        Some(screenshot)
+                    }
+                    None => None,
+                };
+                let future = future
+                    .then_swapchain_present(
+                        Arc::clone(&engine.present_queue),
+                        Arc::clone(engine.screen.swapchain()),
+                        image_num,
+                    )
+                    .then_signal_fence_and_flush();
+
+                let mut report = PresentReport {
+                    frame: self.frame,
+                    image: image_num,
+                    suboptimal,
+                    error: None,
+                    displayed: Displayed::Unknown,
+                };
+                self.frame += 1;
+
+                match future {
+                    Ok(future) => {
+                        self.previous_frame_end = Some(future.boxed());
+                        self.screenshots.extend(screenshot);
+
+                        let now = Instant::now();
+                        self.stats.record(now - self.last_present);
+                        self.last_present = now;
+                        if let Some(timer) = &engine.frame_timer {
+                            timer.record(now);
                         }
                     }
+                    Err(FlushError::OutOfDate) => {
+                        self.recreate_swapchain = true;
+                        self.previous_frame_end = Some(
+                            sync::now(Arc::clone(engine.hardware.graphics_device())).boxed(),
+                        );
+                        report.error = Some(FlushError::OutOfDate);
+                        report.displayed = Displayed::NotPresented;
+                    }
+                    Err(e) => {
+                        warn!("Failed to flush future: {:?}", e);
+                        self.previous_frame_end = Some(
+                            sync::now(Arc::clone(engine.hardware.graphics_device())).boxed(),
+                        );
+                        report.error = Some(e);
+                        report.displayed = Displayed::NotPresented;
+                    }
                 }
-                _ => (),
+                engine.present_log.record(report);
+
+                engine.resources.end_frame();
             }
-        });
+            _ => (),
+        }
+    }
+}
+
+/// Executes the command buffer of a compute pass on the compute queue.
+///
+/// Returns the semaphore the frame must wait on, or `None` if the compute queue is on another device,
+/// in which case this waits until the compute pass is done.
+fn submit_compute(hardware: &Hardware, command_buffer: PrimaryAutoCommandBuffer) -> Option<Box<dyn GpuFuture>> {
+    let queue = hardware
+        .compute_queue()
+        .expect("Compute passes are only set with a compute queue");
+    let future = sync::now(Arc::clone(queue.device()))
+        .then_execute(Arc::clone(queue), command_buffer)
+        .expect("Couldn't execute the compute pass");
+
+    if hardware.shares_device() {
+        let semaphore = future
+            .then_signal_semaphore_and_flush()
+            .expect("Couldn't submit the compute pass");
+        Some(semaphore.boxed())
+    } else {
+        future
+            .then_signal_fence_and_flush()
+            .expect("Couldn't submit the compute pass")
+            .wait(None)
+            .expect("Couldn't run the compute pass");
+        None
     }
 }
 
 impl Default for Engine {
     fn default() -> Self {
-        Self::new()
+        Self::new_or_panic()
+    }
+}
+
+#[derive(Debug)]
+pub enum EngineError {
+    /// The Vulkan instance, the window or the device couldn't be created.
+    Hardware(HardwareError),
+    Swapchain(SwapchainCreationError),
+    /// The capabilities of the window surface couldn't be queried, for example because the device was lost.
+    Surface(SurfacePropertiesError),
+    /// No swapchain image could be acquired to draw a frame.
+    Acquire(AcquireError),
+    /// The GPU profiler couldn't be created.
+    Profiler(ProfilerError),
+    /// The window surface doesn't report any format to create the swapchain with.
+    NoSurfaceFormat,
+    /// The format forced by [`WindowConfig::format`] isn't supported by the window.
+    UnsupportedFormat {
+        forced: (Format, ColorSpace),
+        supported: Vec<(Format, ColorSpace)>,
+    },
+}
+
+impl fmt::Display for EngineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EngineError::Hardware(e) => write!(f, "{}", e),
+            EngineError::Swapchain(e) => write!(f, "couldn't create the swapchain: {}", e),
+            EngineError::Surface(e) => write!(f, "couldn't query the window surface: {}", e),
+            EngineError::Acquire(e) => write!(f, "couldn't acquire a swapchain image: {}", e),
+            EngineError::Profiler(e) => write!(f, "{}", e),
+            EngineError::NoSurfaceFormat => write!(f, "the window doesn't support any format"),
+            EngineError::UnsupportedFormat { forced, supported } => write!(
+                f,
+                "the format {:?} in the color space {:?} is required, but the window only supports {:?}",
+                forced.0, forced.1, supported
+            ),
+        }
+    }
+}
+
+impl Error for EngineError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            EngineError::Hardware(e) => Some(e),
+            EngineError::Swapchain(e) => Some(e),
+            EngineError::Surface(e) => Some(e),
+            EngineError::Acquire(e) => Some(e),
+            EngineError::Profiler(e) => Some(e),
+            EngineError::NoSurfaceFormat | EngineError::UnsupportedFormat { .. } => None,
+        }
+    }
+}
+
+impl From<HardwareError> for EngineError {
+    fn from(e: HardwareError) -> Self {
+        EngineError::Hardware(e)
+    }
+}
+
+impl From<SwapchainCreationError> for EngineError {
+    fn from(e: SwapchainCreationError) -> Self {
+        EngineError::Swapchain(e)
+    }
+}
+
+impl From<SurfacePropertiesError> for EngineError {
+    fn from(e: SurfacePropertiesError) -> Self {
+        EngineError::Surface(e)
+    }
+}
+
+impl From<AcquireError> for EngineError {
+    fn from(e: AcquireError) -> Self {
+        EngineError::Acquire(e)
+    }
+}
+
+impl From<ProfilerError> for EngineError {
+    fn from(e: ProfilerError) -> Self {
+        EngineError::Profiler(e)
     }
 }
 
 fn window_size_dependent_setup(
-    images: &[Arc<SwapchainImage<Window>>],
+    screen: &Screen,
     render_pass: Arc<RenderPass>,
     viewport: &mut Viewport,
 ) -> Vec<Arc<Framebuffer>> {
+    let images = screen.images();
     let dimensions = images[0].dimensions().width_height();
     viewport.dimensions = [dimensions[0] as f32, dimensions[1] as f32];
 
+    // A single depth and multisampled image is enough: the frames using them are executed one after the other
+    let multisampled = screen.multisampled_attachment();
+    let depth = screen.depth_attachment();
+
     images
         .iter()
         .map(|image| {
             let view = ImageView::new_default(image.clone()).unwrap();
+            let mut attachments: Vec<Arc<dyn ImageViewAbstract>> = Vec::new();
+            if let Some(multisampled) = &multisampled {
+                attachments.push(Arc::clone(multisampled) as Arc<_>);
+            }
+            attachments.push(view);
+            if let Some(depth) = &depth {
+                attachments.push(Arc::clone(depth) as Arc<_>);
+            }
             Framebuffer::new(
                 render_pass.clone(),
                 FramebufferCreateInfo {
-                    attachments: vec![view],
+                    attachments,
                     ..Default::default()
                 },
             )
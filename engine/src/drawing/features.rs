@@ -0,0 +1,170 @@
+use std::error::Error;
+use std::ffi::CString;
+use std::fmt;
+use std::io;
+
+use vulkano::device::physical::PhysicalDevice;
+use vulkano::device::{DeviceCreationError, DeviceExtensions, Features};
+use vulkano::instance::InstanceCreationError;
+
+use crate::drawing::queues::QueueAssignmentError;
+
+/// The device features and extensions an application needs, on top of the ones the engine enables when they are supported.
+///
+/// Devices without the `required` features or the `required_extensions` aren't selected. The `preferred` features are enabled
+/// when the selected device supports them, see [`Hardware::enabled_preferred_features`](crate::drawing::hardware::Hardware::enabled_preferred_features).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FeatureRequest {
+    pub required: Features,
+    pub preferred: Features,
+    /// Enabled on all the devices, for example `khr_storage_buffer_storage_class` for a compute kernel.
+    pub required_extensions: DeviceExtensions,
+}
+
+impl Default for FeatureRequest {
+    fn default() -> Self {
+        FeatureRequest {
+            required: Features::none(),
+            preferred: Features::none(),
+            required_extensions: DeviceExtensions::none(),
+        }
+    }
+}
+
+impl FeatureRequest {
+    pub fn require(mut self, features: &Features) -> Self {
+        self.required = union(&self.required, features);
+        self
+    }
+
+    pub fn prefer(mut self, features: &Features) -> Self {
+        self.preferred = union(&self.preferred, features);
+        self
+    }
+
+    pub fn require_extensions(mut self, extensions: &DeviceExtensions) -> Self {
+        self.required_extensions = self.required_extensions.union(extensions);
+        self
+    }
+
+    /// The names of the required features and extensions `physical` doesn't support.
+    pub(crate) fn missing_from(&self, physical: PhysicalDevice) -> Vec<String> {
+        let mut missing = feature_names(&self.required.difference(physical.supported_features()));
+        missing.extend(extension_names(
+            &self.required_extensions.difference(physical.supported_extensions()),
+        ));
+        missing
+    }
+
+    /// Whether anything is required at all.
+    pub(crate) fn has_requirements(&self) -> bool {
+        self.required != Features::none() || self.required_extensions != DeviceExtensions::none()
+    }
+}
+
+/// The features enabled in either `a` or `b`.
+pub fn union(a: &Features, b: &Features) -> Features {
+    // Features only provide intersections and differences
+    let all = Features::all();
+    all.difference(&all.difference(a).intersection(&all.difference(b)))
+}
+
+/// The names of the features enabled in `features`, as in the fields of [`Features`].
+pub fn feature_names(features: &Features) -> Vec<String> {
+    // Features don't expose their fields generically, but their debug representation lists them all
+    let debug = format!("{:?}", features);
+    debug
+        .trim_start_matches("Features {")
+        .trim_end_matches('}')
+        .split(',')
+        .filter_map(|field| field.trim().strip_suffix(": true"))
+        .map(str::to_owned)
+        .collect()
+}
+
+/// The names of the extensions enabled in `extensions`, as in the Vulkan specification.
+pub fn extension_names(extensions: &DeviceExtensions) -> Vec<String> {
+    Vec::<CString>::from(extensions)
+        .into_iter()
+        .map(|name| name.to_string_lossy().into_owned())
+        .collect()
+}
+
+#[derive(Debug)]
+pub enum HardwareError {
+    /// The Vulkan library couldn't be loaded, or the instance couldn't be created, usually because of a missing driver.
+    Instance(InstanceCreationError),
+    /// The window or its surface couldn't be created.
+    Surface(vulkano_win::CreationError),
+    /// No device supports the extensions the engine needs.
+    NoDevice,
+    /// No queue family of the devices can both draw and present to the window.
+    NoGraphicsQueue,
+    /// No queue family of the devices supports compute, which headless engines need.
+    NoComputeQueue,
+    Queues(QueueAssignmentError),
+    /// No device has all the required features and extensions.
+    ///
+    /// Lists the ones missing from the device closest to supporting them all.
+    MissingFeatures(Vec<String>),
+    Device(DeviceCreationError),
+    /// The file receiving the validation messages couldn't be created.
+    ValidationLog(io::Error),
+}
+
+impl fmt::Display for HardwareError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HardwareError::Instance(e) => write!(f, "couldn't create the Vulkan instance: {}", e),
+            HardwareError::Surface(e) => write!(f, "couldn't create the window: {}", e),
+            HardwareError::NoDevice => write!(f, "no device supports Vulkan presentation"),
+            HardwareError::NoGraphicsQueue => write!(f, "no queue family can draw and present to the window"),
+            HardwareError::NoComputeQueue => write!(f, "no queue family supports compute"),
+            HardwareError::Queues(e) => write!(f, "couldn't select the queue families: {}", e),
+            HardwareError::MissingFeatures(features) => write!(
+                f,
+                "no device supports the required features and extensions, missing: {}",
+                features.join(", ")
+            ),
+            HardwareError::Device(e) => write!(f, "couldn't create the device: {}", e),
+            HardwareError::ValidationLog(e) => write!(f, "couldn't create the validation log: {}", e),
+        }
+    }
+}
+
+impl Error for HardwareError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            HardwareError::Instance(e) => Some(e),
+            HardwareError::Surface(e) => Some(e),
+            HardwareError::Queues(e) => Some(e),
+            HardwareError::Device(e) => Some(e),
+            HardwareError::ValidationLog(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<QueueAssignmentError> for HardwareError {
+    fn from(e: QueueAssignmentError) -> Self {
+        HardwareError::Queues(e)
+    }
+}
+
+impl From<InstanceCreationError> for HardwareError {
+    fn from(e: InstanceCreationError) -> Self {
+        HardwareError::Instance(e)
+    }
+}
+
+impl From<vulkano_win::CreationError> for HardwareError {
+    fn from(e: vulkano_win::CreationError) -> Self {
+        HardwareError::Surface(e)
+    }
+}
+
+impl From<DeviceCreationError> for HardwareError {
+    fn from(e: DeviceCreationError) -> Self {
+        HardwareError::Device(e)
+    }
+}
@@ -0,0 +1,107 @@
+use std::sync::Arc;
+
+use vulkano::command_buffer::{AutoCommandBufferBuilder, PrimaryAutoCommandBuffer};
+use vulkano::device::Device;
+use vulkano::pipeline::graphics::color_blend::{AttachmentBlend, ColorBlendState};
+use vulkano::pipeline::graphics::depth_stencil::{CompareOp, DepthState, DepthStencilState};
+use vulkano::pipeline::graphics::input_assembly::InputAssemblyState;
+use vulkano::pipeline::graphics::viewport::ViewportState;
+use vulkano::pipeline::{GraphicsPipeline, StateMode};
+use vulkano::render_pass::Subpass;
+use vulkano::shader::EntryPoint;
+
+/// Builds a graphics pipeline running `fragment_shader` over the whole viewport.
+///
+/// The vertex stage is provided by the engine: it emits a single triangle covering the screen
+/// (see https://stackoverflow.com/a/59739538), and passes the screen-space coordinates to the fragment shader:
+/// ```glsl
+/// // (0, 0): top-left of the screen, (1, 1): bottom-right of the screen
+/// layout(location = 0) in vec2 uv;
+/// ```
+pub fn pipeline(
+    device: &Arc<Device>,
+    fragment_shader: EntryPoint,
+    subpass: Subpass,
+) -> Arc<GraphicsPipeline> {
+    let vs = vs::load(Arc::clone(device)).expect("Couldn't load the fullscreen vertex shader");
+
+    GraphicsPipeline::start()
+        .vertex_shader(vs.entry_point("main").unwrap(), ())
+        .input_assembly_state(InputAssemblyState::new())
+        .viewport_state(ViewportState::viewport_dynamic_scissor_irrelevant())
+        .fragment_shader(fragment_shader, ())
+        .render_pass(subpass)
+        .build(Arc::clone(device))
+        .expect("Couldn't create the fullscreen pipeline")
+}
+
+/// Like [`pipeline`], for a subpass with a depth attachment: every fragment overwrites the depth,
+/// which the fragment shader must write to `gl_FragDepth`.
+pub fn depth_pipeline(
+    device: &Arc<Device>,
+    fragment_shader: EntryPoint,
+    subpass: Subpass,
+) -> Arc<GraphicsPipeline> {
+    let vs = vs::load(Arc::clone(device)).expect("Couldn't load the fullscreen vertex shader");
+
+    GraphicsPipeline::start()
+        .vertex_shader(vs.entry_point("main").unwrap(), ())
+        .input_assembly_state(InputAssemblyState::new())
+        .viewport_state(ViewportState::viewport_dynamic_scissor_irrelevant())
+        .fragment_shader(fragment_shader, ())
+        .depth_stencil_state(DepthStencilState {
+            depth: Some(DepthState {
+                enable_dynamic: false,
+                write_enable: StateMode::Fixed(true),
+                compare_op: StateMode::Fixed(CompareOp::Always),
+            }),
+            ..Default::default()
+        })
+        .render_pass(subpass)
+        .build(Arc::clone(device))
+        .expect("Couldn't create the fullscreen pipeline")
+}
+
+/// Like [`pipeline`], blending the output of `fragment_shader` with every color attachment of the subpass.
+pub fn blended_pipeline(
+    device: &Arc<Device>,
+    fragment_shader: EntryPoint,
+    subpass: Subpass,
+    blend: AttachmentBlend,
+) -> Arc<GraphicsPipeline> {
+    let vs = vs::load(Arc::clone(device)).expect("Couldn't load the fullscreen vertex shader");
+
+    GraphicsPipeline::start()
+        .vertex_shader(vs.entry_point("main").unwrap(), ())
+        .input_assembly_state(InputAssemblyState::new())
+        .viewport_state(ViewportState::viewport_dynamic_scissor_irrelevant())
+        .fragment_shader(fragment_shader, ())
+        .color_blend_state(ColorBlendState::new(subpass.num_color_attachments()).blend(blend))
+        .render_pass(subpass)
+        .build(Arc::clone(device))
+        .expect("Couldn't create the fullscreen pipeline")
+}
+
+/// Records a fullscreen pass.
+///
+/// The pipeline created by [`pipeline`], its descriptor sets and its push constants must already be bound.
+pub fn draw(builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>) {
+    builder
+        .draw(3, 1, 0, 0)
+        .expect("Couldn't record the fullscreen pass");
+}
+
+#[allow(clippy::needless_question_mark)]
+mod vs {
+    vulkano_shaders::shader! {
+        ty: "vertex",
+        src: "
+#version 450
+layout(location = 0) out vec2 uv;
+
+void main() {
+    uv = vec2((gl_VertexIndex << 1) & 2, gl_VertexIndex & 2);
+    gl_Position = vec4(uv * 2.0 - 1.0, 0.0, 1.0);
+}"
+    }
+}
@@ -0,0 +1,170 @@
+use std::sync::Arc;
+
+use vulkano::command_buffer::{AutoCommandBufferBuilder, PrimaryAutoCommandBuffer};
+use vulkano::pipeline::graphics::color_blend::ColorBlendState;
+use vulkano::pipeline::graphics::depth_stencil::DepthStencilState;
+use vulkano::pipeline::graphics::input_assembly::InputAssemblyState;
+use vulkano::pipeline::graphics::viewport::ViewportState;
+use vulkano::pipeline::{GraphicsPipeline, Pipeline, StateMode};
+use vulkano::render_pass::Subpass;
+
+use crate::drawing::hardware::Hardware;
+
+/// How [`DebugGrid`] draws the ground plane.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GridConfig {
+    /// The distance between two lines, in world units.
+    pub spacing: f32,
+    /// The distance from the camera at which the grid has completely faded out.
+    pub fade_distance: f32,
+    /// The color of the lines, blended over the scene.
+    pub color: [f32; 4],
+}
+
+impl Default for GridConfig {
+    fn default() -> Self {
+        GridConfig {
+            spacing: 1.0,
+            fade_distance: 100.0,
+            color: [0.5, 0.5, 0.5, 1.0],
+        }
+    }
+}
+
+/// Draws an infinite grid on the `y = 0` plane, as a spatial reference while bringing up a scene.
+///
+/// The grid is drawn over the whole viewport: each fragment intersects its view ray with the plane.
+/// If the subpass has a depth attachment, the grid is hidden behind the geometry already drawn.
+pub struct DebugGrid {
+    pipeline: Arc<GraphicsPipeline>,
+}
+
+impl DebugGrid {
+    pub fn new(hardware: &Hardware, subpass: Subpass) -> Self {
+        let device = hardware.graphics_device();
+        let vs = vs::load(Arc::clone(device)).expect("Couldn't load the grid vertex shader");
+        let fs = fs::load(Arc::clone(device)).expect("Couldn't load the grid fragment shader");
+
+        let depth_stencil = if subpass.has_depth() {
+            let mut state = DepthStencilState::simple_depth_test();
+            // The grid is transparent: it must not hide what is drawn after it
+            if let Some(depth) = &mut state.depth {
+                depth.write_enable = StateMode::Fixed(false);
+            }
+            state
+        } else {
+            DepthStencilState::disabled()
+        };
+
+        let pipeline = GraphicsPipeline::start()
+            .vertex_shader(vs.entry_point("main").unwrap(), ())
+            .input_assembly_state(InputAssemblyState::new())
+            .viewport_state(ViewportState::viewport_dynamic_scissor_irrelevant())
+            .fragment_shader(fs.entry_point("main").unwrap(), ())
+            .color_blend_state(ColorBlendState::new(subpass.num_color_attachments()).blend_alpha())
+            .depth_stencil_state(depth_stencil)
+            .render_pass(subpass)
+            .build(Arc::clone(device))
+            .expect("Couldn't create the grid pipeline");
+
+        DebugGrid { pipeline }
+    }
+
+    /// Records the grid in the current subpass, whose viewport must already be set.
+    ///
+    /// `view_projection` transforms world coordinates to clip space, as a column-major matrix.
+    pub fn draw(
+        &self,
+        builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        view_projection: [[f32; 4]; 4],
+        config: &GridConfig,
+    ) {
+        let parameters = fs::ty::Parameters {
+            view_projection,
+            color: config.color,
+            spacing: config.spacing,
+            fade_distance: config.fade_distance,
+        };
+
+        builder
+            .bind_pipeline_graphics(Arc::clone(&self.pipeline))
+            .push_constants(Arc::clone(self.pipeline.layout()), 0, parameters)
+            .draw(3, 1, 0, 0)
+            .expect("Couldn't record the grid");
+    }
+}
+
+#[allow(clippy::needless_question_mark)]
+mod vs {
+    vulkano_shaders::shader! {
+        ty: "vertex",
+        src: "
+#version 450
+layout(location = 0) out vec3 near_point;
+layout(location = 1) out vec3 far_point;
+
+layout(push_constant) uniform Parameters {
+    mat4 view_projection;
+    vec4 color;
+    float spacing;
+    float fade_distance;
+} parameters;
+
+vec3 unproject(vec2 position, float depth, mat4 inverse_view_projection) {
+    vec4 point = inverse_view_projection * vec4(position, depth, 1.0);
+    return point.xyz / point.w;
+}
+
+void main() {
+    vec2 uv = vec2((gl_VertexIndex << 1) & 2, gl_VertexIndex & 2);
+    vec2 position = uv * 2.0 - 1.0;
+
+    mat4 inverse_view_projection = inverse(parameters.view_projection);
+    near_point = unproject(position, 0.0, inverse_view_projection);
+    far_point = unproject(position, 1.0, inverse_view_projection);
+
+    gl_Position = vec4(position, 0.0, 1.0);
+}"
+    }
+}
+
+#[allow(clippy::needless_question_mark)]
+mod fs {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        src: "
+#version 450
+layout(location = 0) in vec3 near_point;
+layout(location = 1) in vec3 far_point;
+layout(location = 0) out vec4 f_color;
+
+layout(push_constant) uniform Parameters {
+    mat4 view_projection;
+    vec4 color;
+    float spacing;
+    float fade_distance;
+} parameters;
+
+void main() {
+    vec3 ray = far_point - near_point;
+    float t = -near_point.y / ray.y;
+    vec3 position = near_point + t * ray;
+
+    // Lines are one pixel wide, whatever their distance
+    vec2 coordinates = position.xz / parameters.spacing;
+    vec2 distance_to_line = abs(fract(coordinates - 0.5) - 0.5) / fwidth(coordinates);
+    float line = 1.0 - min(min(distance_to_line.x, distance_to_line.y), 1.0);
+
+    float fade = 1.0 - clamp(distance(position, near_point) / parameters.fade_distance, 0.0, 1.0);
+
+    // Discarding after the derivatives are computed, so they stay defined
+    if (t <= 0.0 || line * fade <= 0.0) {
+        discard;
+    }
+
+    vec4 clip = parameters.view_projection * vec4(position, 1.0);
+    gl_FragDepth = clip.z / clip.w;
+    f_color = vec4(parameters.color.rgb, parameters.color.a * line * fade);
+}"
+    }
+}
@@ -1,47 +1,156 @@
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 
-use log::{debug, info, trace};
+use image::ImageError;
+use log::{debug, info, trace, warn};
+use vulkano::buffer::{BufferContents, BufferUsage, CpuAccessibleBuffer, DeviceLocalBuffer, TypedBufferAccess};
+use vulkano::command_buffer::{
+    AutoCommandBufferBuilder, CommandBufferExecFuture, CommandBufferUsage, PrimaryAutoCommandBuffer,
+};
+use vulkano::descriptor_set::WriteDescriptorSet;
 use vulkano::device::{Device, DeviceCreateInfo, Queue, QueueCreateInfo};
-use vulkano::device::DeviceExtensions;
+use vulkano::device::{DeviceExtensions, Features};
 use vulkano::device::physical::{PhysicalDevice, PhysicalDeviceType};
-use vulkano::instance::{Instance, InstanceCreateInfo};
+use vulkano::format::Format;
+use vulkano::image::view::ImageView;
+use vulkano::image::{ImageCreateFlags, ImageDimensions, ImageLayout, ImageUsage, ImmutableImage, MipmapsCount};
+use vulkano::instance::{Instance, InstanceCreateInfo, InstanceCreationError, InstanceExtensions};
+use vulkano::pipeline::cache::PipelineCache;
+use vulkano::shader::EntryPoint;
 use vulkano::swapchain::Surface;
+use vulkano::sync;
+use vulkano::sync::{GpuFuture, NowFuture};
+use vulkano::{DeviceSize, Version};
 use vulkano_win::VkSurfaceBuild;
 use winit::event_loop::EventLoop;
-use winit::window::{Window, WindowBuilder};
+use winit::window::Window;
+
+use crate::drawing::capture::Capture;
+use crate::drawing::compute;
+use crate::drawing::compute::ComputeError;
+use crate::drawing::descriptor_pool::DescriptorAllocator;
+use crate::drawing::features::{feature_names, union, FeatureRequest, HardwareError};
+use crate::drawing::queues;
+use crate::drawing::queues::{QueueAssignment, QueueAssignmentError, QueueFamilyInfo, QueueRole};
+use crate::drawing::readback::ReadbackHandle;
+use crate::drawing::subgroup::SubgroupProperties;
+use crate::drawing::validation;
+use crate::drawing::validation::{Validation, ValidationConfig};
+use crate::drawing::window::WindowConfig;
 
 /// Relay between the [`Engine`] and Vulkan.
 pub struct Hardware {
-    surface: Arc<Surface<Window>>,
+    // None if headless
+    surface: Option<Arc<Surface<Window>>>,
     graphics_queue: Arc<Queue>,
-    compute_queue: Arc<Queue>,
+    // None if no queue family supports compute
+    compute_queue: Option<Arc<Queue>>,
+    transfer_queue: Option<Arc<Queue>>,
+    capture: Capture,
+    descriptor_pool: DescriptorAllocator,
+    pipeline_cache: Arc<PipelineCache>,
+    enabled_preferred: Features,
+    _validation: Option<Validation>,
+    // u64::MAX if there is no threshold
+    dedicated_threshold: AtomicU64,
+}
+
+/// How the devices and their queues are selected.
+///
+/// The default selects the best device automatically, preferring discrete GPUs.
+#[derive(Debug, Clone, Default)]
+pub struct HardwareConfig {
+    /// Selects a device of this type first, for example an integrated GPU to save battery.
+    pub preferred_device_type: Option<PhysicalDeviceType>,
+    /// Selects a device whose name contains this text first, ignoring the case.
+    pub preferred_device_name: Option<String>,
+    /// The queue families to use instead of selecting them automatically. The devices are still selected as usual.
+    pub queues: QueueAssignment,
+    /// The features to enable, see [`Hardware::enabled_features`] and [`Hardware::enabled_preferred_features`].
+    pub features: FeatureRequest,
+    /// Enables the validation layer, reporting its messages as described. `None` disables it.
+    pub validation: Option<ValidationConfig>,
+}
+
+impl HardwareConfig {
+    fn has_preference(&self) -> bool {
+        self.preferred_device_type.is_some() || self.preferred_device_name.is_some()
+    }
+
+    /// Whether `physical` matches all the preferences.
+    fn prefers(&self, physical: PhysicalDevice) -> bool {
+        let properties = physical.properties();
+        let type_matches = self
+            .preferred_device_type
+            .is_none_or(|device_type| properties.device_type == device_type);
+        let name_matches = self.preferred_device_name.as_ref().is_none_or(|name| {
+            properties
+                .device_name
+                .to_lowercase()
+                .contains(&name.to_lowercase())
+        });
+        type_matches && name_matches
+    }
 }
 
 impl Hardware {
-    pub fn new(event_loop: &EventLoop<()>) -> Self {
+    /// Selects the devices as described by `config`, and connects to them.
+    ///
+    /// When no device matches the preferences of `config`, the best device is selected instead.
+    pub fn new(
+        event_loop: &EventLoop<()>,
+        window: &WindowConfig,
+        config: &HardwareConfig,
+    ) -> Result<Self, HardwareError> {
+        Self::create(Some((event_loop, window)), config)
+    }
+
+    /// Same as [`Hardware::new`], without any window, to run compute shaders on machines without a display.
+    ///
+    /// The graphics queue can't present, and [`Hardware::surface`] and [`Hardware::window`] panic.
+    pub fn headless(config: &HardwareConfig) -> Result<Self, HardwareError> {
+        Self::create(None, config)
+    }
+
+    fn create(
+        window: Option<(&EventLoop<()>, &WindowConfig)>,
+        config: &HardwareConfig,
+    ) -> Result<Self, HardwareError> {
+        let queues = &config.queues;
+        let features = &config.features;
+
         debug!("Vulkan and window initialization…");
+        let capture = Capture::new();
+
         trace!("Connecting to Vulkan…");
-        let required_extensions = vulkano_win::required_extensions();
-        let instance = Instance::new(InstanceCreateInfo {
-            enabled_extensions: required_extensions,
-            ..Default::default()
-        })
-            .expect("Couldn't instantiate the Vulkan instance");
+        let enabled_layers = config.validation.as_ref().map(validation::layers).unwrap_or_default();
+        let instance = create_instance(window.is_some(), enabled_layers)?;
+
+        let validation = match &config.validation {
+            Some(validation) if instance.enabled_extensions().ext_debug_utils => {
+                Some(Validation::new(&instance, validation).map_err(HardwareError::ValidationLog)?)
+            }
+            _ => None,
+        };
 
-        trace!("Creating the surface…");
-        let surface = WindowBuilder::new()
-            .build_vk_surface(event_loop, Arc::clone(&instance))
-            .expect("Couldn't create a Vulkan surface");
+        let surface = match window {
+            Some((event_loop, window)) => {
+                trace!("Creating the surface…");
+                Some(window.builder().build_vk_surface(event_loop, Arc::clone(&instance))?)
+            }
+            None => None,
+        };
 
         // The extensions required by the engine
         let device_extensions = DeviceExtensions {
-            khr_swapchain: true,
+            khr_swapchain: surface.is_some(),
             ..DeviceExtensions::none()
         };
 
         info!("Selecting the devices to use…");
-        let physical_candidates: Vec<(i32, PhysicalDevice)> = PhysicalDevice::enumerate(&instance)
-            .map(|physical| {
+        let mut physical_candidates: Vec<(i32, PhysicalDevice)> = PhysicalDevice::enumerate(&instance)
+            .inspect(|physical| {
                 let properties = physical.properties();
                 info!(
                     " - {} ({:?})",
@@ -49,13 +158,19 @@ impl Hardware {
                 );
                 trace!("   API: {}", physical.api_version());
                 trace!("   Driver: {}", properties.driver_version);
-                physical
             })
             .filter(|physical| {
                 physical
                     .supported_extensions()
                     .is_superset_of(&device_extensions)
             })
+            .filter(|physical| {
+                let missing = features.missing_from(*physical);
+                if !missing.is_empty() {
+                    info!("   Missing the required {}", missing.join(", "));
+                }
+                missing.is_empty()
+            })
             .map(|physical| {
                 // Assign a score to each type of device
                 // Lower means better
@@ -71,6 +186,33 @@ impl Hardware {
             })
             .collect();
 
+        if physical_candidates.is_empty() {
+            let closest = PhysicalDevice::enumerate(&instance)
+                .filter(|physical| physical.supported_extensions().is_superset_of(&device_extensions))
+                .map(|physical| features.missing_from(physical))
+                .min_by_key(Vec::len);
+            return match closest {
+                Some(missing) if features.has_requirements() => Err(HardwareError::MissingFeatures(missing)),
+                _ => Err(HardwareError::NoDevice),
+            };
+        }
+
+        // The preferred devices come first, whatever their type
+        if config.has_preference() {
+            if physical_candidates.iter().any(|(_, physical)| config.prefers(*physical)) {
+                for (score, physical) in &mut physical_candidates {
+                    if config.prefers(*physical) {
+                        *score = -1;
+                    }
+                }
+            } else {
+                warn!(
+                    "No device matches the preferences {:?} {:?}, selecting the best device instead",
+                    config.preferred_device_type, config.preferred_device_name,
+                );
+            }
+        }
+
         // Debug the different queues
         trace!("Available family queues:");
         for (score, physical_candidate) in &physical_candidates {
@@ -103,26 +245,37 @@ impl Hardware {
         }
 
         // Find a graphics queue and a compute queue
-        let (_, graphics_physical, graphics_family) = physical_candidates
+        let (_, graphics_physical, mut graphics_family) = physical_candidates
             .iter()
             .filter_map(|(score, physical)| {
                 physical
                     .queue_families()
                     .find(|family| {
                         family.supports_graphics()
-                            && family.supports_surface(&surface).unwrap_or(false)
+                            && surface
+                                .as_ref()
+                                .is_none_or(|surface| Self::can_present(*physical, family.id(), surface))
                     })
                     .map(|family| (score, physical, family))
             })
             .min_by_key(|(score, _, _)| *score)
-            .expect("Could not find a suitable graphics queue family");
+            .ok_or(HardwareError::NoGraphicsQueue)?;
+        if let Some(id) = queues.graphics {
+            graphics_family = queues::validate(*graphics_physical, QueueRole::Graphics, id)?;
+            let presents = surface
+                .as_ref()
+                .is_none_or(|surface| Self::can_present(*graphics_physical, id, surface));
+            if !presents {
+                return Err(QueueAssignmentError::CannotPresent(id).into());
+            }
+        }
         info!(
             "Selected for graphics: {} / family {}",
             graphics_physical.properties().device_name,
             graphics_family.id()
         );
 
-        let (_, compute_physical, compute_family) = physical_candidates
+        let mut compute = physical_candidates
             .iter()
             .filter_map(|(score, physical)| {
                 physical
@@ -131,105 +284,229 @@ impl Hardware {
                     .map(|family| (score, physical, family))
             })
             .min_by_key(|(score, _, _)| *score)
-            .expect("Could not find a suitable compute queue family");
-        info!(
-            "Selected for compute: {} / family {}",
-            compute_physical.properties().device_name,
-            compute_family.id()
-        );
+            .map(|(_, physical, family)| (physical, family));
+        if let Some(id) = queues.compute {
+            // An explicit assignment is on the device selected for compute, or on the graphics device without one
+            let physical = compute.map_or(graphics_physical, |(physical, _)| physical);
+            compute = Some((physical, queues::validate(*physical, QueueRole::Compute, id)?));
+        }
+        match compute {
+            Some((compute_physical, compute_family)) => {
+                if compute_physical.index() == graphics_physical.index()
+                    && graphics_family.id() == compute_family.id()
+                    && graphics_family.queues_count() < 2
+                    && (queues.graphics.is_some() || queues.compute.is_some())
+                {
+                    return Err(QueueAssignmentError::NotEnoughQueues(graphics_family.id()).into());
+                }
+                info!(
+                    "Selected for compute: {} / family {}",
+                    compute_physical.properties().device_name,
+                    compute_family.id()
+                );
+            }
+            None if window.is_none() => return Err(HardwareError::NoComputeQueue),
+            None => warn!("No queue family supports compute, compute shaders are unavailable"),
+        }
+
+        // A family that only supports transfers is usually backed by a dedicated DMA engine,
+        // which can upload data while the graphics queue is busy rendering
+        let transfer_family = match queues.transfer {
+            Some(id) => {
+                let family = queues::validate(*graphics_physical, QueueRole::Transfer, id)?;
+                let shared_with_compute = compute.is_some_and(|(compute_physical, compute_family)| {
+                    graphics_physical.index() == compute_physical.index() && id == compute_family.id()
+                });
+                if id == graphics_family.id() || shared_with_compute {
+                    return Err(QueueAssignmentError::TransferNotDedicated(id).into());
+                }
+                Some(family)
+            }
+            None => graphics_physical.queue_families().find(|family| {
+                family.explicitly_supports_transfers()
+                    && !family.supports_graphics()
+                    && !family.supports_compute()
+            }),
+        };
+        match transfer_family {
+            Some(family) => info!(
+                "Selected for transfers: {} / family {}",
+                graphics_physical.properties().device_name,
+                family.id()
+            ),
+            None => info!("No dedicated transfer queue family, transfers will use the graphics queue"),
+        }
+
+        // Sparse resources, depth clamping, multiple viewports, arrays of textures and conditional rendering
+        // are opt-in, but the features must be enabled when the device is created
+        let graphics_features = graphics_physical.supported_features().intersection(&Features {
+            sparse_binding: true,
+            sparse_residency_buffer: true,
+            depth_clamp: true,
+            multi_viewport: true,
+            shader_output_viewport_index: true,
+            shader_sampled_image_array_dynamic_indexing: true,
+            runtime_descriptor_array: true,
+            descriptor_binding_variable_descriptor_count: true,
+            conditional_rendering: true,
+            ..Features::none()
+        });
+        let enabled_preferred = graphics_physical.supported_features().intersection(&features.preferred);
+        let unavailable = feature_names(&features.preferred.difference(&enabled_preferred));
+        if !unavailable.is_empty() {
+            warn!("Preferred features unavailable on the graphics device: {}", unavailable.join(", "));
+        }
+        let graphics_features = union(&union(&graphics_features, &features.required), &enabled_preferred);
+
+        // Push descriptors and conditional rendering are optional: pipelines declaring push descriptor sets,
+        // and condition buffers, fail without them.
+        // Descriptor indexing is core since Vulkan 1.2, older devices need the extension
+        let graphics_supported = graphics_physical.supported_extensions();
+        let descriptor_indexing = graphics_physical.api_version() < Version::V1_2
+            && graphics_supported.ext_descriptor_indexing
+            && graphics_supported.khr_maintenance3;
+        let graphics_extensions = DeviceExtensions {
+            khr_push_descriptor: graphics_supported.khr_push_descriptor,
+            ext_conditional_rendering: graphics_supported.ext_conditional_rendering,
+            ext_descriptor_indexing: descriptor_indexing,
+            khr_maintenance3: descriptor_indexing,
+            ..DeviceExtensions::none()
+        };
 
         debug!("Creating the device(s)…");
         // Case 1: different GPUs
         // Case 2: same GPU, but different families
         // Case 3: same GPU, same family
-        let graphics_device: Arc<Device>;
         let graphics_queue: Arc<Queue>;
-        let compute_device: Arc<Device>;
-        let compute_queue: Arc<Queue>;
-        if graphics_physical.index() == compute_physical.index() {
-            let queue_create_infos = if graphics_family.id() == compute_family.id() {
-                vec![QueueCreateInfo {
-                    family: graphics_family,
-                    queues: vec![0.5, 0.5],
-                    _ne: Default::default(),
-                }]
-            } else {
-                vec![
-                    QueueCreateInfo::family(graphics_family),
-                    QueueCreateInfo::family(compute_family),
-                ]
-            };
+        let compute_queue: Option<Arc<Queue>>;
+        let transfer_queue: Option<Arc<Queue>>;
+        match compute {
+            Some((compute_physical, compute_family)) if compute_physical.index() != graphics_physical.index() => {
+                let mut queue_create_infos = vec![QueueCreateInfo::family(graphics_family)];
+                queue_create_infos.extend(transfer_family.map(QueueCreateInfo::family));
 
-            let (device, mut queues) = Device::new(
-                *graphics_physical,
-                DeviceCreateInfo {
-                    enabled_extensions: graphics_physical
-                        .required_extensions()
-                        .union(&device_extensions),
-                    queue_create_infos,
-                    ..Default::default()
-                },
-            )
-                .expect("Couldn't instantiate the device");
-
-            graphics_device = Arc::clone(&device);
-            compute_device = Arc::clone(&device);
-            graphics_queue = queues
-                .next()
-                .expect("Couldn't instantiate the graphics queue");
-            compute_queue = queues
-                .next()
-                .expect("Couldn't instantiate the compute queue");
-        } else {
-            let (graphics_device_, mut graphics_queues) = Device::new(
-                *graphics_physical,
-                DeviceCreateInfo {
-                    enabled_extensions: graphics_physical
-                        .required_extensions()
-                        .union(&device_extensions),
-                    queue_create_infos: vec![QueueCreateInfo::family(graphics_family)],
-                    ..Default::default()
-                },
-            )
-                .expect("Couldn't instantiate the graphics device");
-
-            let (compute_device_, mut compute_queues) = Device::new(
-                *compute_physical,
-                DeviceCreateInfo {
-                    enabled_extensions: compute_physical
-                        .required_extensions()
-                        .union(&device_extensions),
-                    queue_create_infos: vec![QueueCreateInfo::family(compute_family)],
-                    ..Default::default()
-                },
-            )
-                .expect("Couldn't instantiate the compute device");
-
-            graphics_device = graphics_device_;
-            graphics_queue = graphics_queues
-                .next()
-                .expect("Couldn't instantiate the graphics queue");
-            compute_device = compute_device_;
-            compute_queue = compute_queues
-                .next()
-                .expect("Couldn't instantiate the compute queue");
+                let (_, mut graphics_queues) = Device::new(
+                    *graphics_physical,
+                    DeviceCreateInfo {
+                        enabled_extensions: graphics_physical
+                            .required_extensions()
+                            .union(&device_extensions)
+                            .union(&graphics_extensions)
+                            .union(&features.required_extensions),
+                        enabled_features: graphics_features.clone(),
+                        queue_create_infos,
+                        ..Default::default()
+                    },
+                )?;
+
+                let (_, mut compute_queues) = Device::new(
+                    *compute_physical,
+                    DeviceCreateInfo {
+                        enabled_extensions: compute_physical
+                            .required_extensions()
+                            .union(&device_extensions)
+                            .union(&features.required_extensions),
+                        enabled_features: features.required.clone(),
+                        queue_create_infos: vec![QueueCreateInfo::family(compute_family)],
+                        ..Default::default()
+                    },
+                )?;
+
+                graphics_queue = graphics_queues
+                    .next()
+                    .expect("Couldn't instantiate the graphics queue");
+                transfer_queue = transfer_family.map(|_| {
+                    graphics_queues
+                        .next()
+                        .expect("Couldn't instantiate the transfer queue")
+                });
+                compute_queue = Some(
+                    compute_queues
+                        .next()
+                        .expect("Couldn't instantiate the compute queue"),
+                );
+            }
+            _ => {
+                let compute_family = compute.map(|(_, family)| family);
+                let mut queue_create_infos = match compute_family {
+                    Some(compute_family) if compute_family.id() == graphics_family.id() => vec![QueueCreateInfo {
+                        family: graphics_family,
+                        queues: vec![0.5, 0.5],
+                        _ne: Default::default(),
+                    }],
+                    _ => vec![QueueCreateInfo::family(graphics_family)],
+                };
+                queue_create_infos.extend(
+                    compute_family
+                        .filter(|family| family.id() != graphics_family.id())
+                        .map(QueueCreateInfo::family),
+                );
+                queue_create_infos.extend(transfer_family.map(QueueCreateInfo::family));
+
+                let (_, mut queues) = Device::new(
+                    *graphics_physical,
+                    DeviceCreateInfo {
+                        enabled_extensions: graphics_physical
+                            .required_extensions()
+                            .union(&device_extensions)
+                            .union(&graphics_extensions)
+                            .union(&features.required_extensions),
+                        enabled_features: graphics_features.clone(),
+                        queue_create_infos,
+                        ..Default::default()
+                    },
+                )?;
+
+                graphics_queue = queues
+                    .next()
+                    .expect("Couldn't instantiate the graphics queue");
+                compute_queue = compute_family.map(|_| {
+                    queues
+                        .next()
+                        .expect("Couldn't instantiate the compute queue")
+                });
+                transfer_queue = transfer_family.map(|_| {
+                    queues
+                        .next()
+                        .expect("Couldn't instantiate the transfer queue")
+                });
+            }
         }
 
         trace!("Done creating the devices.");
 
-        Hardware {
+        let descriptor_pool = DescriptorAllocator::new(Arc::clone(graphics_queue.device()));
+        let pipeline_cache = PipelineCache::empty(Arc::clone(graphics_queue.device()))
+            .expect("Couldn't create the pipeline cache");
+
+        Ok(Hardware {
             surface,
             graphics_queue,
             compute_queue,
-        }
+            transfer_queue,
+            capture,
+            descriptor_pool,
+            pipeline_cache,
+            enabled_preferred,
+            _validation: validation,
+            dedicated_threshold: AtomicU64::new(u64::MAX),
+        })
     }
 
+    /// The surface of the window. Panics if the hardware is [headless](Hardware::headless).
     pub fn surface(&self) -> &Arc<Surface<Window>> {
-        &self.surface
+        self.surface
+            .as_ref()
+            .expect("Headless hardware has no surface")
     }
 
+    /// Panics if the hardware is [headless](Hardware::headless).
     pub fn window(&self) -> &Window {
-        self.surface.window()
+        self.surface().window()
+    }
+
+    pub fn is_headless(&self) -> bool {
+        self.surface.is_none()
     }
 
     pub fn graphics_queue(&self) -> &Arc<Queue> {
@@ -240,11 +517,443 @@ impl Hardware {
         self.graphics_queue.device()
     }
 
-    pub fn compute_queue(&self) -> &Arc<Queue> {
-        &self.compute_queue
+    /// The queue compute shaders are submitted to, or `None` if no queue family of the devices supports compute.
+    ///
+    /// Windowed engines start without one, [`Hardware::headless`] fails instead.
+    pub fn compute_queue(&self) -> Option<&Arc<Queue>> {
+        self.compute_queue.as_ref()
+    }
+
+    /// The device of the compute queue, which is a different device from [`Hardware::graphics_device`]
+    /// when the compute queue was assigned to another GPU, see [`Hardware::shares_device`].
+    pub fn compute_device(&self) -> Option<&Arc<Device>> {
+        self.compute_queue.as_ref().map(|queue| queue.device())
+    }
+
+    /// Whether the graphics and compute queues belong to the same device, and can use the same resources.
+    ///
+    /// Otherwise, resources of the compute device must be copied to be used by the graphics queue,
+    /// see [`Hardware::copy_compute_to_graphics`]. Always true without a compute queue.
+    pub fn shares_device(&self) -> bool {
+        self.compute_device()
+            .is_none_or(|device| Arc::ptr_eq(self.graphics_device(), device))
+    }
+
+    /// Whether the queue family `queue_family` of `physical` can present images to `surface`.
+    ///
+    /// The engine only uses graphics queues that can present to its window.
+    pub fn can_present<W>(physical: PhysicalDevice, queue_family: u32, surface: &Surface<W>) -> bool {
+        physical
+            .queue_family_by_id(queue_family)
+            .is_some_and(|family| family.supports_surface(surface).unwrap_or(false))
+    }
+
+    /// The queue families of `physical` and what they support, to choose a [`QueueAssignment`].
+    pub fn list_queue_families(physical: PhysicalDevice) -> Vec<QueueFamilyInfo> {
+        queues::list(physical)
+    }
+
+    /// For each queue family of `physical`, its id and whether it can present images to `surface`.
+    pub fn presentable_families<W>(physical: PhysicalDevice, surface: &Surface<W>) -> Vec<(u32, bool)> {
+        physical
+            .queue_families()
+            .map(|family| (family.id(), family.supports_surface(surface).unwrap_or(false)))
+            .collect()
+    }
+
+    pub fn capture(&self) -> &Capture {
+        &self.capture
     }
 
-    pub fn compute_device(&self) -> &Arc<Device> {
-        self.compute_queue.device()
+    /// A descriptor pool of the graphics device which grows by configurable blocks, and reports its usage.
+    pub fn descriptor_pool(&self) -> &DescriptorAllocator {
+        &self.descriptor_pool
     }
+
+    /// The features enabled on the graphics device.
+    pub fn enabled_features(&self) -> &Features {
+        self.graphics_device().enabled_features()
+    }
+
+    /// The preferred features of the [`FeatureRequest`] the graphics device supports, which are enabled.
+    pub fn enabled_preferred_features(&self) -> &Features {
+        &self.enabled_preferred
+    }
+
+    /// The compilation results of the pipelines built with it, see [`PipelineBuilder::cache`](crate::drawing::pipeline::PipelineBuilder::cache).
+    ///
+    /// It starts empty: persist it between runs with `get_data` and `PipelineCache::with_data`.
+    pub fn pipeline_cache(&self) -> &Arc<PipelineCache> {
+        &self.pipeline_cache
+    }
+
+    /// The size from which resources created with [`AllocationHint::Auto`](crate::drawing::memory::AllocationHint::Auto) get a dedicated allocation.
+    ///
+    /// `None`, the default, leaves the decision to the driver, like vulkano does.
+    pub fn dedicated_threshold(&self) -> Option<DeviceSize> {
+        match self.dedicated_threshold.load(Ordering::Relaxed) {
+            u64::MAX => None,
+            threshold => Some(threshold),
+        }
+    }
+
+    pub fn set_dedicated_threshold(&self, threshold: Option<DeviceSize>) {
+        self.dedicated_threshold
+            .store(threshold.unwrap_or(u64::MAX), Ordering::Relaxed);
+    }
+
+    /// The subgroup capabilities of the compute device, or `None` if it doesn't report them (before Vulkan 1.1)
+    /// or there is no compute queue.
+    pub fn subgroup_properties(&self) -> Option<SubgroupProperties> {
+        SubgroupProperties::new(&self.compute_device()?.physical_device())
+    }
+
+    /// The queue uploads should be submitted to.
+    ///
+    /// This is a dedicated transfer queue of the graphics device if it has one, the graphics queue otherwise.
+    pub fn transfer_queue(&self) -> &Arc<Queue> {
+        self.transfer_queue.as_ref().unwrap_or(&self.graphics_queue)
+    }
+
+    /// Whether [`Hardware::transfer_queue`] is a dedicated transfer queue, which can run in parallel with rendering.
+    pub fn has_dedicated_transfer_queue(&self) -> bool {
+        self.transfer_queue.is_some()
+    }
+
+    /// Whether the graphics device supports push descriptors, see [`PipelineBuilder::push_descriptor_set`](crate::drawing::pipeline::PipelineBuilder::push_descriptor_set).
+    pub fn supports_push_descriptors(&self) -> bool {
+        self.graphics_device().enabled_extensions().khr_push_descriptor
+    }
+
+    /// A queue of the graphics device that can bind memory to sparse resources, if the device supports them.
+    pub fn sparse_queue(&self) -> Option<&Arc<Queue>> {
+        if !self.graphics_device().enabled_features().sparse_binding {
+            return None;
+        }
+
+        [Some(&self.graphics_queue), self.transfer_queue.as_ref()]
+            .into_iter()
+            .flatten()
+            .find(|queue| queue.family().supports_sparse_binding())
+    }
+
+    /// Whether the graphics device supports `VK_EXT_conditional_rendering`, to skip draws depending on a value
+    /// in a buffer, see [`ConditionBuffer`](crate::drawing::conditional::ConditionBuffer).
+    pub fn supports_conditional_rendering(&self) -> bool {
+        let device = self.graphics_device();
+        device.enabled_extensions().ext_conditional_rendering && device.enabled_features().conditional_rendering
+    }
+
+    /// Copies `data` into a new device-local buffer through the transfer queue, waiting for the copy to finish.
+    pub fn upload_buffer<T, D>(&self, data: D, usage: BufferUsage) -> Arc<DeviceLocalBuffer<[T]>>
+        where
+            D: IntoIterator<Item = T>,
+            D::IntoIter: ExactSizeIterator,
+            [T]: BufferContents,
+    {
+        let (buffer, upload) = self.upload_buffer_async(data, usage);
+
+        upload
+            .then_signal_fence_and_flush()
+            .expect("Couldn't submit the upload")
+            .wait(None)
+            .expect("Couldn't upload the buffer");
+
+        buffer
+    }
+
+    /// Copies `data` into a new device-local buffer through the transfer queue.
+    ///
+    /// The buffer must not be used before the returned future has been reached:
+    /// join it with the future of the submission that reads the buffer.
+    ///
+    /// The buffer is shared between all the queue families of the graphics device,
+    /// so it doesn't need an ownership transfer to be used by the graphics or compute queues afterwards.
+    pub fn upload_buffer_async<T, D>(
+        &self,
+        data: D,
+        usage: BufferUsage,
+    ) -> (
+        Arc<DeviceLocalBuffer<[T]>>,
+        CommandBufferExecFuture<NowFuture, PrimaryAutoCommandBuffer>,
+    )
+        where
+            D: IntoIterator<Item = T>,
+            D::IntoIter: ExactSizeIterator,
+            [T]: BufferContents,
+    {
+        let device = self.graphics_device();
+        let queue = self.transfer_queue();
+
+        let source = CpuAccessibleBuffer::from_iter(
+            Arc::clone(device),
+            BufferUsage::transfer_source(),
+            false,
+            data,
+        )
+            .expect("Couldn't create the staging buffer");
+
+        let destination = DeviceLocalBuffer::array(
+            Arc::clone(device),
+            source.len(),
+            BufferUsage {
+                transfer_destination: true,
+                ..usage
+            },
+            device.active_queue_families(),
+        )
+            .expect("Couldn't create the device-local buffer");
+
+        let mut builder = AutoCommandBufferBuilder::primary(
+            Arc::clone(device),
+            queue.family(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+            .expect("Couldn't create the upload command buffer");
+        builder
+            .copy_buffer(source, Arc::clone(&destination))
+            .expect("Couldn't record the upload");
+        let command_buffer = builder.build().expect("Couldn't build the upload command buffer");
+
+        let upload = sync::now(Arc::clone(device))
+            .then_execute(Arc::clone(queue), command_buffer)
+            .expect("Couldn't execute the upload");
+
+        (destination, upload)
+    }
+
+    /// Loads the images at `paths` as sRGB textures, uploading them all in a single submission through the transfer queue,
+    /// and waits for the upload to finish.
+    ///
+    /// The results are in the order of `paths`: images that can't be read or decoded don't prevent the others
+    /// from being loaded.
+    pub fn load_textures<P>(&self, paths: &[P]) -> Vec<Result<Arc<ImageView<ImmutableImage>>, ImageError>>
+        where
+            P: AsRef<Path>,
+    {
+        let device = self.graphics_device();
+        let queue = self.transfer_queue();
+
+        let mut builder = AutoCommandBufferBuilder::primary(
+            Arc::clone(device),
+            queue.family(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+            .expect("Couldn't create the upload command buffer");
+
+        // The staging buffers are kept alive by the command buffer until the upload is done
+        let results: Vec<_> = paths
+            .iter()
+            .map(|path| {
+                let image = image::open(path)?.into_rgba8();
+                let (width, height) = image.dimensions();
+
+                let staging = CpuAccessibleBuffer::from_iter(
+                    Arc::clone(device),
+                    BufferUsage::transfer_source(),
+                    false,
+                    image.into_raw(),
+                )
+                    .expect("Couldn't create the staging buffer");
+
+                let (texture, initializer) = ImmutableImage::uninitialized(
+                    Arc::clone(device),
+                    ImageDimensions::Dim2d {
+                        width,
+                        height,
+                        array_layers: 1,
+                    },
+                    Format::R8G8B8A8_SRGB,
+                    MipmapsCount::One,
+                    ImageUsage {
+                        transfer_destination: true,
+                        sampled: true,
+                        ..ImageUsage::none()
+                    },
+                    ImageCreateFlags::none(),
+                    ImageLayout::ShaderReadOnlyOptimal,
+                    device.active_queue_families(),
+                )
+                    .expect("Couldn't create the texture");
+                builder
+                    .copy_buffer_to_image(staging, initializer)
+                    .expect("Couldn't record the upload of a texture");
+
+                Ok(ImageView::new_default(texture).expect("Couldn't create the texture view"))
+            })
+            .collect();
+
+        let command_buffer = builder.build().expect("Couldn't build the upload command buffer");
+        sync::now(Arc::clone(device))
+            .then_execute(Arc::clone(queue), command_buffer)
+            .expect("Couldn't execute the upload")
+            .then_signal_fence_and_flush()
+            .expect("Couldn't submit the upload")
+            .wait(None)
+            .expect("Couldn't upload the textures");
+
+        results
+    }
+
+    /// Copies `buffer`, written by the compute queue, into a new device-local buffer of the graphics device,
+    /// waiting for the copy to finish.
+    ///
+    /// Buffers can't be shared between devices: when the compute queue is on another GPU, the contents are staged
+    /// through host-visible memory, which is slow. When both queues share a device, the buffer can be used directly
+    /// instead, this copy is only useful to release it. The buffer must have the `transfer_source` usage.
+    ///
+    /// Without a compute queue, the buffer is copied through the graphics queue.
+    pub fn copy_compute_to_graphics<T, B>(&self, buffer: Arc<B>, usage: BufferUsage) -> Arc<DeviceLocalBuffer<[T]>>
+        where
+            B: TypedBufferAccess<Content = [T]> + 'static,
+            T: Copy + Send + Sync + 'static,
+            [T]: BufferContents,
+    {
+        let queue = self.compute_queue().unwrap_or(&self.graphics_queue);
+        let device = queue.device();
+
+        let staging = unsafe {
+            CpuAccessibleBuffer::<[T]>::uninitialized_array(
+                Arc::clone(device),
+                buffer.len(),
+                BufferUsage::transfer_destination(),
+                true,
+            )
+        }
+            .expect("Couldn't create the staging buffer");
+
+        let mut builder = AutoCommandBufferBuilder::primary(
+            Arc::clone(device),
+            queue.family(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+            .expect("Couldn't create the download command buffer");
+        builder
+            .copy_buffer(buffer, Arc::clone(&staging))
+            .expect("Couldn't record the download");
+        let command_buffer = builder.build().expect("Couldn't build the download command buffer");
+
+        sync::now(Arc::clone(device))
+            .then_execute(Arc::clone(queue), command_buffer)
+            .expect("Couldn't execute the download")
+            .then_signal_fence_and_flush()
+            .expect("Couldn't submit the download")
+            .wait(None)
+            .expect("Couldn't download the buffer");
+
+        let contents = staging
+            .read()
+            .expect("The staging buffer is still in use")
+            .to_vec();
+        self.upload_buffer(contents, usage)
+    }
+
+    /// Starts copying `buffer` to the CPU through the graphics queue, without waiting for the copy.
+    ///
+    /// The buffer must have the `transfer_source` usage. Poll the returned handle in the following frames.
+    pub fn read_buffer_async<T, B>(&self, buffer: Arc<B>) -> ReadbackHandle<T>
+        where
+            B: TypedBufferAccess<Content = [T]> + 'static,
+            T: Copy + Send + Sync + 'static,
+            [T]: BufferContents,
+    {
+        ReadbackHandle::new(self, buffer)
+    }
+
+    /// Runs the compute shader `entry_point` on the compute queue, and waits until it is done.
+    ///
+    /// A pipeline is built for the call, with `writes` bound to the descriptor set 0 of the shader,
+    /// and `dispatch` work groups are dispatched. For shaders dispatched each frame, build the pipeline once instead.
+    pub fn run_compute(
+        &self,
+        entry_point: EntryPoint,
+        writes: impl IntoIterator<Item = WriteDescriptorSet>,
+        dispatch: [u32; 3],
+    ) -> Result<(), ComputeError> {
+        compute::run_compute(self, entry_point, writes, dispatch)
+    }
+
+    /// The alignment required by the graphics device for the offsets of uniform buffer bindings, in bytes.
+    pub fn uniform_alignment(&self) -> DeviceSize {
+        self.graphics_device()
+            .physical_device()
+            .properties()
+            .min_uniform_buffer_offset_alignment
+    }
+
+    /// The alignment required by the graphics device for the offsets of storage buffer bindings, in bytes.
+    pub fn storage_alignment(&self) -> DeviceSize {
+        self.graphics_device()
+            .physical_device()
+            .properties()
+            .min_storage_buffer_offset_alignment
+    }
+
+    /// Rounds `size` up, so consecutive uniform buffers of this size can be bound at dynamic offsets.
+    pub fn align_uniform(&self, size: DeviceSize) -> DeviceSize {
+        align(size, self.uniform_alignment())
+    }
+
+    /// Rounds `size` up, so consecutive storage buffers of this size can be bound at dynamic offsets.
+    pub fn align_storage(&self, size: DeviceSize) -> DeviceSize {
+        align(size, self.storage_alignment())
+    }
+}
+
+/// Rounds `size` up to the next multiple of `alignment`, which Vulkan guarantees to be a power of two.
+fn align(size: DeviceSize, alignment: DeviceSize) -> DeviceSize {
+    (size + alignment - 1) & !(alignment - 1)
+}
+
+/// Creates an instance with the extensions needed by the engine, and those needed to create a window if `windowed`.
+fn create_instance(windowed: bool, enabled_layers: Vec<String>) -> Result<Arc<Instance>, HardwareError> {
+    // Lets the surface report the color spaces of wide-gamut and HDR displays, when available
+    let supported_extensions = InstanceExtensions::supported_by_core()
+        .map_err(InstanceCreationError::LoadingError)?;
+    let enabled_extensions = InstanceExtensions {
+        ext_swapchain_colorspace: supported_extensions.ext_swapchain_colorspace,
+        ext_debug_utils: !enabled_layers.is_empty() && supported_extensions.ext_debug_utils,
+        ..if windowed {
+            vulkano_win::required_extensions()
+        } else {
+            InstanceExtensions::none()
+        }
+    };
+    let instance = Instance::new(InstanceCreateInfo {
+        enabled_extensions,
+        enabled_layers,
+        ..Default::default()
+    })?;
+    Ok(instance)
+}
+
+/// A GPU the engine could run on, see [`list_devices`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceInfo {
+    pub name: String,
+    pub device_type: PhysicalDeviceType,
+    /// The highest version of Vulkan supported by the device.
+    pub api_version: Version,
+    /// The version of the driver, in a format specific to each vendor.
+    pub driver_version: u32,
+}
+
+/// The GPUs available to Vulkan, without creating a window or a device, for example to let the user pick one
+/// with [`HardwareConfig::preferred_device_name`].
+///
+/// Some of them may not be able to render to a window, they are then skipped when the engine is created.
+pub fn list_devices() -> Result<Vec<DeviceInfo>, HardwareError> {
+    let instance = create_instance(false, Vec::new())?;
+
+    Ok(PhysicalDevice::enumerate(&instance)
+        .map(|physical| {
+            let properties = physical.properties();
+            DeviceInfo {
+                name: properties.device_name.clone(),
+                device_type: properties.device_type,
+                api_version: physical.api_version(),
+                driver_version: properties.driver_version,
+            }
+        })
+        .collect())
 }
@@ -0,0 +1,71 @@
+use std::sync::Arc;
+
+use log::debug;
+use vulkano::buffer::{BufferContents, CpuAccessibleBuffer, TypedBufferAccess};
+use vulkano::command_buffer::PrimaryAutoCommandBuffer;
+use vulkano::device::{Device, Queue};
+use vulkano::pipeline::graphics::viewport::Viewport;
+use vulkano::render_pass::{Framebuffer, RenderPass};
+
+use crate::drawing::engine::EngineError;
+use crate::drawing::hardware::{Hardware, HardwareConfig};
+use crate::drawing::offscreen;
+use crate::drawing::offscreen::OffscreenError;
+use crate::drawing::readback::ReadbackHandle;
+
+/// The Quasar Engine without a window, to run compute shaders on machines without a display.
+///
+/// It has no swapchain, so it can't [`run`](crate::drawing::engine::Engine::run) a draw loop.
+/// Submit command buffers to its queues directly.
+pub struct HeadlessEngine {
+    pub hardware: Arc<Hardware>,
+}
+
+impl HeadlessEngine {
+    /// Instantiates the Quasar Engine without a window.
+    pub fn new() -> Result<HeadlessEngine, EngineError> {
+        Self::with_config(&HardwareConfig::default())
+    }
+
+    /// Same as [`HeadlessEngine::new`], selecting the devices as described by `hardware`.
+    pub fn with_config(hardware: &HardwareConfig) -> Result<HeadlessEngine, EngineError> {
+        let hardware = Arc::new(Hardware::headless(hardware)?);
+
+        debug!("Headless Vulkan initialization finished.");
+        Ok(HeadlessEngine { hardware })
+    }
+
+    pub fn compute_queue(&self) -> &Arc<Queue> {
+        self.hardware
+            .compute_queue()
+            .expect("Headless engines always have a compute queue")
+    }
+
+    pub fn compute_device(&self) -> &Arc<Device> {
+        self.compute_queue().device()
+    }
+
+    /// Renders a single image of `dimensions`, see [`Engine::render_offscreen`](crate::drawing::engine::Engine::render_offscreen).
+    pub fn render_offscreen<D>(
+        &self,
+        render_pass: Arc<RenderPass>,
+        dimensions: [u32; 2],
+        draw: D,
+    ) -> Result<Arc<CpuAccessibleBuffer<[u8]>>, OffscreenError>
+        where
+            D: FnOnce(&Hardware, &Arc<Framebuffer>, &Viewport) -> PrimaryAutoCommandBuffer,
+    {
+        let format = offscreen::color_format(&render_pass)?;
+        offscreen::render_on(&self.hardware, dimensions, format, render_pass, draw)
+    }
+
+    /// Starts copying `buffer` to the CPU, see [`Hardware::read_buffer_async`].
+    pub fn read_buffer_async<T, B>(&self, buffer: Arc<B>) -> ReadbackHandle<T>
+        where
+            B: TypedBufferAccess<Content = [T]> + 'static,
+            T: Copy + Send + Sync + 'static,
+            [T]: BufferContents,
+    {
+        self.hardware.read_buffer_async(buffer)
+    }
+}
@@ -0,0 +1,87 @@
+use std::collections::HashSet;
+use std::sync::Mutex;
+
+use winit::event::{ElementState, MouseButton, VirtualKeyCode, WindowEvent};
+
+/// Whether `event` comes from the keyboard or the mouse, and is forwarded to the input handler of the engine.
+pub(crate) fn is_input(event: &WindowEvent) -> bool {
+    matches!(
+        event,
+        WindowEvent::KeyboardInput { .. }
+            | WindowEvent::ReceivedCharacter(_)
+            | WindowEvent::ModifiersChanged(_)
+            | WindowEvent::MouseInput { .. }
+            | WindowEvent::MouseWheel { .. }
+            | WindowEvent::CursorMoved { .. }
+            | WindowEvent::CursorEntered { .. }
+            | WindowEvent::CursorLeft { .. }
+            | WindowEvent::Focused(_)
+    )
+}
+
+#[derive(Default)]
+struct State {
+    keys: HashSet<VirtualKeyCode>,
+    buttons: HashSet<MouseButton>,
+    cursor: Option<[f64; 2]>,
+}
+
+/// The keys and mouse buttons currently pressed, and the position of the cursor, updated by [`Engine::run`].
+///
+/// Clone [`Engine::input`](crate::drawing::engine::Engine::input) into the draw closure to read it each frame,
+/// for example to move the camera while a key is held.
+/// Everything is released when the window loses the focus, since the releases aren't received anymore.
+#[derive(Default)]
+pub struct InputState {
+    state: Mutex<State>,
+}
+
+impl InputState {
+    pub fn is_key_pressed(&self, key: VirtualKeyCode) -> bool {
+        self.lock().keys.contains(&key)
+    }
+
+    pub fn is_button_pressed(&self, button: MouseButton) -> bool {
+        self.lock().buttons.contains(&button)
+    }
+
+    pub fn pressed_keys(&self) -> Vec<VirtualKeyCode> {
+        self.lock().keys.iter().copied().collect()
+    }
+
+    /// The position of the cursor, in pixels from the top-left corner of the window, or `None` if it is outside.
+    pub fn cursor_position(&self) -> Option<[f64; 2]> {
+        self.lock().cursor
+    }
+
+    pub(crate) fn handle(&self, event: &WindowEvent) {
+        let mut state = self.lock();
+        match event {
+            WindowEvent::KeyboardInput { input, .. } => {
+                if let Some(key) = input.virtual_keycode {
+                    match input.state {
+                        ElementState::Pressed => state.keys.insert(key),
+                        ElementState::Released => state.keys.remove(&key),
+                    };
+                }
+            }
+            WindowEvent::MouseInput { state: pressed, button, .. } => {
+                match pressed {
+                    ElementState::Pressed => state.buttons.insert(*button),
+                    ElementState::Released => state.buttons.remove(button),
+                };
+            }
+            WindowEvent::CursorMoved { position, .. } => state.cursor = Some([position.x, position.y]),
+            WindowEvent::CursorLeft { .. } => state.cursor = None,
+            WindowEvent::Focused(false) => {
+                state.keys.clear();
+                state.buttons.clear();
+            }
+            _ => (),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().expect("The input state was poisoned")
+    }
+}
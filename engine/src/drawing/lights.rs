@@ -0,0 +1,201 @@
+use std::sync::{Arc, Mutex};
+
+use bytemuck::{Pod, Zeroable};
+use log::warn;
+use vulkano::buffer::{BufferUsage, CpuAccessibleBuffer};
+use vulkano::device::Device;
+
+use crate::drawing::buffered::DoubleBuffered;
+
+/// The maximum number of lights sent to the shaders: lights added beyond it are ignored.
+pub const MAX_LIGHTS: usize = 64;
+
+/// A light of the forward renderer, see [`DeferredLight`](crate::drawing::deferred::DeferredLight) for the deferred one.
+///
+/// Colors are linear, and already multiplied by the intensity of the light.
+/// Directions are in world space, and don't need to be normalized.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Light {
+    /// A light infinitely far away, such as the sun, shining towards `direction`.
+    Directional { direction: [f32; 3], color: [f32; 3] },
+    /// A light shining in every direction from `position`, fading out until `range`.
+    Point { position: [f32; 3], color: [f32; 3], range: f32 },
+    /// A light shining from `position` towards `direction`, within a cone.
+    ///
+    /// The light is full within `inner_angle` of the direction, and fades out until `outer_angle`, in radians.
+    Spot {
+        position: [f32; 3],
+        direction: [f32; 3],
+        color: [f32; 3],
+        range: f32,
+        inner_angle: f32,
+        outer_angle: f32,
+    },
+}
+
+impl Light {
+    fn gpu(&self) -> GpuLight {
+        match *self {
+            Light::Directional { direction, color } => GpuLight {
+                position: [0.0, 0.0, 0.0, 0.0],
+                direction: [direction[0], direction[1], direction[2], 0.0],
+                color: [color[0], color[1], color[2], 0.0],
+                cone: [0.0; 4],
+            },
+            Light::Point { position, color, range } => GpuLight {
+                position: [position[0], position[1], position[2], 1.0],
+                direction: [0.0; 4],
+                color: [color[0], color[1], color[2], range],
+                cone: [0.0; 4],
+            },
+            Light::Spot { position, direction, color, range, inner_angle, outer_angle } => GpuLight {
+                position: [position[0], position[1], position[2], 2.0],
+                direction: [direction[0], direction[1], direction[2], 0.0],
+                color: [color[0], color[1], color[2], range],
+                cone: [inner_angle.cos(), outer_angle.cos(), 0.0, 0.0],
+            },
+        }
+    }
+}
+
+/// A light, laid out as the `Light` struct of the shaders, see [`Lights`].
+#[repr(C)]
+#[derive(Default, Copy, Clone, Debug, Pod, Zeroable)]
+pub struct GpuLight {
+    /// `w`: 0 for directional lights, 1 for point lights, 2 for spot lights.
+    pub position: [f32; 4],
+    pub direction: [f32; 4],
+    /// `w`: the range of point and spot lights.
+    pub color: [f32; 4],
+    /// `x` and `y`: the cosines of the inner and outer angles of spot lights.
+    pub cone: [f32; 4],
+}
+
+/// The lights, laid out as the `Lights` storage buffer of the shaders, see [`Lights`].
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+pub struct LightBlock {
+    pub count: u32,
+    pub _padding: [u32; 3],
+    pub lights: [GpuLight; MAX_LIGHTS],
+}
+
+/// Identifies a light added to [`Lights`], to remove it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LightId(u64);
+
+struct State {
+    next_id: u64,
+    lights: Vec<(LightId, Light)>,
+    // Incremented each time the lights change
+    version: u64,
+    uploaded: Option<u64>,
+}
+
+/// The lights of a forward renderer, in a storage buffer read by the fragment shaders.
+///
+/// The buffer is only rewritten when lights are added or removed. Declare it in the shaders as:
+/// ```glsl
+/// struct Light {
+///     vec4 position;   // w: 0 for directional lights, 1 for point lights, 2 for spot lights
+///     vec4 direction;
+///     vec4 color;      // w: the range of point and spot lights
+///     vec4 cone;       // x, y: the cosines of the inner and outer angles of spot lights
+/// };
+///
+/// layout(std430, set = 0, binding = 0) readonly buffer Lights {
+///     uint count;
+///     Light lights[64];
+/// } lights;
+/// ```
+/// and bind [`Lights::buffer`] with `WriteDescriptorSet::buffer`.
+pub struct Lights {
+    buffer: DoubleBuffered<LightBlock>,
+    state: Mutex<State>,
+}
+
+impl Lights {
+    /// Creates an empty set of lights, for `frames` frames in flight.
+    pub fn new(device: &Arc<Device>, frames: usize) -> Self {
+        let empty = LightBlock {
+            count: 0,
+            _padding: [0; 3],
+            lights: [GpuLight::default(); MAX_LIGHTS],
+        };
+
+        Lights {
+            buffer: DoubleBuffered::with_copies(device, BufferUsage::storage_buffer(), empty, frames + 1)
+                .expect("Couldn't create the light buffers"),
+            state: Mutex::new(State {
+                next_id: 0,
+                lights: Vec::new(),
+                version: 0,
+                uploaded: None,
+            }),
+        }
+    }
+
+    pub fn add(&self, light: Light) -> LightId {
+        let mut state = self.lock();
+        let id = LightId(state.next_id);
+        state.next_id += 1;
+        state.lights.push((id, light));
+        state.version += 1;
+
+        if state.lights.len() > MAX_LIGHTS {
+            warn!("More than {} lights were added, the last ones aren't rendered", MAX_LIGHTS);
+        }
+        id
+    }
+
+    /// Removes the light `id`, returning it if it wasn't already removed.
+    pub fn remove(&self, id: LightId) -> Option<Light> {
+        let mut state = self.lock();
+        let index = state.lights.iter().position(|(light_id, _)| *light_id == id)?;
+        let (_, light) = state.lights.remove(index);
+        state.version += 1;
+        Some(light)
+    }
+
+    /// Replaces the light `id`, for example to move it. Returns `false` if it was removed.
+    pub fn update(&self, id: LightId, light: Light) -> bool {
+        let mut state = self.lock();
+        match state.lights.iter_mut().find(|(light_id, _)| *light_id == id) {
+            Some((_, existing)) => {
+                *existing = light;
+                state.version += 1;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The lights, in the order they were added.
+    pub fn lights(&self) -> Vec<Light> {
+        self.lock().lights.iter().map(|(_, light)| *light).collect()
+    }
+
+    /// The buffer holding the lights, to bind for the current frame.
+    ///
+    /// Call it once per frame: if the lights changed, they are written into a copy the GPU isn't reading from.
+    pub fn buffer(&self) -> Arc<CpuAccessibleBuffer<LightBlock>> {
+        let mut state = self.lock();
+        if state.uploaded != Some(state.version) {
+            match self.buffer.current_mut() {
+                Ok(mut block) => {
+                    block.count = state.lights.len().min(MAX_LIGHTS) as u32;
+                    for (gpu, (_, light)) in block.lights.iter_mut().zip(&state.lights) {
+                        *gpu = light.gpu();
+                    }
+                    state.uploaded = Some(state.version);
+                }
+                Err(e) => warn!("Couldn't update the lights, they will be updated next frame: {}", e),
+            }
+        }
+        Arc::clone(self.buffer.current())
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().expect("The lights were poisoned")
+    }
+}
@@ -0,0 +1,505 @@
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::num::NonZeroUsize;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::thread;
+
+use log::debug;
+use vulkano::pipeline::graphics::input_assembly::PrimitiveTopology;
+use vulkano::pipeline::graphics::vertex_input::BuffersDefinition;
+use vulkano::pipeline::GraphicsPipeline;
+use vulkano::render_pass::Subpass;
+use vulkano::shader::ShaderModule;
+
+use crate::drawing::hardware::Hardware;
+use crate::drawing::pipeline::{PipelineBuilder, PipelineError};
+
+/// The configuration of a pipeline built by [`PipelineBuilder`], referring to its shaders, subpass
+/// and vertex input by the names they are registered with in a [`PipelineRegistry`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct PipelineDescription {
+    pub name: String,
+    /// The shader module whose `main` entry point is the vertex shader.
+    pub vertex_shader: String,
+    /// The shader module whose `main` entry point is the fragment shader.
+    pub fragment_shader: String,
+    pub subpass: String,
+    /// `None` if the pipeline doesn't read vertex buffers.
+    pub vertex_input: Option<String>,
+    pub topology: PrimitiveTopology,
+    pub primitive_restart: bool,
+    pub depth_test: bool,
+    pub depth_clamp: bool,
+    pub viewport_count: u32,
+}
+
+impl PipelineDescription {
+    /// A description with the defaults of [`PipelineBuilder`].
+    pub fn new(name: &str, vertex_shader: &str, fragment_shader: &str, subpass: &str) -> Self {
+        PipelineDescription {
+            name: name.to_owned(),
+            vertex_shader: vertex_shader.to_owned(),
+            fragment_shader: fragment_shader.to_owned(),
+            subpass: subpass.to_owned(),
+            vertex_input: None,
+            topology: PrimitiveTopology::TriangleList,
+            primitive_restart: false,
+            depth_test: false,
+            depth_clamp: false,
+            viewport_count: 1,
+        }
+    }
+
+    /// Builds the pipeline on `hardware`, through its pipeline cache.
+    pub fn build(
+        &self,
+        hardware: &Hardware,
+        registry: &PipelineRegistry,
+    ) -> Result<Arc<GraphicsPipeline>, ManifestError> {
+        let vertex_shader = registry.shader(&self.vertex_shader)?;
+        let fragment_shader = registry.shader(&self.fragment_shader)?;
+        let subpass = registry
+            .subpasses
+            .get(&self.subpass)
+            .ok_or_else(|| ManifestError::UnknownSubpass(self.subpass.clone()))?;
+
+        let mut builder = PipelineBuilder::new(
+            vertex_shader
+                .entry_point("main")
+                .ok_or_else(|| ManifestError::NoEntryPoint(self.vertex_shader.clone()))?,
+            fragment_shader
+                .entry_point("main")
+                .ok_or_else(|| ManifestError::NoEntryPoint(self.fragment_shader.clone()))?,
+            subpass.clone(),
+        )
+            .topology(self.topology)
+            .primitive_restart(self.primitive_restart)
+            .depth_test(self.depth_test)
+            .depth_clamp(self.depth_clamp)
+            .viewport_count(self.viewport_count)
+            .cache(Arc::clone(hardware.pipeline_cache()));
+        if let Some(name) = &self.vertex_input {
+            let vertex_input = registry
+                .vertex_inputs
+                .get(name)
+                .ok_or_else(|| ManifestError::UnknownVertexInput(name.clone()))?;
+            builder = builder.vertex_input(vertex_input.clone());
+        }
+
+        builder
+            .build(Arc::clone(hardware.graphics_device()))
+            .map_err(|error| ManifestError::Pipeline {
+                name: self.name.clone(),
+                error,
+            })
+    }
+}
+
+/// The shaders, subpasses and vertex inputs the pipelines of a [`PipelineManifest`] refer to by name.
+#[derive(Default, Clone)]
+pub struct PipelineRegistry {
+    shaders: HashMap<String, Arc<ShaderModule>>,
+    subpasses: HashMap<String, Subpass>,
+    vertex_inputs: HashMap<String, BuffersDefinition>,
+}
+
+impl PipelineRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_shader(mut self, name: &str, module: Arc<ShaderModule>) -> Self {
+        self.shaders.insert(name.to_owned(), module);
+        self
+    }
+
+    pub fn with_subpass(mut self, name: &str, subpass: Subpass) -> Self {
+        self.subpasses.insert(name.to_owned(), subpass);
+        self
+    }
+
+    pub fn with_vertex_input(mut self, name: &str, vertex_input: BuffersDefinition) -> Self {
+        self.vertex_inputs.insert(name.to_owned(), vertex_input);
+        self
+    }
+
+    fn shader(&self, name: &str) -> Result<&Arc<ShaderModule>, ManifestError> {
+        self.shaders
+            .get(name)
+            .ok_or_else(|| ManifestError::UnknownShader(name.to_owned()))
+    }
+}
+
+/// The pipelines an application uses, to build them all at startup, see [`Engine::load_pipelines`](crate::drawing::engine::Engine::load_pipelines).
+///
+/// It is saved as text, one section per pipeline:
+/// ```text
+/// [mesh]
+/// vertex_shader = mesh.vert
+/// fragment_shader = mesh.frag
+/// subpass = main
+/// vertex_input = vertex
+/// topology = TriangleList
+/// primitive_restart = false
+/// depth_test = true
+/// depth_clamp = false
+/// viewport_count = 1
+/// ```
+///
+/// Pipeline names are unique, and each key is set once per pipeline. `vertex_shader`, `fragment_shader` and `subpass`
+/// are required, the other keys default to the values of [`PipelineDescription::new`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PipelineManifest {
+    pub pipelines: Vec<PipelineDescription>,
+}
+
+impl PipelineManifest {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_pipeline(mut self, pipeline: PipelineDescription) -> Self {
+        self.pipelines.push(pipeline);
+        self
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ManifestError> {
+        std::fs::read_to_string(path)?.parse()
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), ManifestError> {
+        Ok(std::fs::write(path, self.to_string())?)
+    }
+}
+
+impl fmt::Display for PipelineManifest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, pipeline) in self.pipelines.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            writeln!(f, "[{}]", pipeline.name)?;
+            writeln!(f, "vertex_shader = {}", pipeline.vertex_shader)?;
+            writeln!(f, "fragment_shader = {}", pipeline.fragment_shader)?;
+            writeln!(f, "subpass = {}", pipeline.subpass)?;
+            if let Some(vertex_input) = &pipeline.vertex_input {
+                writeln!(f, "vertex_input = {}", vertex_input)?;
+            }
+            writeln!(f, "topology = {:?}", pipeline.topology)?;
+            writeln!(f, "primitive_restart = {}", pipeline.primitive_restart)?;
+            writeln!(f, "depth_test = {}", pipeline.depth_test)?;
+            writeln!(f, "depth_clamp = {}", pipeline.depth_clamp)?;
+            writeln!(f, "viewport_count = {}", pipeline.viewport_count)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for PipelineManifest {
+    type Err = ManifestError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut pipelines: Vec<PipelineDescription> = Vec::new();
+        // The line of the section of each pipeline, and the keys set in the current one
+        let mut sections: Vec<usize> = Vec::new();
+        let mut keys: HashSet<&str> = HashSet::new();
+
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            let error = |message: &str| ManifestError::Parse {
+                line: index + 1,
+                message: message.to_owned(),
+            };
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if let Some(name) = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+                let name = name.trim();
+                if name.is_empty() {
+                    return Err(error("expected a pipeline name"));
+                }
+                if pipelines.iter().any(|pipeline| pipeline.name == name) {
+                    return Err(error(&format!("the pipeline `{}` is already defined", name)));
+                }
+                pipelines.push(PipelineDescription::new(name, "", "", ""));
+                sections.push(index + 1);
+                keys.clear();
+                continue;
+            }
+
+            let (key, value) = line
+                .split_once('=')
+                .map(|(key, value)| (key.trim(), value.trim()))
+                .ok_or_else(|| error("expected `key = value`"))?;
+            let pipeline = pipelines
+                .last_mut()
+                .ok_or_else(|| error("expected a `[name]` section first"))?;
+            if !keys.insert(key) {
+                return Err(error(&format!("`{}` is already set for this pipeline", key)));
+            }
+            let flag = |value: &str| value.parse::<bool>().map_err(|_| error("expected `true` or `false`"));
+            let name = |value: &str| match value {
+                "" => Err(error("expected a name")),
+                name => Ok(name.to_owned()),
+            };
+
+            match key {
+                "vertex_shader" => pipeline.vertex_shader = name(value)?,
+                "fragment_shader" => pipeline.fragment_shader = name(value)?,
+                "subpass" => pipeline.subpass = name(value)?,
+                "vertex_input" => pipeline.vertex_input = Some(name(value)?),
+                "topology" => {
+                    pipeline.topology = parse_topology(value).ok_or_else(|| error("unknown topology"))?
+                }
+                "primitive_restart" => pipeline.primitive_restart = flag(value)?,
+                "depth_test" => pipeline.depth_test = flag(value)?,
+                "depth_clamp" => pipeline.depth_clamp = flag(value)?,
+                "viewport_count" => {
+                    pipeline.viewport_count = value.parse().map_err(|_| error("expected a number"))?
+                }
+                _ => return Err(error("unknown key")),
+            }
+        }
+
+        for (pipeline, &line) in pipelines.iter().zip(&sections) {
+            let missing = [
+                ("vertex_shader", &pipeline.vertex_shader),
+                ("fragment_shader", &pipeline.fragment_shader),
+                ("subpass", &pipeline.subpass),
+            ]
+                .into_iter()
+                .find(|(_, value)| value.is_empty());
+            if let Some((key, _)) = missing {
+                return Err(ManifestError::Parse {
+                    line,
+                    message: format!("the pipeline `{}` has no `{}`", pipeline.name, key),
+                });
+            }
+        }
+
+        Ok(PipelineManifest { pipelines })
+    }
+}
+
+fn parse_topology(name: &str) -> Option<PrimitiveTopology> {
+    [
+        PrimitiveTopology::PointList,
+        PrimitiveTopology::LineList,
+        PrimitiveTopology::LineStrip,
+        PrimitiveTopology::TriangleList,
+        PrimitiveTopology::TriangleStrip,
+        PrimitiveTopology::TriangleFan,
+        PrimitiveTopology::LineListWithAdjacency,
+        PrimitiveTopology::LineStripWithAdjacency,
+        PrimitiveTopology::TriangleListWithAdjacency,
+        PrimitiveTopology::TriangleStripWithAdjacency,
+        PrimitiveTopology::PatchList,
+    ]
+        .into_iter()
+        .find(|topology| format!("{:?}", topology) == name)
+}
+
+/// How many pipelines of a manifest are built, reported while loading.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LoadProgress {
+    pub built: usize,
+    pub total: usize,
+}
+
+/// Builds the pipelines of `manifest` on as many threads as the CPU has cores, reporting each one to `progress`.
+pub(crate) fn load<P>(
+    hardware: &Hardware,
+    manifest: &PipelineManifest,
+    registry: &PipelineRegistry,
+    mut progress: P,
+) -> Result<HashMap<String, Arc<GraphicsPipeline>>, ManifestError>
+    where
+        P: FnMut(LoadProgress),
+{
+    let total = manifest.pipelines.len();
+    let threads = thread::available_parallelism()
+        .map_or(1, NonZeroUsize::get)
+        .min(total);
+    debug!("Building {} pipelines on {} threads…", total, threads);
+
+    let next = AtomicUsize::new(0);
+    let (sender, receiver) = mpsc::channel();
+    let mut pipelines = HashMap::with_capacity(total);
+
+    thread::scope(|scope| {
+        for _ in 0..threads {
+            let sender = sender.clone();
+            let next = &next;
+            scope.spawn(move || loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let description = match manifest.pipelines.get(index) {
+                    Some(description) => description,
+                    None => break,
+                };
+                let result = description.build(hardware, registry);
+                if sender.send((description.name.clone(), result)).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(sender);
+
+        for (built, (name, result)) in receiver.iter().enumerate() {
+            match result {
+                Ok(pipeline) => {
+                    pipelines.insert(name, pipeline);
+                }
+                Err(e) => {
+                    // Stops the other threads after the pipeline they are building
+                    next.store(total, Ordering::Relaxed);
+                    return Err(e);
+                }
+            }
+            progress(LoadProgress {
+                built: built + 1,
+                total,
+            });
+        }
+        Ok(())
+    })?;
+
+    Ok(pipelines)
+}
+
+#[derive(Debug)]
+pub enum ManifestError {
+    Io(io::Error),
+    /// The manifest isn't valid, at this line (starting from 1).
+    Parse { line: usize, message: String },
+    /// No shader module is registered with this name.
+    UnknownShader(String),
+    /// The shader module doesn't have a `main` entry point.
+    NoEntryPoint(String),
+    /// No subpass is registered with this name.
+    UnknownSubpass(String),
+    /// No vertex input is registered with this name.
+    UnknownVertexInput(String),
+    /// The pipeline couldn't be built.
+    Pipeline { name: String, error: PipelineError },
+}
+
+impl fmt::Display for ManifestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ManifestError::Io(e) => write!(f, "couldn't access the manifest: {}", e),
+            ManifestError::Parse { line, message } => {
+                write!(f, "invalid manifest at line {}: {}", line, message)
+            }
+            ManifestError::UnknownShader(name) => write!(f, "no shader is registered as '{}'", name),
+            ManifestError::NoEntryPoint(name) => {
+                write!(f, "the shader '{}' doesn't have a main entry point", name)
+            }
+            ManifestError::UnknownSubpass(name) => write!(f, "no subpass is registered as '{}'", name),
+            ManifestError::UnknownVertexInput(name) => {
+                write!(f, "no vertex input is registered as '{}'", name)
+            }
+            ManifestError::Pipeline { name, error } => {
+                write!(f, "couldn't build the pipeline '{}': {}", name, error)
+            }
+        }
+    }
+}
+
+impl Error for ManifestError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ManifestError::Io(e) => Some(e),
+            ManifestError::Pipeline { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for ManifestError {
+    fn from(e: io::Error) -> Self {
+        ManifestError::Io(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The line and the message of the parse error of `text`.
+    fn parse_error(text: &str) -> (usize, String) {
+        match text.parse::<PipelineManifest>() {
+            Err(ManifestError::Parse { line, message }) => (line, message),
+            other => panic!("expected a parse error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut mesh = PipelineDescription::new("mesh", "mesh.vert", "mesh.frag", "main");
+        mesh.vertex_input = Some("vertex".to_owned());
+        mesh.topology = PrimitiveTopology::TriangleStrip;
+        mesh.depth_test = true;
+        let manifest = PipelineManifest::new()
+            .with_pipeline(mesh)
+            .with_pipeline(PipelineDescription::new("ui", "ui.vert", "ui.frag", "overlay"));
+
+        assert_eq!(manifest.to_string().parse::<PipelineManifest>().unwrap(), manifest);
+    }
+
+    #[test]
+    fn missing_shaders() {
+        let (line, message) = parse_error("[mesh]\nfragment_shader = mesh.frag\nsubpass = main\n");
+        assert_eq!(line, 1);
+        assert!(message.contains("vertex_shader"), "{}", message);
+
+        let (line, message) = parse_error("\n[mesh]\nvertex_shader = mesh.vert\nsubpass = main\n");
+        assert_eq!(line, 2);
+        assert!(message.contains("fragment_shader"), "{}", message);
+
+        let (line, _) = parse_error("[mesh]\nvertex_shader =\n");
+        assert_eq!(line, 2);
+    }
+
+    #[test]
+    fn missing_subpass() {
+        let (line, message) = parse_error("[mesh]\nvertex_shader = mesh.vert\nfragment_shader = mesh.frag\n");
+        assert_eq!(line, 1);
+        assert!(message.contains("subpass"), "{}", message);
+
+        let (line, message) = parse_error("[mesh]\nsubpass = \n");
+        assert_eq!(line, 2);
+        assert_eq!(message, "expected a name");
+    }
+
+    #[test]
+    fn duplicate_pipelines() {
+        let pipeline = "vertex_shader = a.vert\nfragment_shader = a.frag\nsubpass = main\n";
+        let text = format!("[mesh]\n{}[ mesh ]\n{}", pipeline, pipeline);
+        let (line, message) = parse_error(&text);
+        assert_eq!(line, 5);
+        assert!(message.contains("already defined"), "{}", message);
+
+        let (line, _) = parse_error("[]\n");
+        assert_eq!(line, 1);
+    }
+
+    #[test]
+    fn duplicate_shaders() {
+        let (line, message) =
+            parse_error("[mesh]\nvertex_shader = a.vert\nvertex_shader = b.vert\nfragment_shader = a.frag\n");
+        assert_eq!(line, 3);
+        assert!(message.contains("vertex_shader"), "{}", message);
+
+        // Each pipeline has its own keys
+        let pipeline = "vertex_shader = a.vert\nfragment_shader = a.frag\nsubpass = main\n";
+        let text = format!("[a]\n{}[b]\n{}", pipeline, pipeline);
+        assert_eq!(text.parse::<PipelineManifest>().unwrap().pipelines.len(), 2);
+    }
+}
@@ -0,0 +1,119 @@
+use std::error::Error;
+use std::fmt;
+use std::sync::Arc;
+
+use vulkano::device::Device;
+use vulkano::memory::pool::{
+    AllocFromRequirementsFilter, AllocLayout, MappingRequirement, MemoryPool,
+    PotentialDedicatedAllocation, StdMemoryPoolAlloc,
+};
+use vulkano::memory::{
+    DedicatedAllocation, DeviceMemory, DeviceMemoryAllocationError, MemoryAllocateInfo,
+    MemoryRequirements,
+};
+use vulkano::{DeviceSize, Version};
+
+/// How the memory of a resource is allocated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AllocationHint {
+    /// Like vulkano: a dedicated allocation if the driver prefers one,
+    /// or if the resource is larger than the threshold of the hardware, see `Hardware::set_dedicated_threshold`.
+    #[default]
+    Auto,
+    /// Sub-allocated from the standard memory pool, even if the driver would prefer a dedicated allocation.
+    Pooled,
+    /// An allocation of its own, which avoids fragmenting the pool with large resources.
+    Dedicated,
+}
+
+/// Allocates device-local memory (if possible) for `resource`.
+///
+/// Dedicated allocations need Vulkan 1.1 or `khr_dedicated_allocation`: without them, memory is always pooled.
+pub(crate) fn allocate(
+    device: &Arc<Device>,
+    requirements: &MemoryRequirements,
+    resource: DedicatedAllocation,
+    hint: AllocationHint,
+    threshold: Option<DeviceSize>,
+) -> Result<PotentialDedicatedAllocation<StdMemoryPoolAlloc>, AllocationError> {
+    // The standard pool panics too when no memory type matches, so both paths are checked here
+    let memory_type = device
+        .physical_device()
+        .memory_types()
+        .filter(|memory_type| requirements.memory_type_bits & (1 << memory_type.id()) != 0)
+        .min_by_key(|memory_type| !memory_type.is_device_local())
+        .ok_or(AllocationError::NoMemoryType)?;
+
+    let dedicated = match hint {
+        AllocationHint::Auto => threshold.is_some_and(|threshold| requirements.size >= threshold),
+        AllocationHint::Pooled => false,
+        AllocationHint::Dedicated => true,
+    };
+    let supported = device.api_version() >= Version::V1_1
+        || device.enabled_extensions().khr_dedicated_allocation;
+
+    if dedicated && supported {
+        let memory = DeviceMemory::allocate(
+            Arc::clone(device),
+            MemoryAllocateInfo {
+                allocation_size: requirements.size,
+                memory_type_index: memory_type.id(),
+                dedicated_allocation: Some(resource),
+                ..Default::default()
+            },
+        )?;
+        return Ok(PotentialDedicatedAllocation::Dedicated(memory));
+    }
+
+    // The pool still makes a dedicated allocation when the driver prefers it, if `resource` is given
+    let resource = match hint {
+        AllocationHint::Pooled => None,
+        _ => Some(resource),
+    };
+    let allocation = MemoryPool::alloc_from_requirements(
+        &Device::standard_pool(device),
+        requirements,
+        AllocLayout::Optimal,
+        MappingRequirement::DoNotMap,
+        resource,
+        |memory_type| {
+            if memory_type.is_device_local() {
+                AllocFromRequirementsFilter::Preferred
+            } else {
+                AllocFromRequirementsFilter::Allowed
+            }
+        },
+    )?;
+    Ok(allocation)
+}
+
+#[derive(Debug)]
+pub enum AllocationError {
+    /// None of the memory types of the device can back the resource.
+    NoMemoryType,
+    Allocation(DeviceMemoryAllocationError),
+}
+
+impl fmt::Display for AllocationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AllocationError::NoMemoryType => write!(f, "no memory type of the device can back the resource"),
+            AllocationError::Allocation(e) => write!(f, "couldn't allocate the memory of the resource: {}", e),
+        }
+    }
+}
+
+impl Error for AllocationError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            AllocationError::Allocation(e) => Some(e),
+            AllocationError::NoMemoryType => None,
+        }
+    }
+}
+
+impl From<DeviceMemoryAllocationError> for AllocationError {
+    fn from(e: DeviceMemoryAllocationError) -> Self {
+        AllocationError::Allocation(e)
+    }
+}
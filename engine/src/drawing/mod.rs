@@ -1,3 +1,55 @@
+pub mod buffered;
+pub mod camera;
+pub mod capture;
+pub mod clear;
+pub mod compute;
+pub mod conditional;
+pub mod deferred;
+pub mod depth;
+pub mod depth_resolve;
+pub mod descriptor_pool;
+pub mod descriptors;
+pub mod diagnostics;
+pub mod dirty;
 pub mod engine;
-mod hardware;
-mod screen;
+pub mod features;
+pub mod fullscreen;
+pub mod grid;
+pub mod hardware;
+pub mod headless;
+pub mod input;
+pub mod lights;
+pub mod manifest;
+pub mod memory;
+pub mod offscreen;
+pub mod pacing;
+pub mod pipeline;
+pub mod present;
+pub mod profiler;
+pub mod queues;
+pub mod readback;
+pub mod reflection;
+pub mod render_pass;
+pub mod replay;
+pub mod resources;
+pub mod sampler;
+pub mod screen;
+pub mod screenshot;
+pub mod shaders;
+pub mod shadow;
+pub mod shapes;
+pub mod sparse;
+pub mod sprite;
+pub mod statistics;
+pub mod storage;
+pub mod submit;
+pub mod subgroup;
+pub mod target;
+pub mod texel;
+pub mod text;
+pub mod tone_map;
+pub mod uniform;
+pub mod validation;
+pub mod vertex;
+mod warm;
+pub mod window;
@@ -0,0 +1,349 @@
+use std::error::Error;
+use std::fmt;
+use std::sync::Arc;
+
+use log::debug;
+use vulkano::buffer::{BufferUsage, CpuAccessibleBuffer};
+use vulkano::command_buffer::{
+    AutoCommandBufferBuilder, CommandBufferExecError, CommandBufferUsage, PrimaryAutoCommandBuffer,
+    PrimaryCommandBuffer,
+};
+use vulkano::format::Format;
+use vulkano::image::view::{ImageView, ImageViewCreationError};
+use vulkano::image::{AttachmentImage, ImageAccess, ImageCreationError, ImageUsage, SampleCount};
+use vulkano::pipeline::graphics::viewport::Viewport;
+use vulkano::render_pass::{Framebuffer, FramebufferCreateInfo, FramebufferCreationError, RenderPass};
+use vulkano::sync::{FlushError, GpuFuture};
+
+use crate::drawing::features::HardwareError;
+use crate::drawing::hardware::{Hardware, HardwareConfig};
+use crate::drawing::screen::Screen;
+
+/// Renders a single image of `width` by `height` pixels in `format`, without a window, and returns its pixels.
+///
+/// For batch and server rendering, such as thumbnails: a headless device is created for the call,
+/// `draw` records the commands into the framebuffer, and the call returns once the GPU is done.
+/// The pixels are tightly packed rows, in `format`.
+///
+/// `render_pass` must have a single-sampled color attachment in `format`, which is the image returned.
+/// Its other attachments can be depth or stencil attachments, which are created for the call, with
+/// the sample count of the render pass.
+pub fn render<D>(
+    width: u32,
+    height: u32,
+    format: Format,
+    render_pass: Arc<RenderPass>,
+    draw: D,
+) -> Result<Vec<u8>, OffscreenError>
+    where
+        D: FnOnce(&Hardware, &Arc<Framebuffer>, &Viewport) -> PrimaryAutoCommandBuffer,
+{
+    let hardware = Hardware::headless(&HardwareConfig::default())?;
+    let pixels = render_on(&hardware, [width, height], format, render_pass, draw)?;
+
+    let pixels = pixels
+        .read()
+        .expect("The offscreen image is still in use")
+        .to_vec();
+    Ok(pixels)
+}
+
+/// The format of the color attachment of `render_pass`, which is the image rendered offscreen.
+pub(crate) fn color_format(render_pass: &RenderPass) -> Result<Format, OffscreenError> {
+    render_pass
+        .attachments()
+        .iter()
+        .filter_map(|attachment| attachment.format)
+        .find(|format| {
+            let aspects = format.aspects();
+            !aspects.depth && !aspects.stencil
+        })
+        .ok_or(OffscreenError::NoColorAttachment)
+}
+
+/// Same as [`render`], on the graphics queue of `hardware`, returning the buffer the image is copied into.
+pub(crate) fn render_on<D>(
+    hardware: &Hardware,
+    dimensions: [u32; 2],
+    format: Format,
+    render_pass: Arc<RenderPass>,
+    draw: D,
+) -> Result<Arc<CpuAccessibleBuffer<[u8]>>, OffscreenError>
+    where
+        D: FnOnce(&Hardware, &Arc<Framebuffer>, &Viewport) -> PrimaryAutoCommandBuffer,
+{
+    let device = hardware.graphics_device();
+    let [width, height] = dimensions;
+
+    let mut color = None;
+    let mut attachments = Vec::new();
+    for attachment in render_pass.attachments() {
+        let attachment_format = attachment.format.unwrap_or(format);
+        let aspects = attachment_format.aspects();
+
+        let view = if aspects.depth || aspects.stencil {
+            let image = if attachment.samples == SampleCount::Sample1 {
+                AttachmentImage::transient(Arc::clone(device), dimensions, attachment_format)?
+            } else {
+                AttachmentImage::transient_multisampled(
+                    Arc::clone(device),
+                    dimensions,
+                    attachment.samples,
+                    attachment_format,
+                )?
+            };
+            ImageView::new_default(image)?
+        } else {
+            if color.is_some() {
+                return Err(OffscreenError::AttachmentCount(render_pass.attachments().len()));
+            }
+            if attachment_format != format {
+                return Err(OffscreenError::Format {
+                    expected: attachment.format,
+                    image: format,
+                });
+            }
+            if attachment.samples != SampleCount::Sample1 {
+                return Err(OffscreenError::Samples {
+                    expected: attachment.samples,
+                    image: SampleCount::Sample1,
+                });
+            }
+
+            let image = AttachmentImage::with_usage(
+                Arc::clone(device),
+                dimensions,
+                format,
+                ImageUsage {
+                    transfer_source: true,
+                    ..ImageUsage::color_attachment()
+                },
+            )?;
+            color = Some(Arc::clone(&image));
+            ImageView::new_default(image)?
+        };
+        attachments.push(view as Arc<_>);
+    }
+    let color = color.ok_or(OffscreenError::NoColorAttachment)?;
+
+    let framebuffer = Framebuffer::new(
+        render_pass,
+        FramebufferCreateInfo {
+            attachments,
+            ..Default::default()
+        },
+    )?;
+    let viewport = Viewport {
+        origin: [0.0, 0.0],
+        dimensions: [width as f32, height as f32],
+        depth_range: 0.0..1.0,
+    };
+
+    let block_size = format
+        .block_size()
+        .ok_or(OffscreenError::Format {
+            expected: None,
+            image: format,
+        })?;
+    let pixels = unsafe {
+        CpuAccessibleBuffer::<[u8]>::uninitialized_array(
+            Arc::clone(device),
+            u64::from(width) * u64::from(height) * block_size,
+            BufferUsage::transfer_destination(),
+            true,
+        )
+    }
+        .expect("Couldn't create the buffer of the offscreen image");
+
+    let mut builder = AutoCommandBufferBuilder::primary(
+        Arc::clone(device),
+        hardware.graphics_queue().family(),
+        CommandBufferUsage::OneTimeSubmit,
+    )
+        .expect("Couldn't create the download command buffer");
+    builder
+        .copy_image_to_buffer(color, Arc::clone(&pixels) as Arc<_>)
+        .expect("Couldn't record the download of the offscreen image");
+    let download = builder.build().expect("Couldn't build the download command buffer");
+
+    let command_buffer = draw(hardware, &framebuffer, &viewport);
+    command_buffer
+        .execute(Arc::clone(hardware.graphics_queue()))?
+        .then_execute(Arc::clone(hardware.graphics_queue()), download)?
+        .then_signal_fence_and_flush()?
+        .wait(None)?;
+
+    debug!("Rendered an offscreen image of {}x{} in {:?}", width, height, format);
+    Ok(pixels)
+}
+
+/// Runs `draw` with a framebuffer around `image` instead of a swapchain image, and waits until it is done.
+pub(crate) fn render_into<D>(
+    hardware: &Hardware,
+    screen: &Screen,
+    render_pass: Arc<RenderPass>,
+    image: Arc<dyn ImageAccess>,
+    draw: D,
+) -> Result<(), OffscreenError>
+    where
+        D: FnOnce(&Hardware, &Screen, &Arc<Framebuffer>, &Viewport) -> PrimaryAutoCommandBuffer,
+{
+    check_compatible(&render_pass, image.as_ref())?;
+
+    let dimensions = image.dimensions().width_height();
+    let viewport = Viewport {
+        origin: [0.0, 0.0],
+        dimensions: [dimensions[0] as f32, dimensions[1] as f32],
+        depth_range: 0.0..1.0,
+    };
+
+    let view = ImageView::new_default(image)?;
+    let framebuffer = Framebuffer::new(
+        render_pass,
+        FramebufferCreateInfo {
+            attachments: vec![view],
+            ..Default::default()
+        },
+    )?;
+
+    let command_buffer = draw(hardware, screen, &framebuffer, &viewport);
+    command_buffer
+        .execute(Arc::clone(hardware.graphics_queue()))?
+        .then_signal_fence_and_flush()?
+        .wait(None)?;
+
+    Ok(())
+}
+
+fn check_compatible(render_pass: &RenderPass, image: &dyn ImageAccess) -> Result<(), OffscreenError> {
+    let attachments = render_pass.attachments();
+    if attachments.len() != 1 {
+        return Err(OffscreenError::AttachmentCount(attachments.len()));
+    }
+
+    let attachment = &attachments[0];
+    if attachment.format != Some(image.format()) {
+        return Err(OffscreenError::Format {
+            expected: attachment.format,
+            image: image.format(),
+        });
+    }
+    if attachment.samples != image.samples() {
+        return Err(OffscreenError::Samples {
+            expected: attachment.samples,
+            image: image.samples(),
+        });
+    }
+    if !image.inner().image.usage().color_attachment {
+        return Err(OffscreenError::NotColorAttachment);
+    }
+
+    Ok(())
+}
+
+#[derive(Debug)]
+pub enum OffscreenError {
+    /// The render pass must have a single color attachment, the image.
+    AttachmentCount(usize),
+    /// The render pass only has depth and stencil attachments, there is no color attachment to render the image into.
+    NoColorAttachment,
+    Format {
+        expected: Option<Format>,
+        image: Format,
+    },
+    Samples {
+        expected: SampleCount,
+        image: SampleCount,
+    },
+    /// The image wasn't created with the `color_attachment` usage.
+    NotColorAttachment,
+    /// No headless device could render the image.
+    Hardware(HardwareError),
+    Image(ImageCreationError),
+    View(ImageViewCreationError),
+    Framebuffer(FramebufferCreationError),
+    Execution(CommandBufferExecError),
+    Flush(FlushError),
+}
+
+impl fmt::Display for OffscreenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OffscreenError::AttachmentCount(count) => write!(
+                f,
+                "the render pass has {} attachments, but only the image is provided",
+                count
+            ),
+            OffscreenError::NoColorAttachment => write!(f, "the render pass has no color attachment"),
+            OffscreenError::Format { expected, image } => write!(
+                f,
+                "the render pass expects a {:?} image, but the image is {:?}",
+                expected, image
+            ),
+            OffscreenError::Samples { expected, image } => write!(
+                f,
+                "the render pass expects {:?} samples, but the image has {:?}",
+                expected, image
+            ),
+            OffscreenError::NotColorAttachment => {
+                write!(f, "the image can't be used as a color attachment")
+            }
+            OffscreenError::Hardware(e) => write!(f, "couldn't create the device: {}", e),
+            OffscreenError::Image(e) => write!(f, "couldn't create the attachments: {}", e),
+            OffscreenError::View(e) => write!(f, "couldn't create a view of the image: {}", e),
+            OffscreenError::Framebuffer(e) => write!(f, "couldn't create the framebuffer: {}", e),
+            OffscreenError::Execution(e) => write!(f, "couldn't execute the draw: {}", e),
+            OffscreenError::Flush(e) => write!(f, "couldn't wait for the draw: {}", e),
+        }
+    }
+}
+
+impl Error for OffscreenError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            OffscreenError::Hardware(e) => Some(e),
+            OffscreenError::Image(e) => Some(e),
+            OffscreenError::View(e) => Some(e),
+            OffscreenError::Framebuffer(e) => Some(e),
+            OffscreenError::Execution(e) => Some(e),
+            OffscreenError::Flush(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<HardwareError> for OffscreenError {
+    fn from(e: HardwareError) -> Self {
+        OffscreenError::Hardware(e)
+    }
+}
+
+impl From<ImageCreationError> for OffscreenError {
+    fn from(e: ImageCreationError) -> Self {
+        OffscreenError::Image(e)
+    }
+}
+
+impl From<ImageViewCreationError> for OffscreenError {
+    fn from(e: ImageViewCreationError) -> Self {
+        OffscreenError::View(e)
+    }
+}
+
+impl From<FramebufferCreationError> for OffscreenError {
+    fn from(e: FramebufferCreationError) -> Self {
+        OffscreenError::Framebuffer(e)
+    }
+}
+
+impl From<CommandBufferExecError> for OffscreenError {
+    fn from(e: CommandBufferExecError) -> Self {
+        OffscreenError::Execution(e)
+    }
+}
+
+impl From<FlushError> for OffscreenError {
+    fn from(e: FlushError) -> Self {
+        OffscreenError::Flush(e)
+    }
+}
@@ -0,0 +1,192 @@
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use winit::monitor::MonitorHandle;
+use winit::window::Window;
+
+/// The maximum number of frames the engine renders per second.
+///
+/// Present modes that don't wait for the vertical blank (`Mailbox`, `Immediate`) otherwise render
+/// as many frames as the GPU can, most of which are never displayed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FpsCap {
+    /// Renders at the refresh rate of the monitor the window is on.
+    ///
+    /// Falls back to [`FpsCap::Uncapped`] if the refresh rate can't be queried.
+    MatchRefresh,
+    Fixed(u32),
+    #[default]
+    Uncapped,
+}
+
+impl FpsCap {
+    /// The minimum duration between the start of two frames, or `None` if frames aren't paced.
+    pub(crate) fn interval(&self, window: &Window) -> Option<Duration> {
+        let fps = match self {
+            FpsCap::MatchRefresh => window.current_monitor().as_ref().and_then(refresh_rate)?,
+            FpsCap::Fixed(fps) => *fps,
+            FpsCap::Uncapped => return None,
+        };
+
+        if fps == 0 {
+            None
+        } else {
+            Some(Duration::from_secs(1) / fps)
+        }
+    }
+}
+
+/// The refresh rate of `monitor`, in Hz.
+///
+/// Winit doesn't expose the current video mode: this is the highest refresh rate of the modes
+/// with the monitor's current resolution.
+pub fn refresh_rate(monitor: &MonitorHandle) -> Option<u32> {
+    let size = monitor.size();
+
+    monitor
+        .video_modes()
+        .filter(|mode| mode.size() == size)
+        .map(|mode| u32::from(mode.refresh_rate()))
+        .max()
+}
+
+/// How long [`Engine::run`](crate::drawing::engine::Engine::run) waits for a swapchain image before skipping the frame,
+/// see [`Engine::set_acquire_timeout`](crate::drawing::engine::Engine::set_acquire_timeout).
+///
+/// Without a timeout, a stalled compositor blocks the draw loop. Skipped frames are counted
+/// in [`FrameStats::skipped`], and logged each second.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AcquireTimeout {
+    timeout: Duration,
+    recreate_after: Option<u32>,
+}
+
+impl AcquireTimeout {
+    /// Skips the frame when no image is available after `timeout`, and tries again with the next one.
+    pub fn new(timeout: Duration) -> Self {
+        AcquireTimeout {
+            timeout,
+            recreate_after: None,
+        }
+    }
+
+    /// Recreates the swapchain after `frames` consecutive frames are skipped, in case it is stuck.
+    pub fn recreate_after(mut self, frames: u32) -> Self {
+        self.recreate_after = Some(frames);
+        self
+    }
+
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    pub(crate) fn should_recreate(&self, consecutive: u32) -> bool {
+        self.recreate_after.is_some_and(|frames| consecutive >= frames)
+    }
+}
+
+/// How long the frames rendered by [`Engine::run_frames`](crate::drawing::engine::Engine::run_frames) took.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FrameStats {
+    /// The number of frames presented.
+    pub frames: u64,
+    /// From the start of the first frame to the end of the GPU work of the last one.
+    pub total: Duration,
+    /// The shortest time between the presentation of two frames.
+    pub min: Duration,
+    /// The longest time between the presentation of two frames.
+    pub max: Duration,
+    /// The frames skipped because no swapchain image was acquired in time, see [`AcquireTimeout`].
+    pub skipped: u64,
+}
+
+impl FrameStats {
+    pub fn average(&self) -> Duration {
+        if self.frames == 0 {
+            Duration::ZERO
+        } else {
+            self.total / self.frames as u32
+        }
+    }
+
+    pub(crate) fn record(&mut self, frame: Duration) {
+        self.min = if self.frames == 0 { frame } else { self.min.min(frame) };
+        self.max = self.max.max(frame);
+        self.frames += 1;
+    }
+}
+
+/// The timing of the frames presented recently, see [`FrameTimer`].
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FrameTiming {
+    /// The time between the last two presents.
+    pub last_frame_time: Duration,
+    /// The frames presented per second, over the frames kept by the timer.
+    pub average_fps: f32,
+    /// The number of frames presented since the timer was enabled.
+    pub frames: u64,
+}
+
+struct TimerState {
+    last_present: Option<Instant>,
+    // The most recent frame times, oldest first
+    times: VecDeque<Duration>,
+    total: Duration,
+    timing: FrameTiming,
+}
+
+/// Measures the wall-clock time between the frames presented by [`Engine::run`](crate::drawing::engine::Engine::run),
+/// see [`Engine::frame_timer`](crate::drawing::engine::Engine::frame_timer).
+///
+/// Clone it into the draw closure, or another thread, to read [`FrameTimer::timing`].
+pub struct FrameTimer {
+    window: usize,
+    state: Mutex<TimerState>,
+}
+
+impl FrameTimer {
+    /// Averages the frame rate over the last `window` frames.
+    pub(crate) fn new(window: usize) -> Self {
+        FrameTimer {
+            window: window.max(1),
+            state: Mutex::new(TimerState {
+                last_present: None,
+                times: VecDeque::with_capacity(window.max(1)),
+                total: Duration::ZERO,
+                timing: FrameTiming::default(),
+            }),
+        }
+    }
+
+    pub fn timing(&self) -> FrameTiming {
+        self.lock().timing
+    }
+
+    /// Records a frame presented at `now`.
+    pub(crate) fn record(&self, now: Instant) {
+        let mut state = self.lock();
+        state.timing.frames += 1;
+
+        let last_present = state.last_present.replace(now);
+        let frame_time = match last_present {
+            Some(last_present) => now - last_present,
+            None => return,
+        };
+
+        if state.times.len() == self.window {
+            let oldest = state.times.pop_front().unwrap_or_default();
+            state.total -= oldest;
+        }
+        state.times.push_back(frame_time);
+        state.total += frame_time;
+
+        let average = state.total.as_secs_f32() / state.times.len() as f32;
+        state.timing.last_frame_time = frame_time;
+        state.timing.average_fps = if average > 0.0 { 1.0 / average } else { 0.0 };
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, TimerState> {
+        self.state.lock().expect("The frame timer was poisoned")
+    }
+}
@@ -0,0 +1,425 @@
+use std::error::Error;
+use std::fmt;
+use std::sync::Arc;
+
+use vulkano::descriptor_set::layout::DescriptorType;
+use vulkano::device::Device;
+use vulkano::pipeline::graphics::depth_stencil::DepthStencilState;
+use vulkano::pipeline::graphics::input_assembly::{InputAssemblyState, PrimitiveTopology};
+use vulkano::pipeline::graphics::rasterization::{DepthBias, DepthBiasState, RasterizationState};
+use vulkano::pipeline::graphics::vertex_input::BuffersDefinition;
+use vulkano::pipeline::graphics::viewport::{Scissor, Viewport, ViewportState};
+use vulkano::pipeline::graphics::GraphicsPipelineCreationError;
+use vulkano::pipeline::cache::PipelineCache;
+use vulkano::pipeline::{DynamicState, GraphicsPipeline, StateMode};
+use vulkano::render_pass::Subpass;
+use vulkano::shader::EntryPoint;
+
+use crate::drawing::reflection::{reflect, ReflectedBinding};
+
+/// Builds a graphics pipeline with a vertex and a fragment shader, drawing to a dynamic viewport.
+///
+/// By default, vertices are assembled into a list of triangles, without primitive restart,
+/// without depth test, and the viewport is the only dynamic state.
+pub struct PipelineBuilder<'a> {
+    vertex_shader: EntryPoint<'a>,
+    fragment_shader: EntryPoint<'a>,
+    subpass: Subpass,
+    vertex_input: BuffersDefinition,
+    topology: PrimitiveTopology,
+    primitive_restart: bool,
+    dynamic_states: Vec<DynamicState>,
+    depth_test: bool,
+    depth_bias: Option<DepthBias>,
+    depth_clamp: bool,
+    viewport_count: u32,
+    push_descriptor_sets: Vec<u32>,
+    expected_bindings: Option<Vec<(u32, u32, DescriptorType)>>,
+    cache: Option<Arc<PipelineCache>>,
+}
+
+impl<'a> PipelineBuilder<'a> {
+    pub fn new(
+        vertex_shader: EntryPoint<'a>,
+        fragment_shader: EntryPoint<'a>,
+        subpass: Subpass,
+    ) -> Self {
+        PipelineBuilder {
+            vertex_shader,
+            fragment_shader,
+            subpass,
+            vertex_input: BuffersDefinition::new(),
+            topology: PrimitiveTopology::TriangleList,
+            primitive_restart: false,
+            dynamic_states: vec![DynamicState::Viewport],
+            depth_test: false,
+            depth_bias: None,
+            depth_clamp: false,
+            viewport_count: 1,
+            push_descriptor_sets: Vec::new(),
+            expected_bindings: None,
+            cache: None,
+        }
+    }
+
+    /// The layout of the vertex and instance buffers.
+    pub fn vertex_input(mut self, vertex_input: BuffersDefinition) -> Self {
+        self.vertex_input = vertex_input;
+        self
+    }
+
+    /// How vertices are assembled into primitives.
+    pub fn topology(mut self, topology: PrimitiveTopology) -> Self {
+        self.topology = topology;
+        self
+    }
+
+    /// Whether the special index value (`0xFFFF` or `0xFFFFFFFF`, depending on the index type) starts a new primitive.
+    ///
+    /// Only indexed draws are affected, and only strip and fan topologies can be restarted.
+    pub fn primitive_restart(mut self, enabled: bool) -> Self {
+        self.primitive_restart = enabled;
+        self
+    }
+
+    /// The states set while recording draws, instead of being baked into the pipeline.
+    ///
+    /// The viewport is always dynamic. The other supported states are `Scissor`, `LineWidth` and `DepthBias`,
+    /// whose values are then undefined until the corresponding `set_*` command is recorded:
+    /// drawing without setting each of them fails with `DynamicStateNotSet`.
+    pub fn dynamic_states<I>(mut self, states: I) -> Self
+        where
+            I: IntoIterator<Item = DynamicState>,
+    {
+        self.dynamic_states = vec![DynamicState::Viewport];
+        for state in states {
+            if !self.dynamic_states.contains(&state) {
+                self.dynamic_states.push(state);
+            }
+        }
+        self
+    }
+
+    /// Whether fragments further than the depth already in the depth attachment are discarded.
+    ///
+    /// Passing fragments write their depth. The subpass must have a depth attachment.
+    pub fn depth_test(mut self, enabled: bool) -> Self {
+        self.depth_test = enabled;
+        self
+    }
+
+    /// Offsets the depth of each fragment by `constant_factor`, plus `slope_factor` times the depth slope of its polygon.
+    ///
+    /// Mostly used when rendering shadow maps, to avoid surfaces shadowing themselves.
+    /// Ignored if `DepthBias` is a dynamic state.
+    pub fn depth_bias(mut self, constant_factor: f32, slope_factor: f32) -> Self {
+        self.depth_bias = Some(DepthBias {
+            constant_factor,
+            clamp: 0.0,
+            slope_factor,
+        });
+        self
+    }
+
+    /// Whether the depth of fragments outside the near and far planes is clamped, instead of the fragments being clipped.
+    ///
+    /// Mostly used when rendering shadow maps, so occluders in front of the near plane still cast shadows.
+    /// Needs the `depth_clamp` device feature, which the engine enables when the graphics device supports it.
+    pub fn depth_clamp(mut self, enabled: bool) -> Self {
+        self.depth_clamp = enabled;
+        self
+    }
+
+    /// The number of viewports primitives can be drawn to, set with `set_viewport(0, viewports)` while recording.
+    ///
+    /// The vertex shader selects the viewport of each primitive by writing `gl_ViewportIndex`,
+    /// which needs the `shader_output_viewport_index` device feature.
+    /// More than one viewport needs the `multi_viewport` device feature.
+    /// The engine enables both when the graphics device supports them.
+    pub fn viewport_count(mut self, count: u32) -> Self {
+        self.viewport_count = count;
+        self
+    }
+
+    /// Declares the descriptor set `set` as a push descriptor set: instead of being allocated and bound,
+    /// its descriptors are recorded into the command buffer with [`DescriptorSetBuilder::push`](crate::drawing::descriptors::DescriptorSetBuilder::push).
+    ///
+    /// Suited to descriptors that change with each draw.
+    /// Needs the `khr_push_descriptor` device extension, see [`Hardware::supports_push_descriptors`](crate::drawing::hardware::Hardware::supports_push_descriptors).
+    pub fn push_descriptor_set(mut self, set: u32) -> Self {
+        if !self.push_descriptor_sets.contains(&set) {
+            self.push_descriptor_sets.push(set);
+        }
+        self
+    }
+
+    /// The descriptor bindings the shaders declare, from which the pipeline layout is derived.
+    pub fn reflected_bindings(&self) -> Vec<ReflectedBinding> {
+        reflect(&[&self.vertex_shader, &self.fragment_shader])
+    }
+
+    /// The descriptors the application binds, as `(set, binding, type)`.
+    ///
+    /// Building fails if they don't match exactly the bindings the shaders declare,
+    /// instead of failing later when the descriptor sets are bound.
+    pub fn expect_bindings<I>(mut self, bindings: I) -> Self
+        where
+            I: IntoIterator<Item = (u32, u32, DescriptorType)>,
+    {
+        self.expected_bindings = Some(bindings.into_iter().collect());
+        self
+    }
+
+    /// Reuses the compilation results stored in `cache`, and stores the new ones,
+    /// for example [`Hardware::pipeline_cache`](crate::drawing::hardware::Hardware::pipeline_cache).
+    pub fn cache(mut self, cache: Arc<PipelineCache>) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    pub fn build(self, device: Arc<Device>) -> Result<Arc<GraphicsPipeline>, PipelineError> {
+        if let Some(expected) = &self.expected_bindings {
+            check_bindings(self.reflected_bindings(), expected)?;
+        }
+
+        if !self.push_descriptor_sets.is_empty() {
+            if !device.enabled_extensions().khr_push_descriptor {
+                return Err(PipelineError::PushDescriptorsNotSupported);
+            }
+            let reflected = self.reflected_bindings();
+            if let Some(&set) = self
+                .push_descriptor_sets
+                .iter()
+                .find(|&&set| !reflected.iter().any(|binding| binding.set == set))
+            {
+                return Err(PipelineError::UnknownPushDescriptorSet(set));
+            }
+        }
+
+        let mut input_assembly = InputAssemblyState::new().topology(self.topology);
+        if self.primitive_restart {
+            if !is_restartable(self.topology) {
+                return Err(PipelineError::PrimitiveRestart(self.topology));
+            }
+            input_assembly = input_assembly.primitive_restart_enable();
+        }
+
+        let max_viewports = device.physical_device().properties().max_viewports;
+        if self.viewport_count == 0 || self.viewport_count > max_viewports {
+            return Err(PipelineError::ViewportCount {
+                requested: self.viewport_count,
+                max: max_viewports,
+            });
+        }
+        if self.viewport_count > 1 && !device.enabled_features().multi_viewport {
+            return Err(PipelineError::MultiViewportNotSupported);
+        }
+
+        let mut viewport = ViewportState::FixedScissor {
+            scissors: vec![Scissor::irrelevant(); self.viewport_count as usize],
+            viewport_count_dynamic: false,
+        };
+        let mut rasterization = RasterizationState::new();
+        if let Some(bias) = self.depth_bias {
+            rasterization.depth_bias = Some(DepthBiasState {
+                enable_dynamic: false,
+                bias: StateMode::Fixed(bias),
+            });
+        }
+        if self.depth_clamp {
+            if !device.enabled_features().depth_clamp {
+                return Err(PipelineError::DepthClampNotSupported);
+            }
+            rasterization.depth_clamp_enable = true;
+        }
+        for state in &self.dynamic_states {
+            match state {
+                DynamicState::Viewport => {}
+                DynamicState::Scissor => {
+                    viewport = ViewportState::viewport_dynamic_scissor_dynamic(self.viewport_count);
+                }
+                DynamicState::LineWidth => rasterization.line_width = StateMode::Dynamic,
+                DynamicState::DepthBias => {
+                    rasterization.depth_bias = Some(DepthBiasState {
+                        enable_dynamic: false,
+                        bias: StateMode::Dynamic,
+                    });
+                }
+                other => return Err(PipelineError::UnsupportedDynamicState(*other)),
+            }
+        }
+
+        let depth_stencil = if self.depth_test {
+            DepthStencilState::simple_depth_test()
+        } else {
+            DepthStencilState::disabled()
+        };
+
+        let push_descriptor_sets = self.push_descriptor_sets;
+        let mut builder = GraphicsPipeline::start()
+            .vertex_input_state(self.vertex_input)
+            .vertex_shader(self.vertex_shader, ())
+            .input_assembly_state(input_assembly)
+            .viewport_state(viewport)
+            .rasterization_state(rasterization)
+            .depth_stencil_state(depth_stencil)
+            .fragment_shader(self.fragment_shader, ())
+            .render_pass(self.subpass);
+        if let Some(cache) = self.cache {
+            builder = builder.build_with_cache(cache);
+        }
+
+        Ok(builder
+            .with_auto_layout(device, |sets| {
+                for &set in &push_descriptor_sets {
+                    sets[set as usize].push_descriptor = true;
+                }
+            })?)
+    }
+}
+
+fn check_bindings(
+    reflected: Vec<ReflectedBinding>,
+    expected: &[(u32, u32, DescriptorType)],
+) -> Result<(), PipelineError> {
+    let declared_by_shaders = reflected.iter().all(|shader| {
+        expected.iter().any(|&(set, binding, descriptor_type)| {
+            shader.set == set && shader.binding == binding && shader.accepts(descriptor_type)
+        })
+    });
+    let bound = expected.iter().all(|&(set, binding, _)| {
+        reflected
+            .iter()
+            .any(|shader| shader.set == set && shader.binding == binding)
+    });
+
+    if declared_by_shaders && bound {
+        Ok(())
+    } else {
+        let mut provided = expected.to_vec();
+        provided.sort_by_key(|&(set, binding, _)| (set, binding));
+        Err(PipelineError::BindingMismatch {
+            shader: reflected,
+            provided,
+        })
+    }
+}
+
+/// Whether primitive restart is meaningful for `topology`.
+///
+/// Vulkan only allows restarting lists through optional device features, which the engine doesn't enable.
+fn is_restartable(topology: PrimitiveTopology) -> bool {
+    matches!(
+        topology,
+        PrimitiveTopology::LineStrip
+            | PrimitiveTopology::TriangleStrip
+            | PrimitiveTopology::TriangleFan
+            | PrimitiveTopology::LineStripWithAdjacency
+            | PrimitiveTopology::TriangleStripWithAdjacency
+    )
+}
+
+/// Splits `viewport` into a grid of `columns` by `rows` viewports, row by row, for example for split-screen.
+///
+/// Pass them to `set_viewport(0, …)` when drawing with a pipeline built with as many viewports,
+/// see [`PipelineBuilder::viewport_count`].
+pub fn split_viewport(viewport: &Viewport, columns: u32, rows: u32) -> Vec<Viewport> {
+    let width = viewport.dimensions[0] / columns as f32;
+    let height = viewport.dimensions[1] / rows as f32;
+
+    (0..rows)
+        .flat_map(|row| (0..columns).map(move |column| (column, row)))
+        .map(|(column, row)| Viewport {
+            origin: [
+                viewport.origin[0] + column as f32 * width,
+                viewport.origin[1] + row as f32 * height,
+            ],
+            dimensions: [width, height],
+            depth_range: viewport.depth_range.clone(),
+        })
+        .collect()
+}
+
+#[derive(Debug)]
+pub enum PipelineError {
+    /// Primitive restart was requested for a topology that isn't a strip or a fan.
+    PrimitiveRestart(PrimitiveTopology),
+    /// The builder can't make this state dynamic.
+    UnsupportedDynamicState(DynamicState),
+    /// The descriptors given to [`PipelineBuilder::expect_bindings`] don't match the bindings of the shaders.
+    BindingMismatch {
+        shader: Vec<ReflectedBinding>,
+        provided: Vec<(u32, u32, DescriptorType)>,
+    },
+    /// The number of viewports is zero, or larger than what the device supports.
+    ViewportCount { requested: u32, max: u32 },
+    /// Several viewports were requested, but the device doesn't have the `multi_viewport` feature.
+    MultiViewportNotSupported,
+    /// Push descriptor sets were declared, but the device doesn't have the `khr_push_descriptor` extension.
+    PushDescriptorsNotSupported,
+    /// A push descriptor set isn't used by the shaders.
+    UnknownPushDescriptorSet(u32),
+    /// Depth clamping was requested, but the device doesn't have the `depth_clamp` feature.
+    DepthClampNotSupported,
+    Creation(GraphicsPipelineCreationError),
+}
+
+impl fmt::Display for PipelineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PipelineError::PrimitiveRestart(topology) => write!(
+                f,
+                "primitive restart can only be used with strip and fan topologies, not {:?}",
+                topology
+            ),
+            PipelineError::UnsupportedDynamicState(state) => {
+                write!(f, "the dynamic state {:?} isn't supported", state)
+            }
+            PipelineError::BindingMismatch { shader, provided } => {
+                writeln!(f, "the bindings don't match the shaders")?;
+                writeln!(f, "the shaders declare:")?;
+                for binding in shader {
+                    writeln!(f, " - {}", binding)?;
+                }
+                write!(f, "but the application provides:")?;
+                for (set, binding, descriptor_type) in provided {
+                    write!(f, "\n - set {} binding {}: {:?}", set, binding, descriptor_type)?;
+                }
+                Ok(())
+            }
+            PipelineError::ViewportCount { requested, max } => write!(
+                f,
+                "the pipeline needs between 1 and {} viewports, not {}",
+                max, requested
+            ),
+            PipelineError::MultiViewportNotSupported => {
+                write!(f, "the device doesn't support multiple viewports")
+            }
+            PipelineError::PushDescriptorsNotSupported => {
+                write!(f, "the device doesn't support push descriptors")
+            }
+            PipelineError::UnknownPushDescriptorSet(set) => {
+                write!(f, "the shaders don't use the push descriptor set {}", set)
+            }
+            PipelineError::DepthClampNotSupported => {
+                write!(f, "the device doesn't support depth clamping")
+            }
+            PipelineError::Creation(e) => write!(f, "couldn't create the pipeline: {}", e),
+        }
+    }
+}
+
+impl Error for PipelineError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            PipelineError::Creation(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<GraphicsPipelineCreationError> for PipelineError {
+    fn from(e: GraphicsPipelineCreationError) -> Self {
+        PipelineError::Creation(e)
+    }
+}
@@ -0,0 +1,121 @@
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use vulkano::device::{DeviceOwned, Queue};
+use vulkano::swapchain::SwapchainCreationError;
+use vulkano::sync::FlushError;
+
+use crate::drawing::hardware::Hardware;
+
+/// The number of frames a [`PresentLog`] remembers.
+const PRESENT_LOG_LENGTH: usize = 120;
+
+/// Whether a frame reached the display.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Displayed {
+    /// The frame couldn't be submitted or presented, see [`PresentReport::error`].
+    NotPresented,
+    /// The frame was queued for presentation. Whether it was displayed, or replaced by a newer frame with the
+    /// `Mailbox` present mode, needs `VK_KHR_present_id` and `VK_KHR_present_wait`, which vulkano 0.29 doesn't expose.
+    #[default]
+    Unknown,
+}
+
+/// What happened to a frame submitted for presentation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PresentReport {
+    /// The number of the frame, counted from the start of the engine.
+    pub frame: u64,
+    /// The swapchain image the frame was drawn to.
+    pub image: usize,
+    /// The swapchain no longer matches the surface exactly: the frame was presented, but it will be recreated.
+    pub suboptimal: bool,
+    /// Why the frame couldn't be submitted or presented.
+    pub error: Option<FlushError>,
+    pub displayed: Displayed,
+}
+
+/// The presentation status of the most recent frames, see [`Engine::present_log`](crate::drawing::engine::Engine::present_log).
+#[derive(Default)]
+pub struct PresentLog {
+    reports: Mutex<VecDeque<PresentReport>>,
+}
+
+impl PresentLog {
+    /// The most recent frame.
+    pub fn last(&self) -> Option<PresentReport> {
+        self.lock().back().cloned()
+    }
+
+    /// The most recent frames, from the oldest to the newest.
+    pub fn recent(&self) -> Vec<PresentReport> {
+        self.lock().iter().cloned().collect()
+    }
+
+    pub(crate) fn record(&self, report: PresentReport) {
+        let mut reports = self.lock();
+        if reports.len() == PRESENT_LOG_LENGTH {
+            reports.pop_front();
+        }
+        reports.push_back(report);
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, VecDeque<PresentReport>> {
+        self.reports.lock().expect("The present log was poisoned")
+    }
+}
+
+/// Checks that the window can be presented to through `queue`.
+pub(crate) fn check_queue(hardware: &Hardware, queue: &Arc<Queue>) -> Result<(), PresentQueueError> {
+    let device = hardware.graphics_device();
+    if queue.device() != device {
+        return Err(PresentQueueError::OtherDevice);
+    }
+
+    let family = queue.family().id();
+    if !Hardware::can_present(device.physical_device(), family, hardware.surface()) {
+        return Err(PresentQueueError::CannotPresent(family));
+    }
+    Ok(())
+}
+
+#[derive(Debug)]
+pub enum PresentQueueError {
+    /// The queue doesn't belong to the graphics device, which owns the swapchain.
+    OtherDevice,
+    /// The queue family can't present to the window.
+    CannotPresent(u32),
+    /// The swapchain couldn't be shared with the queue family.
+    Swapchain(SwapchainCreationError),
+}
+
+impl fmt::Display for PresentQueueError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PresentQueueError::OtherDevice => {
+                write!(f, "the queue doesn't belong to the graphics device")
+            }
+            PresentQueueError::CannotPresent(family) => {
+                write!(f, "the queue family {} can't present to the window", family)
+            }
+            PresentQueueError::Swapchain(e) => write!(f, "couldn't recreate the swapchain: {}", e),
+        }
+    }
+}
+
+impl Error for PresentQueueError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            PresentQueueError::Swapchain(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<SwapchainCreationError> for PresentQueueError {
+    fn from(e: SwapchainCreationError) -> Self {
+        PresentQueueError::Swapchain(e)
+    }
+}
@@ -0,0 +1,378 @@
+use std::error::Error;
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use log::{trace, warn};
+use vulkano::command_buffer::{AutoCommandBufferBuilder, PrimaryAutoCommandBuffer};
+use vulkano::query::{QueryPool, QueryPoolCreateInfo, QueryPoolCreationError, QueryResultFlags, QueryType};
+use vulkano::sync::PipelineStage;
+
+use crate::drawing::hardware::Hardware;
+
+/// The maximum number of zones per frame, unless configured otherwise.
+const DEFAULT_MAX_ZONES: u32 = 64;
+
+/// The GPU time spent in a zone.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ZoneTiming {
+    /// The names of the enclosing zones and of this zone, separated by `/`.
+    pub path: String,
+    /// The number of enclosing zones.
+    pub depth: usize,
+    pub duration: Duration,
+}
+
+/// The zones of a frame, in the order they were opened.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FrameProfile {
+    pub zones: Vec<ZoneTiming>,
+}
+
+impl FrameProfile {
+    /// The zones from the longest to the shortest.
+    pub fn sorted(&self) -> Vec<&ZoneTiming> {
+        let mut zones: Vec<&ZoneTiming> = self.zones.iter().collect();
+        zones.sort_by_key(|zone| std::cmp::Reverse(zone.duration));
+        zones
+    }
+}
+
+impl fmt::Display for FrameProfile {
+    /// For example `main: 3.40ms, main/shadows: 1.20ms`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, zone) in self.sorted().into_iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}: {:.2}ms", zone.path, zone.duration.as_secs_f64() * 1000.0)?;
+        }
+        Ok(())
+    }
+}
+
+struct PendingZone {
+    path: String,
+    depth: usize,
+    // The query of the end timestamp is the next one
+    begin: u32,
+    closed: bool,
+}
+
+struct State {
+    frame: usize,
+    zones: Vec<Vec<PendingZone>>,
+    next_query: u32,
+    open: Vec<usize>,
+    last: Option<FrameProfile>,
+    warned_full: bool,
+}
+
+/// Measures the GPU time spent in named zones, with timestamp queries.
+///
+/// Each frame, call [`Profiler::begin_frame`] at the start of the command buffer, outside of any render pass,
+/// then open zones with [`Profiler::zone`]. Results arrive a few frames later, once the GPU is done with them,
+/// see [`Profiler::last_frame`].
+///
+/// If the graphics queue doesn't support timestamps, zones measure nothing.
+pub struct Profiler {
+    // None if timestamps aren't supported
+    pool: Option<Arc<QueryPool>>,
+    max_zones: u32,
+    // Nanoseconds per tick
+    period: f64,
+    valid_bits: u32,
+    state: Mutex<State>,
+}
+
+impl Profiler {
+    /// A profiler keeping the queries of `frames` frames in flight, of up to 64 zones each.
+    pub fn new(hardware: &Hardware, frames: usize) -> Result<Self, ProfilerError> {
+        Self::with_max_zones(hardware, frames, DEFAULT_MAX_ZONES)
+    }
+
+    /// A profiler keeping the queries of `frames` frames in flight, of up to `max_zones` zones each.
+    ///
+    /// Both must be at least one.
+    pub fn with_max_zones(hardware: &Hardware, frames: usize, max_zones: u32) -> Result<Self, ProfilerError> {
+        if frames == 0 {
+            return Err(ProfilerError::NoFrames);
+        }
+        if max_zones == 0 {
+            return Err(ProfilerError::NoZones);
+        }
+        // Each zone has a begin and an end timestamp
+        let query_count = u32::try_from(frames)
+            .ok()
+            .and_then(|frames| frames.checked_mul(max_zones))
+            .and_then(|queries| queries.checked_mul(2))
+            .ok_or(ProfilerError::TooManyQueries { frames, max_zones })?;
+
+        let device = hardware.graphics_device();
+        let valid_bits = hardware
+            .graphics_queue()
+            .family()
+            .timestamp_valid_bits()
+            .unwrap_or(0);
+
+        let pool = if valid_bits == 0 {
+            warn!("The graphics queue doesn't support timestamps, GPU zones won't be measured");
+            None
+        } else {
+            Some(QueryPool::new(
+                Arc::clone(device),
+                QueryPoolCreateInfo {
+                    query_count,
+                    ..QueryPoolCreateInfo::query_type(QueryType::Timestamp)
+                },
+            )?)
+        };
+
+        Ok(Profiler {
+            pool,
+            max_zones,
+            period: device.physical_device().properties().timestamp_period as f64,
+            valid_bits,
+            state: Mutex::new(State {
+                frame: 0,
+                zones: (0..frames).map(|_| Vec::new()).collect(),
+                next_query: 0,
+                open: Vec::new(),
+                last: None,
+                warned_full: false,
+            }),
+        })
+    }
+
+    /// Starts a new frame: collects the results of the frame whose queries are reused, and resets them.
+    ///
+    /// Must be recorded outside of a render pass.
+    pub fn begin_frame(&self, builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>) {
+        let pool = match &self.pool {
+            Some(pool) => pool,
+            None => return,
+        };
+        let mut state = self.lock();
+        if !state.open.is_empty() {
+            warn!("{} GPU zones weren't closed before the next frame", state.open.len());
+            state.open.clear();
+        }
+
+        let frame = (state.frame + 1) % state.zones.len();
+        state.frame = frame;
+        let first = self.first_query(frame);
+        let zones = std::mem::take(&mut state.zones[frame]);
+        if let Some(profile) = self.collect(pool, first, &zones) {
+            state.last = Some(profile);
+        }
+
+        unsafe {
+            builder
+                .reset_query_pool(Arc::clone(pool), first..first + self.max_zones * 2)
+                .expect("Couldn't reset the profiler queries");
+        }
+        state.next_query = 0;
+    }
+
+    /// Opens a zone named `name`, closed when the returned guard is dropped.
+    ///
+    /// The guard dereferences to `builder`: record the commands of the zone, and open nested zones, through it.
+    pub fn zone<'a>(
+        &'a self,
+        builder: &'a mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        name: &str,
+    ) -> Zone<'a> {
+        let index = self.begin_zone(builder, name);
+        Zone {
+            profiler: self,
+            builder,
+            index,
+        }
+    }
+
+    /// The zones of the most recent frame whose results are available.
+    pub fn last_frame(&self) -> Option<FrameProfile> {
+        self.lock().last.clone()
+    }
+
+    fn begin_zone(
+        &self,
+        builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        name: &str,
+    ) -> Option<usize> {
+        let pool = self.pool.as_ref()?;
+        let mut state = self.lock();
+
+        if state.next_query + 2 > self.max_zones * 2 {
+            if !state.warned_full {
+                warn!("More than {} GPU zones in a frame, the next ones aren't measured", self.max_zones);
+                state.warned_full = true;
+            }
+            return None;
+        }
+
+        let frame = state.frame;
+        let path = match state.open.last() {
+            Some(&parent) => format!("{}/{}", state.zones[frame][parent].path, name),
+            None => name.to_owned(),
+        };
+        let begin = state.next_query;
+        state.next_query += 2;
+
+        unsafe {
+            builder
+                .write_timestamp(Arc::clone(pool), self.first_query(frame) + begin, PipelineStage::BottomOfPipe)
+                .expect("Couldn't write the timestamp of a GPU zone");
+        }
+
+        let depth = state.open.len();
+        state.zones[frame].push(PendingZone {
+            path,
+            depth,
+            begin,
+            closed: false,
+        });
+        let index = state.zones[frame].len() - 1;
+        state.open.push(index);
+        Some(index)
+    }
+
+    fn end_zone(&self, builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>, index: usize) {
+        let pool = match &self.pool {
+            Some(pool) => pool,
+            None => return,
+        };
+        let mut state = self.lock();
+        let frame = state.frame;
+        // The zone belongs to a frame that was already restarted
+        if state.open.last() != Some(&index) {
+            return;
+        }
+        state.open.pop();
+
+        let zone = &mut state.zones[frame][index];
+        zone.closed = true;
+        unsafe {
+            builder
+                .write_timestamp(Arc::clone(pool), self.first_query(frame) + zone.begin + 1, PipelineStage::BottomOfPipe)
+                .expect("Couldn't write the timestamp of a GPU zone");
+        }
+    }
+
+    /// The timings of `zones`, if the GPU has written all their timestamps.
+    fn collect(&self, pool: &QueryPool, first: u32, zones: &[PendingZone]) -> Option<FrameProfile> {
+        let count = zones.iter().map(|zone| zone.begin + 2).max()?;
+        let mut timestamps = vec![0u64; count as usize];
+        let available = pool
+            .queries_range(first..first + count)?
+            .get_results(&mut timestamps, QueryResultFlags::default());
+        match available {
+            Ok(true) => {}
+            Ok(false) => {
+                trace!("The GPU zones of a frame aren't available yet, they are skipped");
+                return None;
+            }
+            Err(e) => {
+                warn!("Couldn't read the GPU zones: {}", e);
+                return None;
+            }
+        }
+
+        let mask = if self.valid_bits >= 64 { u64::MAX } else { (1 << self.valid_bits) - 1 };
+        let zones = zones
+            .iter()
+            .filter(|zone| zone.closed)
+            .map(|zone| {
+                let begin = timestamps[zone.begin as usize] & mask;
+                let end = timestamps[zone.begin as usize + 1] & mask;
+                let ticks = end.wrapping_sub(begin) & mask;
+                ZoneTiming {
+                    path: zone.path.clone(),
+                    depth: zone.depth,
+                    duration: Duration::from_nanos((ticks as f64 * self.period) as u64),
+                }
+            })
+            .collect();
+        Some(FrameProfile { zones })
+    }
+
+    fn first_query(&self, frame: usize) -> u32 {
+        frame as u32 * self.max_zones * 2
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().expect("The profiler was poisoned")
+    }
+}
+
+#[derive(Debug)]
+pub enum ProfilerError {
+    /// The queries are kept for zero frames.
+    NoFrames,
+    /// Zero zones can be measured per frame.
+    NoZones,
+    /// The timestamps of all the frames don't fit in a query pool.
+    TooManyQueries { frames: usize, max_zones: u32 },
+    /// Creating the query pool failed.
+    Pool(QueryPoolCreationError),
+}
+
+impl fmt::Display for ProfilerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProfilerError::NoFrames => write!(f, "the GPU zones must be kept for at least one frame"),
+            ProfilerError::NoZones => write!(f, "at least one GPU zone must be measured per frame"),
+            ProfilerError::TooManyQueries { frames, max_zones } => write!(
+                f,
+                "{} frames of {} GPU zones need more timestamp queries than a query pool can hold",
+                frames, max_zones
+            ),
+            ProfilerError::Pool(e) => write!(f, "couldn't create the profiler query pool: {}", e),
+        }
+    }
+}
+
+impl Error for ProfilerError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ProfilerError::Pool(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<QueryPoolCreationError> for ProfilerError {
+    fn from(e: QueryPoolCreationError) -> Self {
+        ProfilerError::Pool(e)
+    }
+}
+
+/// An open zone of a [`Profiler`], closed when dropped.
+pub struct Zone<'a> {
+    profiler: &'a Profiler,
+    builder: &'a mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+    index: Option<usize>,
+}
+
+impl Deref for Zone<'_> {
+    type Target = AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>;
+
+    fn deref(&self) -> &Self::Target {
+        self.builder
+    }
+}
+
+impl DerefMut for Zone<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.builder
+    }
+}
+
+impl Drop for Zone<'_> {
+    fn drop(&mut self) {
+        if let Some(index) = self.index {
+            self.profiler.end_zone(self.builder, index);
+        }
+    }
+}
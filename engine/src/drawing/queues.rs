@@ -0,0 +1,147 @@
+use std::error::Error;
+use std::fmt;
+
+use vulkano::device::physical::{PhysicalDevice, QueueFamily};
+
+/// What a queue family of a physical device can do.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueueFamilyInfo {
+    pub id: u32,
+    pub queue_count: u32,
+    pub graphics: bool,
+    pub compute: bool,
+    /// Whether the family declares transfer support. Graphics and compute families can always transfer.
+    pub explicit_transfers: bool,
+    pub sparse_binding: bool,
+}
+
+/// The roles the engine creates queues for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueueRole {
+    /// Rendering and presenting to the window.
+    Graphics,
+    Compute,
+    /// Uploads running in parallel with rendering.
+    Transfer,
+}
+
+impl fmt::Display for QueueRole {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QueueRole::Graphics => write!(f, "graphics"),
+            QueueRole::Compute => write!(f, "compute"),
+            QueueRole::Transfer => write!(f, "transfer"),
+        }
+    }
+}
+
+/// The queue families to use for each role, instead of the ones selected automatically.
+///
+/// Families are identified by their id on the device selected for the role, as listed by
+/// [`Hardware::list_queue_families`](crate::drawing::hardware::Hardware::list_queue_families):
+/// the transfer family is on the graphics device.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct QueueAssignment {
+    pub(crate) graphics: Option<u32>,
+    pub(crate) compute: Option<u32>,
+    pub(crate) transfer: Option<u32>,
+}
+
+impl QueueAssignment {
+    /// The family rendering and presenting, which must support both.
+    pub fn graphics(mut self, family: u32) -> Self {
+        self.graphics = Some(family);
+        self
+    }
+
+    pub fn compute(mut self, family: u32) -> Self {
+        self.compute = Some(family);
+        self
+    }
+
+    /// The family of the dedicated transfer queue, which can't be the graphics or the compute family.
+    pub fn transfer(mut self, family: u32) -> Self {
+        self.transfer = Some(family);
+        self
+    }
+}
+
+pub(crate) fn list(physical: PhysicalDevice) -> Vec<QueueFamilyInfo> {
+    physical
+        .queue_families()
+        .map(|family| QueueFamilyInfo {
+            id: family.id(),
+            queue_count: family.queues_count() as u32,
+            graphics: family.supports_graphics(),
+            compute: family.supports_compute(),
+            explicit_transfers: family.explicitly_supports_transfers(),
+            sparse_binding: family.supports_sparse_binding(),
+        })
+        .collect()
+}
+
+/// The family `id` of `physical`, if it can fulfill `role`. Presentation is checked by the caller.
+pub(crate) fn validate(
+    physical: PhysicalDevice,
+    role: QueueRole,
+    id: u32,
+) -> Result<QueueFamily, QueueAssignmentError> {
+    let family = physical
+        .queue_family_by_id(id)
+        .ok_or(QueueAssignmentError::UnknownFamily { role, family: id })?;
+
+    let supported = match role {
+        QueueRole::Graphics => family.supports_graphics(),
+        QueueRole::Compute => family.supports_compute(),
+        QueueRole::Transfer => family.explicitly_supports_transfers(),
+    };
+    if supported {
+        Ok(family)
+    } else {
+        Err(QueueAssignmentError::Unsupported { role, family: id })
+    }
+}
+
+#[derive(Debug)]
+pub enum QueueAssignmentError {
+    /// The device selected for the role doesn't have this family.
+    UnknownFamily { role: QueueRole, family: u32 },
+    /// The family can't perform the operations of the role.
+    Unsupported { role: QueueRole, family: u32 },
+    /// The graphics family can't present to the window.
+    CannotPresent(u32),
+    /// The transfer family is also used for graphics or compute.
+    TransferNotDedicated(u32),
+    /// The graphics and compute roles share a family with a single queue.
+    NotEnoughQueues(u32),
+}
+
+impl fmt::Display for QueueAssignmentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QueueAssignmentError::UnknownFamily { role, family } => write!(
+                f,
+                "the device selected for {} doesn't have a queue family {}",
+                role, family
+            ),
+            QueueAssignmentError::Unsupported { role, family } => {
+                write!(f, "the queue family {} doesn't support {} operations", family, role)
+            }
+            QueueAssignmentError::CannotPresent(family) => {
+                write!(f, "the queue family {} can't present to the window", family)
+            }
+            QueueAssignmentError::TransferNotDedicated(family) => write!(
+                f,
+                "the queue family {} is already used for graphics or compute, it can't be the transfer family",
+                family
+            ),
+            QueueAssignmentError::NotEnoughQueues(family) => write!(
+                f,
+                "the queue family {} has a single queue, it can't be used for both graphics and compute",
+                family
+            ),
+        }
+    }
+}
+
+impl Error for QueueAssignmentError {}
@@ -0,0 +1,89 @@
+use std::sync::Arc;
+
+use vulkano::buffer::{BufferContents, BufferUsage, CpuAccessibleBuffer, TypedBufferAccess};
+use vulkano::command_buffer::{
+    AutoCommandBufferBuilder, CommandBufferExecFuture, CommandBufferUsage, PrimaryAutoCommandBuffer,
+};
+use vulkano::sync;
+use vulkano::sync::{FenceSignalFuture, GpuFuture, NowFuture};
+
+use crate::drawing::hardware::Hardware;
+
+type ReadbackFuture = FenceSignalFuture<CommandBufferExecFuture<NowFuture, PrimaryAutoCommandBuffer>>;
+
+/// A copy of a buffer to the CPU, in flight on the GPU.
+///
+/// Poll it over the following frames instead of waiting for the copy.
+pub struct ReadbackHandle<T>
+    where
+        [T]: BufferContents,
+{
+    // None once the contents have been returned
+    pending: Option<(ReadbackFuture, Arc<CpuAccessibleBuffer<[T]>>)>,
+}
+
+impl<T> ReadbackHandle<T>
+    where
+        T: Copy + Send + Sync + 'static,
+        [T]: BufferContents,
+{
+    /// Copies `buffer` into a host-visible staging buffer, through the graphics queue.
+    pub(crate) fn new<B>(hardware: &Hardware, buffer: Arc<B>) -> Self
+        where
+            B: TypedBufferAccess<Content = [T]> + 'static,
+    {
+        let device = hardware.graphics_device();
+        let queue = hardware.graphics_queue();
+
+        let staging = unsafe {
+            CpuAccessibleBuffer::<[T]>::uninitialized_array(
+                Arc::clone(device),
+                buffer.len(),
+                BufferUsage::transfer_destination(),
+                true,
+            )
+        }
+            .expect("Couldn't create the readback buffer");
+
+        let mut builder = AutoCommandBufferBuilder::primary(
+            Arc::clone(device),
+            queue.family(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+            .expect("Couldn't create the readback command buffer");
+        builder
+            .copy_buffer(buffer, Arc::clone(&staging))
+            .expect("Couldn't record the readback");
+        let command_buffer = builder.build().expect("Couldn't build the readback command buffer");
+
+        let future = sync::now(Arc::clone(device))
+            .then_execute(Arc::clone(queue), command_buffer)
+            .expect("Couldn't execute the readback")
+            .then_signal_fence_and_flush()
+            .expect("Couldn't submit the readback");
+
+        ReadbackHandle {
+            pending: Some((future, staging)),
+        }
+    }
+
+    /// The contents of the buffer if the copy has finished, without blocking.
+    ///
+    /// The contents are only returned once: the staging buffer is then released,
+    /// and the following calls return `None`.
+    pub fn poll(&mut self) -> Option<Vec<T>> {
+        let (future, staging) = self.pending.as_mut()?;
+
+        // Releases the GPU lock on the staging buffer if the fence is signaled
+        future.cleanup_finished();
+        let contents = staging.read().ok()?.to_vec();
+
+        self.pending = None;
+        Some(contents)
+    }
+
+    /// Whether [`ReadbackHandle::poll`] already returned the contents.
+    pub fn is_consumed(&self) -> bool {
+        self.pending.is_none()
+    }
+}
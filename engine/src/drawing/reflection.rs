@@ -0,0 +1,68 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use vulkano::descriptor_set::layout::DescriptorType;
+use vulkano::shader::EntryPoint;
+
+/// A descriptor binding declared by shaders, as reflected when they were compiled.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReflectedBinding {
+    pub set: u32,
+    pub binding: u32,
+    /// The descriptor types the shaders can use this binding as, for example a uniform buffer can be dynamic or not.
+    pub descriptor_types: Vec<DescriptorType>,
+    /// The number of array elements, 1 if the binding isn't an array.
+    pub count: u32,
+}
+
+impl ReflectedBinding {
+    /// Whether a descriptor of this type can be bound.
+    pub fn accepts(&self, descriptor_type: DescriptorType) -> bool {
+        self.descriptor_types.contains(&descriptor_type)
+    }
+}
+
+impl fmt::Display for ReflectedBinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "set {} binding {}: ", self.set, self.binding)?;
+        for (i, descriptor_type) in self.descriptor_types.iter().enumerate() {
+            if i > 0 {
+                write!(f, " or ")?;
+            }
+            write!(f, "{:?}", descriptor_type)?;
+        }
+        if self.count != 1 {
+            write!(f, " [{}]", self.count)?;
+        }
+        Ok(())
+    }
+}
+
+/// The bindings declared by all the `shaders`, sorted by set then binding.
+///
+/// A binding used by several shaders must be compatible with all of them:
+/// only the descriptor types they all accept are kept, and the largest count.
+pub fn reflect(shaders: &[&EntryPoint]) -> Vec<ReflectedBinding> {
+    let mut bindings = BTreeMap::<(u32, u32), ReflectedBinding>::new();
+
+    for shader in shaders {
+        for ((set, binding), requirements) in shader.descriptor_requirements() {
+            bindings
+                .entry((set, binding))
+                .and_modify(|reflected| {
+                    reflected
+                        .descriptor_types
+                        .retain(|t| requirements.descriptor_types.contains(t));
+                    reflected.count = reflected.count.max(requirements.descriptor_count);
+                })
+                .or_insert_with(|| ReflectedBinding {
+                    set,
+                    binding,
+                    descriptor_types: requirements.descriptor_types.clone(),
+                    count: requirements.descriptor_count,
+                });
+        }
+    }
+
+    bindings.into_values().collect()
+}
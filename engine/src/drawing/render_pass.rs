@@ -0,0 +1,310 @@
+use std::error::Error;
+use std::fmt;
+use std::sync::Arc;
+
+use vulkano::device::Device;
+use vulkano::format::Format;
+use vulkano::image::{ImageLayout, SampleCount};
+use vulkano::render_pass::{
+    AttachmentDescription, AttachmentReference, LoadOp, RenderPass, RenderPassCreateInfo,
+    RenderPassCreationError, StoreOp, SubpassDescription,
+};
+
+/// How an attachment is used by the subpass.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttachmentUsage {
+    Color,
+    DepthStencil,
+    /// Receives the resolved samples of a multisampled color attachment, in the order they are added.
+    Resolve,
+}
+
+/// An attachment of a render pass built by [`RenderPassBuilder`].
+#[derive(Debug, Clone)]
+pub struct Attachment {
+    usage: AttachmentUsage,
+    format: Format,
+    samples: SampleCount,
+    load: LoadOp,
+    store: StoreOp,
+    initial_layout: ImageLayout,
+    final_layout: ImageLayout,
+}
+
+impl Attachment {
+    /// A color attachment, cleared at the start of the pass and stored at the end.
+    ///
+    /// By default, its final layout is `ColorAttachmentOptimal`.
+    /// Use `ShaderReadOnlyOptimal` if the next pass samples it, or `PresentSrc` for a swapchain image.
+    pub fn color(format: Format) -> Self {
+        Attachment {
+            usage: AttachmentUsage::Color,
+            format,
+            samples: SampleCount::Sample1,
+            load: LoadOp::Clear,
+            store: StoreOp::Store,
+            initial_layout: ImageLayout::Undefined,
+            final_layout: ImageLayout::ColorAttachmentOptimal,
+        }
+    }
+
+    /// A single-sampled color attachment into which a multisampled color attachment is resolved at the end of the pass.
+    ///
+    /// Its previous contents are discarded. By default, its final layout is `ColorAttachmentOptimal`,
+    /// use `PresentSrc` for a swapchain image.
+    pub fn resolve(format: Format) -> Self {
+        Attachment {
+            usage: AttachmentUsage::Resolve,
+            format,
+            samples: SampleCount::Sample1,
+            load: LoadOp::DontCare,
+            store: StoreOp::Store,
+            initial_layout: ImageLayout::Undefined,
+            final_layout: ImageLayout::ColorAttachmentOptimal,
+        }
+    }
+
+    /// A depth (and/or stencil) attachment, cleared at the start of the pass and discarded at the end.
+    pub fn depth(format: Format) -> Self {
+        Attachment {
+            usage: AttachmentUsage::DepthStencil,
+            format,
+            samples: SampleCount::Sample1,
+            load: LoadOp::Clear,
+            store: StoreOp::DontCare,
+            initial_layout: ImageLayout::Undefined,
+            final_layout: ImageLayout::DepthStencilAttachmentOptimal,
+        }
+    }
+
+    pub fn samples(mut self, samples: SampleCount) -> Self {
+        self.samples = samples;
+        self
+    }
+
+    pub fn load(mut self, load: LoadOp) -> Self {
+        self.load = load;
+        self
+    }
+
+    pub fn store(mut self, store: StoreOp) -> Self {
+        self.store = store;
+        self
+    }
+
+    /// The layout the image is in when the pass starts, and the layout it is transitioned to when the pass ends.
+    pub fn layouts(mut self, initial_layout: ImageLayout, final_layout: ImageLayout) -> Self {
+        self.initial_layout = initial_layout;
+        self.final_layout = final_layout;
+        self
+    }
+
+    fn validate(&self, index: usize) -> Result<(), RenderPassError> {
+        let aspects = self.format.aspects();
+        let format_matches = match self.usage {
+            AttachmentUsage::Color | AttachmentUsage::Resolve => aspects.color,
+            AttachmentUsage::DepthStencil => aspects.depth || aspects.stencil,
+        };
+        if !format_matches {
+            return Err(RenderPassError::InvalidFormat {
+                attachment: index,
+                format: self.format,
+                usage: self.usage,
+            });
+        }
+
+        if self.initial_layout == ImageLayout::Undefined && self.load == LoadOp::Load {
+            return Err(RenderPassError::LoadFromUndefined { attachment: index });
+        }
+
+        if self.initial_layout != ImageLayout::Undefined {
+            self.validate_layout(index, self.initial_layout)?;
+        }
+        self.validate_layout(index, self.final_layout)
+    }
+
+    fn validate_layout(&self, index: usize, layout: ImageLayout) -> Result<(), RenderPassError> {
+        let valid = match layout {
+            ImageLayout::General
+            | ImageLayout::ShaderReadOnlyOptimal
+            | ImageLayout::TransferSrcOptimal
+            | ImageLayout::TransferDstOptimal => true,
+            ImageLayout::ColorAttachmentOptimal | ImageLayout::PresentSrc => {
+                self.usage != AttachmentUsage::DepthStencil
+            }
+            ImageLayout::DepthStencilAttachmentOptimal
+            | ImageLayout::DepthStencilReadOnlyOptimal => {
+                self.usage == AttachmentUsage::DepthStencil
+            }
+            ImageLayout::Undefined | ImageLayout::Preinitialized => false,
+        };
+
+        if valid {
+            Ok(())
+        } else {
+            Err(RenderPassError::InvalidLayout {
+                attachment: index,
+                layout,
+                usage: self.usage,
+            })
+        }
+    }
+
+    fn description(&self) -> AttachmentDescription {
+        // The stencil aspect follows the same operations as the depth aspect
+        let (stencil_load_op, stencil_store_op) = if self.format.aspects().stencil {
+            (self.load, self.store)
+        } else {
+            (LoadOp::DontCare, StoreOp::DontCare)
+        };
+
+        AttachmentDescription {
+            format: Some(self.format),
+            samples: self.samples,
+            load_op: self.load,
+            store_op: self.store,
+            stencil_load_op,
+            stencil_store_op,
+            initial_layout: self.initial_layout,
+            final_layout: self.final_layout,
+            ..Default::default()
+        }
+    }
+}
+
+/// Builds a single-subpass render pass, with explicit control over the layouts of each attachment.
+///
+/// Attachments are numbered in the order they are added, which is also the order
+/// the framebuffer must list its image views in.
+#[derive(Debug, Clone, Default)]
+pub struct RenderPassBuilder {
+    attachments: Vec<Attachment>,
+}
+
+impl RenderPassBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn attachment(mut self, attachment: Attachment) -> Self {
+        self.attachments.push(attachment);
+        self
+    }
+
+    /// Validates the layouts of the attachments against their usage, then creates the render pass.
+    pub fn build(self, device: Arc<Device>) -> Result<Arc<RenderPass>, RenderPassError> {
+        let mut color_attachments = Vec::new();
+        let mut resolve_attachments = Vec::new();
+        let mut depth_stencil_attachment = None;
+
+        for (index, attachment) in self.attachments.iter().enumerate() {
+            attachment.validate(index)?;
+
+            match attachment.usage {
+                AttachmentUsage::Color => color_attachments.push(Some(AttachmentReference {
+                    attachment: index as u32,
+                    layout: ImageLayout::ColorAttachmentOptimal,
+                    ..Default::default()
+                })),
+                AttachmentUsage::Resolve => resolve_attachments.push(Some(AttachmentReference {
+                    attachment: index as u32,
+                    layout: ImageLayout::ColorAttachmentOptimal,
+                    ..Default::default()
+                })),
+                AttachmentUsage::DepthStencil => {
+                    if depth_stencil_attachment.is_some() {
+                        return Err(RenderPassError::MultipleDepthStencilAttachments);
+                    }
+                    depth_stencil_attachment = Some(AttachmentReference {
+                        attachment: index as u32,
+                        layout: ImageLayout::DepthStencilAttachmentOptimal,
+                        ..Default::default()
+                    });
+                }
+            }
+        }
+
+        let create_info = RenderPassCreateInfo {
+            attachments: self.attachments.iter().map(Attachment::description).collect(),
+            subpasses: vec![SubpassDescription {
+                color_attachments,
+                resolve_attachments,
+                depth_stencil_attachment,
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        Ok(RenderPass::new(device, create_info)?)
+    }
+}
+
+#[derive(Debug)]
+pub enum RenderPassError {
+    /// The format of the attachment doesn't have the aspects its usage needs.
+    InvalidFormat {
+        attachment: usize,
+        format: Format,
+        usage: AttachmentUsage,
+    },
+    /// The layout can't be used by an attachment with this usage, or can't be transitioned to.
+    InvalidLayout {
+        attachment: usize,
+        layout: ImageLayout,
+        usage: AttachmentUsage,
+    },
+    /// The attachment is loaded, but its initial layout is `Undefined`: its contents would be garbage.
+    LoadFromUndefined { attachment: usize },
+    /// A subpass can only have a single depth/stencil attachment.
+    MultipleDepthStencilAttachments,
+    Creation(RenderPassCreationError),
+}
+
+impl fmt::Display for RenderPassError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RenderPassError::InvalidFormat {
+                attachment,
+                format,
+                usage,
+            } => write!(
+                f,
+                "attachment {} has the format {:?}, which can't be used as a {:?} attachment",
+                attachment, format, usage
+            ),
+            RenderPassError::InvalidLayout {
+                attachment,
+                layout,
+                usage,
+            } => write!(
+                f,
+                "attachment {} can't use the layout {:?}, which is invalid for a {:?} attachment",
+                attachment, layout, usage
+            ),
+            RenderPassError::LoadFromUndefined { attachment } => write!(
+                f,
+                "attachment {} is loaded, but its initial layout is Undefined",
+                attachment
+            ),
+            RenderPassError::MultipleDepthStencilAttachments => {
+                write!(f, "a subpass can only have a single depth/stencil attachment")
+            }
+            RenderPassError::Creation(e) => write!(f, "couldn't create the render pass: {}", e),
+        }
+    }
+}
+
+impl Error for RenderPassError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            RenderPassError::Creation(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<RenderPassCreationError> for RenderPassError {
+    fn from(e: RenderPassCreationError) -> Self {
+        RenderPassError::Creation(e)
+    }
+}
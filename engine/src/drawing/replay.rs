@@ -0,0 +1,74 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use vulkano::command_buffer::PrimaryAutoCommandBuffer;
+use vulkano::render_pass::Framebuffer;
+
+/// Requests [`Engine::run_static`](crate::drawing::engine::Engine::run_static) to record its command buffers again,
+/// for example after the scene or the camera changed.
+///
+/// Clones refer to the same scene.
+#[derive(Clone, Default)]
+pub struct Invalidation {
+    dirty: Arc<AtomicBool>,
+}
+
+impl Invalidation {
+    /// The command buffers are recorded again before the next frame.
+    pub fn invalidate(&self) {
+        self.dirty.store(true, Ordering::Release);
+    }
+
+    /// Whether the command buffers were invalidated since the last call.
+    fn take(&self) -> bool {
+        self.dirty.swap(false, Ordering::AcqRel)
+    }
+}
+
+/// The command buffers recorded for each framebuffer of the swapchain.
+pub(crate) struct RecordedFrames {
+    invalidation: Invalidation,
+    frames: Vec<(Arc<Framebuffer>, Arc<PrimaryAutoCommandBuffer>)>,
+}
+
+impl RecordedFrames {
+    pub(crate) fn new(invalidation: Invalidation) -> Self {
+        RecordedFrames {
+            invalidation,
+            frames: Vec::new(),
+        }
+    }
+
+    /// The command buffer recorded for `framebuffer`, recording it if needed.
+    ///
+    /// Everything is recorded again when the scene is invalidated, and the command buffers of
+    /// framebuffers that don't exist anymore (because the swapchain was recreated) are dropped.
+    pub(crate) fn get_or_record<R>(
+        &mut self,
+        framebuffers: &[Arc<Framebuffer>],
+        framebuffer: &Arc<Framebuffer>,
+        record: R,
+    ) -> Arc<PrimaryAutoCommandBuffer>
+        where
+            R: FnOnce() -> PrimaryAutoCommandBuffer,
+    {
+        if self.invalidation.take() {
+            self.frames.clear();
+        }
+        self.frames
+            .retain(|(recorded, _)| framebuffers.iter().any(|f| Arc::ptr_eq(f, recorded)));
+
+        if let Some((_, command_buffer)) = self
+            .frames
+            .iter()
+            .find(|(recorded, _)| Arc::ptr_eq(recorded, framebuffer))
+        {
+            return Arc::clone(command_buffer);
+        }
+
+        let command_buffer = Arc::new(record());
+        self.frames
+            .push((Arc::clone(framebuffer), Arc::clone(&command_buffer)));
+        command_buffer
+    }
+}
@@ -0,0 +1,214 @@
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use vulkano::buffer::BufferAccess;
+use vulkano::image::ImageViewAbstract;
+use vulkano::pipeline::GraphicsPipeline;
+
+/// A typed reference to a resource owned by [`Resources`].
+///
+/// Handles are cheap to copy. Once the resource is released, the handle becomes invalid,
+/// even if its slot is reused by another resource.
+pub struct Handle<T: ?Sized> {
+    index: u32,
+    generation: u32,
+    marker: PhantomData<fn() -> Arc<T>>,
+}
+
+/// A mesh, referenced through its vertex buffer.
+pub type MeshHandle = Handle<dyn BufferAccess>;
+pub type TextureHandle = Handle<dyn ImageViewAbstract>;
+pub type PipelineHandle = Handle<GraphicsPipeline>;
+
+// Implemented manually: deriving would require T to implement the traits as well
+impl<T: ?Sized> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T: ?Sized> Copy for Handle<T> {}
+
+impl<T: ?Sized> PartialEq for Handle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.index == other.index && self.generation == other.generation
+    }
+}
+
+impl<T: ?Sized> Eq for Handle<T> {}
+
+impl<T: ?Sized> Hash for Handle<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.index.hash(state);
+        self.generation.hash(state);
+    }
+}
+
+impl<T: ?Sized> fmt::Debug for Handle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Handle({}v{})", self.index, self.generation)
+    }
+}
+
+struct Slot<T: ?Sized> {
+    generation: u32,
+    value: Option<Arc<T>>,
+}
+
+/// The resources of a single type.
+pub struct Registry<T: ?Sized> {
+    slots: Vec<Slot<T>>,
+    free: Vec<u32>,
+    // Released resources, with the frame they were released at
+    retired: Vec<(u64, Arc<T>)>,
+}
+
+impl<T: ?Sized> Default for Registry<T> {
+    fn default() -> Self {
+        Registry {
+            slots: Vec::new(),
+            free: Vec::new(),
+            retired: Vec::new(),
+        }
+    }
+}
+
+impl<T: ?Sized> Registry<T> {
+    fn insert(&mut self, value: Arc<T>) -> Handle<T> {
+        let index = match self.free.pop() {
+            Some(index) => index,
+            None => {
+                self.slots.push(Slot {
+                    generation: 0,
+                    value: None,
+                });
+                (self.slots.len() - 1) as u32
+            }
+        };
+
+        let slot = &mut self.slots[index as usize];
+        slot.value = Some(value);
+        Handle {
+            index,
+            generation: slot.generation,
+            marker: PhantomData,
+        }
+    }
+
+    fn slot(&self, handle: Handle<T>) -> Option<&Slot<T>> {
+        self.slots
+            .get(handle.index as usize)
+            .filter(|slot| slot.generation == handle.generation)
+    }
+
+    fn get(&self, handle: Handle<T>) -> Option<Arc<T>> {
+        self.slot(handle).and_then(|slot| slot.value.clone())
+    }
+
+    fn release(&mut self, handle: Handle<T>, frame: u64) -> bool {
+        if self.slot(handle).is_none() {
+            return false;
+        }
+
+        let slot = &mut self.slots[handle.index as usize];
+        match slot.value.take() {
+            Some(value) => {
+                slot.generation = slot.generation.wrapping_add(1);
+                self.free.push(handle.index);
+                self.retired.push((frame, value));
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn collect(&mut self, oldest_in_flight: u64) {
+        self.retired.retain(|(frame, _)| *frame >= oldest_in_flight);
+    }
+}
+
+/// A kind of resource [`Resources`] can own.
+pub trait Resource {
+    fn registry(resources: &Resources) -> &Mutex<Registry<Self>>;
+}
+
+impl Resource for dyn BufferAccess {
+    fn registry(resources: &Resources) -> &Mutex<Registry<Self>> {
+        &resources.meshes
+    }
+}
+
+impl Resource for dyn ImageViewAbstract {
+    fn registry(resources: &Resources) -> &Mutex<Registry<Self>> {
+        &resources.textures
+    }
+}
+
+impl Resource for GraphicsPipeline {
+    fn registry(resources: &Resources) -> &Mutex<Registry<Self>> {
+        &resources.pipelines
+    }
+}
+
+/// GPU resources owned by the engine, referenced through [`Handle`]s.
+///
+/// Releasing a resource invalidates its handle immediately, but the resource itself is only destroyed
+/// once the frames that may still use it are finished.
+pub struct Resources {
+    meshes: Mutex<Registry<dyn BufferAccess>>,
+    textures: Mutex<Registry<dyn ImageViewAbstract>>,
+    pipelines: Mutex<Registry<GraphicsPipeline>>,
+    frame: AtomicU64,
+    frames_in_flight: u64,
+}
+
+impl Resources {
+    pub(crate) fn new(frames_in_flight: u64) -> Self {
+        Resources {
+            meshes: Default::default(),
+            textures: Default::default(),
+            pipelines: Default::default(),
+            frame: AtomicU64::new(0),
+            frames_in_flight,
+        }
+    }
+
+    pub fn insert<T: Resource + ?Sized>(&self, resource: Arc<T>) -> Handle<T> {
+        lock(T::registry(self)).insert(resource)
+    }
+
+    /// The resource referenced by `handle`, or `None` if it was released.
+    pub fn get<T: Resource + ?Sized>(&self, handle: Handle<T>) -> Option<Arc<T>> {
+        lock(T::registry(self)).get(handle)
+    }
+
+    /// Whether `handle` still references a resource.
+    pub fn is_valid<T: Resource + ?Sized>(&self, handle: Handle<T>) -> bool {
+        lock(T::registry(self)).slot(handle).is_some()
+    }
+
+    /// Invalidates `handle`, and destroys the resource once no frame in flight can use it.
+    ///
+    /// Returns `false` if the handle was already invalid.
+    pub fn release<T: Resource + ?Sized>(&self, handle: Handle<T>) -> bool {
+        let frame = self.frame.load(Ordering::Acquire);
+        lock(T::registry(self)).release(handle, frame)
+    }
+
+    /// Called by the engine after each frame is submitted, to destroy the resources no frame can use anymore.
+    pub(crate) fn end_frame(&self) {
+        let frame = self.frame.fetch_add(1, Ordering::AcqRel) + 1;
+        let oldest_in_flight = frame.saturating_sub(self.frames_in_flight);
+
+        lock(&self.meshes).collect(oldest_in_flight);
+        lock(&self.textures).collect(oldest_in_flight);
+        lock(&self.pipelines).collect(oldest_in_flight);
+    }
+}
+
+fn lock<T: ?Sized>(registry: &Mutex<Registry<T>>) -> std::sync::MutexGuard<'_, Registry<T>> {
+    registry.lock().expect("The resources were poisoned")
+}
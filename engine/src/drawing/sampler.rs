@@ -0,0 +1,98 @@
+use std::sync::Arc;
+
+use log::warn;
+use vulkano::device::Device;
+use vulkano::pipeline::graphics::depth_stencil::CompareOp;
+use vulkano::sampler::{
+    BorderColor, Filter, Sampler, SamplerAddressMode, SamplerCreateInfo, SamplerCreationError,
+    SamplerMipmapMode, LOD_CLAMP_NONE,
+};
+
+/// Builds a sampler, by default with linear filtering and clamping to the edges.
+#[derive(Debug, Clone)]
+pub struct SamplerBuilder {
+    create_info: SamplerCreateInfo,
+}
+
+impl Default for SamplerBuilder {
+    fn default() -> Self {
+        SamplerBuilder {
+            create_info: SamplerCreateInfo {
+                mag_filter: Filter::Linear,
+                min_filter: Filter::Linear,
+                address_mode: [SamplerAddressMode::ClampToEdge; 3],
+                ..Default::default()
+            },
+        }
+    }
+}
+
+impl SamplerBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The filter used both when magnifying and minifying.
+    pub fn filter(mut self, filter: Filter) -> Self {
+        self.create_info.mag_filter = filter;
+        self.create_info.min_filter = filter;
+        self
+    }
+
+    pub fn mipmap_mode(mut self, mipmap_mode: SamplerMipmapMode) -> Self {
+        self.create_info.mipmap_mode = mipmap_mode;
+        self
+    }
+
+    /// How coordinates outside of the image are handled, on all axes.
+    pub fn address_mode(mut self, address_mode: SamplerAddressMode) -> Self {
+        self.create_info.address_mode = [address_mode; 3];
+        self
+    }
+
+    /// The color returned outside of the image, with [`SamplerAddressMode::ClampToBorder`].
+    pub fn border_color(mut self, border_color: BorderColor) -> Self {
+        self.create_info.border_color = border_color;
+        self
+    }
+
+    /// Makes this a comparison sampler (`sampler2DShadow` in GLSL), for depth images only.
+    ///
+    /// Sampling returns how many of the texels pass `compare` against the reference depth:
+    /// with linear filtering, the 2×2 neighbouring texels are compared, which smooths shadow edges.
+    pub fn compare(mut self, compare: Option<CompareOp>) -> Self {
+        self.create_info.compare = compare;
+        self
+    }
+
+    /// Offsets the mip level selected for each sample: negative values sharpen, positive values blur.
+    ///
+    /// It is clamped to the `max_sampler_lod_bias` of the device. There is no bias by default.
+    pub fn lod_bias(mut self, bias: f32) -> Self {
+        self.create_info.mip_lod_bias = bias;
+        self
+    }
+
+    /// The range of mip levels that can be sampled, for example to leave out levels that aren't streamed yet.
+    ///
+    /// `None` as the maximum doesn't clamp, which is the default, with a minimum of 0.
+    /// A minimum above the maximum is lowered to the maximum.
+    pub fn lod(mut self, min: f32, max: Option<f32>) -> Self {
+        let max = max.unwrap_or(LOD_CLAMP_NONE);
+        self.create_info.lod = min.min(max)..=max;
+        self
+    }
+
+    pub fn build(mut self, device: Arc<Device>) -> Result<Arc<Sampler>, SamplerCreationError> {
+        let limit = device.physical_device().properties().max_sampler_lod_bias;
+        if self.create_info.mip_lod_bias.abs() > limit {
+            warn!(
+                "The LOD bias {} is clamped to the maximum of the device, {}",
+                self.create_info.mip_lod_bias, limit
+            );
+            self.create_info.mip_lod_bias = self.create_info.mip_lod_bias.clamp(-limit, limit);
+        }
+
+        Sampler::new(device, self.create_info)
+    }
+}
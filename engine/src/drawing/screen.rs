@@ -1,37 +1,84 @@
-use std::sync::Arc;
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 
-use log::{debug, trace};
-use vulkano::image::{ImageUsage, SwapchainImage};
-use vulkano::swapchain::{Swapchain, SwapchainCreateInfo, SwapchainCreationError};
+use log::{debug, trace, warn};
+use vulkano::buffer::{BufferUsage, CpuAccessibleBuffer};
+use vulkano::command_buffer::{AutoCommandBufferBuilder, CommandBufferUsage, PrimaryAutoCommandBuffer};
+use vulkano::device::physical::SurfacePropertiesError;
+use vulkano::device::Queue;
+use vulkano::format::Format;
+use vulkano::render_pass::StoreOp;
+use vulkano::image::view::ImageView;
+use vulkano::image::{AttachmentImage, ImageLayout, ImageUsage, SampleCount, SwapchainImage};
+use vulkano::swapchain::{
+    ColorSpace, CompositeAlpha, PresentMode, SurfaceTransform, Swapchain, SwapchainCreateInfo, SwapchainCreationError,
+};
+use vulkano::sync::Sharing;
 use winit::event_loop::EventLoop;
 use winit::window::Window;
 
+use crate::drawing::camera;
+use crate::drawing::engine::EngineError;
 use crate::drawing::hardware::Hardware;
+use crate::drawing::render_pass::{Attachment, RenderPassBuilder};
+use crate::drawing::screenshot::{PendingScreenshot, ScreenshotError};
+use crate::drawing::window::{Clear, WindowConfig};
 
 pub struct Screen {
     hardware: Arc<Hardware>,
     swapchain: Arc<Swapchain<Window>>,
     images: Vec<Arc<SwapchainImage<Window>>>,
+    clear: Clear,
+    pre_transform: bool,
+    depth_format: Option<Format>,
+    sample_count: SampleCount,
+    // Shared with the screens recreated from this one, until the engine takes them
+    screenshots: Arc<Mutex<Vec<PathBuf>>>,
 }
 
 impl Screen {
-    pub fn new(hardware: Arc<Hardware>, event_loop: &EventLoop<()>) -> Self {
+    pub fn new(
+        hardware: Arc<Hardware>,
+        _event_loop: &EventLoop<()>,
+        window: &WindowConfig,
+    ) -> Result<Self, EngineError> {
         debug!("Creating a painter…");
 
         trace!("Creating the swap-chain…");
-        let (mut swapchain, images) = {
+        let (swapchain, images) = {
             let capabilities = hardware
                 .graphics_device()
                 .physical_device()
-                .surface_capabilities(hardware.surface(), Default::default())
-                .expect("Could not query the surface capabilities");
+                .surface_capabilities(hardware.surface(), Default::default())?;
 
-            let format = hardware
-                .graphics_device()
-                .physical_device()
-                .surface_formats(hardware.surface(), Default::default())
-                .expect("Could not select any format capabilities")[0]
-                .0;
+            let supported = supported_formats(&hardware)?;
+            let (format, color_space) = match window.format {
+                Some(forced) => force_format(supported, forced)?,
+                None => select_format(&supported, window.color_space.unwrap_or(ColorSpace::SrgbNonLinear))
+                    .ok_or(EngineError::NoSurfaceFormat)?,
+            };
+            debug!("Selected the format {:?} in the color space {:?}", format, color_space);
+
+            let supported_alpha = capabilities.supported_composite_alpha;
+            let composite_alpha = match window.composite_alpha {
+                Some(preferred) if supported_alpha.supports(preferred) => preferred,
+                preferred => {
+                    if let Some(preferred) = preferred {
+                        warn!("The composite alpha {:?} isn't supported by the window", preferred);
+                    }
+                    supported_alpha
+                        .iter()
+                        .next()
+                        .expect("Could not select an alpha capability")
+                }
+            };
+            debug!("Selected the composite alpha {:?}", composite_alpha);
+
+            let present_mode = select_present_mode(&hardware, window.present_mode)?;
+            debug!("Selected the present mode {:?}", present_mode);
+
+            let (pre_transform, image_extent) = transform_and_extent(&hardware, window.pre_transform)?;
 
             Swapchain::new(
                 Arc::clone(hardware.graphics_device()),
@@ -39,37 +86,261 @@ impl Screen {
                 SwapchainCreateInfo {
                     min_image_count: capabilities.min_image_count,
                     image_format: Some(format),
-                    image_extent: hardware.window().inner_size().into(),
-                    image_usage: ImageUsage::color_attachment(),
-                    composite_alpha: capabilities
-                        .supported_composite_alpha
-                        .iter()
-                        .next()
-                        .expect("Could not select an alpha capability"),
+                    image_color_space: color_space,
+                    image_extent,
+                    // Allows screenshots, see capture_to_buffer
+                    image_usage: ImageUsage {
+                        transfer_source: capabilities.supported_usage_flags.transfer_source,
+                        ..ImageUsage::color_attachment()
+                    },
+                    composite_alpha,
+                    pre_transform,
+                    present_mode,
                     ..Default::default()
                 },
-            )
-                .expect("Could not create the swapchain")
+            )?
         };
 
-        Screen {
+        let sample_count = select_sample_count(&hardware, window);
+
+        Ok(Screen {
             hardware,
             swapchain,
             images,
-        }
+            clear: window.clear,
+            pre_transform: window.pre_transform,
+            depth_format: window.depth_format,
+            sample_count,
+            screenshots: Arc::new(Mutex::new(Vec::new())),
+        })
     }
 
     pub fn swapchain(&self) -> &Arc<Swapchain<Window>> {
         &self.swapchain
     }
 
-    pub fn images(&self) -> &Vec<Arc<SwapchainImage<Window>>> {
+    pub fn format(&self) -> Format {
+        self.swapchain.image_format()
+    }
+
+    /// How the compositor interprets the pixels of the swapchain images.
+    pub fn color_space(&self) -> ColorSpace {
+        self.swapchain.image_color_space()
+    }
+
+    /// The pairs of format and color space the window can be presented with.
+    pub fn supported_formats(&self) -> Result<Vec<(Format, ColorSpace)>, SurfacePropertiesError> {
+        supported_formats(&self.hardware)
+    }
+
+    /// The size of the swapchain images, in pixels, which is also the size of the viewport given to the draw closure.
+    ///
+    /// It may differ from the inner size of the window: drivers clamp the requested size to the extents
+    /// supported by the surface. It is updated when the swapchain is recreated.
+    pub fn image_extent(&self) -> [u32; 2] {
+        self.swapchain.image_extent()
+    }
+
+    /// The width of the swapchain images divided by their height, as given to [`Camera::projection`](crate::drawing::camera::Camera::projection).
+    pub fn aspect_ratio(&self) -> f32 {
+        let [width, height] = self.image_extent();
+        width as f32 / height as f32
+    }
+
+    pub fn images(&self) -> &[Arc<SwapchainImage<Window>>] {
         &self.images
     }
 
-    pub fn recreate(&self) -> Result<Screen, SwapchainCreationError> {
+    /// The format of the depth attachment of the framebuffers, see [`WindowConfig::depth_format`].
+    ///
+    /// Build the render pass with it, for example with [`Attachment::depth`](crate::drawing::render_pass::Attachment::depth).
+    pub fn depth_format(&self) -> Option<Format> {
+        self.depth_format
+    }
+
+    /// The number of samples per pixel of the color and depth images, see [`WindowConfig::sample_count`].
+    pub fn sample_count(&self) -> SampleCount {
+        self.sample_count
+    }
+
+    /// The attachments of a render pass drawing to the window, in the order of the framebuffers given to
+    /// the draw closure of [`Engine::run`](crate::drawing::engine::Engine::run):
+    /// - with multisampling, the multisampled color image, then the swapchain image it is resolved into;
+    /// - otherwise, the swapchain image;
+    /// - then the depth image, if the window has a [depth format](Screen::depth_format).
+    ///
+    /// Add other attachments, or change their operations, before building it.
+    pub fn render_pass(&self) -> RenderPassBuilder {
+        let format = self.format();
+        let mut builder = RenderPassBuilder::new();
+
+        if self.sample_count == SampleCount::Sample1 {
+            builder = builder.attachment(self.clear().attachment(format));
+        } else {
+            // The samples are only needed until they are resolved
+            builder = builder
+                .attachment(Attachment::color(format).samples(self.sample_count).store(StoreOp::DontCare))
+                .attachment(Attachment::resolve(format).layouts(ImageLayout::Undefined, ImageLayout::PresentSrc));
+        }
+
+        if let Some(depth) = self.depth_format {
+            builder = builder.attachment(Attachment::depth(depth).samples(self.sample_count));
+        }
+        builder
+    }
+
+    /// A new multisampled color image with the size of the swapchain images, if the window uses multisampling.
+    pub(crate) fn multisampled_attachment(&self) -> Option<Arc<ImageView<AttachmentImage>>> {
+        if self.sample_count == SampleCount::Sample1 {
+            return None;
+        }
+
+        let image = AttachmentImage::transient_multisampled(
+            Arc::clone(self.hardware.graphics_device()),
+            self.image_extent(),
+            self.sample_count,
+            self.format(),
+        )
+            .expect("Couldn't create the multisampled image");
+        Some(ImageView::new_default(image).expect("Couldn't create the view of the multisampled image"))
+    }
+
+    /// A new depth image with the size of the swapchain images, if the window has a depth format.
+    pub(crate) fn depth_attachment(&self) -> Option<Arc<ImageView<AttachmentImage>>> {
+        let format = self.depth_format?;
+        let image = AttachmentImage::transient_multisampled(
+            Arc::clone(self.hardware.graphics_device()),
+            self.image_extent(),
+            self.sample_count,
+            format,
+        )
+            .expect("Couldn't create the depth image");
+        Some(ImageView::new_default(image).expect("Couldn't create the view of the depth image"))
+    }
+
+    /// How the window starts each frame.
+    ///
+    /// When the window uses `PreMultiplied` composite alpha, the clear color is premultiplied by its alpha.
+    pub fn clear(&self) -> Clear {
+        if self.composite_alpha() == CompositeAlpha::PreMultiplied {
+            self.clear.premultiplied()
+        } else {
+            self.clear
+        }
+    }
+
+    /// How the compositor blends the window with what is behind it, see [`WindowConfig::composite_alpha`].
+    pub fn composite_alpha(&self) -> CompositeAlpha {
+        self.swapchain.composite_alpha()
+    }
+
+    /// How frames are queued for display, see [`WindowConfig::present_mode`].
+    pub fn present_mode(&self) -> PresentMode {
+        self.swapchain.present_mode()
+    }
+
+    /// How the compositor rotates and mirrors the swapchain images when presenting them.
+    ///
+    /// It is `Identity` unless [`WindowConfig::pre_transform`] is set, in which case the images must be drawn
+    /// with this transform already applied, see [`pretransform_matrix`](crate::drawing::camera::pretransform_matrix).
+    pub fn pre_transform(&self) -> SurfaceTransform {
+        self.swapchain.pre_transform()
+    }
+
+    /// Records a copy of the swapchain image `image_num` into a new CPU-accessible buffer.
+    ///
+    /// Swapchain images can only be accessed while they are acquired, so the copy must be recorded in
+    /// the command buffer of the frame drawing `image_num`, after its render pass: the buffer holds the image
+    /// once that command buffer has executed. The pixels are in the [`format`](Screen::format) of the swapchain,
+    /// see [`to_rgba`](crate::drawing::screenshot::to_rgba) to convert them.
+    ///
+    /// Fails if the window doesn't allow copying its swapchain images.
+    pub fn capture_to_buffer(
+        &self,
+        builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        image_num: usize,
+    ) -> Result<Arc<CpuAccessibleBuffer<[u8]>>, ScreenshotError> {
+        if !self.swapchain.create_info().image_usage.transfer_source {
+            return Err(ScreenshotError::TransferNotSupported);
+        }
+
+        let format = self.format();
+        let [width, height] = self.image_extent();
+        let block_size = format
+            .block_size()
+            .ok_or(ScreenshotError::UnsupportedFormat(format))?;
+
+        let buffer = unsafe {
+            CpuAccessibleBuffer::<[u8]>::uninitialized_array(
+                Arc::clone(self.hardware.graphics_device()),
+                u64::from(width) * u64::from(height) * block_size,
+                BufferUsage::transfer_destination(),
+                true,
+            )
+        }
+            .expect("Couldn't create the screenshot buffer");
+
+        builder
+            .copy_image_to_buffer(Arc::clone(&self.images[image_num]) as Arc<_>, Arc::clone(&buffer) as Arc<_>)
+            .expect("Couldn't record the copy of the swapchain image");
+        Ok(buffer)
+    }
+
+    /// Saves the next frame presented by [`Engine::run`](crate::drawing::engine::Engine::run) to a PNG file at `path`,
+    /// for example when a key is pressed.
+    ///
+    /// The file is written a few frames later, once the GPU has copied the image, without blocking the draw loop.
+    /// Errors are logged.
+    pub fn save_screenshot<P: AsRef<Path>>(&self, path: P) {
+        self.screenshots
+            .lock()
+            .expect("The screenshot requests were poisoned")
+            .push(path.as_ref().to_owned());
+    }
+
+    /// The command buffer copying `image_num` for the screenshots requested since the previous frame, if any,
+    /// to execute on `queue` before presenting it.
+    pub(crate) fn take_screenshot(
+        &self,
+        queue: &Arc<Queue>,
+        image_num: usize,
+    ) -> Option<(PrimaryAutoCommandBuffer, PendingScreenshot)> {
+        let paths = mem::take(&mut *self.screenshots.lock().expect("The screenshot requests were poisoned"));
+        if paths.is_empty() {
+            return None;
+        }
+
+        let mut builder = AutoCommandBufferBuilder::primary(
+            Arc::clone(queue.device()),
+            queue.family(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+            .expect("Couldn't create the screenshot command buffer");
+
+        match self.capture_to_buffer(&mut builder, image_num) {
+            Ok(buffer) => Some((
+                builder.build().expect("Couldn't build the screenshot command buffer"),
+                PendingScreenshot {
+                    buffer,
+                    paths,
+                    format: self.format(),
+                    extent: self.image_extent(),
+                    opaque: self.composite_alpha() == CompositeAlpha::Opaque,
+                },
+            )),
+            Err(e) => {
+                warn!("Couldn't take the screenshot: {}", e);
+                None
+            }
+        }
+    }
+
+    pub fn recreate(&self) -> Result<Screen, EngineError> {
+        let (pre_transform, image_extent) = transform_and_extent(&self.hardware, self.pre_transform)?;
+        // The present mode and the other options are kept from the current swapchain
         let (new_swapchain, new_images) = self.swapchain.recreate(SwapchainCreateInfo {
-            image_extent: self.hardware.surface().window().inner_size().into(),
+            image_extent,
+            pre_transform,
             ..self.swapchain.create_info()
         })?;
 
@@ -77,6 +348,172 @@ impl Screen {
             hardware: Arc::clone(&self.hardware),
             swapchain: new_swapchain,
             images: new_images,
+            clear: self.clear,
+            pre_transform: self.pre_transform,
+            depth_format: self.depth_format,
+            sample_count: self.sample_count,
+            screenshots: Arc::clone(&self.screenshots),
         })
     }
+
+    /// Recreates the swapchain, with images shared by the queue `families`.
+    pub(crate) fn with_sharing(&self, families: &[u32]) -> Result<Screen, SwapchainCreationError> {
+        let mut families = families.to_vec();
+        families.sort_unstable();
+        families.dedup();
+        let image_sharing = if families.len() > 1 {
+            Sharing::Concurrent(families.into_iter().collect())
+        } else {
+            Sharing::Exclusive
+        };
+
+        let (new_swapchain, new_images) = self.swapchain.recreate(SwapchainCreateInfo {
+            image_sharing,
+            ..self.swapchain.create_info()
+        })?;
+
+        Ok(Screen {
+            hardware: Arc::clone(&self.hardware),
+            swapchain: new_swapchain,
+            images: new_images,
+            clear: self.clear,
+            pre_transform: self.pre_transform,
+            depth_format: self.depth_format,
+            sample_count: self.sample_count,
+            screenshots: Arc::clone(&self.screenshots),
+        })
+    }
+}
+
+/// The transform of the swapchain, and the size of its images, which are rotated with the display.
+fn transform_and_extent(
+    hardware: &Hardware,
+    pre_transform: bool,
+) -> Result<(SurfaceTransform, [u32; 2]), SurfacePropertiesError> {
+    let [width, height]: [u32; 2] = hardware.window().inner_size().into();
+    if !pre_transform {
+        return Ok((SurfaceTransform::Identity, [width, height]));
+    }
+
+    let transform = hardware
+        .graphics_device()
+        .physical_device()
+        .surface_capabilities(hardware.surface(), Default::default())?
+        .current_transform;
+    if camera::swaps_axes(transform) {
+        Ok((transform, [height, width]))
+    } else {
+        Ok((transform, [width, height]))
+    }
+}
+
+/// The sample count of `window` if the device supports it for its color and depth images, `Sample1` otherwise.
+fn select_sample_count(hardware: &Hardware, window: &WindowConfig) -> SampleCount {
+    let requested = window.sample_count;
+    if requested == SampleCount::Sample1 {
+        return requested;
+    }
+
+    let properties = hardware.graphics_device().physical_device().properties();
+    let supported = properties.framebuffer_color_sample_counts.contains(requested)
+        && (window.depth_format.is_none() || properties.framebuffer_depth_sample_counts.contains(requested));
+    if !supported {
+        warn!("The device doesn't support {:?} multisampling, it is disabled", requested);
+        return SampleCount::Sample1;
+    }
+
+    if matches!(window.clear, Clear::Preserve) {
+        warn!("Multisampled windows are cleared each frame, their contents can't be preserved");
+    }
+    debug!("Rendering with {:?} multisampling", requested);
+    requested
+}
+
+/// The `preferred` present mode if the surface supports it, `Fifo` otherwise.
+fn select_present_mode(
+    hardware: &Hardware,
+    preferred: Option<PresentMode>,
+) -> Result<PresentMode, SurfacePropertiesError> {
+    let preferred = match preferred {
+        Some(preferred) => preferred,
+        None => return Ok(PresentMode::Fifo),
+    };
+
+    let supported = hardware
+        .graphics_device()
+        .physical_device()
+        .surface_present_modes(hardware.surface())?
+        .any(|mode| mode == preferred);
+    if supported {
+        Ok(preferred)
+    } else {
+        warn!("The present mode {:?} isn't supported by the window, falling back to Fifo", preferred);
+        Ok(PresentMode::Fifo)
+    }
+}
+
+fn supported_formats(hardware: &Hardware) -> Result<Vec<(Format, ColorSpace)>, SurfacePropertiesError> {
+    hardware
+        .graphics_device()
+        .physical_device()
+        .surface_formats(hardware.surface(), Default::default())
+}
+
+/// Checks that the `forced` format is supported by the window, see [`WindowConfig::format`].
+fn force_format(
+    supported: Vec<(Format, ColorSpace)>,
+    forced: (Format, ColorSpace),
+) -> Result<(Format, ColorSpace), EngineError> {
+    if supported.contains(&forced) {
+        Ok(forced)
+    } else {
+        Err(EngineError::UnsupportedFormat { forced, supported })
+    }
+}
+
+/// Selects the first format supported in the `preferred` color space, falling back to sRGB,
+/// then to the first format. `None` if the window doesn't support any format.
+fn select_format(formats: &[(Format, ColorSpace)], preferred: ColorSpace) -> Option<(Format, ColorSpace)> {
+    if let Some(format) = formats.iter().find(|(_, color_space)| *color_space == preferred) {
+        return Some(*format);
+    }
+
+    warn!("The color space {:?} isn't supported by the window, falling back to sRGB", preferred);
+    formats
+        .iter()
+        .find(|(_, color_space)| *color_space == ColorSpace::SrgbNonLinear)
+        .or_else(|| formats.first())
+        .copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FORMATS: [(Format, ColorSpace); 3] = [
+        (Format::A2B10G10R10_UNORM_PACK32, ColorSpace::Hdr10St2084),
+        (Format::B8G8R8A8_SRGB, ColorSpace::SrgbNonLinear),
+        (Format::R16G16B16A16_SFLOAT, ColorSpace::ExtendedSrgbLinear),
+    ];
+
+    #[test]
+    fn selects_preferred_color_space() {
+        assert_eq!(select_format(&FORMATS, ColorSpace::ExtendedSrgbLinear), Some(FORMATS[2]));
+    }
+
+    #[test]
+    fn falls_back_to_srgb() {
+        assert_eq!(select_format(&FORMATS, ColorSpace::DisplayP3NonLinear), Some(FORMATS[1]));
+    }
+
+    #[test]
+    fn falls_back_to_first() {
+        let formats = [FORMATS[0], FORMATS[2]];
+        assert_eq!(select_format(&formats, ColorSpace::SrgbNonLinear), Some(FORMATS[0]));
+    }
+
+    #[test]
+    fn no_formats() {
+        assert_eq!(select_format(&[], ColorSpace::SrgbNonLinear), None);
+    }
 }
@@ -0,0 +1,100 @@
+use std::error::Error;
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use image::RgbaImage;
+use log::{info, warn};
+use vulkano::buffer::CpuAccessibleBuffer;
+use vulkano::format::Format;
+
+/// Converts the pixels of a swapchain image of `extent`, as copied by
+/// [`Screen::capture_to_buffer`](crate::drawing::screen::Screen::capture_to_buffer), to RGBA.
+///
+/// sRGB formats are kept encoded, as PNG expects. When `opaque` is set, the alpha channel is ignored,
+/// since the compositor doesn't read it.
+pub fn to_rgba(pixels: &[u8], format: Format, extent: [u32; 2], opaque: bool) -> Result<RgbaImage, ScreenshotError> {
+    let swap_red_blue = match format {
+        Format::B8G8R8A8_UNORM | Format::B8G8R8A8_SRGB => true,
+        Format::R8G8B8A8_UNORM | Format::R8G8B8A8_SRGB => false,
+        // Packed in a little-endian u32, the bytes are in the RGBA order
+        Format::A8B8G8R8_UNORM_PACK32 | Format::A8B8G8R8_SRGB_PACK32 => false,
+        _ => return Err(ScreenshotError::UnsupportedFormat(format)),
+    };
+
+    let mut rgba = pixels.to_vec();
+    for pixel in rgba.chunks_exact_mut(4) {
+        if swap_red_blue {
+            pixel.swap(0, 2);
+        }
+        if opaque {
+            pixel[3] = 255;
+        }
+    }
+
+    RgbaImage::from_raw(extent[0], extent[1], rgba).ok_or(ScreenshotError::SizeMismatch)
+}
+
+/// A swapchain image being copied to the CPU, written to PNG files once the copy is done.
+pub(crate) struct PendingScreenshot {
+    pub(crate) buffer: Arc<CpuAccessibleBuffer<[u8]>>,
+    pub(crate) paths: Vec<PathBuf>,
+    pub(crate) format: Format,
+    pub(crate) extent: [u32; 2],
+    pub(crate) opaque: bool,
+}
+
+impl PendingScreenshot {
+    /// Writes the files if the GPU is done copying the image, without blocking.
+    ///
+    /// Returns `true` once the screenshot is finished, whether it could be written or not.
+    pub(crate) fn try_save(&self) -> bool {
+        // The buffer stays locked until the frame that copied it is cleaned up
+        let pixels = match self.buffer.read() {
+            Ok(pixels) => pixels,
+            Err(_) => return false,
+        };
+
+        let image = match to_rgba(&pixels, self.format, self.extent, self.opaque) {
+            Ok(image) => image,
+            Err(e) => {
+                warn!("Couldn't take the screenshot: {}", e);
+                return true;
+            }
+        };
+
+        for path in &self.paths {
+            match image.save(path) {
+                Ok(()) => info!("Saved a screenshot to {}", path.display()),
+                Err(e) => warn!("Couldn't save the screenshot to {}: {}", path.display(), e),
+            }
+        }
+        true
+    }
+}
+
+#[derive(Debug)]
+pub enum ScreenshotError {
+    /// The swapchain images can't be copied: the window doesn't support the `transfer_source` usage.
+    TransferNotSupported,
+    /// The format of the swapchain can't be converted to RGBA.
+    UnsupportedFormat(Format),
+    /// The buffer doesn't hold an image of the size of the swapchain.
+    SizeMismatch,
+}
+
+impl fmt::Display for ScreenshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScreenshotError::TransferNotSupported => {
+                write!(f, "the swapchain images of this window can't be copied")
+            }
+            ScreenshotError::UnsupportedFormat(format) => {
+                write!(f, "screenshots of the format {:?} aren't supported", format)
+            }
+            ScreenshotError::SizeMismatch => write!(f, "the buffer doesn't match the size of the image"),
+        }
+    }
+}
+
+impl Error for ScreenshotError {}
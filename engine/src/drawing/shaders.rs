@@ -0,0 +1,132 @@
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use log::debug;
+use vulkano::device::Device;
+use vulkano::shader::{ShaderCreationError, ShaderModule};
+
+/// The first word of SPIR-V modules.
+const SPIRV_MAGIC: u32 = 0x0723_0203;
+
+/// Loads compiled SPIR-V shaders at runtime, to iterate on them without rebuilding the crate.
+///
+/// Compile the shaders with `glslc shader.frag -o shader.frag.spv`, then build pipelines from the modules
+/// as with the ones of `vulkano_shaders::shader!`. The SPIR-V isn't validated beyond what vulkano checks
+/// when reflecting it, so loading is unsafe: run the validation layer while iterating on shaders.
+pub struct ShaderLoader {
+    device: Arc<Device>,
+}
+
+impl ShaderLoader {
+    pub fn new(device: Arc<Device>) -> Self {
+        ShaderLoader { device }
+    }
+
+    /// Loads the `.spv` file at `path`, see [`ShaderLoader::load_from_spirv`].
+    ///
+    /// # Safety
+    ///
+    /// - the file must contain a valid SPIR-V module, see [`ShaderLoader::load_from_spirv`].
+    pub unsafe fn load<P: AsRef<Path>>(&self, path: P) -> Result<Arc<ShaderModule>, ShaderError> {
+        Self::load_from_spirv(&self.device, path.as_ref())
+    }
+
+    /// Reads the `.spv` file at `path` and creates a shader module on `device`.
+    ///
+    /// Modules written with either endianness are accepted.
+    ///
+    /// # Safety
+    ///
+    /// - the file must contain a valid SPIR-V module, as produced by `glslc`: only its length and magic number
+    ///   are checked, and vulkano only reflects its interface.
+    pub unsafe fn load_from_spirv(device: &Arc<Device>, path: &Path) -> Result<Arc<ShaderModule>, ShaderError> {
+        let bytes = fs::read(path).map_err(|error| ShaderError::Io {
+            path: path.to_owned(),
+            error,
+        })?;
+
+        let words = spirv_words(&bytes).map_err(|kind| match kind {
+            WordsError::Length => ShaderError::InvalidLength {
+                path: path.to_owned(),
+                length: bytes.len(),
+            },
+            WordsError::Magic => ShaderError::NotSpirv(path.to_owned()),
+        })?;
+
+        debug!("Loading the shader {} ({} words)", path.display(), words.len());
+        ShaderModule::from_words(Arc::clone(device), &words).map_err(|error| ShaderError::Creation {
+            path: path.to_owned(),
+            error,
+        })
+    }
+}
+
+enum WordsError {
+    Length,
+    Magic,
+}
+
+/// The words of a SPIR-V module, swapping their bytes if it was written with the other endianness.
+fn spirv_words(bytes: &[u8]) -> Result<Vec<u32>, WordsError> {
+    if bytes.is_empty() || !bytes.len().is_multiple_of(4) {
+        return Err(WordsError::Length);
+    }
+
+    let words: Vec<u32> = bytes
+        .chunks_exact(4)
+        .map(|word| u32::from_le_bytes([word[0], word[1], word[2], word[3]]))
+        .collect();
+
+    if words[0] == SPIRV_MAGIC {
+        Ok(words)
+    } else if words[0] == SPIRV_MAGIC.swap_bytes() {
+        Ok(words.into_iter().map(u32::swap_bytes).collect())
+    } else {
+        Err(WordsError::Magic)
+    }
+}
+
+#[derive(Debug)]
+pub enum ShaderError {
+    Io { path: PathBuf, error: io::Error },
+    /// SPIR-V is made of 32-bit words: the length of the file, in bytes, isn't a multiple of 4.
+    InvalidLength { path: PathBuf, length: usize },
+    /// The file doesn't start with the SPIR-V magic number, it may be GLSL source instead.
+    NotSpirv(PathBuf),
+    /// The module was refused by vulkano, for example because it requires an unsupported capability.
+    Creation { path: PathBuf, error: ShaderCreationError },
+}
+
+impl fmt::Display for ShaderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShaderError::Io { path, error } => write!(f, "couldn't read the shader {}: {}", path.display(), error),
+            ShaderError::InvalidLength { path, length } => write!(
+                f,
+                "the shader {} isn't SPIR-V: its length ({} bytes) isn't a multiple of 4",
+                path.display(),
+                length
+            ),
+            ShaderError::NotSpirv(path) => {
+                write!(f, "the shader {} doesn't start with the SPIR-V magic number", path.display())
+            }
+            ShaderError::Creation { path, error } => {
+                write!(f, "couldn't create the shader {}: {}", path.display(), error)
+            }
+        }
+    }
+}
+
+impl Error for ShaderError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ShaderError::Io { error, .. } => Some(error),
+            ShaderError::Creation { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,138 @@
+use std::sync::Arc;
+
+use vulkano::command_buffer::{AutoCommandBufferBuilder, PrimaryAutoCommandBuffer, SubpassContents};
+use vulkano::format::{ClearValue, Format};
+use vulkano::image::view::ImageView;
+use vulkano::image::{AttachmentImage, ImageLayout, ImageUsage};
+use vulkano::pipeline::graphics::depth_stencil::CompareOp;
+use vulkano::pipeline::graphics::viewport::Viewport;
+use vulkano::render_pass::{Framebuffer, FramebufferCreateInfo, RenderPass, StoreOp, Subpass};
+use vulkano::sampler::{BorderColor, Sampler, SamplerAddressMode};
+
+use crate::drawing::hardware::Hardware;
+use crate::drawing::render_pass::{Attachment, RenderPassBuilder};
+use crate::drawing::sampler::SamplerBuilder;
+
+/// The depth format of shadow maps.
+pub const SHADOW_FORMAT: Format = Format::D32_SFLOAT;
+
+/// A square depth image rendered from the point of view of a light, then sampled to know what it lights.
+///
+/// Occluders are drawn with [`ShadowMap::render`], using pipelines built for [`ShadowMap::subpass`]
+/// with a depth test and, to avoid shadow acne, a depth bias.
+/// The main pass then samples [`ShadowMap::view`] with [`ShadowMap::sampler`] as a `sampler2DShadow`:
+/// the comparison is filtered, which smooths the shadow edges.
+pub struct ShadowMap {
+    view: Arc<ImageView<AttachmentImage>>,
+    render_pass: Arc<RenderPass>,
+    framebuffer: Arc<Framebuffer>,
+    sampler: Arc<Sampler>,
+    resolution: u32,
+}
+
+impl ShadowMap {
+    /// Creates a shadow map of `resolution`×`resolution` texels.
+    pub fn new(hardware: &Hardware, resolution: u32) -> Self {
+        let device = hardware.graphics_device();
+
+        let image = AttachmentImage::with_usage(
+            Arc::clone(device),
+            [resolution, resolution],
+            SHADOW_FORMAT,
+            ImageUsage {
+                sampled: true,
+                ..ImageUsage::none()
+            },
+        )
+            .expect("Couldn't create the shadow map image");
+        let view = ImageView::new_default(image).expect("Couldn't create the shadow map view");
+
+        let render_pass = RenderPassBuilder::new()
+            .attachment(
+                Attachment::depth(SHADOW_FORMAT)
+                    .store(StoreOp::Store)
+                    .layouts(ImageLayout::Undefined, ImageLayout::ShaderReadOnlyOptimal),
+            )
+            .build(Arc::clone(device))
+            .expect("Couldn't create the shadow map render pass");
+
+        let framebuffer = Framebuffer::new(
+            Arc::clone(&render_pass),
+            FramebufferCreateInfo {
+                attachments: vec![Arc::clone(&view) as _],
+                ..Default::default()
+            },
+        )
+            .expect("Couldn't create the shadow map framebuffer");
+
+        // Everything outside of the light's frustum is lit
+        let sampler = SamplerBuilder::new()
+            .address_mode(SamplerAddressMode::ClampToBorder)
+            .border_color(BorderColor::FloatOpaqueWhite)
+            .compare(Some(CompareOp::Less))
+            .build(Arc::clone(device))
+            .expect("Couldn't create the shadow map sampler");
+
+        ShadowMap {
+            view,
+            render_pass,
+            framebuffer,
+            sampler,
+            resolution,
+        }
+    }
+
+    pub fn resolution(&self) -> u32 {
+        self.resolution
+    }
+
+    /// The depth image, to be bound with [`ShadowMap::sampler`] once it is rendered.
+    pub fn view(&self) -> &Arc<ImageView<AttachmentImage>> {
+        &self.view
+    }
+
+    /// A comparison sampler: sampling returns the proportion of texels further from the light
+    /// than the reference depth.
+    pub fn sampler(&self) -> &Arc<Sampler> {
+        &self.sampler
+    }
+
+    pub fn render_pass(&self) -> &Arc<RenderPass> {
+        &self.render_pass
+    }
+
+    /// The subpass the occluder pipelines must be built for.
+    pub fn subpass(&self) -> Subpass {
+        Subpass::from(Arc::clone(&self.render_pass), 0).unwrap()
+    }
+
+    /// A viewport covering the whole shadow map.
+    pub fn viewport(&self) -> Viewport {
+        Viewport {
+            origin: [0.0, 0.0],
+            dimensions: [self.resolution as f32, self.resolution as f32],
+            depth_range: 0.0..1.0,
+        }
+    }
+
+    /// Records the shadow pass: clears the shadow map, then lets `draw` record the occluders.
+    ///
+    /// The viewport is already set. Once the pass ends, the shadow map is ready to be sampled.
+    pub fn render<F>(&self, builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>, draw: F)
+        where
+            F: FnOnce(&mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>),
+    {
+        builder
+            .begin_render_pass(
+                Arc::clone(&self.framebuffer),
+                SubpassContents::Inline,
+                [ClearValue::Depth(1.0)],
+            )
+            .expect("Couldn't begin the shadow pass")
+            .set_viewport(0, [self.viewport()]);
+        draw(builder);
+        builder
+            .end_render_pass()
+            .expect("Couldn't end the shadow pass");
+    }
+}
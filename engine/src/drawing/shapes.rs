@@ -0,0 +1,517 @@
+use std::f32::consts::PI;
+use std::sync::Arc;
+
+use bytemuck::{Pod, Zeroable};
+use vulkano::buffer::{BufferUsage, CpuBufferPool};
+use vulkano::command_buffer::{AutoCommandBufferBuilder, PrimaryAutoCommandBuffer};
+use vulkano::pipeline::graphics::color_blend::ColorBlendState;
+use vulkano::pipeline::graphics::input_assembly::InputAssemblyState;
+use vulkano::pipeline::graphics::vertex_input::BuffersDefinition;
+use vulkano::pipeline::graphics::viewport::{Viewport, ViewportState};
+use vulkano::pipeline::{GraphicsPipeline, Pipeline};
+use vulkano::render_pass::Subpass;
+
+use crate::drawing::hardware::Hardware;
+
+/// Width of the antialiased border of the shapes, in pixels.
+const FRINGE_WIDTH: f32 = 1.0;
+
+/// Maximum distance between a circle and the polygon approximating it, in pixels.
+const ARC_TOLERANCE: f32 = 0.25;
+
+#[repr(C)]
+#[derive(Default, Copy, Clone, Debug, Pod, Zeroable)]
+pub struct ShapeVertex {
+    /// In pixels, from the top-left corner of the viewport.
+    pub position: [f32; 2],
+    pub color: [f32; 4],
+}
+
+vulkano::impl_vertex!(ShapeVertex, position, color);
+
+/// Filled and stroked 2D shapes, triangulated on the CPU and drawn by [`ShapeRenderer::draw`].
+///
+/// Coordinates are in pixels, from the top-left corner of the viewport.
+/// Each shape is surrounded by a thin border fading to transparent, which antialiases its edges.
+#[derive(Default, Clone)]
+pub struct ShapeBatch {
+    vertices: Vec<ShapeVertex>,
+    indices: Vec<u32>,
+}
+
+impl ShapeBatch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn circle(&mut self, center: [f32; 2], radius: f32, color: [f32; 4]) -> &mut Self {
+        let points = arc(center, radius, 0.0, 2.0 * PI, segments(radius, 2.0 * PI));
+        self.fill_convex(&points, color)
+    }
+
+    /// A rectangle whose corners are rounded with `corner_radius`, which is clamped to half of its smallest side.
+    pub fn rounded_rectangle(
+        &mut self,
+        min: [f32; 2],
+        max: [f32; 2],
+        corner_radius: f32,
+        color: [f32; 4],
+    ) -> &mut Self {
+        let radius = corner_radius
+            .min((max[0] - min[0]) / 2.0)
+            .min((max[1] - min[1]) / 2.0)
+            .max(0.0);
+        if radius == 0.0 {
+            return self.rectangle(min, max, color);
+        }
+
+        let corners = [
+            ([max[0] - radius, min[1] + radius], -PI / 2.0),
+            ([max[0] - radius, max[1] - radius], 0.0),
+            ([min[0] + radius, max[1] - radius], PI / 2.0),
+            ([min[0] + radius, min[1] + radius], PI),
+        ];
+        let segments = segments(radius, PI / 2.0);
+        let points: Vec<[f32; 2]> = corners
+            .iter()
+            .flat_map(|&(center, start)| arc(center, radius, start, start + PI / 2.0, segments))
+            .collect();
+        self.fill_convex(&points, color)
+    }
+
+    pub fn rectangle(&mut self, min: [f32; 2], max: [f32; 2], color: [f32; 4]) -> &mut Self {
+        let points = [min, [max[0], min[1]], max, [min[0], max[1]]];
+        self.fill_convex(&points, color)
+    }
+
+    /// A simple polygon (its edges don't cross), convex or not, in either winding order.
+    ///
+    /// Polygons with less than 3 points are ignored.
+    pub fn polygon(&mut self, points: &[[f32; 2]], color: [f32; 4]) -> &mut Self {
+        if points.len() < 3 {
+            return self;
+        }
+        let points = counter_clockwise(points);
+        let triangles = triangulate(&points);
+        self.fill(&points, &triangles, color)
+    }
+
+    /// The outline of a circle, `width` pixels wide, centered on the circle.
+    pub fn stroke_circle(&mut self, center: [f32; 2], radius: f32, width: f32, color: [f32; 4]) -> &mut Self {
+        let points = arc(center, radius, 0.0, 2.0 * PI, segments(radius + width / 2.0, 2.0 * PI));
+        self.stroke(&points, width, color)
+    }
+
+    /// The closed outline of a polygon, `width` pixels wide, centered on its edges.
+    ///
+    /// Polygons with less than 3 points are ignored.
+    pub fn stroke_polygon(&mut self, points: &[[f32; 2]], width: f32, color: [f32; 4]) -> &mut Self {
+        if points.len() < 3 {
+            return self;
+        }
+        self.stroke(points, width, color)
+    }
+
+    /// Removes all the shapes.
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+        self.indices.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+
+    pub fn vertices(&self) -> &[ShapeVertex] {
+        &self.vertices
+    }
+
+    pub fn indices(&self) -> &[u32] {
+        &self.indices
+    }
+
+    fn fill_convex(&mut self, points: &[[f32; 2]], color: [f32; 4]) -> &mut Self {
+        let points = counter_clockwise(points);
+        let triangles: Vec<[usize; 3]> = (1..points.len() - 1).map(|i| [0, i, i + 1]).collect();
+        self.fill(&points, &triangles, color)
+    }
+
+    /// Adds the `triangles` of the counter-clockwise polygon `points`, and its antialiased border.
+    fn fill(&mut self, points: &[[f32; 2]], triangles: &[[usize; 3]], color: [f32; 4]) -> &mut Self {
+        let transparent = [color[0], color[1], color[2], 0.0];
+
+        // Each point is split into an inner opaque vertex and an outer transparent one,
+        // so the edges fade over FRINGE_WIDTH pixels
+        let base = self.outline(points, &[(-FRINGE_WIDTH / 2.0, color), (FRINGE_WIDTH / 2.0, transparent)]);
+        for triangle in triangles {
+            self.indices.extend(triangle.iter().map(|&i| base + 2 * i as u32));
+        }
+
+        self
+    }
+
+    /// Adds a band `width` pixels wide centered on the closed outline `points`, in either winding order.
+    fn stroke(&mut self, points: &[[f32; 2]], width: f32, color: [f32; 4]) -> &mut Self {
+        let points = counter_clockwise(points);
+        let transparent = [color[0], color[1], color[2], 0.0];
+
+        // An opaque core, faded on both sides like the border of filled shapes
+        let core = (width / 2.0 - FRINGE_WIDTH / 2.0).max(0.0);
+        let edge = width / 2.0 + FRINGE_WIDTH / 2.0;
+        self.outline(
+            &points,
+            &[(-edge, transparent), (-core, color), (core, color), (edge, transparent)],
+        );
+
+        self
+    }
+
+    /// Adds one vertex per ring for each point of the counter-clockwise outline `points`, each ring moved
+    /// outwards by its distance in pixels, and fills the space between consecutive rings.
+    ///
+    /// Returns the index of the first vertex: the vertex of the ring `r` for the point `i` is at
+    /// `base + rings.len() * i + r`.
+    fn outline(&mut self, points: &[[f32; 2]], rings: &[(f32, [f32; 4])]) -> u32 {
+        let n = points.len();
+        let base = self.vertices.len() as u32;
+
+        for i in 0..n {
+            let previous = points[(i + n - 1) % n];
+            let next = points[(i + 1) % n];
+            let offset = miter(outward_normal(previous, points[i]), outward_normal(points[i], next));
+
+            self.vertices.extend(rings.iter().map(|&(distance, color)| ShapeVertex {
+                position: [
+                    points[i][0] + offset[0] * distance,
+                    points[i][1] + offset[1] * distance,
+                ],
+                color,
+            }));
+        }
+
+        let vertex = |i: usize, ring: usize| base + (rings.len() * i + ring) as u32;
+        for ring in 1..rings.len() {
+            for i in 0..n {
+                let j = (i + 1) % n;
+                let [a, b] = [ring - 1, ring];
+                self.indices
+                    .extend([vertex(i, a), vertex(j, a), vertex(j, b), vertex(i, a), vertex(j, b), vertex(i, b)]);
+            }
+        }
+
+        base
+    }
+}
+
+/// Draws [`ShapeBatch`]es with a single indexed draw call each.
+pub struct ShapeRenderer {
+    pipeline: Arc<GraphicsPipeline>,
+    vertices: CpuBufferPool<ShapeVertex>,
+    indices: CpuBufferPool<u32>,
+}
+
+impl ShapeRenderer {
+    /// `subpass` is the one [`ShapeRenderer::draw`] will be called from.
+    pub fn new(hardware: &Hardware, subpass: Subpass) -> Self {
+        let device = hardware.graphics_device();
+        let vs = vs::load(Arc::clone(device)).expect("Couldn't load the shape vertex shader");
+        let fs = fs::load(Arc::clone(device)).expect("Couldn't load the shape fragment shader");
+
+        let pipeline = GraphicsPipeline::start()
+            .vertex_input_state(BuffersDefinition::new().vertex::<ShapeVertex>())
+            .vertex_shader(vs.entry_point("main").unwrap(), ())
+            .input_assembly_state(InputAssemblyState::new())
+            .viewport_state(ViewportState::viewport_dynamic_scissor_irrelevant())
+            .fragment_shader(fs.entry_point("main").unwrap(), ())
+            .color_blend_state(ColorBlendState::new(subpass.num_color_attachments()).blend_alpha())
+            .render_pass(subpass)
+            .build(Arc::clone(device))
+            .expect("Couldn't create the shape pipeline");
+
+        ShapeRenderer {
+            pipeline,
+            vertices: CpuBufferPool::vertex_buffer(Arc::clone(device)),
+            indices: CpuBufferPool::new(Arc::clone(device), BufferUsage::index_buffer()),
+        }
+    }
+
+    /// Records the drawing of `shapes`, in the order they were added.
+    ///
+    /// Must be called inside the subpass given to [`ShapeRenderer::new`].
+    /// The buffers are taken from rings of buffers that grow as needed, so it is safe to call this every frame.
+    pub fn draw(
+        &self,
+        builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        viewport: &Viewport,
+        shapes: &ShapeBatch,
+    ) {
+        if shapes.is_empty() {
+            return;
+        }
+
+        let count = shapes.indices.len() as u32;
+        let vertices = self
+            .vertices
+            .chunk(shapes.vertices.iter().copied())
+            .expect("Couldn't allocate the shape vertex buffer");
+        let indices = self
+            .indices
+            .chunk(shapes.indices.iter().copied())
+            .expect("Couldn't allocate the shape index buffer");
+
+        builder
+            .set_viewport(0, [viewport.clone()])
+            .bind_pipeline_graphics(Arc::clone(&self.pipeline))
+            .push_constants(
+                Arc::clone(self.pipeline.layout()),
+                0,
+                vs::ty::Screen {
+                    size: viewport.dimensions,
+                },
+            )
+            .bind_vertex_buffers(0, vertices)
+            .bind_index_buffer(indices)
+            .draw_indexed(count, 1, 0, 0, 0)
+            .expect("Couldn't record the shape draw call");
+    }
+}
+
+/// The number of segments approximating an arc of `angle` radians, within [`ARC_TOLERANCE`].
+fn segments(radius: f32, angle: f32) -> usize {
+    if radius <= ARC_TOLERANCE {
+        return 3;
+    }
+    let step = 2.0 * (1.0 - ARC_TOLERANCE / radius).acos();
+    ((angle / step).ceil() as usize).clamp(3, 256)
+}
+
+/// The points of an arc, from `start` to `end` radians (excluded), clockwise on screen since y goes down.
+fn arc(center: [f32; 2], radius: f32, start: f32, end: f32, segments: usize) -> Vec<[f32; 2]> {
+    (0..segments)
+        .map(|i| {
+            let angle = start + (end - start) * i as f32 / segments as f32;
+            [center[0] + radius * angle.cos(), center[1] + radius * angle.sin()]
+        })
+        .collect()
+}
+
+/// Twice the signed area of the polygon, positive if it is counter-clockwise in a y-up frame.
+fn signed_area(points: &[[f32; 2]]) -> f32 {
+    let n = points.len();
+    (0..n)
+        .map(|i| {
+            let [a, b] = [points[i], points[(i + 1) % n]];
+            a[0] * b[1] - b[0] * a[1]
+        })
+        .sum()
+}
+
+/// The points in the winding order expected by [`triangulate`] and the fringe.
+fn counter_clockwise(points: &[[f32; 2]]) -> Vec<[f32; 2]> {
+    let mut points = points.to_vec();
+    if signed_area(&points) < 0.0 {
+        points.reverse();
+    }
+    points
+}
+
+/// The unit normal of the edge from `a` to `b`, pointing outside of a counter-clockwise polygon.
+fn outward_normal(a: [f32; 2], b: [f32; 2]) -> [f32; 2] {
+    let [dx, dy] = [b[0] - a[0], b[1] - a[1]];
+    let length = (dx * dx + dy * dy).sqrt();
+    if length == 0.0 {
+        [0.0, 0.0]
+    } else {
+        [dy / length, -dx / length]
+    }
+}
+
+/// The offset of a corner between two edges, so both edges are moved by one unit.
+fn miter(a: [f32; 2], b: [f32; 2]) -> [f32; 2] {
+    let average = [(a[0] + b[0]) / 2.0, (a[1] + b[1]) / 2.0];
+    let length_squared = average[0] * average[0] + average[1] * average[1];
+    // Sharp corners would produce very long spikes
+    let scale = 1.0 / length_squared.max(0.25);
+    [average[0] * scale, average[1] * scale]
+}
+
+/// Splits a simple counter-clockwise polygon into triangles, by ear clipping.
+///
+/// Returns the indices of the points of each triangle.
+pub fn triangulate(points: &[[f32; 2]]) -> Vec<[usize; 3]> {
+    let mut remaining: Vec<usize> = (0..points.len()).collect();
+    let mut triangles = Vec::with_capacity(points.len().saturating_sub(2));
+
+    while remaining.len() > 3 {
+        let n = remaining.len();
+        let ear = (0..n).find(|&i| {
+            let [a, b, c] = [remaining[(i + n - 1) % n], remaining[i], remaining[(i + 1) % n]];
+            is_ear(points, &remaining, a, b, c)
+        });
+
+        match ear {
+            Some(i) => {
+                triangles.push([remaining[(i + n - 1) % n], remaining[i], remaining[(i + 1) % n]]);
+                remaining.remove(i);
+            }
+            // Only happens with self-intersecting or degenerate polygons: fill the rest as a fan
+            None => break,
+        }
+    }
+
+    for i in 1..remaining.len().saturating_sub(1) {
+        triangles.push([remaining[0], remaining[i], remaining[i + 1]]);
+    }
+    triangles
+}
+
+/// Whether the corner `b` is convex and the triangle `abc` contains no other remaining point.
+fn is_ear(points: &[[f32; 2]], remaining: &[usize], a: usize, b: usize, c: usize) -> bool {
+    let [pa, pb, pc] = [points[a], points[b], points[c]];
+    if cross(pa, pb, pc) <= 0.0 {
+        return false;
+    }
+
+    remaining
+        .iter()
+        .filter(|&&i| i != a && i != b && i != c)
+        .all(|&i| !in_triangle(points[i], pa, pb, pc))
+}
+
+fn cross(a: [f32; 2], b: [f32; 2], c: [f32; 2]) -> f32 {
+    (b[0] - a[0]) * (c[1] - a[1]) - (b[1] - a[1]) * (c[0] - a[0])
+}
+
+fn in_triangle(p: [f32; 2], a: [f32; 2], b: [f32; 2], c: [f32; 2]) -> bool {
+    cross(a, b, p) >= 0.0 && cross(b, c, p) >= 0.0 && cross(c, a, p) >= 0.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The total area of the triangles, each counted positively if it is counter-clockwise.
+    fn area(points: &[[f32; 2]], triangles: &[[usize; 3]]) -> f32 {
+        triangles
+            .iter()
+            .map(|&[a, b, c]| cross(points[a], points[b], points[c]) / 2.0)
+            .sum()
+    }
+
+    fn assert_counter_clockwise(points: &[[f32; 2]], triangles: &[[usize; 3]]) {
+        for &[a, b, c] in triangles {
+            assert!(cross(points[a], points[b], points[c]) > 0.0, "{:?} is clockwise or flat", [a, b, c]);
+        }
+    }
+
+    #[test]
+    fn triangulate_convex() {
+        let square = [[0.0, 0.0], [2.0, 0.0], [2.0, 2.0], [0.0, 2.0]];
+        let triangles = triangulate(&square);
+
+        assert_eq!(triangles.len(), 2);
+        assert_counter_clockwise(&square, &triangles);
+        assert_eq!(area(&square, &triangles), 4.0);
+    }
+
+    #[test]
+    fn triangulate_concave() {
+        let l_shape = [[0.0, 0.0], [2.0, 0.0], [2.0, 1.0], [1.0, 1.0], [1.0, 2.0], [0.0, 2.0]];
+        let triangles = triangulate(&l_shape);
+
+        assert_eq!(triangles.len(), 4);
+        assert_counter_clockwise(&l_shape, &triangles);
+        assert_eq!(area(&l_shape, &triangles), 3.0);
+    }
+
+    #[test]
+    fn triangulate_clockwise() {
+        let clockwise = [[0.0, 2.0], [1.0, 2.0], [1.0, 1.0], [2.0, 1.0], [2.0, 0.0], [0.0, 0.0]];
+        assert!(signed_area(&clockwise) < 0.0);
+
+        let points = counter_clockwise(&clockwise);
+        let triangles = triangulate(&points);
+
+        assert_eq!(triangles.len(), 4);
+        assert_counter_clockwise(&points, &triangles);
+        assert_eq!(area(&points, &triangles), 3.0);
+    }
+
+    #[test]
+    fn triangulate_collinear() {
+        let square = [[0.0, 0.0], [1.0, 0.0], [2.0, 0.0], [2.0, 2.0], [0.0, 2.0]];
+        let triangles = triangulate(&square);
+
+        assert_eq!(triangles.len(), 3);
+        assert_eq!(area(&square, &triangles), 4.0);
+    }
+
+    #[test]
+    fn triangulate_degenerate() {
+        // No corner is convex, so the ear clipping gives up and fills a fan
+        let line = [[0.0, 0.0], [1.0, 0.0], [2.0, 0.0], [3.0, 0.0]];
+        let triangles = triangulate(&line);
+
+        assert_eq!(triangles, vec![[0, 1, 2], [0, 2, 3]]);
+    }
+
+    #[test]
+    fn stroke_square() {
+        let mut batch = ShapeBatch::new();
+        batch.stroke_polygon(&[[0.0, 0.0], [4.0, 0.0], [4.0, 4.0], [0.0, 4.0]], 2.0, [1.0; 4]);
+
+        // 4 rings per point, and 3 bands of 2 triangles per edge
+        assert_eq!(batch.vertices().len(), 16);
+        assert_eq!(batch.indices().len(), 3 * 4 * 6);
+
+        let xs: Vec<f32> = batch.vertices().iter().map(|vertex| vertex.position[0]).collect();
+        let min = xs.iter().copied().fold(f32::INFINITY, f32::min);
+        let max = xs.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+        assert_eq!([min, max], [-1.5, 5.5]);
+    }
+
+    #[test]
+    fn stroke_ignores_lines() {
+        let mut batch = ShapeBatch::new();
+        batch.stroke_polygon(&[[0.0, 0.0], [4.0, 0.0]], 2.0, [1.0; 4]);
+
+        assert!(batch.is_empty());
+    }
+}
+
+#[allow(clippy::needless_question_mark)]
+mod vs {
+    vulkano_shaders::shader! {
+        ty: "vertex",
+        src: "
+#version 450
+layout(location = 0) in vec2 position;
+layout(location = 1) in vec4 color;
+
+layout(location = 0) out vec4 f_color;
+
+layout(push_constant) uniform Screen {
+    vec2 size;
+} screen;
+
+void main() {
+    gl_Position = vec4(position / screen.size * 2.0 - 1.0, 0.0, 1.0);
+    f_color = color;
+}"
+    }
+}
+
+#[allow(clippy::needless_question_mark)]
+mod fs {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        src: "
+#version 450
+layout(location = 0) in vec4 f_color;
+layout(location = 0) out vec4 color;
+
+void main() {
+    color = f_color;
+}"
+    }
+}
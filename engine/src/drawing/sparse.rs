@@ -0,0 +1,331 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::ops::Range;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use vulkano::buffer::sys::{BufferCreationError, SparseLevel, UnsafeBuffer, UnsafeBufferCreateInfo};
+use vulkano::buffer::{BufferAccess, BufferInner, BufferUsage};
+use vulkano::command_buffer::submit::{
+    SubmitBindSparseBatchBuilder, SubmitBindSparseBufferBindBuilder, SubmitBindSparseBuilder,
+    SubmitBindSparseError,
+};
+use vulkano::device::{Device, DeviceOwned, Queue};
+use vulkano::memory::{DeviceMemory, DeviceMemoryAllocationError, MemoryAllocateInfo};
+use vulkano::sync::{AccessError, Fence, FenceWaitError, Sharing};
+use vulkano::DeviceSize;
+
+use crate::drawing::hardware::Hardware;
+
+/// A device-local buffer whose memory is bound page by page, so only the parts in use take memory.
+///
+/// Reading a page that isn't resident returns zeros, and writes to it are discarded.
+///
+/// Sparse buffers need the `sparse_binding` and `sparse_residency_buffer` device features,
+/// which the engine enables when the graphics device supports them.
+pub struct SparseBuffer {
+    buffer: UnsafeBuffer,
+    size: DeviceSize,
+    page_size: DeviceSize,
+    memory_type: u32,
+    queue: Arc<Queue>,
+    pages: Mutex<HashMap<DeviceSize, DeviceMemory>>,
+    gpu_lock: AtomicUsize,
+}
+
+impl SparseBuffer {
+    /// Creates a buffer of `size` bytes, without any resident page.
+    pub fn new(hardware: &Hardware, size: DeviceSize, usage: BufferUsage) -> Result<Arc<Self>, SparseError> {
+        let device = hardware.graphics_device();
+        let features = device.enabled_features();
+        if !features.sparse_residency_buffer {
+            return Err(SparseError::Unsupported);
+        }
+        let queue = hardware.sparse_queue().ok_or(SparseError::Unsupported)?;
+
+        let queue_families: Vec<u32> = device
+            .active_queue_families()
+            .map(|family| family.id())
+            .collect();
+
+        let buffer = UnsafeBuffer::new(
+            Arc::clone(device),
+            UnsafeBufferCreateInfo {
+                sharing: if queue_families.len() >= 2 {
+                    Sharing::Concurrent(queue_families.into_iter().collect())
+                } else {
+                    Sharing::Exclusive
+                },
+                size,
+                sparse: Some(SparseLevel {
+                    sparse_residency: true,
+                    ..SparseLevel::none()
+                }),
+                usage,
+                ..Default::default()
+            },
+        )?;
+
+        // The alignment of a sparse buffer is the size of its pages
+        let requirements = buffer.memory_requirements();
+        let memory_type = device
+            .physical_device()
+            .memory_types()
+            .filter(|memory_type| requirements.memory_type_bits & (1 << memory_type.id()) != 0)
+            .min_by_key(|memory_type| !memory_type.is_device_local())
+            .ok_or(SparseError::NoMemoryType)?
+            .id();
+
+        Ok(Arc::new(SparseBuffer {
+            buffer,
+            size,
+            page_size: requirements.alignment,
+            memory_type,
+            queue: Arc::clone(queue),
+            pages: Mutex::new(HashMap::new()),
+            gpu_lock: AtomicUsize::new(0),
+        }))
+    }
+
+    /// The granularity memory is bound with, in bytes.
+    pub fn page_size(&self) -> DeviceSize {
+        self.page_size
+    }
+
+    pub fn page_count(&self) -> DeviceSize {
+        self.size.div_ceil(self.page_size)
+    }
+
+    /// The page containing the byte at `offset`.
+    pub fn page_of(&self, offset: DeviceSize) -> DeviceSize {
+        offset / self.page_size
+    }
+
+    pub fn is_resident(&self, page: DeviceSize) -> bool {
+        self.pages
+            .lock()
+            .expect("The sparse pages were poisoned")
+            .contains_key(&page)
+    }
+
+    /// Allocates memory for the `pages` that aren't resident yet, and waits until it is bound.
+    ///
+    /// Fails with [`SparseError::InUse`] while a submitted command buffer uses the buffer.
+    pub fn bind(&self, pages: Range<DeviceSize>) -> Result<(), SparseError> {
+        self.exclusively(|| self.bind_pages(pages))
+    }
+
+    /// Unbinds the resident `pages` and frees their memory, once the unbinding is done.
+    ///
+    /// Fails with [`SparseError::InUse`] while a submitted command buffer uses the buffer.
+    pub fn unbind(&self, pages: Range<DeviceSize>) -> Result<(), SparseError> {
+        self.exclusively(|| self.unbind_pages(pages))
+    }
+
+    /// Runs `f` while holding the lock command buffers take on the buffer, so none can use it meanwhile.
+    fn exclusively<F>(&self, f: F) -> Result<(), SparseError>
+        where
+            F: FnOnce() -> Result<(), SparseError>,
+    {
+        self.gpu_lock
+            .compare_exchange(0, 1, Ordering::SeqCst, Ordering::SeqCst)
+            .map_err(|_| SparseError::InUse)?;
+        let result = f();
+        self.gpu_lock.fetch_sub(1, Ordering::SeqCst);
+        result
+    }
+
+    fn bind_pages(&self, pages: Range<DeviceSize>) -> Result<(), SparseError> {
+        self.check_range(&pages)?;
+        let mut resident = self.pages.lock().expect("The sparse pages were poisoned");
+
+        let mut allocated = Vec::new();
+        for page in pages.filter(|page| !resident.contains_key(page)) {
+            let memory = DeviceMemory::allocate(
+                Arc::clone(self.device()),
+                MemoryAllocateInfo {
+                    allocation_size: self.page_size,
+                    memory_type_index: self.memory_type,
+                    ..Default::default()
+                },
+            )?;
+            allocated.push((page, memory));
+        }
+
+        let mut binds = unsafe { SubmitBindSparseBufferBindBuilder::new(&self.buffer) };
+        for (page, memory) in &allocated {
+            unsafe {
+                binds.add_bind(page * self.page_size, self.page_size, memory, 0);
+            }
+        }
+        self.submit(binds)?;
+
+        resident.extend(allocated);
+        Ok(())
+    }
+
+    fn unbind_pages(&self, pages: Range<DeviceSize>) -> Result<(), SparseError> {
+        self.check_range(&pages)?;
+        let mut resident = self.pages.lock().expect("The sparse pages were poisoned");
+
+        let mut binds = unsafe { SubmitBindSparseBufferBindBuilder::new(&self.buffer) };
+        let unbound: Vec<DeviceSize> = pages.filter(|page| resident.contains_key(page)).collect();
+        for page in &unbound {
+            unsafe {
+                binds.add_unbind(page * self.page_size, self.page_size);
+            }
+        }
+        self.submit(binds)?;
+
+        for page in unbound {
+            resident.remove(&page);
+        }
+        Ok(())
+    }
+
+    fn check_range(&self, pages: &Range<DeviceSize>) -> Result<(), SparseError> {
+        if pages.end > self.page_count() {
+            return Err(SparseError::OutOfBounds {
+                pages: pages.clone(),
+                count: self.page_count(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Submits `binds` to the sparse queue, and waits for them to be processed.
+    fn submit(&self, binds: SubmitBindSparseBufferBindBuilder) -> Result<(), SparseError> {
+        let fence = Fence::from_pool(Arc::clone(self.device())).map_err(FenceWaitError::OomError)?;
+
+        let mut batch = SubmitBindSparseBatchBuilder::new();
+        batch.add_buffer(binds);
+        let mut submit = SubmitBindSparseBuilder::new();
+        submit.add(batch);
+        unsafe {
+            submit.set_fence_signal(&fence);
+        }
+        submit.submit(&self.queue)?;
+
+        fence.wait(None)?;
+        Ok(())
+    }
+}
+
+unsafe impl BufferAccess for SparseBuffer {
+    fn inner(&self) -> BufferInner<'_> {
+        BufferInner {
+            buffer: &self.buffer,
+            offset: 0,
+        }
+    }
+
+    fn size(&self) -> DeviceSize {
+        self.size
+    }
+
+    fn conflict_key(&self) -> (u64, u64) {
+        (self.buffer.key(), 0)
+    }
+
+    fn try_gpu_lock(&self, _: bool, _: &Queue) -> Result<(), AccessError> {
+        match self
+            .gpu_lock
+            .compare_exchange(0, 1, Ordering::SeqCst, Ordering::SeqCst)
+        {
+            Ok(_) => Ok(()),
+            Err(_) => Err(AccessError::AlreadyInUse),
+        }
+    }
+
+    unsafe fn increase_gpu_lock(&self) {
+        self.gpu_lock.fetch_add(1, Ordering::SeqCst);
+    }
+
+    unsafe fn unlock(&self) {
+        self.gpu_lock.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+unsafe impl DeviceOwned for SparseBuffer {
+    fn device(&self) -> &Arc<Device> {
+        self.buffer.device()
+    }
+}
+
+#[derive(Debug)]
+pub enum SparseError {
+    /// The graphics device doesn't support partially resident buffers.
+    Unsupported,
+    /// None of the memory types of the device can back the buffer.
+    NoMemoryType,
+    /// The pages are past the end of the buffer.
+    OutOfBounds {
+        pages: Range<DeviceSize>,
+        count: DeviceSize,
+    },
+    /// A submitted command buffer still uses the buffer: its pages can't be bound or unbound until it is done.
+    InUse,
+    Creation(BufferCreationError),
+    Allocation(DeviceMemoryAllocationError),
+    Bind(SubmitBindSparseError),
+    Wait(FenceWaitError),
+}
+
+impl fmt::Display for SparseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SparseError::Unsupported => write!(
+                f,
+                "the graphics device doesn't support partially resident buffers"
+            ),
+            SparseError::NoMemoryType => write!(f, "no memory type can back the sparse buffer"),
+            SparseError::OutOfBounds { pages, count } => write!(
+                f,
+                "the pages {:?} are out of bounds, the buffer only has {} pages",
+                pages, count
+            ),
+            SparseError::InUse => write!(f, "the sparse buffer is in use by the GPU"),
+            SparseError::Creation(e) => write!(f, "couldn't create the sparse buffer: {}", e),
+            SparseError::Allocation(e) => write!(f, "couldn't allocate a page: {}", e),
+            SparseError::Bind(e) => write!(f, "couldn't bind the pages: {}", e),
+            SparseError::Wait(e) => write!(f, "couldn't wait for the pages to be bound: {}", e),
+        }
+    }
+}
+
+impl Error for SparseError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SparseError::Creation(e) => Some(e),
+            SparseError::Allocation(e) => Some(e),
+            SparseError::Bind(e) => Some(e),
+            SparseError::Wait(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<BufferCreationError> for SparseError {
+    fn from(e: BufferCreationError) -> Self {
+        SparseError::Creation(e)
+    }
+}
+
+impl From<DeviceMemoryAllocationError> for SparseError {
+    fn from(e: DeviceMemoryAllocationError) -> Self {
+        SparseError::Allocation(e)
+    }
+}
+
+impl From<SubmitBindSparseError> for SparseError {
+    fn from(e: SubmitBindSparseError) -> Self {
+        SparseError::Bind(e)
+    }
+}
+
+impl From<FenceWaitError> for SparseError {
+    fn from(e: FenceWaitError) -> Self {
+        SparseError::Wait(e)
+    }
+}
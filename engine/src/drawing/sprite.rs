@@ -0,0 +1,547 @@
+use std::error::Error;
+use std::fmt;
+use std::path::Path;
+use std::sync::{Arc, Mutex, Weak};
+use std::time::Duration;
+
+use image::ImageError;
+use vulkano::command_buffer::{AutoCommandBufferBuilder, PrimaryAutoCommandBuffer};
+use vulkano::descriptor_set::{PersistentDescriptorSet, WriteDescriptorSet};
+use vulkano::image::{ImageAccess, ImmutableImage};
+use vulkano::image::view::ImageView;
+use vulkano::pipeline::{GraphicsPipeline, Pipeline, PipelineBindPoint};
+use vulkano::pipeline::graphics::color_blend::ColorBlendState;
+use vulkano::pipeline::graphics::input_assembly::{InputAssemblyState, PrimitiveTopology};
+use vulkano::pipeline::graphics::viewport::{Viewport, ViewportState};
+use vulkano::render_pass::Subpass;
+use vulkano::sampler::{Filter, Sampler, SamplerAddressMode, SamplerCreateInfo};
+
+use crate::drawing::hardware::Hardware;
+
+/// A rectangle of texture coordinates, between 0 and 1.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UvRect {
+    /// The top-left corner.
+    pub min: [f32; 2],
+    /// The bottom-right corner.
+    pub max: [f32; 2],
+}
+
+impl UvRect {
+    /// The whole texture.
+    pub const FULL: UvRect = UvRect {
+        min: [0.0, 0.0],
+        max: [1.0, 1.0],
+    };
+}
+
+/// A texture in device-local memory, drawn by [`SpriteRenderer`].
+pub struct Texture {
+    view: Arc<ImageView<ImmutableImage>>,
+}
+
+impl Texture {
+    /// Loads the image at `path` as an sRGB texture, and waits for the upload to finish.
+    ///
+    /// To load several textures, [`Hardware::load_textures`] uploads them in a single submission.
+    pub fn from_file<P: AsRef<Path>>(hardware: &Hardware, path: P) -> Result<Arc<Texture>, ImageError> {
+        let view = hardware
+            .load_textures(&[path])
+            .pop()
+            .expect("Loading a texture should return a result")?;
+        Ok(Self::from_view(view))
+    }
+
+    /// Wraps a texture loaded with [`Hardware::load_textures`].
+    pub fn from_view(view: Arc<ImageView<ImmutableImage>>) -> Arc<Texture> {
+        Arc::new(Texture { view })
+    }
+
+    pub fn view(&self) -> &Arc<ImageView<ImmutableImage>> {
+        &self.view
+    }
+
+    /// Width and height, in pixels.
+    pub fn size(&self) -> [u32; 2] {
+        self.view.image().dimensions().width_height()
+    }
+}
+
+/// Draws textured quads, one draw call per sprite.
+///
+/// The descriptor set of each texture is created the first time it is drawn, and kept until the texture is dropped.
+pub struct SpriteRenderer {
+    pipeline: Arc<GraphicsPipeline>,
+    sampler: Arc<Sampler>,
+    descriptor_sets: Mutex<Vec<(Weak<Texture>, Arc<PersistentDescriptorSet>)>>,
+}
+
+impl SpriteRenderer {
+    /// `subpass` is the one [`SpriteRenderer::draw_sprite`] will be called from.
+    pub fn new(hardware: &Hardware, subpass: Subpass) -> Self {
+        let device = hardware.graphics_device();
+        let vs = vs::load(Arc::clone(device)).expect("Couldn't load the sprite vertex shader");
+        let fs = fs::load(Arc::clone(device)).expect("Couldn't load the sprite fragment shader");
+
+        let pipeline = GraphicsPipeline::start()
+            .vertex_shader(vs.entry_point("main").unwrap(), ())
+            .input_assembly_state(
+                InputAssemblyState::new().topology(PrimitiveTopology::TriangleStrip),
+            )
+            .viewport_state(ViewportState::viewport_dynamic_scissor_irrelevant())
+            .fragment_shader(fs.entry_point("main").unwrap(), ())
+            .color_blend_state(ColorBlendState::new(subpass.num_color_attachments()).blend_alpha())
+            .render_pass(subpass)
+            .build(Arc::clone(device))
+            .expect("Couldn't create the sprite pipeline");
+
+        let sampler = Sampler::new(
+            Arc::clone(device),
+            SamplerCreateInfo {
+                mag_filter: Filter::Linear,
+                min_filter: Filter::Linear,
+                address_mode: [SamplerAddressMode::ClampToEdge; 3],
+                ..Default::default()
+            },
+        )
+            .expect("Couldn't create the sprite sampler");
+
+        SpriteRenderer {
+            pipeline,
+            sampler,
+            descriptor_sets: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Records the drawing of the whole `texture`, with its top-left corner at `position` and stretched to `size`,
+    /// both in pixels.
+    ///
+    /// Must be called inside the subpass given to [`SpriteRenderer::new`].
+    pub fn draw_sprite(
+        &self,
+        builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        viewport: &Viewport,
+        texture: &Arc<Texture>,
+        position: [f32; 2],
+        size: [f32; 2],
+    ) {
+        self.draw_region(builder, viewport, texture, UvRect::FULL, position, size);
+    }
+
+    /// Same as [`SpriteRenderer::draw_sprite`], but only draws the `region` of `texture`,
+    /// for example the current frame of a [`SpriteAnimation`].
+    pub fn draw_region(
+        &self,
+        builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        viewport: &Viewport,
+        texture: &Arc<Texture>,
+        region: UvRect,
+        position: [f32; 2],
+        size: [f32; 2],
+    ) {
+        builder
+            .set_viewport(0, [viewport.clone()])
+            .bind_pipeline_graphics(Arc::clone(&self.pipeline))
+            .bind_descriptor_sets(
+                PipelineBindPoint::Graphics,
+                Arc::clone(self.pipeline.layout()),
+                0,
+                self.descriptor_set(texture),
+            )
+            .push_constants(
+                Arc::clone(self.pipeline.layout()),
+                0,
+                vs::ty::Sprite {
+                    screen: viewport.dimensions,
+                    position,
+                    size,
+                    uv_min: region.min,
+                    uv_max: region.max,
+                },
+            )
+            .draw(4, 1, 0, 0)
+            .expect("Couldn't record the sprite draw call");
+    }
+
+    fn descriptor_set(&self, texture: &Arc<Texture>) -> Arc<PersistentDescriptorSet> {
+        let mut sets = self.descriptor_sets.lock().expect("The sprite descriptor sets were poisoned");
+        sets.retain(|(texture, _)| texture.strong_count() > 0);
+
+        let weak = Arc::downgrade(texture);
+        if let Some((_, set)) = sets.iter().find(|(texture, _)| texture.ptr_eq(&weak)) {
+            return Arc::clone(set);
+        }
+
+        let set = PersistentDescriptorSet::new(
+            Arc::clone(&self.pipeline.layout().set_layouts()[0]),
+            [WriteDescriptorSet::image_view_sampler(
+                0,
+                Arc::clone(&texture.view) as Arc<_>,
+                Arc::clone(&self.sampler),
+            )],
+        )
+            .expect("Couldn't create the sprite descriptor set");
+        sets.push((weak, Arc::clone(&set)));
+        set
+    }
+}
+
+/// A texture divided into a grid of frames of the same size, read row by row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpriteSheet {
+    columns: u32,
+    rows: u32,
+    frame_count: u32,
+}
+
+impl SpriteSheet {
+    /// A sheet of `frame_count` frames, on a grid of `columns` by `rows`.
+    ///
+    /// The last row may be incomplete: `frame_count` must be at least 1 and at most `columns * rows`.
+    pub fn new(columns: u32, rows: u32, frame_count: u32) -> Result<Self, SpriteError> {
+        if columns == 0 || rows == 0 {
+            return Err(SpriteError::EmptySheet);
+        }
+        // A grid too large for a u32 can hold any frame count
+        let fits = columns.checked_mul(rows).is_none_or(|cells| frame_count <= cells);
+        if frame_count == 0 || !fits {
+            return Err(SpriteError::FrameCount {
+                columns,
+                rows,
+                frame_count,
+            });
+        }
+        Ok(SpriteSheet {
+            columns,
+            rows,
+            frame_count,
+        })
+    }
+
+    pub fn frame_count(&self) -> u32 {
+        self.frame_count
+    }
+
+    /// The texture coordinates of the frame `index`.
+    pub fn frame(&self, index: u32) -> UvRect {
+        let index = index.min(self.frame_count - 1);
+        let width = 1.0 / self.columns as f32;
+        let height = 1.0 / self.rows as f32;
+        let column = (index % self.columns) as f32;
+        let row = (index / self.columns) as f32;
+
+        UvRect {
+            min: [column * width, row * height],
+            max: [(column + 1.0) * width, (row + 1.0) * height],
+        }
+    }
+}
+
+/// What a [`SpriteAnimation`] does after its last frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Playback {
+    /// Starts again from the first frame.
+    #[default]
+    Loop,
+    /// Stays on the last frame.
+    Once,
+}
+
+/// Plays the frames of a [`SpriteSheet`] over time.
+#[derive(Debug, Clone)]
+pub struct SpriteAnimation {
+    sheet: SpriteSheet,
+    durations: Vec<Duration>,
+    playback: Playback,
+    // The sum of the durations
+    cycle: Duration,
+    frame: u32,
+    // The time spent on the current frame
+    elapsed: Duration,
+}
+
+impl SpriteAnimation {
+    /// Plays all the frames of `sheet` at `fps` frames per second.
+    pub fn new(sheet: SpriteSheet, fps: f32, playback: Playback) -> Result<Self, SpriteError> {
+        // Also rejects zero, negative and NaN frame rates, whose durations aren't valid
+        let duration = Duration::try_from_secs_f32(1.0 / fps).map_err(|_| SpriteError::FrameRate(fps))?;
+        Self::with_durations(sheet, vec![duration; sheet.frame_count() as usize], playback)
+    }
+
+    /// Plays the frames of `sheet`, each for its own duration.
+    ///
+    /// Some frames may last zero seconds, but not all of them.
+    pub fn with_durations(
+        sheet: SpriteSheet,
+        durations: Vec<Duration>,
+        playback: Playback,
+    ) -> Result<Self, SpriteError> {
+        if durations.len() != sheet.frame_count() as usize {
+            return Err(SpriteError::DurationCount {
+                durations: durations.len(),
+                frames: sheet.frame_count(),
+            });
+        }
+        let cycle: Duration = durations.iter().sum();
+        if cycle.is_zero() {
+            return Err(SpriteError::ZeroDurations);
+        }
+        Ok(SpriteAnimation {
+            sheet,
+            durations,
+            playback,
+            cycle,
+            frame: 0,
+            elapsed: Duration::ZERO,
+        })
+    }
+
+    /// Advances the animation by `dt`, the time since the previous frame was rendered.
+    ///
+    /// Several frames are skipped if `dt` is longer than them.
+    pub fn update(&mut self, dt: Duration) {
+        self.elapsed += dt;
+        if self.playback == Playback::Loop {
+            // Whole cycles come back to the current frame, only the rest needs to be stepped through
+            self.elapsed = Duration::from_nanos((self.elapsed.as_nanos() % self.cycle.as_nanos()) as u64);
+        }
+
+        while self.elapsed >= self.durations[self.frame as usize] {
+            let last = self.frame + 1 == self.sheet.frame_count();
+            if last && self.playback == Playback::Once {
+                self.elapsed = self.durations[self.frame as usize];
+                return;
+            }
+
+            self.elapsed -= self.durations[self.frame as usize];
+            self.frame = if last { 0 } else { self.frame + 1 };
+        }
+    }
+
+    /// The index of the frame displayed now.
+    pub fn frame_index(&self) -> u32 {
+        self.frame
+    }
+
+    /// The texture coordinates of the frame displayed now.
+    pub fn current_frame(&self) -> UvRect {
+        self.sheet.frame(self.frame)
+    }
+
+    /// Whether a [`Playback::Once`] animation has reached the end of its last frame. Looping animations never finish.
+    pub fn is_finished(&self) -> bool {
+        self.playback == Playback::Once
+            && self.frame + 1 == self.sheet.frame_count()
+            && self.elapsed >= self.durations[self.frame as usize]
+    }
+
+    /// Starts the animation again from the first frame.
+    pub fn restart(&mut self) {
+        self.frame = 0;
+        self.elapsed = Duration::ZERO;
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SpriteError {
+    /// A sprite sheet needs at least one row and one column.
+    EmptySheet,
+    /// The frame count is zero, or more than the cells of the grid.
+    FrameCount {
+        columns: u32,
+        rows: u32,
+        frame_count: u32,
+    },
+    /// The frame rate isn't positive, or is too small for the duration of a frame to be represented.
+    FrameRate(f32),
+    /// The number of durations isn't the number of frames of the sheet.
+    DurationCount { durations: usize, frames: u32 },
+    /// All the frames last zero seconds.
+    ZeroDurations,
+}
+
+impl fmt::Display for SpriteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SpriteError::EmptySheet => write!(f, "a sprite sheet needs at least one row and one column"),
+            SpriteError::FrameCount {
+                columns,
+                rows,
+                frame_count,
+            } => write!(f, "a sprite sheet of {}×{} can't have {} frames", columns, rows, frame_count),
+            SpriteError::FrameRate(fps) => write!(f, "an animation can't play at {} frames per second", fps),
+            SpriteError::DurationCount { durations, frames } => write!(
+                f,
+                "the sprite sheet has {} frames, but {} durations were given",
+                frames, durations
+            ),
+            SpriteError::ZeroDurations => write!(f, "the frames of an animation can't all last zero seconds"),
+        }
+    }
+}
+
+impl Error for SpriteError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn try_animation(durations: &[u64], playback: Playback) -> Result<SpriteAnimation, SpriteError> {
+        let sheet = SpriteSheet::new(durations.len() as u32, 1, durations.len() as u32)?;
+        let durations = durations.iter().map(|&millis| Duration::from_millis(millis)).collect();
+        SpriteAnimation::with_durations(sheet, durations, playback)
+    }
+
+    fn animation(durations: &[u64], playback: Playback) -> SpriteAnimation {
+        try_animation(durations, playback).unwrap()
+    }
+
+    #[test]
+    fn loops() {
+        let mut animation = animation(&[100; 4], Playback::Loop);
+        animation.update(Duration::from_millis(250));
+        assert_eq!(animation.frame_index(), 2);
+
+        animation.update(Duration::from_millis(200));
+        assert_eq!(animation.frame_index(), 0);
+        assert!(!animation.is_finished());
+    }
+
+    #[test]
+    fn plays_once() {
+        let mut animation = animation(&[100; 4], Playback::Once);
+        animation.update(Duration::from_millis(350));
+        assert_eq!(animation.frame_index(), 3);
+        assert!(!animation.is_finished());
+
+        animation.update(Duration::from_millis(1000));
+        assert_eq!(animation.frame_index(), 3);
+        assert!(animation.is_finished());
+
+        animation.restart();
+        assert_eq!(animation.frame_index(), 0);
+        assert!(!animation.is_finished());
+    }
+
+    #[test]
+    fn variable_durations() {
+        let mut animation = animation(&[100, 300, 50], Playback::Loop);
+        animation.update(Duration::from_millis(150));
+        assert_eq!(animation.frame_index(), 1);
+
+        animation.update(Duration::from_millis(249));
+        assert_eq!(animation.frame_index(), 1);
+
+        animation.update(Duration::from_millis(1));
+        assert_eq!(animation.frame_index(), 2);
+
+        animation.update(Duration::from_millis(50));
+        assert_eq!(animation.frame_index(), 0);
+    }
+
+    #[test]
+    fn skips_frames_and_cycles() {
+        let mut short = animation(&[100, 300, 50], Playback::Loop);
+        short.update(Duration::from_millis(420));
+        assert_eq!(short.frame_index(), 2);
+
+        // An hour is exactly 8000 cycles, so only the remaining 150ms move the animation
+        let mut long = animation(&[100, 300, 50], Playback::Loop);
+        long.update(Duration::from_secs(3600) + Duration::from_millis(150));
+        assert_eq!(long.frame_index(), 1);
+    }
+
+    #[test]
+    fn zero_durations() {
+        let mut animation = animation(&[0, 100, 0], Playback::Loop);
+        animation.update(Duration::from_millis(50));
+        assert_eq!(animation.frame_index(), 1);
+
+        animation.update(Duration::from_millis(50));
+        assert_eq!(animation.frame_index(), 1);
+    }
+
+    #[test]
+    fn all_zero_durations() {
+        assert_eq!(try_animation(&[0, 0], Playback::Loop).unwrap_err(), SpriteError::ZeroDurations);
+    }
+
+    #[test]
+    fn invalid_sheets() {
+        assert_eq!(SpriteSheet::new(0, 4, 1), Err(SpriteError::EmptySheet));
+        assert_eq!(SpriteSheet::new(4, 0, 1), Err(SpriteError::EmptySheet));
+        assert!(matches!(SpriteSheet::new(2, 2, 0), Err(SpriteError::FrameCount { .. })));
+        assert!(matches!(SpriteSheet::new(2, 2, 5), Err(SpriteError::FrameCount { .. })));
+
+        // The grid has more cells than a u32 can count
+        assert!(SpriteSheet::new(u32::MAX, 2, u32::MAX).is_ok());
+    }
+
+    #[test]
+    fn invalid_frame_rates() {
+        let sheet = SpriteSheet::new(2, 1, 2).unwrap();
+        for fps in [0.0, -10.0, f32::NAN, 1e-40] {
+            assert!(matches!(
+                SpriteAnimation::new(sheet, fps, Playback::Loop),
+                Err(SpriteError::FrameRate(_))
+            ));
+        }
+        assert!(SpriteAnimation::new(sheet, 24.0, Playback::Loop).is_ok());
+    }
+
+    #[test]
+    fn duration_count() {
+        let sheet = SpriteSheet::new(2, 1, 2).unwrap();
+        assert_eq!(
+            SpriteAnimation::with_durations(sheet, vec![Duration::from_millis(10)], Playback::Loop).unwrap_err(),
+            SpriteError::DurationCount {
+                durations: 1,
+                frames: 2
+            }
+        );
+    }
+}
+
+#[allow(clippy::needless_question_mark)]
+mod vs {
+    vulkano_shaders::shader! {
+        ty: "vertex",
+        src: "
+#version 450
+layout(location = 0) out vec2 f_uv;
+
+layout(push_constant) uniform Sprite {
+    vec2 screen;
+    vec2 position;
+    vec2 size;
+    vec2 uv_min;
+    vec2 uv_max;
+} sprite;
+
+void main() {
+    // Corners of the quad, in triangle strip order
+    vec2 corner = vec2(gl_VertexIndex & 1, (gl_VertexIndex >> 1) & 1);
+    vec2 pixel = sprite.position + corner * sprite.size;
+
+    gl_Position = vec4(pixel / sprite.screen * 2.0 - 1.0, 0.0, 1.0);
+    f_uv = mix(sprite.uv_min, sprite.uv_max, corner);
+}"
+    }
+}
+
+#[allow(clippy::needless_question_mark)]
+mod fs {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        src: "
+#version 450
+layout(location = 0) in vec2 f_uv;
+layout(location = 0) out vec4 color;
+
+layout(set = 0, binding = 0) uniform sampler2D tex;
+
+void main() {
+    color = texture(tex, f_uv);
+}"
+    }
+}
@@ -0,0 +1,272 @@
+use std::error::Error;
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex};
+
+use log::{trace, warn};
+use vulkano::command_buffer::{AutoCommandBufferBuilder, PrimaryAutoCommandBuffer};
+use vulkano::query::{
+    QueryControlFlags, QueryPipelineStatisticFlags, QueryPool, QueryPoolCreateInfo, QueryPoolCreationError,
+    QueryResultFlags, QueryType,
+};
+
+use crate::drawing::hardware::Hardware;
+
+/// The number of counters of [`PipelineStatistics`].
+const COUNTERS: usize = 11;
+
+/// The work done by the GPU within a [`StatisticsQuery`] region.
+///
+/// Every counter is available on devices with the `pipeline_statistics_query` feature, but the counters of
+/// geometry and tessellation shaders stay at zero unless those stages are used, which requires
+/// the `geometry_shader` and `tessellation_shader` features. Counts are approximate:
+/// implementations may, for example, count vertices shared between primitives once or several times.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PipelineStatistics {
+    pub input_assembly_vertices: u64,
+    pub input_assembly_primitives: u64,
+    pub vertex_shader_invocations: u64,
+    pub geometry_shader_invocations: u64,
+    pub geometry_shader_primitives: u64,
+    /// The primitives that reached the clipping stage.
+    pub clipping_invocations: u64,
+    /// The primitives that came out of the clipping stage, which may split them.
+    pub clipping_primitives: u64,
+    /// Compared to the area drawn, shows the overdraw of a pass.
+    pub fragment_shader_invocations: u64,
+    pub tessellation_control_shader_patches: u64,
+    pub tessellation_evaluation_shader_invocations: u64,
+    pub compute_shader_invocations: u64,
+}
+
+impl PipelineStatistics {
+    // The counters are written in the order of the bits of their flags
+    fn from_counters(counters: &[u64]) -> Self {
+        PipelineStatistics {
+            input_assembly_vertices: counters[0],
+            input_assembly_primitives: counters[1],
+            vertex_shader_invocations: counters[2],
+            geometry_shader_invocations: counters[3],
+            geometry_shader_primitives: counters[4],
+            clipping_invocations: counters[5],
+            clipping_primitives: counters[6],
+            fragment_shader_invocations: counters[7],
+            tessellation_control_shader_patches: counters[8],
+            tessellation_evaluation_shader_invocations: counters[9],
+            compute_shader_invocations: counters[10],
+        }
+    }
+}
+
+fn all_statistics() -> QueryPipelineStatisticFlags {
+    QueryPipelineStatisticFlags {
+        input_assembly_vertices: true,
+        input_assembly_primitives: true,
+        vertex_shader_invocations: true,
+        geometry_shader_invocations: true,
+        geometry_shader_primitives: true,
+        clipping_invocations: true,
+        clipping_primitives: true,
+        fragment_shader_invocations: true,
+        tessellation_control_shader_patches: true,
+        tessellation_evaluation_shader_invocations: true,
+        compute_shader_invocations: true,
+    }
+}
+
+struct State {
+    frame: usize,
+    // Whether the query of each frame was recorded since it was last reset
+    recorded: Vec<bool>,
+    last: Option<PipelineStatistics>,
+}
+
+/// Counts the work done by the GPU in a region of each frame, with pipeline statistics queries.
+///
+/// Each frame, call [`StatisticsQuery::begin_frame`] at the start of the command buffer, outside of any render pass,
+/// then wrap the draws to measure with [`StatisticsQuery::region`]. Results arrive a few frames later,
+/// once the GPU is done with them, see [`StatisticsQuery::last_frame`].
+///
+/// Requires the `pipeline_statistics_query` feature, see [`FeatureRequest`](crate::drawing::features::FeatureRequest).
+pub struct StatisticsQuery {
+    pool: Arc<QueryPool>,
+    state: Mutex<State>,
+}
+
+impl StatisticsQuery {
+    /// Keeps the queries of `frames` frames in flight, at least one.
+    pub fn new(hardware: &Hardware, frames: usize) -> Result<Self, StatisticsError> {
+        if !hardware.enabled_features().pipeline_statistics_query {
+            return Err(StatisticsError::FeatureNotEnabled);
+        }
+        if frames == 0 {
+            return Err(StatisticsError::NoFrames);
+        }
+
+        let pool = QueryPool::new(
+            Arc::clone(hardware.graphics_device()),
+            QueryPoolCreateInfo {
+                query_count: frames as u32,
+                ..QueryPoolCreateInfo::query_type(QueryType::PipelineStatistics(all_statistics()))
+            },
+        )?;
+
+        Ok(StatisticsQuery {
+            pool,
+            state: Mutex::new(State {
+                frame: 0,
+                recorded: vec![false; frames],
+                last: None,
+            }),
+        })
+    }
+
+    /// Starts a new frame: collects the results of the frame whose query is reused, and resets it.
+    ///
+    /// Must be recorded outside of a render pass.
+    pub fn begin_frame(&self, builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>) {
+        let mut state = self.lock();
+        let frame = (state.frame + 1) % state.recorded.len();
+        state.frame = frame;
+
+        if state.recorded[frame] {
+            if let Some(statistics) = self.collect(frame as u32) {
+                state.last = Some(statistics);
+            }
+        }
+        state.recorded[frame] = false;
+
+        unsafe {
+            builder
+                .reset_query_pool(Arc::clone(&self.pool), frame as u32..frame as u32 + 1)
+                .expect("Couldn't reset the statistics query");
+        }
+    }
+
+    /// Starts counting, until the returned guard is dropped.
+    ///
+    /// The guard dereferences to `builder`: record the draws to measure through it.
+    /// Only the first region of each frame is measured, and it must begin and end in the same subpass.
+    pub fn region<'a>(
+        &'a self,
+        builder: &'a mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+    ) -> StatisticsRegion<'a> {
+        let mut state = self.lock();
+        let frame = state.frame;
+        let query = if state.recorded[frame] {
+            warn!("Only the first statistics region of a frame is measured");
+            None
+        } else {
+            state.recorded[frame] = true;
+            unsafe {
+                builder
+                    .begin_query(Arc::clone(&self.pool), frame as u32, QueryControlFlags { precise: false })
+                    .expect("Couldn't begin the statistics query");
+            }
+            Some(frame as u32)
+        };
+
+        StatisticsRegion {
+            pool: &self.pool,
+            builder,
+            query,
+        }
+    }
+
+    /// The statistics of the most recent frame whose results are available.
+    pub fn last_frame(&self) -> Option<PipelineStatistics> {
+        self.lock().last
+    }
+
+    /// The statistics of `query`, if the GPU has written them.
+    fn collect(&self, query: u32) -> Option<PipelineStatistics> {
+        let mut counters = [0u64; COUNTERS];
+        let available = self
+            .pool
+            .queries_range(query..query + 1)?
+            .get_results(&mut counters, QueryResultFlags::default());
+        match available {
+            Ok(true) => Some(PipelineStatistics::from_counters(&counters)),
+            Ok(false) => {
+                trace!("The pipeline statistics of a frame aren't available yet, they are skipped");
+                None
+            }
+            Err(e) => {
+                warn!("Couldn't read the pipeline statistics: {}", e);
+                None
+            }
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().expect("The statistics query was poisoned")
+    }
+}
+
+/// A region measured by a [`StatisticsQuery`], which ends when dropped.
+pub struct StatisticsRegion<'a> {
+    pool: &'a Arc<QueryPool>,
+    builder: &'a mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+    query: Option<u32>,
+}
+
+impl Deref for StatisticsRegion<'_> {
+    type Target = AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>;
+
+    fn deref(&self) -> &Self::Target {
+        self.builder
+    }
+}
+
+impl DerefMut for StatisticsRegion<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.builder
+    }
+}
+
+impl Drop for StatisticsRegion<'_> {
+    fn drop(&mut self) {
+        if let Some(query) = self.query {
+            self.builder
+                .end_query(Arc::clone(self.pool), query)
+                .expect("Couldn't end the statistics query");
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum StatisticsError {
+    /// The `pipeline_statistics_query` feature wasn't enabled when creating the device.
+    FeatureNotEnabled,
+    /// The queries are kept for zero frames.
+    NoFrames,
+    /// Creating the query pool failed.
+    Pool(QueryPoolCreationError),
+}
+
+impl fmt::Display for StatisticsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StatisticsError::FeatureNotEnabled => {
+                write!(f, "the pipeline_statistics_query feature isn't enabled")
+            }
+            StatisticsError::NoFrames => write!(f, "the statistics must be kept for at least one frame"),
+            StatisticsError::Pool(e) => write!(f, "couldn't create the statistics query pool: {}", e),
+        }
+    }
+}
+
+impl Error for StatisticsError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            StatisticsError::Pool(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<QueryPoolCreationError> for StatisticsError {
+    fn from(e: QueryPoolCreationError) -> Self {
+        StatisticsError::Pool(e)
+    }
+}
@@ -0,0 +1,313 @@
+use std::error::Error;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::Range;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use vulkano::device::Device;
+use vulkano::format::Format;
+use vulkano::image::sys::{UnsafeImage, UnsafeImageCreateInfo};
+use vulkano::image::view::{ImageView, ImageViewCreateInfo};
+use vulkano::image::{
+    ImageAccess, ImageCreationError, ImageDescriptorLayouts, ImageDimensions, ImageInner,
+    ImageLayout, ImageUsage,
+};
+use vulkano::memory::pool::{MemoryPoolAlloc, PotentialDedicatedAllocation, StdMemoryPoolAlloc};
+use vulkano::memory::DedicatedAllocation;
+use vulkano::sync::{AccessError, Sharing};
+use vulkano::DeviceSize;
+
+use crate::drawing::hardware::Hardware;
+use crate::drawing::memory;
+use crate::drawing::memory::{AllocationError, AllocationHint};
+
+/// A 2D image compute shaders can write to, with multiple mip levels.
+///
+/// Each level is bound as its own storage image, through [`MipmappedStorageImage::level_views`]:
+/// for example, a mipmap pyramid is generated by dispatching once per level, reading the previous one.
+///
+/// Like vulkano's `StorageImage`, the image always stays in the `General` layout.
+#[derive(Debug)]
+pub struct MipmappedStorageImage {
+    image: UnsafeImage,
+    // Keeps the memory bound to the image alive
+    _memory: PotentialDedicatedAllocation<StdMemoryPoolAlloc>,
+    dimensions: ImageDimensions,
+    gpu_lock: AtomicUsize,
+}
+
+impl MipmappedStorageImage {
+    /// Creates an image of `mip_levels` levels, the first one being `dimensions` large.
+    ///
+    /// The image is shared between all the queue families of `device`.
+    pub fn new(
+        device: &Arc<Device>,
+        dimensions: [u32; 2],
+        format: Format,
+        mip_levels: u32,
+    ) -> Result<Arc<Self>, StorageImageError> {
+        Self::create(device, dimensions, format, mip_levels, AllocationHint::Auto, None)
+    }
+
+    /// Like [`MipmappedStorageImage::new`], choosing how the memory is allocated.
+    ///
+    /// With [`AllocationHint::Auto`], the image gets a dedicated allocation
+    /// if it is larger than the threshold of `hardware`.
+    pub fn with_allocation(
+        hardware: &Hardware,
+        dimensions: [u32; 2],
+        format: Format,
+        mip_levels: u32,
+        allocation: AllocationHint,
+    ) -> Result<Arc<Self>, StorageImageError> {
+        Self::create(
+            hardware.graphics_device(),
+            dimensions,
+            format,
+            mip_levels,
+            allocation,
+            hardware.dedicated_threshold(),
+        )
+    }
+
+    fn create(
+        device: &Arc<Device>,
+        dimensions: [u32; 2],
+        format: Format,
+        mip_levels: u32,
+        allocation: AllocationHint,
+        dedicated_threshold: Option<DeviceSize>,
+    ) -> Result<Arc<Self>, StorageImageError> {
+        let dimensions = ImageDimensions::Dim2d {
+            width: dimensions[0],
+            height: dimensions[1],
+            array_layers: 1,
+        };
+
+        let features = device
+            .physical_device()
+            .format_properties(format)
+            .optimal_tiling_features;
+        if !features.storage_image {
+            return Err(StorageImageError::UnsupportedFormat(format));
+        }
+
+        let max = dimensions.max_mip_levels();
+        if mip_levels == 0 || mip_levels > max {
+            return Err(StorageImageError::InvalidMipLevels {
+                requested: mip_levels,
+                max,
+            });
+        }
+
+        let queue_families: Vec<u32> = device
+            .active_queue_families()
+            .map(|family| family.id())
+            .collect();
+
+        let image = UnsafeImage::new(
+            Arc::clone(device),
+            UnsafeImageCreateInfo {
+                dimensions,
+                format: Some(format),
+                mip_levels,
+                usage: ImageUsage {
+                    storage: true,
+                    sampled: features.sampled_image,
+                    transfer_source: true,
+                    transfer_destination: true,
+                    ..ImageUsage::none()
+                },
+                sharing: if queue_families.len() >= 2 {
+                    Sharing::Concurrent(queue_families.into_iter().collect())
+                } else {
+                    Sharing::Exclusive
+                },
+                ..Default::default()
+            },
+        )?;
+
+        let requirements = image.memory_requirements();
+        let memory = memory::allocate(
+            device,
+            &requirements,
+            DedicatedAllocation::Image(&image),
+            allocation,
+            dedicated_threshold,
+        )?;
+        unsafe {
+            image
+                .bind_memory(memory.memory(), memory.offset())
+                .map_err(ImageCreationError::from)?;
+        }
+
+        Ok(Arc::new(MipmappedStorageImage {
+            image,
+            _memory: memory,
+            dimensions,
+            gpu_lock: AtomicUsize::new(0),
+        }))
+    }
+
+    /// One view per mip level, indexed by level.
+    pub fn level_views(self: &Arc<Self>) -> Vec<Arc<ImageView<Self>>> {
+        (0..self.mip_levels())
+            .map(|level| {
+                ImageView::new(
+                    Arc::clone(self),
+                    ImageViewCreateInfo {
+                        mip_levels: level..level + 1,
+                        ..ImageViewCreateInfo::from_image(self.as_ref())
+                    },
+                )
+                    .expect("Couldn't create the view of a mip level")
+            })
+            .collect()
+    }
+
+    /// The size of the mip `level`, in pixels.
+    pub fn level_dimensions(&self, level: u32) -> Option<[u32; 2]> {
+        self.dimensions
+            .mip_level_dimensions(level)
+            .map(|dimensions| dimensions.width_height())
+    }
+}
+
+unsafe impl ImageAccess for MipmappedStorageImage {
+    fn inner(&self) -> ImageInner<'_> {
+        ImageInner {
+            image: &self.image,
+            first_layer: 0,
+            num_layers: 1,
+            first_mipmap_level: 0,
+            num_mipmap_levels: self.image.mip_levels() as usize,
+        }
+    }
+
+    fn initial_layout_requirement(&self) -> ImageLayout {
+        ImageLayout::General
+    }
+
+    fn final_layout_requirement(&self) -> ImageLayout {
+        ImageLayout::General
+    }
+
+    fn descriptor_layouts(&self) -> Option<ImageDescriptorLayouts> {
+        Some(ImageDescriptorLayouts {
+            storage_image: ImageLayout::General,
+            combined_image_sampler: ImageLayout::General,
+            sampled_image: ImageLayout::General,
+            input_attachment: ImageLayout::General,
+        })
+    }
+
+    fn conflict_key(&self) -> u64 {
+        self.image.key()
+    }
+
+    fn current_mip_levels_access(&self) -> Range<u32> {
+        0..self.mip_levels()
+    }
+
+    fn current_array_layers_access(&self) -> Range<u32> {
+        0..1
+    }
+
+    fn try_gpu_lock(
+        &self,
+        _: bool,
+        _: bool,
+        expected_layout: ImageLayout,
+    ) -> Result<(), AccessError> {
+        if expected_layout != ImageLayout::General && expected_layout != ImageLayout::Undefined {
+            return Err(AccessError::UnexpectedImageLayout {
+                requested: expected_layout,
+                allowed: ImageLayout::General,
+            });
+        }
+
+        match self
+            .gpu_lock
+            .compare_exchange(0, 1, Ordering::SeqCst, Ordering::SeqCst)
+        {
+            Ok(_) => Ok(()),
+            Err(_) => Err(AccessError::AlreadyInUse),
+        }
+    }
+
+    unsafe fn increase_gpu_lock(&self) {
+        self.gpu_lock.fetch_add(1, Ordering::SeqCst);
+    }
+
+    unsafe fn unlock(&self, new_layout: Option<ImageLayout>) {
+        assert!(new_layout.is_none() || new_layout == Some(ImageLayout::General));
+        self.gpu_lock.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl PartialEq for MipmappedStorageImage {
+    fn eq(&self, other: &Self) -> bool {
+        self.inner() == other.inner()
+    }
+}
+
+impl Eq for MipmappedStorageImage {}
+
+impl Hash for MipmappedStorageImage {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.inner().hash(state);
+    }
+}
+
+#[derive(Debug)]
+pub enum StorageImageError {
+    /// The device can't use images of this format as storage images.
+    UnsupportedFormat(Format),
+    /// The image must have at least one mip level, and can't have more than its size allows.
+    InvalidMipLevels { requested: u32, max: u32 },
+    Creation(ImageCreationError),
+    /// The memory of the image couldn't be allocated.
+    Allocation(AllocationError),
+}
+
+impl fmt::Display for StorageImageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StorageImageError::UnsupportedFormat(format) => write!(
+                f,
+                "the format {:?} can't be used for storage images by this device",
+                format
+            ),
+            StorageImageError::InvalidMipLevels { requested, max } => write!(
+                f,
+                "{} mip levels were requested, but the image can only have between 1 and {}",
+                requested, max
+            ),
+            StorageImageError::Creation(e) => write!(f, "couldn't create the image: {}", e),
+            StorageImageError::Allocation(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for StorageImageError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            StorageImageError::Creation(e) => Some(e),
+            StorageImageError::Allocation(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<ImageCreationError> for StorageImageError {
+    fn from(e: ImageCreationError) -> Self {
+        StorageImageError::Creation(e)
+    }
+}
+
+impl From<AllocationError> for StorageImageError {
+    fn from(e: AllocationError) -> Self {
+        StorageImageError::Allocation(e)
+    }
+}
@@ -0,0 +1,33 @@
+use vulkano::device::physical::{PhysicalDevice, SubgroupFeatures};
+use vulkano::shader::ShaderStages;
+
+/// What a device supports for subgroup operations (wave intrinsics) in shaders.
+#[derive(Debug, Clone)]
+pub struct SubgroupProperties {
+    /// The number of invocations in a subgroup, unless a specific size is requested.
+    pub size: u32,
+    /// The smallest size a subgroup can be given. Same as `size` if the device can't control it.
+    pub min_size: u32,
+    /// The largest size a subgroup can be given. Same as `size` if the device can't control it.
+    pub max_size: u32,
+    /// The groups of subgroup operations shaders can use.
+    pub supported_operations: SubgroupFeatures,
+    /// The shader stages subgroup operations can be used in.
+    pub supported_stages: ShaderStages,
+}
+
+impl SubgroupProperties {
+    /// Reads the subgroup properties of `physical`, which are only reported since Vulkan 1.1.
+    pub(crate) fn new(physical: &PhysicalDevice) -> Option<Self> {
+        let properties = physical.properties();
+
+        let size = properties.subgroup_size?;
+        Some(SubgroupProperties {
+            size,
+            min_size: properties.min_subgroup_size.unwrap_or(size),
+            max_size: properties.max_subgroup_size.unwrap_or(size),
+            supported_operations: properties.subgroup_supported_operations?,
+            supported_stages: properties.subgroup_supported_stages?,
+        })
+    }
+}
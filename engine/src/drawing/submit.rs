@@ -0,0 +1,130 @@
+use std::error::Error;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+use log::warn;
+use vulkano::command_buffer::submit::{SubmitCommandBufferBuilder, SubmitCommandBufferError};
+use vulkano::command_buffer::{PrimaryAutoCommandBuffer, PrimaryCommandBuffer};
+use vulkano::device::Queue;
+use vulkano::sync::{Fence, FenceWaitError, PipelineStages, Semaphore};
+use vulkano::OomError;
+
+/// Work submitted with [`submit_to`], and the synchronization primitives it uses.
+///
+/// The command buffer and the semaphores are kept alive until the submission is dropped;
+/// dropping it before the GPU is done blocks until it is.
+pub struct Submission {
+    fence: Fence,
+    _command_buffer: PrimaryAutoCommandBuffer,
+    _semaphores: Vec<Arc<Semaphore>>,
+}
+
+impl Submission {
+    /// The fence signaled when the command buffer has finished executing.
+    pub fn fence(&self) -> &Fence {
+        &self.fence
+    }
+
+    pub fn is_done(&self) -> Result<bool, OomError> {
+        self.fence.ready()
+    }
+
+    pub fn wait(&self, timeout: Option<Duration>) -> Result<(), FenceWaitError> {
+        self.fence.wait(timeout)
+    }
+}
+
+impl Drop for Submission {
+    fn drop(&mut self) {
+        // The GPU may still be using the command buffer and the semaphores
+        if let Err(e) = self.fence.wait(None) {
+            warn!("Couldn't wait for a manual submission: {}", e);
+        }
+    }
+}
+
+/// Submits `command_buffer` to `queue`, bypassing the automatic synchronization of the engine.
+///
+/// The queue waits for each semaphore of `waits` before executing the given stages,
+/// and signals `signals` once the command buffer is done.
+///
+/// # Safety
+///
+/// Vulkano doesn't track this submission:
+/// - each semaphore in `waits` must have a pending signal operation, submitted before or along with this one,
+///   and no other submission may wait for that same signal;
+/// - each semaphore in `signals` must be unsignaled, without a pending signal operation;
+/// - the resources used by `command_buffer` must not be accessed concurrently by other submissions,
+///   unless synchronized by the semaphores;
+/// - `command_buffer` must not be executing already.
+pub unsafe fn submit_to(
+    queue: &Arc<Queue>,
+    command_buffer: PrimaryAutoCommandBuffer,
+    waits: &[(Arc<Semaphore>, PipelineStages)],
+    signals: &[Arc<Semaphore>],
+) -> Result<Submission, SubmitError> {
+    let fence = Fence::from_pool(Arc::clone(queue.device()))?;
+
+    {
+        let mut builder = SubmitCommandBufferBuilder::new();
+        for (semaphore, stages) in waits {
+            builder.add_wait_semaphore(semaphore, *stages);
+        }
+        builder.add_command_buffer(command_buffer.inner());
+        for semaphore in signals {
+            builder.add_signal_semaphore(semaphore);
+        }
+        builder.set_fence_signal(&fence);
+        builder.submit(queue)?;
+    }
+
+    let semaphores = waits
+        .iter()
+        .map(|(semaphore, _)| semaphore)
+        .chain(signals)
+        .cloned()
+        .collect();
+
+    Ok(Submission {
+        fence,
+        _command_buffer: command_buffer,
+        _semaphores: semaphores,
+    })
+}
+
+#[derive(Debug)]
+pub enum SubmitError {
+    Fence(OomError),
+    Submit(SubmitCommandBufferError),
+}
+
+impl fmt::Display for SubmitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SubmitError::Fence(e) => write!(f, "couldn't create the fence: {}", e),
+            SubmitError::Submit(e) => write!(f, "couldn't submit the command buffer: {}", e),
+        }
+    }
+}
+
+impl Error for SubmitError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SubmitError::Fence(e) => Some(e),
+            SubmitError::Submit(e) => Some(e),
+        }
+    }
+}
+
+impl From<OomError> for SubmitError {
+    fn from(e: OomError) -> Self {
+        SubmitError::Fence(e)
+    }
+}
+
+impl From<SubmitCommandBufferError> for SubmitError {
+    fn from(e: SubmitCommandBufferError) -> Self {
+        SubmitError::Submit(e)
+    }
+}
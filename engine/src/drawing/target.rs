@@ -0,0 +1,222 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use vulkano::format::Format;
+use vulkano::image::{AttachmentImage, ImageAccess, ImageUsage};
+use vulkano::image::view::{ImageView, ImageViewAbstract};
+use vulkano::render_pass::{Framebuffer, FramebufferCreateInfo, RenderPass};
+use vulkano::OomError;
+
+use crate::drawing::hardware::Hardware;
+
+/// The format used by [`RenderTarget::hdr`]: enough range and precision for linear HDR colors.
+pub const HDR_FORMAT: Format = Format::R16G16B16A16_SFLOAT;
+
+/// An offscreen color image that can be rendered into, then sampled by a later pass.
+pub struct RenderTarget {
+    image: Arc<AttachmentImage>,
+    view: Arc<ImageView<AttachmentImage>>,
+}
+
+impl RenderTarget {
+    pub fn new(hardware: &Hardware, dimensions: [u32; 2], format: Format) -> Self {
+        let image = AttachmentImage::with_usage(
+            Arc::clone(hardware.graphics_device()),
+            dimensions,
+            format,
+            ImageUsage {
+                sampled: true,
+                transfer_source: true,
+                ..ImageUsage::none()
+            },
+        )
+            .expect("Couldn't create the render target image");
+
+        let view = ImageView::new_default(Arc::clone(&image))
+            .expect("Couldn't create the render target view");
+
+        RenderTarget { image, view }
+    }
+
+    /// Creates a render target storing linear HDR colors, see [`HDR_FORMAT`].
+    pub fn hdr(hardware: &Hardware, dimensions: [u32; 2]) -> Self {
+        Self::new(hardware, dimensions, HDR_FORMAT)
+    }
+
+    pub fn image(&self) -> &Arc<AttachmentImage> {
+        &self.image
+    }
+
+    pub fn view(&self) -> &Arc<ImageView<AttachmentImage>> {
+        &self.view
+    }
+
+    pub fn format(&self) -> Format {
+        self.view.format().unwrap()
+    }
+
+    pub fn dimensions(&self) -> [u32; 2] {
+        self.image.dimensions().width_height()
+    }
+
+    /// Creates a framebuffer rendering into this target, for a render pass with a single color attachment.
+    pub fn framebuffer(&self, render_pass: Arc<RenderPass>) -> Arc<Framebuffer> {
+        Framebuffer::new(
+            render_pass,
+            FramebufferCreateInfo {
+                attachments: vec![Arc::clone(&self.view) as _],
+                ..Default::default()
+            },
+        )
+            .expect("Couldn't create the render target framebuffer")
+    }
+}
+
+struct ManagedTarget {
+    target: Arc<RenderTarget>,
+    // The framebuffers rendering into the target, with their render pass
+    framebuffers: Vec<(Arc<RenderPass>, Arc<Framebuffer>)>,
+}
+
+/// Render targets owned by the engine, referenced by name.
+///
+/// Each target can be resized independently of the window and of the other targets,
+/// for example to tune the quality of a reflection buffer.
+pub struct RenderTargets {
+    hardware: Arc<Hardware>,
+    targets: Mutex<HashMap<String, ManagedTarget>>,
+}
+
+impl RenderTargets {
+    pub(crate) fn new(hardware: Arc<Hardware>) -> Self {
+        RenderTargets {
+            hardware,
+            targets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Creates a target called `name`, replacing any target with the same name.
+    pub fn add(&self, name: &str, dimensions: [u32; 2], format: Format) -> Arc<RenderTarget> {
+        let target = Arc::new(RenderTarget::new(&self.hardware, dimensions, format));
+        self.lock().insert(
+            name.to_owned(),
+            ManagedTarget {
+                target: Arc::clone(&target),
+                framebuffers: Vec::new(),
+            },
+        );
+        target
+    }
+
+    pub fn get(&self, name: &str) -> Option<Arc<RenderTarget>> {
+        self.lock()
+            .get(name)
+            .map(|managed| Arc::clone(&managed.target))
+    }
+
+    /// A framebuffer rendering into the target `name` with `render_pass`, see [`RenderTarget::framebuffer`].
+    ///
+    /// The framebuffer is created once per render pass, and recreated when the target is resized.
+    pub fn framebuffer(&self, name: &str, render_pass: &Arc<RenderPass>) -> Option<Arc<Framebuffer>> {
+        let mut targets = self.lock();
+        let managed = targets.get_mut(name)?;
+
+        let existing = managed
+            .framebuffers
+            .iter()
+            .find(|(pass, _)| Arc::ptr_eq(pass, render_pass));
+        if let Some((_, framebuffer)) = existing {
+            return Some(Arc::clone(framebuffer));
+        }
+
+        let framebuffer = managed.target.framebuffer(Arc::clone(render_pass));
+        managed
+            .framebuffers
+            .push((Arc::clone(render_pass), Arc::clone(&framebuffer)));
+        Some(framebuffer)
+    }
+
+    /// Reallocates the target `name` with new dimensions, keeping its format, and recreates its framebuffers.
+    ///
+    /// Waits for the GPU to be idle first, since previous frames may still be using the target.
+    /// The target and framebuffers obtained before the resize keep the old dimensions.
+    pub fn resize(&self, name: &str, dimensions: [u32; 2]) -> Result<Arc<RenderTarget>, TargetError> {
+        let properties = self.hardware.graphics_device().physical_device().properties();
+        let max = [
+            properties.max_framebuffer_width.min(properties.max_image_dimension2_d),
+            properties.max_framebuffer_height.min(properties.max_image_dimension2_d),
+        ];
+        if dimensions[0] > max[0] || dimensions[1] > max[1] {
+            return Err(TargetError::TooLarge { dimensions, max });
+        }
+
+        let mut targets = self.lock();
+        let managed = targets
+            .get_mut(name)
+            .ok_or_else(|| TargetError::UnknownTarget(name.to_owned()))?;
+
+        self.hardware.graphics_queue().wait()?;
+        self.hardware.transfer_queue().wait()?;
+
+        let target = Arc::new(RenderTarget::new(
+            &self.hardware,
+            dimensions,
+            managed.target.format(),
+        ));
+        for (render_pass, framebuffer) in &mut managed.framebuffers {
+            *framebuffer = target.framebuffer(Arc::clone(render_pass));
+        }
+        managed.target = Arc::clone(&target);
+
+        Ok(target)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, ManagedTarget>> {
+        self.targets
+            .lock()
+            .expect("The render targets were poisoned")
+    }
+}
+
+#[derive(Debug)]
+pub enum TargetError {
+    UnknownTarget(String),
+    /// The dimensions exceed the largest framebuffer the device supports.
+    TooLarge {
+        dimensions: [u32; 2],
+        max: [u32; 2],
+    },
+    /// Couldn't wait for the GPU to stop using the target.
+    Wait(OomError),
+}
+
+impl fmt::Display for TargetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TargetError::UnknownTarget(name) => write!(f, "there is no render target called '{}'", name),
+            TargetError::TooLarge { dimensions, max } => write!(
+                f,
+                "the render target can't be {}×{}, the device supports at most {}×{}",
+                dimensions[0], dimensions[1], max[0], max[1]
+            ),
+            TargetError::Wait(e) => write!(f, "couldn't wait for the GPU to be idle: {}", e),
+        }
+    }
+}
+
+impl Error for TargetError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            TargetError::Wait(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<OomError> for TargetError {
+    fn from(e: OomError) -> Self {
+        TargetError::Wait(e)
+    }
+}
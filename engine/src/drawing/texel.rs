@@ -0,0 +1,81 @@
+use std::error::Error;
+use std::fmt;
+use std::sync::Arc;
+
+use vulkano::buffer::view::{BufferView, BufferViewCreateInfo, BufferViewCreationError};
+use vulkano::buffer::BufferAccess;
+use vulkano::format::Format;
+
+/// Creates a view reading the texels of `buffer` in `format`, to bind as a texel buffer
+/// (`samplerBuffer` or `imageBuffer` in GLSL).
+///
+/// The buffer must have the `uniform_texel_buffer` or `storage_texel_buffer` usage,
+/// and `format` must support the usages of the buffer.
+pub fn texel_buffer_view<B>(buffer: Arc<B>, format: Format) -> Result<Arc<BufferView<B>>, TexelBufferError>
+    where
+        B: BufferAccess + ?Sized,
+{
+    let usage = buffer.inner().buffer.usage();
+    let features = buffer
+        .device()
+        .physical_device()
+        .format_properties(format)
+        .buffer_features;
+
+    if usage.uniform_texel_buffer && !features.uniform_texel_buffer {
+        return Err(TexelBufferError::UnsupportedFormat {
+            format,
+            storage: false,
+        });
+    }
+    if usage.storage_texel_buffer && !features.storage_texel_buffer {
+        return Err(TexelBufferError::UnsupportedFormat {
+            format,
+            storage: true,
+        });
+    }
+
+    Ok(BufferView::new(
+        buffer,
+        BufferViewCreateInfo {
+            format: Some(format),
+            ..Default::default()
+        },
+    )?)
+}
+
+#[derive(Debug)]
+pub enum TexelBufferError {
+    /// The device can't read texels of this format from uniform texel buffers, or storage texel buffers if `storage`.
+    UnsupportedFormat { format: Format, storage: bool },
+    Creation(BufferViewCreationError),
+}
+
+impl fmt::Display for TexelBufferError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TexelBufferError::UnsupportedFormat { format, storage } => write!(
+                f,
+                "the format {:?} can't be used in {} texel buffers",
+                format,
+                if *storage { "storage" } else { "uniform" }
+            ),
+            TexelBufferError::Creation(e) => write!(f, "couldn't create the buffer view: {}", e),
+        }
+    }
+}
+
+impl Error for TexelBufferError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            TexelBufferError::Creation(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<BufferViewCreationError> for TexelBufferError {
+    fn from(e: BufferViewCreationError) -> Self {
+        TexelBufferError::Creation(e)
+    }
+}
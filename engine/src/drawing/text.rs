@@ -0,0 +1,428 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use ab_glyph::{Font, FontVec, InvalidFont, PxScale, ScaleFont};
+use bytemuck::{Pod, Zeroable};
+use log::{debug, trace};
+use vulkano::buffer::CpuBufferPool;
+use vulkano::command_buffer::{AutoCommandBufferBuilder, PrimaryAutoCommandBuffer};
+use vulkano::descriptor_set::{PersistentDescriptorSet, WriteDescriptorSet};
+use vulkano::format::Format;
+use vulkano::image::{ImageDimensions, ImmutableImage, MipmapsCount};
+use vulkano::image::view::ImageView;
+use vulkano::pipeline::{GraphicsPipeline, Pipeline, PipelineBindPoint};
+use vulkano::pipeline::graphics::color_blend::ColorBlendState;
+use vulkano::pipeline::graphics::input_assembly::{InputAssemblyState, PrimitiveTopology};
+use vulkano::pipeline::graphics::vertex_input::BuffersDefinition;
+use vulkano::pipeline::graphics::viewport::{Viewport, ViewportState};
+use vulkano::render_pass::Subpass;
+use vulkano::sampler::{Filter, Sampler, SamplerAddressMode, SamplerCreateInfo};
+use vulkano::sync::GpuFuture;
+
+use crate::drawing::hardware::Hardware;
+
+/// Width of the glyph atlas, in pixels, unless a glyph is wider. Its height depends on the number and size of glyphs.
+const ATLAS_WIDTH: u32 = 512;
+
+/// Space left between two glyphs of the atlas, so linear filtering doesn't bleed into the neighbours.
+const ATLAS_PADDING: u32 = 1;
+
+/// A single glyph, drawn as one instance of a textured quad.
+#[repr(C)]
+#[derive(Default, Copy, Clone, Pod, Zeroable)]
+pub struct GlyphInstance {
+    /// Top-left corner of the quad, in pixels.
+    pub position: [f32; 2],
+    /// Size of the quad, in pixels.
+    pub size: [f32; 2],
+    pub uv_min: [f32; 2],
+    pub uv_max: [f32; 2],
+    pub color: [f32; 4],
+}
+
+vulkano::impl_vertex!(GlyphInstance, position, size, uv_min, uv_max, color);
+
+/// A piece of text to display.
+pub struct Text<'a> {
+    pub text: &'a str,
+    /// Top-left corner of the first line, in pixels.
+    pub position: [f32; 2],
+    pub color: [f32; 4],
+}
+
+/// Location of a glyph in the atlas, and how to place it relatively to the pen.
+#[derive(Clone, Copy)]
+struct GlyphInfo {
+    uv_min: [f32; 2],
+    uv_max: [f32; 2],
+    offset: [f32; 2],
+    size: [f32; 2],
+    advance: f32,
+}
+
+/// Renders text from a glyph atlas, with a single instanced draw call per [`TextRenderer::draw`].
+///
+/// Glyphs are rasterized once, at creation, at a single pixel height. Characters missing from the atlas are skipped.
+pub struct TextRenderer {
+    pipeline: Arc<GraphicsPipeline>,
+    descriptor_set: Arc<PersistentDescriptorSet>,
+    instances: CpuBufferPool<GlyphInstance>,
+    glyphs: HashMap<char, GlyphInfo>,
+    line_height: f32,
+    ascent: f32,
+}
+
+impl TextRenderer {
+    /// Rasterizes the printable ASCII characters of the TrueType/OpenType `font` at `pixel_height`.
+    ///
+    /// `subpass` is the one [`TextRenderer::draw`] will be called from.
+    pub fn new(
+        hardware: &Hardware,
+        subpass: Subpass,
+        font: Vec<u8>,
+        pixel_height: f32,
+    ) -> Result<Self, InvalidFont> {
+        Self::with_characters(hardware, subpass, font, pixel_height, (' '..='~').collect())
+    }
+
+    /// Same as [`TextRenderer::new`], but rasterizes `characters` instead of the printable ASCII characters.
+    pub fn with_characters(
+        hardware: &Hardware,
+        subpass: Subpass,
+        font: Vec<u8>,
+        pixel_height: f32,
+        characters: Vec<char>,
+    ) -> Result<Self, InvalidFont> {
+        let device = hardware.graphics_device();
+        let font = FontVec::try_from_vec(font)?;
+        let font = font.as_scaled(PxScale::from(pixel_height));
+
+        //region Pack the glyphs in rows
+        debug!("Rasterizing {} glyphs…", characters.len());
+        let outlines: Vec<_> = characters
+            .into_iter()
+            .map(|c| {
+                let id = font.glyph_id(c);
+                let outline = font.outline_glyph(id.with_scale(pixel_height));
+                let size = match &outline {
+                    Some(outline) => {
+                        let bounds = outline.px_bounds();
+                        [bounds.width() as u32, bounds.height() as u32]
+                    }
+                    None => [0, 0],
+                };
+                (c, outline, font.h_advance(id), size)
+            })
+            .collect();
+        let sizes: Vec<[u32; 2]> = outlines.iter().map(|&(_, _, _, size)| size).collect();
+        let layout = pack(&sizes);
+        let (atlas_width, atlas_height) = (layout.width, layout.height);
+        trace!("Glyph atlas: {}×{}", atlas_width, atlas_height);
+        //endregion
+
+        //region Rasterize
+        let mut pixels = vec![0u8; (atlas_width * atlas_height) as usize];
+        let mut glyphs = HashMap::with_capacity(outlines.len());
+        for ((c, outline, advance, [width, height]), [x, y]) in outlines.into_iter().zip(layout.positions) {
+            let mut info = GlyphInfo {
+                uv_min: [0.0; 2],
+                uv_max: [0.0; 2],
+                offset: [0.0; 2],
+                size: [0.0; 2],
+                advance,
+            };
+
+            if let Some(outline) = outline {
+                outline.draw(|gx, gy, coverage| {
+                    if gx < width && gy < height {
+                        let index = (y + gy) * atlas_width + x + gx;
+                        pixels[index as usize] = (coverage * 255.0) as u8;
+                    }
+                });
+
+                let bounds = outline.px_bounds();
+                info.offset = [bounds.min.x, bounds.min.y];
+                info.size = [width as f32, height as f32];
+                info.uv_min = [
+                    x as f32 / atlas_width as f32,
+                    y as f32 / atlas_height as f32,
+                ];
+                info.uv_max = [
+                    (x + width) as f32 / atlas_width as f32,
+                    (y + height) as f32 / atlas_height as f32,
+                ];
+            }
+
+            glyphs.insert(c, info);
+        }
+        //endregion
+
+        let (atlas, upload) = ImmutableImage::from_iter(
+            pixels,
+            ImageDimensions::Dim2d {
+                width: atlas_width,
+                height: atlas_height,
+                array_layers: 1,
+            },
+            MipmapsCount::One,
+            Format::R8_UNORM,
+            Arc::clone(hardware.transfer_queue()),
+        )
+            .expect("Couldn't create the glyph atlas");
+        upload
+            .then_signal_fence_and_flush()
+            .expect("Couldn't upload the glyph atlas")
+            .wait(None)
+            .expect("Couldn't upload the glyph atlas");
+
+        let vs = vs::load(Arc::clone(device)).expect("Couldn't load the text vertex shader");
+        let fs = fs::load(Arc::clone(device)).expect("Couldn't load the text fragment shader");
+        let pipeline = GraphicsPipeline::start()
+            .vertex_input_state(BuffersDefinition::new().instance::<GlyphInstance>())
+            .vertex_shader(vs.entry_point("main").unwrap(), ())
+            .input_assembly_state(
+                InputAssemblyState::new().topology(PrimitiveTopology::TriangleStrip),
+            )
+            .viewport_state(ViewportState::viewport_dynamic_scissor_irrelevant())
+            .fragment_shader(fs.entry_point("main").unwrap(), ())
+            .color_blend_state(ColorBlendState::new(subpass.num_color_attachments()).blend_alpha())
+            .render_pass(subpass)
+            .build(Arc::clone(device))
+            .expect("Couldn't create the text pipeline");
+
+        let sampler = Sampler::new(
+            Arc::clone(device),
+            SamplerCreateInfo {
+                mag_filter: Filter::Linear,
+                min_filter: Filter::Linear,
+                address_mode: [SamplerAddressMode::ClampToEdge; 3],
+                ..Default::default()
+            },
+        )
+            .expect("Couldn't create the glyph atlas sampler");
+
+        let descriptor_set = PersistentDescriptorSet::new(
+            Arc::clone(&pipeline.layout().set_layouts()[0]),
+            [WriteDescriptorSet::image_view_sampler(
+                0,
+                ImageView::new_default(atlas).unwrap(),
+                sampler,
+            )],
+        )
+            .expect("Couldn't create the text descriptor set");
+
+        Ok(TextRenderer {
+            pipeline,
+            descriptor_set,
+            instances: CpuBufferPool::vertex_buffer(Arc::clone(device)),
+            glyphs,
+            line_height: font.height() + font.line_gap(),
+            ascent: font.ascent(),
+        })
+    }
+
+    /// Lays out `texts` into one instance per visible glyph.
+    pub fn layout(&self, texts: &[Text]) -> Vec<GlyphInstance> {
+        let mut instances = Vec::new();
+
+        for text in texts {
+            let [mut x, mut y] = [text.position[0], text.position[1] + self.ascent];
+
+            for c in text.text.chars() {
+                if c == '\n' {
+                    x = text.position[0];
+                    y += self.line_height;
+                    continue;
+                }
+
+                let glyph = match self.glyphs.get(&c) {
+                    Some(glyph) => glyph,
+                    None => continue,
+                };
+
+                if glyph.size[0] > 0.0 && glyph.size[1] > 0.0 {
+                    instances.push(GlyphInstance {
+                        position: [x + glyph.offset[0], y + glyph.offset[1]],
+                        size: glyph.size,
+                        uv_min: glyph.uv_min,
+                        uv_max: glyph.uv_max,
+                        color: text.color,
+                    });
+                }
+
+                x += glyph.advance;
+            }
+        }
+
+        instances
+    }
+
+    /// Records the drawing of `texts`, using a single instanced draw call.
+    ///
+    /// Must be called inside the subpass given to [`TextRenderer::new`].
+    /// The instance buffer is taken from a ring of buffers that grows as needed, so it is safe to call this every frame.
+    pub fn draw(
+        &self,
+        builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        viewport: &Viewport,
+        texts: &[Text],
+    ) {
+        let instances = self.layout(texts);
+        if instances.is_empty() {
+            return;
+        }
+
+        let count = instances.len() as u32;
+        let instances = self
+            .instances
+            .chunk(instances)
+            .expect("Couldn't allocate the glyph instance buffer");
+
+        builder
+            .set_viewport(0, [viewport.clone()])
+            .bind_pipeline_graphics(Arc::clone(&self.pipeline))
+            .bind_descriptor_sets(
+                PipelineBindPoint::Graphics,
+                Arc::clone(self.pipeline.layout()),
+                0,
+                Arc::clone(&self.descriptor_set),
+            )
+            .push_constants(
+                Arc::clone(self.pipeline.layout()),
+                0,
+                vs::ty::Screen {
+                    size: viewport.dimensions,
+                },
+            )
+            .bind_vertex_buffers(0, instances)
+            .draw(4, count, 0, 0)
+            .expect("Couldn't record the text draw call");
+    }
+}
+
+/// Where the glyphs are placed in the atlas.
+struct AtlasLayout {
+    width: u32,
+    height: u32,
+    /// The top-left corner of each glyph, in pixels.
+    positions: Vec<[u32; 2]>,
+}
+
+/// Packs glyphs of `sizes` in rows, left to right.
+///
+/// The atlas is [`ATLAS_WIDTH`] wide, or the next power of two fitting the widest glyph, and its height is
+/// a power of two.
+fn pack(sizes: &[[u32; 2]]) -> AtlasLayout {
+    let widest = sizes.iter().map(|&[width, _]| width).max().unwrap_or(0);
+    let width = ATLAS_WIDTH.max((widest + 2 * ATLAS_PADDING).next_power_of_two());
+
+    let mut positions = Vec::with_capacity(sizes.len());
+    let (mut x, mut y, mut row_height) = (ATLAS_PADDING, ATLAS_PADDING, 0);
+    for &[glyph_width, glyph_height] in sizes {
+        if x + glyph_width + ATLAS_PADDING > width {
+            x = ATLAS_PADDING;
+            y += row_height + ATLAS_PADDING;
+            row_height = 0;
+        }
+
+        positions.push([x, y]);
+        x += glyph_width + ATLAS_PADDING;
+        row_height = row_height.max(glyph_height);
+    }
+
+    AtlasLayout {
+        width,
+        height: (y + row_height + ATLAS_PADDING).next_power_of_two(),
+        positions,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Every glyph lies inside the atlas, padded from its edges.
+    fn assert_inside(layout: &AtlasLayout, sizes: &[[u32; 2]]) {
+        for (&[x, y], &[width, height]) in layout.positions.iter().zip(sizes) {
+            assert!(x >= ATLAS_PADDING && x + width + ATLAS_PADDING <= layout.width);
+            assert!(y >= ATLAS_PADDING && y + height + ATLAS_PADDING <= layout.height);
+        }
+    }
+
+    #[test]
+    fn packs_rows() {
+        let sizes = [[200, 10], [200, 20], [200, 15]];
+        let layout = pack(&sizes);
+
+        assert_eq!(layout.width, ATLAS_WIDTH);
+        assert_eq!(layout.positions, vec![[1, 1], [202, 1], [1, 22]]);
+        assert_eq!(layout.height, 64);
+        assert_inside(&layout, &sizes);
+    }
+
+    #[test]
+    fn widens_for_oversized_glyphs() {
+        let sizes = [[20, 20], [600, 300], [20, 20]];
+        let layout = pack(&sizes);
+
+        assert_eq!(layout.width, 1024);
+        assert_inside(&layout, &sizes);
+    }
+
+    #[test]
+    fn empty() {
+        let layout = pack(&[]);
+
+        assert_eq!(layout.width, ATLAS_WIDTH);
+        assert!(layout.positions.is_empty());
+    }
+}
+
+#[allow(clippy::needless_question_mark)]
+mod vs {
+    vulkano_shaders::shader! {
+        ty: "vertex",
+        src: "
+#version 450
+layout(location = 0) in vec2 position;
+layout(location = 1) in vec2 size;
+layout(location = 2) in vec2 uv_min;
+layout(location = 3) in vec2 uv_max;
+layout(location = 4) in vec4 color;
+
+layout(location = 0) out vec2 f_uv;
+layout(location = 1) out vec4 f_color;
+
+layout(push_constant) uniform Screen {
+    vec2 size;
+} screen;
+
+void main() {
+    // Corners of the quad, in triangle strip order
+    vec2 corner = vec2(gl_VertexIndex & 1, (gl_VertexIndex >> 1) & 1);
+    vec2 pixel = position + corner * size;
+
+    gl_Position = vec4(pixel / screen.size * 2.0 - 1.0, 0.0, 1.0);
+    f_uv = mix(uv_min, uv_max, corner);
+    f_color = color;
+}"
+    }
+}
+
+#[allow(clippy::needless_question_mark)]
+mod fs {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        src: "
+#version 450
+layout(location = 0) in vec2 f_uv;
+layout(location = 1) in vec4 f_color;
+layout(location = 0) out vec4 color;
+
+layout(set = 0, binding = 0) uniform sampler2D atlas;
+
+void main() {
+    color = vec4(f_color.rgb, f_color.a * texture(atlas, f_uv).r);
+}"
+    }
+}
@@ -0,0 +1,220 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use vulkano::command_buffer::{AutoCommandBufferBuilder, PrimaryAutoCommandBuffer, SubpassContents};
+use vulkano::descriptor_set::{PersistentDescriptorSet, WriteDescriptorSet};
+use vulkano::format::{ClearValue, Format, NumericType};
+use vulkano::pipeline::{GraphicsPipeline, Pipeline, PipelineBindPoint};
+use vulkano::pipeline::graphics::viewport::Viewport;
+use vulkano::render_pass::{Framebuffer, RenderPass, Subpass};
+use vulkano::sampler::{Filter, Sampler, SamplerAddressMode, SamplerCreateInfo};
+
+use crate::drawing::fullscreen;
+use crate::drawing::hardware::Hardware;
+use crate::drawing::target::RenderTarget;
+
+/// The curve used to map linear HDR colors to the displayable range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToneMapOperator {
+    /// `color / (1 + color)`: simple, never clips, but desaturates highlights.
+    Reinhard,
+    /// Krzysztof Narkowicz's fit of the ACES filmic curve: more contrast, film-like highlights.
+    Aces,
+}
+
+impl ToneMapOperator {
+    fn id(self) -> u32 {
+        match self {
+            ToneMapOperator::Reinhard => 0,
+            ToneMapOperator::Aces => 1,
+        }
+    }
+
+    fn from_id(id: u32) -> Self {
+        match id {
+            0 => ToneMapOperator::Reinhard,
+            _ => ToneMapOperator::Aces,
+        }
+    }
+}
+
+/// Fullscreen pass mapping a linear HDR [`RenderTarget`] to the screen.
+///
+/// The operator and the exposure can be changed at any time, including from within the draw closure:
+/// they are sent as push constants each time the pass is drawn.
+pub struct ToneMap {
+    render_pass: Arc<RenderPass>,
+    pipeline: Arc<GraphicsPipeline>,
+    descriptor_set: Arc<PersistentDescriptorSet>,
+    operator: AtomicU32,
+    exposure: AtomicU32,
+    inverse_gamma: f32,
+}
+
+impl ToneMap {
+    /// Prepares a tone-mapping pass reading from `source` and writing to images of `output_format`.
+    pub fn new(
+        hardware: &Hardware,
+        output_format: Format,
+        source: &RenderTarget,
+        operator: ToneMapOperator,
+        exposure: f32,
+    ) -> Self {
+        let device = hardware.graphics_device();
+
+        let render_pass = vulkano::single_pass_renderpass!(
+            Arc::clone(device),
+            attachments: {
+                color: {
+                    load: DontCare,
+                    store: Store,
+                    format: output_format,
+                    samples: 1,
+                }
+            },
+            pass: {
+                color: [color],
+                depth_stencil: {}
+            }
+        )
+            .expect("Couldn't create the tone-mapping render pass");
+
+        let fs = fs::load(Arc::clone(device)).expect("Couldn't load the tone-mapping shader");
+        let pipeline = fullscreen::pipeline(
+            device,
+            fs.entry_point("main").unwrap(),
+            Subpass::from(Arc::clone(&render_pass), 0).unwrap(),
+        );
+
+        let sampler = Sampler::new(
+            Arc::clone(device),
+            SamplerCreateInfo {
+                mag_filter: Filter::Linear,
+                min_filter: Filter::Linear,
+                address_mode: [SamplerAddressMode::ClampToEdge; 3],
+                ..Default::default()
+            },
+        )
+            .expect("Couldn't create the tone-mapping sampler");
+
+        let descriptor_set = PersistentDescriptorSet::new(
+            Arc::clone(&pipeline.layout().set_layouts()[0]),
+            [WriteDescriptorSet::image_view_sampler(
+                0,
+                Arc::clone(source.view()) as _,
+                sampler,
+            )],
+        )
+            .expect("Couldn't create the tone-mapping descriptor set");
+
+        // sRGB formats are encoded by the hardware, others need the gamma to be applied by the shader
+        let inverse_gamma = if output_format.type_color() == Some(NumericType::SRGB) {
+            1.0
+        } else {
+            1.0 / 2.2
+        };
+
+        ToneMap {
+            render_pass,
+            pipeline,
+            descriptor_set,
+            operator: AtomicU32::new(operator.id()),
+            exposure: AtomicU32::new(exposure.to_bits()),
+            inverse_gamma,
+        }
+    }
+
+    /// The render pass the frames given to [`ToneMap::draw`] must be compatible with.
+    ///
+    /// Pass it to [`Engine::run`](crate::drawing::engine::Engine::run) to tone-map straight to the screen.
+    pub fn render_pass(&self) -> &Arc<RenderPass> {
+        &self.render_pass
+    }
+
+    pub fn operator(&self) -> ToneMapOperator {
+        ToneMapOperator::from_id(self.operator.load(Ordering::Relaxed))
+    }
+
+    pub fn set_operator(&self, operator: ToneMapOperator) {
+        self.operator.store(operator.id(), Ordering::Relaxed);
+    }
+
+    pub fn exposure(&self) -> f32 {
+        f32::from_bits(self.exposure.load(Ordering::Relaxed))
+    }
+
+    pub fn set_exposure(&self, exposure: f32) {
+        self.exposure.store(exposure.to_bits(), Ordering::Relaxed);
+    }
+
+    /// Records the tone-mapping pass, writing the whole of `frame`.
+    pub fn draw(
+        &self,
+        builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        frame: &Arc<Framebuffer>,
+        viewport: &Viewport,
+    ) {
+        let parameters = fs::ty::Parameters {
+            exposure: self.exposure(),
+            tone_operator: self.operator().id(),
+            inverse_gamma: self.inverse_gamma,
+        };
+
+        builder
+            .begin_render_pass(Arc::clone(frame), SubpassContents::Inline, [ClearValue::None])
+            .expect("Couldn't begin the tone-mapping pass")
+            .set_viewport(0, [viewport.clone()])
+            .bind_pipeline_graphics(Arc::clone(&self.pipeline))
+            .bind_descriptor_sets(
+                PipelineBindPoint::Graphics,
+                Arc::clone(self.pipeline.layout()),
+                0,
+                Arc::clone(&self.descriptor_set),
+            )
+            .push_constants(Arc::clone(self.pipeline.layout()), 0, parameters);
+        fullscreen::draw(builder);
+        builder
+            .end_render_pass()
+            .expect("Couldn't end the tone-mapping pass");
+    }
+}
+
+#[allow(clippy::needless_question_mark)]
+mod fs {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        src: "
+#version 450
+layout(location = 0) in vec2 uv;
+layout(location = 0) out vec4 f_color;
+
+layout(set = 0, binding = 0) uniform sampler2D source;
+
+layout(push_constant) uniform Parameters {
+    float exposure;
+    uint tone_operator;
+    float inverse_gamma;
+} parameters;
+
+vec3 reinhard(vec3 color) {
+    return color / (1.0 + color);
+}
+
+// https://knarkowicz.wordpress.com/2016/01/06/aces-filmic-tone-mapping-curve/
+vec3 aces(vec3 color) {
+    return clamp((color * (2.51 * color + 0.03)) / (color * (2.43 * color + 0.59) + 0.14), 0.0, 1.0);
+}
+
+void main() {
+    vec3 color = texture(source, uv).rgb * parameters.exposure;
+
+    if (parameters.tone_operator == 0) {
+        color = reinhard(color);
+    } else {
+        color = aces(color);
+    }
+
+    f_color = vec4(pow(color, vec3(parameters.inverse_gamma)), 1.0);
+}"
+    }
+}
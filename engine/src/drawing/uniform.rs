@@ -0,0 +1,119 @@
+use std::error::Error;
+use std::fmt;
+use std::sync::Arc;
+
+use bytemuck::Pod;
+use vulkano::buffer::cpu_access::WriteLockError;
+use vulkano::buffer::{BufferUsage, CpuAccessibleBuffer};
+use vulkano::descriptor_set::WriteDescriptorSet;
+use vulkano::device::Device;
+use vulkano::memory::DeviceMemoryAllocationError;
+
+/// A uniform value updated every frame, with one buffer per swapchain image so the CPU doesn't wait for the GPU.
+///
+/// In the draw closure of [`Engine::run_indexed`](crate::drawing::engine::Engine::run_indexed),
+/// call [`UniformBuffer::update`] with the index of the image, and add the write to the descriptor set of the frame.
+pub struct UniformBuffer<T>
+    where
+        T: Pod + Send + Sync,
+{
+    buffers: Vec<Arc<CpuAccessibleBuffer<T>>>,
+    binding: u32,
+}
+
+impl<T> UniformBuffer<T>
+    where
+        T: Pod + Send + Sync,
+{
+    /// Creates one buffer per swapchain image, holding `initial`, bound at `binding` of the descriptor set.
+    ///
+    /// Fails with [`UniformError::NoImages`] if `images` is zero.
+    pub fn new(device: &Arc<Device>, images: usize, binding: u32, initial: T) -> Result<Self, UniformError> {
+        if images == 0 {
+            return Err(UniformError::NoImages);
+        }
+
+        let buffers = (0..images)
+            .map(|_| {
+                CpuAccessibleBuffer::from_data(Arc::clone(device), BufferUsage::uniform_buffer(), false, initial)
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(UniformBuffer { buffers, binding })
+    }
+
+    /// Writes `value` into the buffer of the swapchain image `image_index`, and returns the descriptor write binding it.
+    ///
+    /// Fails with [`UniformError::Locked`] if the previous frame drawn into this image is still rendering.
+    pub fn update(&self, image_index: usize, value: T) -> Result<WriteDescriptorSet, UniformError> {
+        *self.buffer(image_index)?.write()? = value;
+        self.write(image_index)
+    }
+
+    /// The descriptor write binding the buffer of `image_index`, without updating it.
+    pub fn write(&self, image_index: usize) -> Result<WriteDescriptorSet, UniformError> {
+        let buffer = self.buffer(image_index)?;
+        Ok(WriteDescriptorSet::buffer(self.binding, Arc::clone(buffer) as Arc<_>))
+    }
+
+    pub fn buffer(&self, image_index: usize) -> Result<&Arc<CpuAccessibleBuffer<T>>, UniformError> {
+        self.buffers.get(image_index).ok_or(UniformError::NoSuchImage {
+            index: image_index,
+            images: self.buffers.len(),
+        })
+    }
+
+    /// The number of buffers, one per swapchain image.
+    pub fn images(&self) -> usize {
+        self.buffers.len()
+    }
+}
+
+#[derive(Debug)]
+pub enum UniformError {
+    /// The buffer would be created for zero swapchain images.
+    NoImages,
+    /// The buffer of an image couldn't be created.
+    Creation(DeviceMemoryAllocationError),
+    /// The index is past the swapchain images the buffer was created for.
+    NoSuchImage { index: usize, images: usize },
+    /// The previous frame drawn into this image is still rendering.
+    Locked(WriteLockError),
+}
+
+impl fmt::Display for UniformError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UniformError::NoImages => write!(f, "a uniform buffer needs at least one image"),
+            UniformError::Creation(e) => write!(f, "couldn't create a uniform buffer: {}", e),
+            UniformError::NoSuchImage { index, images } => write!(
+                f,
+                "the uniform buffer has {} images, there is no image {}",
+                images, index
+            ),
+            UniformError::Locked(e) => write!(f, "couldn't write the uniform buffer: {}", e),
+        }
+    }
+}
+
+impl Error for UniformError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            UniformError::NoImages | UniformError::NoSuchImage { .. } => None,
+            UniformError::Creation(e) => Some(e),
+            UniformError::Locked(e) => Some(e),
+        }
+    }
+}
+
+impl From<WriteLockError> for UniformError {
+    fn from(e: WriteLockError) -> Self {
+        UniformError::Locked(e)
+    }
+}
+
+impl From<DeviceMemoryAllocationError> for UniformError {
+    fn from(e: DeviceMemoryAllocationError) -> Self {
+        UniformError::Creation(e)
+    }
+}
@@ -0,0 +1,191 @@
+use std::fs::File;
+use std::io;
+use std::io::{LineWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use log::{debug, error, info, warn};
+use vulkano::instance::debug::{DebugCallback, Message, MessageSeverity, MessageType};
+use vulkano::instance::{layers_list, Instance};
+
+/// The validation layer of the Vulkan SDK.
+const VALIDATION_LAYER: &str = "VK_LAYER_KHRONOS_validation";
+
+/// How severe a validation message is.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// Diagnostics of the layers themselves.
+    Verbose,
+    Info,
+    /// Likely mistakes, such as a performance pitfall.
+    #[default]
+    Warning,
+    /// Invalid usage of the API.
+    Error,
+}
+
+impl Severity {
+    fn of(severity: &MessageSeverity) -> Self {
+        if severity.error {
+            Severity::Error
+        } else if severity.warning {
+            Severity::Warning
+        } else if severity.information {
+            Severity::Info
+        } else {
+            Severity::Verbose
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Severity::Verbose => "verbose",
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        }
+    }
+}
+
+/// Enables the Khronos validation layer, and reports its messages through the `log` crate,
+/// see [`HardwareConfig::validation`](crate::drawing::hardware::HardwareConfig::validation).
+///
+/// The layer must be installed, usually with the Vulkan SDK: without it, the engine starts without validation.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ValidationConfig {
+    min_severity: Severity,
+    log_file: Option<PathBuf>,
+}
+
+impl ValidationConfig {
+    /// Reports the warnings and errors.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ignores the messages less severe than `severity`.
+    pub fn min_severity(mut self, severity: Severity) -> Self {
+        self.min_severity = severity;
+        self
+    }
+
+    /// Also writes each message to the file at `path`, which is truncated, for example to attach it to a bug report.
+    ///
+    /// Each line is a message, for example:
+    /// ```text
+    /// time=1700000000.123 severity=error type=validation id=VUID-vkCmdDraw-None-02859 message="…"
+    /// ```
+    /// The objects involved are described by the message of the layer. Lines are written as soon as
+    /// the messages arrive, so the file is complete even if the application crashes.
+    pub fn log_file<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.log_file = Some(path.as_ref().to_owned());
+        self
+    }
+}
+
+/// The layers to enable on the instance for `config`, if they are installed.
+pub(crate) fn layers(config: &ValidationConfig) -> Vec<String> {
+    let installed = match layers_list() {
+        Ok(mut layers) => layers.any(|layer| layer.name() == VALIDATION_LAYER),
+        Err(e) => {
+            warn!("Couldn't list the Vulkan layers: {}", e);
+            false
+        }
+    };
+
+    if installed {
+        debug!("Enabling {} with the severity {:?}", VALIDATION_LAYER, config.min_severity);
+        vec![VALIDATION_LAYER.to_owned()]
+    } else {
+        warn!("{} isn't installed, validation is disabled", VALIDATION_LAYER);
+        Vec::new()
+    }
+}
+
+/// Keeps reporting the validation messages while it is alive.
+pub(crate) struct Validation {
+    // The callback isn't Sync, but it is only dropped through the mutex
+    _callback: Mutex<DebugCallback>,
+}
+
+impl Validation {
+    /// Reports the messages of `instance`, which must have the `ext_debug_utils` extension enabled.
+    pub(crate) fn new(instance: &Arc<Instance>, config: &ValidationConfig) -> io::Result<Self> {
+        let file = match &config.log_file {
+            Some(path) => {
+                info!("Writing the validation messages to {}", path.display());
+                Some(Mutex::new(LineWriter::new(File::create(path)?)))
+            }
+            None => None,
+        };
+
+        let min_severity = config.min_severity;
+        let callback = DebugCallback::new(
+            instance,
+            MessageSeverity {
+                error: true,
+                warning: min_severity <= Severity::Warning,
+                information: min_severity <= Severity::Info,
+                verbose: min_severity <= Severity::Verbose,
+            },
+            MessageType::all(),
+            move |message| {
+                let severity = Severity::of(&message.severity);
+                if severity < min_severity {
+                    return;
+                }
+
+                log_message(severity, message);
+                if let Some(file) = &file {
+                    let mut file = file.lock().expect("The validation log was poisoned");
+                    if let Err(e) = writeln!(file, "{}", format_message(severity, message)) {
+                        warn!("Couldn't write to the validation log: {}", e);
+                    }
+                }
+            },
+        )
+            .expect("Couldn't register the validation callback");
+
+        Ok(Validation {
+            _callback: Mutex::new(callback),
+        })
+    }
+}
+
+fn message_type(ty: &MessageType) -> &'static str {
+    if ty.validation {
+        "validation"
+    } else if ty.performance {
+        "performance"
+    } else {
+        "general"
+    }
+}
+
+fn log_message(severity: Severity, message: &Message) {
+    let id = message.layer_prefix.unwrap_or("-");
+    match severity {
+        Severity::Error => error!("[{}] {}", id, message.description),
+        Severity::Warning => warn!("[{}] {}", id, message.description),
+        Severity::Info => info!("[{}] {}", id, message.description),
+        Severity::Verbose => debug!("[{}] {}", id, message.description),
+    }
+}
+
+/// A line of the validation log, see [`ValidationConfig::log_file`].
+fn format_message(severity: Severity, message: &Message) -> String {
+    let time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_secs_f64())
+        .unwrap_or(0.0);
+
+    format!(
+        "time={:.3} severity={} type={} id={} message={:?}",
+        time,
+        severity.name(),
+        message_type(&message.ty),
+        message.layer_prefix.unwrap_or("-"),
+        message.description,
+    )
+}
@@ -0,0 +1,53 @@
+use std::sync::Arc;
+
+use bytemuck::{Pod, Zeroable};
+use vulkano::buffer::{BufferUsage, CpuAccessibleBuffer};
+use vulkano::device::Device;
+
+/// A vertex with only a position, read by the shaders as `layout(location = 0) in vec2 position`.
+#[repr(C)]
+#[derive(Default, Copy, Clone, Debug, PartialEq, Pod, Zeroable)]
+pub struct Vertex2d {
+    pub position: [f32; 2],
+}
+
+vulkano::impl_vertex!(Vertex2d, position);
+
+/// A colored vertex, read by the shaders as `in vec2 position` and `in vec4 color`.
+#[repr(C)]
+#[derive(Default, Copy, Clone, Debug, PartialEq, Pod, Zeroable)]
+pub struct Vertex2dColor {
+    pub position: [f32; 2],
+    /// RGBA.
+    pub color: [f32; 4],
+}
+
+vulkano::impl_vertex!(Vertex2dColor, position, color);
+
+/// A vertex of a lit and textured mesh, read by the shaders as `in vec3 position`, `in vec3 normal` and `in vec2 uv`.
+#[repr(C)]
+#[derive(Default, Copy, Clone, Debug, PartialEq, Pod, Zeroable)]
+pub struct Vertex3d {
+    pub position: [f32; 3],
+    pub normal: [f32; 3],
+    pub uv: [f32; 2],
+}
+
+vulkano::impl_vertex!(Vertex3d, position, normal, uv);
+
+/// Copies `vertices` into a new vertex buffer on `device`.
+///
+/// The buffer is host-visible: it suits small or frequently rewritten meshes.
+/// Large static meshes are faster to draw from device-local memory, for example an `ImmutableBuffer`.
+pub fn upload_vertices<V>(device: &Arc<Device>, vertices: &[V]) -> Arc<CpuAccessibleBuffer<[V]>>
+    where
+        V: Pod + Send + Sync,
+{
+    CpuAccessibleBuffer::from_iter(
+        Arc::clone(device),
+        BufferUsage::vertex_buffer(),
+        false,
+        vertices.iter().copied(),
+    )
+        .expect("Couldn't create the vertex buffer")
+}
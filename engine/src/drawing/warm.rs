@@ -0,0 +1,171 @@
+use std::sync::Arc;
+
+use log::{debug, trace};
+use vulkano::command_buffer::{AutoCommandBufferBuilder, CommandBufferUsage, SubpassContents};
+use vulkano::format::{ClearValue, Format, NumericType};
+use vulkano::image::view::ImageView;
+use vulkano::image::{AttachmentImage, ImageUsage, ImageViewAbstract};
+use vulkano::pipeline::graphics::viewport::{Scissor, Viewport, ViewportState};
+use vulkano::pipeline::{DynamicState, GraphicsPipeline, Pipeline};
+use vulkano::render_pass::{Framebuffer, FramebufferCreateInfo, LoadOp};
+use vulkano::sync;
+use vulkano::sync::GpuFuture;
+
+use crate::drawing::hardware::Hardware;
+
+/// Records and executes a throwaway draw with `pipeline` into a 1×1 framebuffer,
+/// so the driver finishes any work it deferred to the first use of the pipeline.
+///
+/// The draw can only be recorded if the pipeline doesn't read any vertex buffer, descriptor or push constant,
+/// and its dynamic states are among the ones the engine can set: other pipelines are skipped, and `false` is returned.
+pub(crate) fn dummy_draw(hardware: &Hardware, pipeline: &Arc<GraphicsPipeline>) -> bool {
+    let needs_resources = !pipeline.vertex_input_state().bindings.is_empty()
+        || pipeline.descriptor_requirements().next().is_some()
+        || !pipeline.layout().push_constant_ranges().is_empty();
+    if needs_resources {
+        trace!("The pipeline reads resources, skipping its dummy draw");
+        return false;
+    }
+
+    let unknown_state = pipeline.dynamic_states().any(|(state, dynamic)| {
+        dynamic
+            && !matches!(
+                state,
+                DynamicState::Viewport | DynamicState::Scissor | DynamicState::LineWidth | DynamicState::DepthBias
+            )
+    });
+    if unknown_state {
+        trace!("The pipeline has dynamic states the engine doesn't set, skipping its dummy draw");
+        return false;
+    }
+
+    let device = hardware.graphics_device();
+    let subpass = pipeline.subpass();
+    let render_pass = subpass.render_pass();
+
+    let mut attachments: Vec<Arc<dyn ImageViewAbstract>> = Vec::new();
+    let mut clear_values = Vec::new();
+    for description in render_pass.attachments() {
+        let format = description.format.expect("Attachments always have a format");
+        let usage = if format.aspects().color {
+            ImageUsage::color_attachment()
+        } else {
+            ImageUsage::depth_stencil_attachment()
+        };
+
+        let image = AttachmentImage::multisampled_with_usage(
+            Arc::clone(device),
+            [1, 1],
+            description.samples,
+            format,
+            usage,
+        )
+            .expect("Couldn't create the warm-up attachment");
+        attachments.push(ImageView::new_default(image).expect("Couldn't create the warm-up view"));
+
+        clear_values.push(match description.load_op {
+            LoadOp::Clear => clear_value(format),
+            _ => ClearValue::None,
+        });
+    }
+
+    let framebuffer = Framebuffer::new(
+        Arc::clone(render_pass),
+        FramebufferCreateInfo {
+            attachments,
+            ..Default::default()
+        },
+    )
+        .expect("Couldn't create the warm-up framebuffer");
+
+    let mut builder = AutoCommandBufferBuilder::primary(
+        Arc::clone(device),
+        hardware.graphics_queue().family(),
+        CommandBufferUsage::OneTimeSubmit,
+    )
+        .expect("Couldn't create the warm-up command buffer");
+
+    builder
+        .begin_render_pass(framebuffer, SubpassContents::Inline, clear_values)
+        .expect("Couldn't begin the warm-up render pass");
+    for _ in 0..subpass.index() {
+        builder
+            .next_subpass(SubpassContents::Inline)
+            .expect("Couldn't reach the pipeline's subpass");
+    }
+
+    // Every dynamic state of the pipeline must be set before drawing
+    let (viewports, scissors) = match pipeline.viewport_state() {
+        Some(ViewportState::Dynamic { count, .. }) => (*count, *count),
+        Some(ViewportState::FixedViewport { viewports, .. }) => (0, viewports.len() as u32),
+        Some(ViewportState::FixedScissor { scissors, .. }) => (scissors.len() as u32, 0),
+        _ => (0, 0),
+    };
+    let is_dynamic = |state| pipeline.dynamic_state(state) == Some(true);
+    if is_dynamic(DynamicState::Viewport) {
+        let viewport = Viewport {
+            origin: [0.0, 0.0],
+            dimensions: [1.0, 1.0],
+            depth_range: 0.0..1.0,
+        };
+        builder.set_viewport(0, vec![viewport; viewports.max(1) as usize]);
+    }
+    if is_dynamic(DynamicState::Scissor) {
+        let scissor = Scissor {
+            origin: [0, 0],
+            dimensions: [1, 1],
+        };
+        builder.set_scissor(0, vec![scissor; scissors.max(1) as usize]);
+    }
+    if is_dynamic(DynamicState::LineWidth) {
+        builder.set_line_width(1.0);
+    }
+    if is_dynamic(DynamicState::DepthBias) {
+        builder.set_depth_bias(0.0, 0.0, 0.0);
+    }
+
+    builder
+        .bind_pipeline_graphics(Arc::clone(pipeline))
+        .draw(3, 1, 0, 0)
+        .expect("Couldn't record the warm-up draw");
+
+    for _ in subpass.index() + 1..render_pass.subpasses().len() as u32 {
+        builder
+            .next_subpass(SubpassContents::Inline)
+            .expect("Couldn't finish the warm-up render pass");
+    }
+    builder
+        .end_render_pass()
+        .expect("Couldn't end the warm-up render pass");
+
+    let command_buffer = builder.build().expect("Couldn't build the warm-up command buffer");
+    sync::now(Arc::clone(device))
+        .then_execute(Arc::clone(hardware.graphics_queue()), command_buffer)
+        .expect("Couldn't execute the warm-up draw")
+        .then_signal_fence_and_flush()
+        .expect("Couldn't submit the warm-up draw")
+        .wait(None)
+        .expect("Couldn't wait for the warm-up draw");
+
+    debug!("Warmed a pipeline with a dummy draw");
+    true
+}
+
+/// A clear value of the type expected by `format`.
+pub(crate) fn clear_value(format: Format) -> ClearValue {
+    let aspects = format.aspects();
+
+    if aspects.depth && aspects.stencil {
+        ClearValue::DepthStencil((1.0, 0))
+    } else if aspects.depth {
+        ClearValue::Depth(1.0)
+    } else if aspects.stencil {
+        ClearValue::Stencil(0)
+    } else {
+        match format.type_color() {
+            Some(NumericType::SINT) => ClearValue::Int([0; 4]),
+            Some(NumericType::UINT) => ClearValue::Uint([0; 4]),
+            _ => ClearValue::Float([0.0; 4]),
+        }
+    }
+}
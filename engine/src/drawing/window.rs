@@ -0,0 +1,232 @@
+use std::error::Error;
+use std::fmt;
+use std::path::Path;
+
+use image::ImageError;
+use vulkano::format::{ClearValue, Format};
+use vulkano::image::{ImageLayout, SampleCount};
+use vulkano::render_pass::LoadOp;
+use vulkano::swapchain::{ColorSpace, CompositeAlpha, PresentMode};
+use winit::dpi::PhysicalSize;
+use winit::window::{BadIcon, Icon, WindowBuilder};
+
+use crate::drawing::render_pass::Attachment;
+
+/// How the window is created.
+#[derive(Debug, Clone)]
+pub struct WindowConfig {
+    /// The title of the window. `None` uses the platform's default.
+    pub title: Option<String>,
+    /// The size of the window when it opens, in pixels. `None` uses the platform's default.
+    ///
+    /// The swapchain follows the actual size of the window, which the platform may adjust.
+    pub size: Option<[u32; 2]>,
+    /// Whether the user can resize the window, `true` by default.
+    pub resizable: bool,
+    /// The smallest size the user can resize the window to, in pixels.
+    ///
+    /// Note that minimizing the window still shrinks it to a zero size on most platforms.
+    pub min_size: Option<[u32; 2]>,
+    /// The largest size the user can resize the window to, in pixels.
+    pub max_size: Option<[u32; 2]>,
+    /// What happens to the contents of the window at the start of each frame.
+    pub clear: Clear,
+    /// The icon of the window, see [`load_icon`]. `None` uses the platform's default icon.
+    pub icon: Option<Icon>,
+    /// How the compositor interprets the pixels of the window, for example `DisplayP3NonLinear` for wide-gamut displays.
+    ///
+    /// `None`, or a color space the display doesn't support, uses `SrgbNonLinear`.
+    /// The color space actually used is given by `Screen::color_space`.
+    pub color_space: Option<ColorSpace>,
+    /// Requires the swapchain images to have exactly this format and color space, for tools expecting a specific format.
+    ///
+    /// It overrides [`WindowConfig::color_space`]. Creating the engine fails with
+    /// [`EngineError::UnsupportedFormat`](crate::drawing::engine::EngineError::UnsupportedFormat)
+    /// if the pair isn't supported.
+    pub format: Option<(Format, ColorSpace)>,
+    /// Renders in the orientation of the display, instead of letting the compositor rotate each frame.
+    ///
+    /// On rotated displays (mostly mobile devices), this saves a pass of the compositor, but the images must be
+    /// drawn rotated: the active camera does it automatically, other projections must be multiplied
+    /// by [`pretransform_matrix`](crate::drawing::camera::pretransform_matrix) of `Screen::pre_transform`.
+    pub pre_transform: bool,
+    /// How the compositor blends the window with what is behind it, for example `PreMultiplied` for transparent overlays.
+    ///
+    /// `None`, or a mode the surface doesn't support, uses the first mode supported by the surface.
+    /// The mode actually used is given by `Screen::composite_alpha`: with `PreMultiplied`, the clear color
+    /// is premultiplied by the engine, but shaders must output premultiplied colors themselves, see [`premultiply`].
+    pub composite_alpha: Option<CompositeAlpha>,
+    /// How frames are queued for display, for example `Mailbox` for low latency or `Immediate` for benchmarks.
+    ///
+    /// `None`, or a mode the surface doesn't support, uses `Fifo`, which waits for the vertical blank.
+    /// The mode actually used is given by `Screen::present_mode`.
+    pub present_mode: Option<PresentMode>,
+    /// Allocates a depth image of this format, for example `D16_UNORM`, recreated with the swapchain.
+    ///
+    /// The render pass given to [`Engine::run`](crate::drawing::engine::Engine::run) must then have
+    /// a depth attachment of this format, after the color attachments, see `Screen::render_pass`.
+    /// `None` renders without depth.
+    pub depth_format: Option<Format>,
+    /// Renders into multisampled images, resolved into the swapchain image at the end of the render pass,
+    /// to antialias the edges of the geometry. `Sample1`, the default, disables multisampling.
+    ///
+    /// Counts the device doesn't support fall back to `Sample1`. The count actually used is given by
+    /// `Screen::sample_count`. The render pass must be built as described by `Screen::render_pass`, whose attachments
+    /// carry the count: the pipelines built for its subpasses take their number of samples from them.
+    pub sample_count: SampleCount,
+}
+
+impl Default for WindowConfig {
+    fn default() -> Self {
+        WindowConfig {
+            title: None,
+            size: None,
+            resizable: true,
+            min_size: None,
+            max_size: None,
+            clear: Clear::default(),
+            icon: None,
+            color_space: None,
+            format: None,
+            pre_transform: false,
+            composite_alpha: None,
+            present_mode: None,
+            depth_format: None,
+            sample_count: SampleCount::Sample1,
+        }
+    }
+}
+
+impl WindowConfig {
+    pub(crate) fn builder(&self) -> WindowBuilder {
+        let mut builder = WindowBuilder::new().with_resizable(self.resizable);
+
+        if let Some(title) = &self.title {
+            builder = builder.with_title(title);
+        }
+
+        if let Some(size) = self.size {
+            builder = builder.with_inner_size(PhysicalSize::<u32>::from(size));
+        }
+
+        if let Some(size) = self.min_size {
+            builder = builder.with_min_inner_size(PhysicalSize::<u32>::from(size));
+        }
+
+        if let Some(size) = self.max_size {
+            builder = builder.with_max_inner_size(PhysicalSize::<u32>::from(size));
+        }
+
+        builder.with_window_icon(self.icon.clone())
+    }
+}
+
+/// Multiplies the RGB components of a color by its alpha, as expected by windows with `PreMultiplied` composite alpha.
+///
+/// In GLSL, the equivalent is `vec4(color.rgb * color.a, color.a)`.
+pub fn premultiply(color: [f32; 4]) -> [f32; 4] {
+    [color[0] * color[3], color[1] * color[3], color[2] * color[3], color[3]]
+}
+
+/// Loads a window icon from an image file (PNG, ICO or BMP).
+pub fn load_icon<P: AsRef<Path>>(path: P) -> Result<Icon, IconError> {
+    let image = image::open(path)?.into_rgba8();
+    let (width, height) = image.dimensions();
+
+    Ok(Icon::from_rgba(image.into_raw(), width, height)?)
+}
+
+#[derive(Debug)]
+pub enum IconError {
+    /// The file couldn't be read or decoded.
+    Image(ImageError),
+    /// The decoded image can't be used as an icon.
+    Icon(BadIcon),
+}
+
+impl fmt::Display for IconError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IconError::Image(e) => write!(f, "couldn't load the icon: {}", e),
+            IconError::Icon(e) => write!(f, "invalid icon: {}", e),
+        }
+    }
+}
+
+impl Error for IconError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            IconError::Image(e) => Some(e),
+            IconError::Icon(e) => Some(e),
+        }
+    }
+}
+
+impl From<ImageError> for IconError {
+    fn from(e: ImageError) -> Self {
+        IconError::Image(e)
+    }
+}
+
+impl From<BadIcon> for IconError {
+    fn from(e: BadIcon) -> Self {
+        IconError::Icon(e)
+    }
+}
+
+/// How a window starts each frame.
+///
+/// Each window carries its own configuration, available to the draw closure through `Screen::clear`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Clear {
+    /// Fills the window with an RGBA color.
+    Color([f32; 4]),
+    /// Keeps what was drawn in the swapchain image, for incremental drawing.
+    ///
+    /// Each swapchain image keeps its own contents: with multiple images, a frame starts
+    /// with what was drawn a few frames ago, not with the previous frame.
+    Preserve,
+}
+
+impl Default for Clear {
+    fn default() -> Self {
+        Clear::Color([0.0, 0.0, 0.0, 1.0])
+    }
+}
+
+impl Clear {
+    /// The same configuration, with a clear color premultiplied by its alpha.
+    pub fn premultiplied(self) -> Self {
+        match self {
+            Clear::Color(color) => Clear::Color(premultiply(color)),
+            Clear::Preserve => Clear::Preserve,
+        }
+    }
+
+    pub fn load_op(&self) -> LoadOp {
+        match self {
+            Clear::Color(_) => LoadOp::Clear,
+            Clear::Preserve => LoadOp::Load,
+        }
+    }
+
+    /// The clear value of the swapchain image, to pass to `begin_render_pass`.
+    pub fn clear_value(&self) -> ClearValue {
+        match self {
+            Clear::Color(color) => ClearValue::Float(*color),
+            Clear::Preserve => ClearValue::None,
+        }
+    }
+
+    /// The swapchain attachment of a render pass drawing to the window, loaded according to this configuration.
+    pub fn attachment(&self, format: Format) -> Attachment {
+        let initial_layout = match self {
+            Clear::Color(_) => ImageLayout::Undefined,
+            Clear::Preserve => ImageLayout::PresentSrc,
+        };
+
+        Attachment::color(format)
+            .load(self.load_op())
+            .layouts(initial_layout, ImageLayout::PresentSrc)
+    }
+}
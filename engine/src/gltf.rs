@@ -0,0 +1,303 @@
+use std::error::Error;
+use std::fmt;
+use std::path::Path;
+use std::sync::Arc;
+
+use bytemuck::{Pod, Zeroable};
+use log::warn;
+use vulkano::buffer::{BufferUsage, DeviceLocalBuffer};
+use vulkano::format::Format;
+use vulkano::image::view::ImageView;
+use vulkano::image::{ImageDimensions, ImmutableImage, MipmapsCount};
+use vulkano::sync::GpuFuture;
+
+use crate::drawing::hardware::Hardware;
+
+/// A vertex of a glTF mesh.
+#[repr(C)]
+#[derive(Default, Copy, Clone, Debug, Pod, Zeroable)]
+pub struct Vertex {
+    pub position: [f32; 3],
+    /// Zero if the mesh doesn't have normals.
+    pub normal: [f32; 3],
+    /// The first set of texture coordinates, zero if the mesh doesn't have any.
+    pub uv: [f32; 2],
+}
+
+vulkano::impl_vertex!(Vertex, position, normal, uv);
+
+/// A glTF file loaded into memory, ready to be uploaded.
+///
+/// Meshes, materials and textures are referenced by their index in the corresponding vectors.
+pub struct Scene {
+    /// All the nodes of the file, including those of the scenes that aren't loaded.
+    pub nodes: Vec<Node>,
+    /// The nodes at the root of the loaded scene.
+    pub roots: Vec<usize>,
+    pub meshes: Vec<Mesh>,
+    pub materials: Vec<Material>,
+    pub textures: Vec<Texture>,
+}
+
+pub struct Node {
+    pub name: Option<String>,
+    /// The transform relative to the parent node, as a column-major matrix.
+    pub transform: [[f32; 4]; 4],
+    pub mesh: Option<usize>,
+    pub children: Vec<usize>,
+}
+
+pub struct Mesh {
+    pub name: Option<String>,
+    pub primitives: Vec<Primitive>,
+}
+
+/// A part of a mesh drawn with a single material, as an indexed list of triangles.
+pub struct Primitive {
+    pub vertices: Vec<Vertex>,
+    pub indices: Vec<u32>,
+    /// `None` for the default material.
+    pub material: Option<usize>,
+}
+
+/// A GPU copy of a [`Primitive`].
+pub struct GpuPrimitive {
+    pub vertices: Arc<DeviceLocalBuffer<[Vertex]>>,
+    pub indices: Arc<DeviceLocalBuffer<[u32]>>,
+    pub material: Option<usize>,
+}
+
+impl Primitive {
+    /// Uploads the vertices and the indices to device-local buffers, see [`Hardware::upload_buffer`].
+    pub fn upload(&self, hardware: &Hardware) -> GpuPrimitive {
+        GpuPrimitive {
+            vertices: hardware.upload_buffer(self.vertices.iter().copied(), BufferUsage::vertex_buffer()),
+            indices: hardware.upload_buffer(self.indices.iter().copied(), BufferUsage::index_buffer()),
+            material: self.material,
+        }
+    }
+}
+
+pub struct Material {
+    pub name: Option<String>,
+    /// Multiplied with the base color texture, in linear RGBA.
+    pub base_color_factor: [f32; 4],
+    pub base_color_texture: Option<usize>,
+}
+
+/// An RGBA image with 8 bits per channel.
+pub struct Texture {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+impl Texture {
+    /// Uploads the texture as an sRGB image, as glTF base color textures are.
+    pub fn upload(&self, hardware: &Hardware) -> Arc<ImageView<ImmutableImage>> {
+        let (image, upload) = ImmutableImage::from_iter(
+            self.pixels.iter().copied(),
+            ImageDimensions::Dim2d {
+                width: self.width,
+                height: self.height,
+                array_layers: 1,
+            },
+            MipmapsCount::One,
+            Format::R8G8B8A8_SRGB,
+            Arc::clone(hardware.transfer_queue()),
+        )
+            .expect("Couldn't create the texture");
+        upload
+            .then_signal_fence_and_flush()
+            .expect("Couldn't upload the texture")
+            .wait(None)
+            .expect("Couldn't upload the texture");
+
+        ImageView::new_default(image).expect("Couldn't create the texture view")
+    }
+}
+
+/// Loads the default scene of a glTF or GLB file, with its buffers and images.
+///
+/// Only static meshes are supported: skins, morph targets and animations are ignored with a warning.
+pub fn load<P: AsRef<Path>>(path: P) -> Result<Scene, GltfError> {
+    let (document, buffers, images) = ::gltf::import(path)?;
+
+    if let Some(extension) = document.extensions_required().next() {
+        return Err(GltfError::Unsupported(format!(
+            "the required extension {}",
+            extension
+        )));
+    }
+    if document.skins().next().is_some() {
+        warn!("Skins aren't supported, the meshes are loaded in their bind pose");
+    }
+    if document.animations().next().is_some() {
+        warn!("Animations aren't supported, they are ignored");
+    }
+
+    let mut meshes = Vec::new();
+    for mesh in document.meshes() {
+        let mut primitives = Vec::new();
+        for primitive in mesh.primitives() {
+            if primitive.mode() != ::gltf::mesh::Mode::Triangles {
+                return Err(GltfError::Unsupported(format!(
+                    "the primitive mode {:?}",
+                    primitive.mode()
+                )));
+            }
+            if primitive.morph_targets().next().is_some() {
+                warn!("Morph targets aren't supported, they are ignored");
+            }
+
+            let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
+            let positions = reader.read_positions().ok_or(GltfError::MissingPositions)?;
+            let mut vertices: Vec<Vertex> = positions
+                .map(|position| Vertex {
+                    position,
+                    ..Default::default()
+                })
+                .collect();
+
+            if let Some(normals) = reader.read_normals() {
+                for (vertex, normal) in vertices.iter_mut().zip(normals) {
+                    vertex.normal = normal;
+                }
+            }
+            if let Some(uvs) = reader.read_tex_coords(0) {
+                for (vertex, uv) in vertices.iter_mut().zip(uvs.into_f32()) {
+                    vertex.uv = uv;
+                }
+            }
+
+            let indices = match reader.read_indices() {
+                Some(indices) => indices.into_u32().collect(),
+                None => (0..vertices.len() as u32).collect(),
+            };
+
+            primitives.push(Primitive {
+                vertices,
+                indices,
+                material: primitive.material().index(),
+            });
+        }
+
+        meshes.push(Mesh {
+            name: mesh.name().map(str::to_owned),
+            primitives,
+        });
+    }
+
+    let materials = document
+        .materials()
+        .map(|material| {
+            let pbr = material.pbr_metallic_roughness();
+            Material {
+                name: material.name().map(str::to_owned),
+                base_color_factor: pbr.base_color_factor(),
+                base_color_texture: pbr
+                    .base_color_texture()
+                    .map(|info| info.texture().source().index()),
+            }
+        })
+        .collect();
+
+    let textures = images
+        .into_iter()
+        .map(texture)
+        .collect::<Result<_, _>>()?;
+
+    let nodes = document
+        .nodes()
+        .map(|node| Node {
+            name: node.name().map(str::to_owned),
+            transform: node.transform().matrix(),
+            mesh: node.mesh().map(|mesh| mesh.index()),
+            children: node.children().map(|child| child.index()).collect(),
+        })
+        .collect();
+
+    let roots = document
+        .default_scene()
+        .or_else(|| document.scenes().next())
+        .map(|scene| scene.nodes().map(|node| node.index()).collect())
+        .unwrap_or_default();
+
+    Ok(Scene {
+        nodes,
+        roots,
+        meshes,
+        materials,
+        textures,
+    })
+}
+
+/// Converts an image to RGBA8.
+fn texture(image: ::gltf::image::Data) -> Result<Texture, GltfError> {
+    use ::gltf::image::Format;
+
+    let pixels = match image.format {
+        Format::R8G8B8A8 => image.pixels,
+        Format::R8G8B8 => image
+            .pixels
+            .chunks_exact(3)
+            .flat_map(|rgb| [rgb[0], rgb[1], rgb[2], 255])
+            .collect(),
+        Format::R8G8 => image
+            .pixels
+            .chunks_exact(2)
+            .flat_map(|rg| [rg[0], rg[1], 0, 255])
+            .collect(),
+        Format::R8 => image
+            .pixels
+            .iter()
+            .flat_map(|&r| [r, r, r, 255])
+            .collect(),
+        other => {
+            return Err(GltfError::Unsupported(format!(
+                "the image format {:?}",
+                other
+            )));
+        }
+    };
+
+    Ok(Texture {
+        width: image.width,
+        height: image.height,
+        pixels,
+    })
+}
+
+#[derive(Debug)]
+pub enum GltfError {
+    Import(::gltf::Error),
+    /// The file uses a feature the loader can't represent.
+    Unsupported(String),
+    /// A primitive doesn't have vertex positions.
+    MissingPositions,
+}
+
+impl fmt::Display for GltfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GltfError::Import(e) => write!(f, "couldn't import the glTF file: {}", e),
+            GltfError::Unsupported(feature) => write!(f, "{} isn't supported", feature),
+            GltfError::MissingPositions => write!(f, "a primitive doesn't have vertex positions"),
+        }
+    }
+}
+
+impl Error for GltfError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            GltfError::Import(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<::gltf::Error> for GltfError {
+    fn from(e: ::gltf::Error) -> Self {
+        GltfError::Import(e)
+    }
+}
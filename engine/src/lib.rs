@@ -1,2 +1,7 @@
+pub mod color;
 pub mod drawing;
+pub mod gltf;
+pub mod testing;
 pub mod world;
+
+pub use drawing::engine::Engine;
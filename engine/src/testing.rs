@@ -0,0 +1,217 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use image::{Rgba, RgbaImage};
+
+/// The result of comparing a rendered image against a reference.
+pub struct ImageDiff {
+    /// The number of pixels with at least one channel further than the tolerance from the reference.
+    pub differing_pixels: u64,
+    pub total_pixels: u64,
+    /// The differing pixels in red over a dimmed copy of the reference.
+    pub diff: RgbaImage,
+}
+
+impl ImageDiff {
+    pub fn matches(&self) -> bool {
+        self.differing_pixels == 0
+    }
+
+    /// The proportion of pixels that differ, between 0 and 100.
+    pub fn percentage(&self) -> f64 {
+        if self.total_pixels == 0 {
+            0.0
+        } else {
+            self.differing_pixels as f64 * 100.0 / self.total_pixels as f64
+        }
+    }
+}
+
+impl fmt::Display for ImageDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} of {} pixels differ ({:.3}%)",
+            self.differing_pixels,
+            self.total_pixels,
+            self.percentage()
+        )
+    }
+}
+
+/// Compares two images pixel by pixel.
+///
+/// A pixel differs if any of its channels is more than `tolerance` away from the reference.
+/// If the sizes differ, every pixel differs, and the diff covers the largest width and height of both.
+pub fn compare_images(rendered: &RgbaImage, reference: &RgbaImage, tolerance: u8) -> ImageDiff {
+    if rendered.dimensions() != reference.dimensions() {
+        let diff = RgbaImage::from_pixel(
+            rendered.width().max(reference.width()),
+            rendered.height().max(reference.height()),
+            Rgba([255, 0, 0, 255]),
+        );
+        let total_pixels = u64::from(diff.width()) * u64::from(diff.height());
+        return ImageDiff {
+            differing_pixels: total_pixels,
+            total_pixels,
+            diff,
+        };
+    }
+
+    let (width, height) = reference.dimensions();
+    let mut differing_pixels = 0;
+    let diff = RgbaImage::from_fn(width, height, |x, y| {
+        let expected = reference.get_pixel(x, y);
+        let actual = rendered.get_pixel(x, y);
+
+        let differs = expected
+            .0
+            .iter()
+            .zip(actual.0.iter())
+            .any(|(e, a)| e.abs_diff(*a) > tolerance);
+
+        if differs {
+            differing_pixels += 1;
+            Rgba([255, 0, 0, 255])
+        } else {
+            let [r, g, b, _] = expected.0;
+            Rgba([r / 4, g / 4, b / 4, 255])
+        }
+    });
+
+    ImageDiff {
+        differing_pixels,
+        total_pixels: u64::from(width) * u64::from(height),
+        diff,
+    }
+}
+
+/// Panics if `rendered` doesn't match the reference PNG at `golden_path`, see [`compare_images`].
+///
+/// To help investigate failures, the rendered image is written next to the reference as `<name>.actual.png`,
+/// and, if the reference exists, the diff image as `<name>.diff.png`.
+pub fn assert_image_matches<P: AsRef<Path>>(rendered: &RgbaImage, golden_path: P, tolerance: u8) {
+    let golden_path = golden_path.as_ref();
+
+    let reference = match image::open(golden_path) {
+        Ok(reference) => reference.into_rgba8(),
+        Err(e) => {
+            let actual = sibling(golden_path, "actual");
+            rendered
+                .save(&actual)
+                .expect("Couldn't write the rendered image");
+            panic!(
+                "Couldn't load the reference image {}: {}\nThe rendered image was written to {}",
+                golden_path.display(),
+                e,
+                actual.display()
+            );
+        }
+    };
+
+    if rendered.dimensions() != reference.dimensions() {
+        let actual = sibling(golden_path, "actual");
+        rendered
+            .save(&actual)
+            .expect("Couldn't write the rendered image");
+        panic!(
+            "The rendered image is {:?}, but the reference {} is {:?}\nThe rendered image was written to {}",
+            rendered.dimensions(),
+            golden_path.display(),
+            reference.dimensions(),
+            actual.display()
+        );
+    }
+
+    let diff = compare_images(rendered, &reference, tolerance);
+    if !diff.matches() {
+        let actual = sibling(golden_path, "actual");
+        let diff_path = sibling(golden_path, "diff");
+        rendered
+            .save(&actual)
+            .expect("Couldn't write the rendered image");
+        diff.diff
+            .save(&diff_path)
+            .expect("Couldn't write the diff image");
+        panic!(
+            "The rendered image doesn't match {} with a tolerance of {}: {}\nRendered: {}\nDiff: {}",
+            golden_path.display(),
+            tolerance,
+            diff,
+            actual.display(),
+            diff_path.display()
+        );
+    }
+}
+
+/// `golden.png` -> `golden.<suffix>.png`
+fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    path.with_file_name(format!("{}.{}.png", stem, suffix))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gradient() -> RgbaImage {
+        RgbaImage::from_fn(4, 4, |x, y| Rgba([x as u8 * 60, y as u8 * 60, 128, 255]))
+    }
+
+    #[test]
+    fn identical() {
+        let diff = compare_images(&gradient(), &gradient(), 0);
+
+        assert!(diff.matches());
+        assert_eq!(diff.differing_pixels, 0);
+        assert_eq!(diff.total_pixels, 16);
+        assert_eq!(diff.percentage(), 0.0);
+    }
+
+    #[test]
+    fn within_tolerance() {
+        let mut rendered = gradient();
+        rendered.get_pixel_mut(1, 2).0[2] += 3;
+        rendered.get_pixel_mut(3, 0).0[0] -= 3;
+
+        assert!(compare_images(&rendered, &gradient(), 3).matches());
+    }
+
+    #[test]
+    fn past_tolerance() {
+        let mut rendered = gradient();
+        rendered.get_pixel_mut(1, 2).0[2] += 4;
+        rendered.get_pixel_mut(3, 0).0[0] -= 3;
+
+        let diff = compare_images(&rendered, &gradient(), 3);
+        assert!(!diff.matches());
+        assert_eq!(diff.differing_pixels, 1);
+        assert_eq!(diff.diff.get_pixel(1, 2), &Rgba([255, 0, 0, 255]));
+        assert_eq!(diff.diff.get_pixel(3, 0), &Rgba([45, 0, 32, 255]));
+    }
+
+    #[test]
+    fn percentage() {
+        let mut rendered = gradient();
+        for x in 0..4 {
+            rendered.get_pixel_mut(x, 0).0[3] = 0;
+        }
+
+        let diff = compare_images(&rendered, &gradient(), 0);
+        assert_eq!(diff.differing_pixels, 4);
+        assert_eq!(diff.percentage(), 25.0);
+    }
+
+    #[test]
+    fn different_sizes() {
+        let diff = compare_images(&RgbaImage::new(2, 6), &gradient(), 255);
+
+        assert!(!diff.matches());
+        assert_eq!(diff.diff.dimensions(), (4, 6));
+        assert_eq!(diff.differing_pixels, 24);
+        assert_eq!(diff.percentage(), 100.0);
+    }
+}
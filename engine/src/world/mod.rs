@@ -1,3 +1,4 @@
 pub mod coordinates;
 pub mod object;
+#[allow(clippy::module_inception)]
 pub mod world;
@@ -1,5 +1,6 @@
 use crate::world::object::Object;
 
 pub struct World {
+    #[allow(dead_code)]
     objects: Vec<Object>,
 }
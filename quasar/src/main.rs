@@ -1,7 +1,7 @@
-use log::info;
+use log::{error, info};
 use simple_logger::SimpleLogger;
 
-use quasar_engine::drawing::engine::Engine;
+use quasar_engine::Engine;
 
 fn main() {
     SimpleLogger::new().init().unwrap_or_else(|info| {
@@ -12,5 +12,7 @@ fn main() {
     });
 
     info!("Starting…");
-    Engine::new();
+    if let Err(e) = Engine::new() {
+        error!("Couldn't start the engine: {}", e);
+    }
 }